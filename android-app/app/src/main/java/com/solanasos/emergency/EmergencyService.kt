@@ -69,8 +69,8 @@ class EmergencyService : Service() {
                 // 1. Get current location
                 val location = getCurrentLocation()
                 
-                // 2. Call 911 (simulated)
-                call911(location)
+                // 2. Call emergency services (simulated)
+                callEmergencyServices(location)
                 
                 // 3. Share location with emergency contacts
                 shareLocationWithContacts(location)
@@ -109,8 +109,8 @@ class EmergencyService : Service() {
         }
     }
     
-    private suspend fun call911(location: Location?) = withContext(Dispatchers.IO) {
-        Log.d(TAG, "Calling 911...")
+    private suspend fun callEmergencyServices(location: Location?) = withContext(Dispatchers.IO) {
+        Log.d(TAG, "Calling emergency services...")
         
         // This would make an actual emergency call
         // For demo purposes, just log the action
@@ -121,11 +121,11 @@ class EmergencyService : Service() {
             "Location unavailable"
         }
         
-        Log.d(TAG, "911 call initiated for $emergencyType at $locationString")
+        Log.d(TAG, "Emergency call initiated for $emergencyType at $locationString")
         
         // Simulate call duration
         Thread.sleep(2000)
-        Log.d(TAG, "911 call completed")
+        Log.d(TAG, "Emergency call completed")
     }
     
     private suspend fun shareLocationWithContacts(location: Location?) = withContext(Dispatchers.IO) {
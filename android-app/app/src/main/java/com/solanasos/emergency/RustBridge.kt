@@ -3,6 +3,7 @@ package com.solanasos.emergency
 import android.content.Context
 import android.util.Log
 import java.io.File
+import java.nio.ByteBuffer
 
 /**
  * JNI Bridge to connect Android app with Rust backend
@@ -25,10 +26,19 @@ class RustBridge(private val context: Context) {
     }
     
     // Voice Recognition Functions
+    // Audio is 16 kHz mono 16-bit PCM. A direct ByteBuffer in native order
+    // (read from position to limit) or a ShortArray is read without copying;
+    // a ByteArray is little-endian.
     external fun initializeVoiceRecognition(): Boolean
     external fun processVoiceInput(audioData: ByteArray): String?
+    external fun processVoiceInput(audioData: ByteBuffer): String?
+    external fun processVoiceInput(audioData: ShortArray): String?
     external fun detectWakeWord(audioData: ByteArray): Boolean
+    external fun detectWakeWord(audioData: ByteBuffer): Boolean
+    external fun detectWakeWord(audioData: ShortArray): Boolean
     external fun detectEmergencyPhrase(audioData: ByteArray): String?
+    external fun detectEmergencyPhrase(audioData: ByteBuffer): String?
+    external fun detectEmergencyPhrase(audioData: ShortArray): String?
     external fun adaptVoiceModel(userAudioData: ByteArray): Boolean
     
     // Emergency Response Functions
@@ -41,6 +51,10 @@ class RustBridge(private val context: Context) {
     external fun activateSilentSOS(location: String): Boolean
     external fun detectCrash(accelerometerData: FloatArray, gpsData: DoubleArray): Boolean
     external fun sendTrustedNetworkAlert(contacts: Array<String>, location: String): Boolean
+    external fun onInputEvent(kind: String, timestampMs: Long): Boolean
+    external fun onSensorSample(kind: String, timestampMs: Long, values: DoubleArray): Boolean
+    external fun startSensorRecording(path: String, label: String): Boolean
+    external fun stopSensorRecording(): Boolean
     
     // Gamification Functions
     external fun awardXP(action: String, amount: Int): Boolean
@@ -58,9 +72,24 @@ class RustBridge(private val context: Context) {
     external fun initializeDatabase(): Boolean
     external fun saveEmergencyContact(name: String, phone: String): Boolean
     external fun getEmergencyContacts(): String
+    external fun importVCard(vcard: String): String
     external fun saveUserPreferences(preferences: String): Boolean
     external fun getUserPreferences(): String
     
+    // Alert Delivery Functions
+    external fun registerSmsGateway(gateway: SmsGateway)
+    external fun reportSmsStatus(idempotencyKey: String, delivered: Boolean, error: String?)
+    external fun renderAlert(contextJson: String): String
+    external fun formatCoordinates(latitude: Double, longitude: Double): String
+    external fun setCountryHints(simCountry: String?, locale: String?)
+    external fun emergencyNumbers(latitude: Double, longitude: Double): String
+    
+    // Emergency Call Functions
+    external fun registerEmergencyDialer(dialer: EmergencyDialer)
+    external fun registerSpeaker(speaker: Speaker)
+    external fun reportCallState(callId: String, state: String, error: String?)
+    external fun callScript(address: String?): String
+    
     // Audio Processing Functions
     external fun processAudioWithNoiseFiltering(audioData: ByteArray): ByteArray
     external fun applyRNNoiseFilter(audioData: ByteArray): ByteArray
@@ -83,6 +112,15 @@ class RustBridge(private val context: Context) {
             // Initialize Rust backend with Android paths
             initializeAndroidPaths(filesDir, cacheDir)
             
+            // Let the backend text emergency contacts
+            registerSmsGateway(SmsManagerGateway(context, this))
+            
+            // Let the backend call emergency services
+            registerEmergencyDialer(TelecomDialer(context, this))
+            
+            // Let the backend ask questions such as "Did you fall?"
+            registerSpeaker(TtsSpeaker(context))
+            
             // Initialize database
             val dbInitialized = initializeDatabase()
             if (!dbInitialized) {
@@ -241,6 +279,38 @@ class RustBridge(private val context: Context) {
     // Helper functions for Android-specific initialization
     private external fun initializeAndroidPaths(filesDir: String, cacheDir: String)
     
+    /**
+     * Sends SMS alerts for the Rust backend
+     *
+     * Report the carrier's result with [reportSmsStatus] using the same idempotency key.
+     */
+    interface SmsGateway {
+        /** Queue [parts] for [number] as one message; return false if it could not be queued */
+        fun sendSms(number: String, parts: Array<String>, idempotencyKey: String): Boolean
+    }
+    
+    /**
+     * Places emergency calls for the Rust backend
+     *
+     * Report each state of the call ("dialing", "connected", "failed" or
+     * "ended") with [reportCallState] using the same call ID.
+     */
+    interface EmergencyDialer {
+        /** Start calling [number]; return false if the call could not be placed */
+        fun dial(number: String, callId: String): Boolean
+        
+        /** Hang up [callId]; return false if it could not be ended */
+        fun hangUp(callId: String): Boolean
+        
+        /** Speak [text] into the connected call [callId]; return false if it can't be spoken */
+        fun speak(callId: String, text: String): Boolean
+    }
+    
+    interface Speaker {
+        /** Queue [text] to be spoken to the user; return false if it could not be queued */
+        fun speak(text: String): Boolean
+    }
+    
     // Data classes for structured data
     data class EmergencyData(
         val emergencyType: String,
@@ -269,12 +339,12 @@ class RustBridge(private val context: Context) {
     
     // Helper function to parse emergency contacts JSON
     private fun parseEmergencyContacts(json: String): List<EmergencyContact> {
-        // Simple JSON parsing - in production would use proper JSON library
         return try {
-            // This is a simplified parser - would use Gson or similar in production
-            val contacts = mutableListOf<EmergencyContact>()
-            // Parse JSON and create EmergencyContact objects
-            contacts
+            val array = org.json.JSONArray(json)
+            (0 until array.length()).map { i ->
+                val contact = array.getJSONObject(i)
+                EmergencyContact(contact.getString("name"), contact.getString("phone"))
+            }
         } catch (e: Exception) {
             Log.e(TAG, "Error parsing emergency contacts", e)
             emptyList()
@@ -0,0 +1,91 @@
+package com.solanasos.emergency
+
+import android.app.Activity
+import android.app.PendingIntent
+import android.content.BroadcastReceiver
+import android.content.Context
+import android.content.Intent
+import android.content.IntentFilter
+import android.os.Build
+import android.telephony.SmsManager
+import android.util.Log
+
+/**
+ * SMS gateway for alerts, backed by SmsManager
+ *
+ * Each message is sent with sent and delivered intents; their results are
+ * reported back to the Rust backend, which records them in the incident timeline.
+ */
+class SmsManagerGateway(
+    private val context: Context,
+    private val bridge: RustBridge
+) : RustBridge.SmsGateway {
+
+    companion object {
+        private const val TAG = "SmsManagerGateway"
+        private const val ACTION_SENT = "com.solanasos.emergency.SMS_SENT"
+        private const val ACTION_DELIVERED = "com.solanasos.emergency.SMS_DELIVERED"
+        private const val EXTRA_KEY = "idempotency_key"
+    }
+
+    private val smsManager: SmsManager =
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.S) {
+            context.getSystemService(SmsManager::class.java)
+        } else {
+            @Suppress("DEPRECATION")
+            SmsManager.getDefault()
+        }
+
+    private val receiver = object : BroadcastReceiver() {
+        override fun onReceive(context: Context, intent: Intent) {
+            val key = intent.getStringExtra(EXTRA_KEY) ?: return
+            when (intent.action) {
+                ACTION_SENT -> if (resultCode != Activity.RESULT_OK) {
+                    bridge.reportSmsStatus(key, false, "send failed (result $resultCode)")
+                }
+                ACTION_DELIVERED -> bridge.reportSmsStatus(key, true, null)
+            }
+        }
+    }
+
+    init {
+        val filter = IntentFilter().apply {
+            addAction(ACTION_SENT)
+            addAction(ACTION_DELIVERED)
+        }
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.TIRAMISU) {
+            context.registerReceiver(receiver, filter, Context.RECEIVER_NOT_EXPORTED)
+        } else {
+            context.registerReceiver(receiver, filter)
+        }
+    }
+
+    override fun sendSms(number: String, parts: Array<String>, idempotencyKey: String): Boolean {
+        return try {
+            // Parts are already split for GSM-7/UCS-2 limits by the backend.
+            // Only the last part reports, so one receipt covers the whole message
+            val sent = ArrayList<PendingIntent?>(parts.size)
+            val delivered = ArrayList<PendingIntent?>(parts.size)
+            for (i in parts.indices) {
+                val last = i == parts.size - 1
+                sent.add(if (last) pendingIntent(ACTION_SENT, idempotencyKey) else null)
+                delivered.add(if (last) pendingIntent(ACTION_DELIVERED, idempotencyKey) else null)
+            }
+            smsManager.sendMultipartTextMessage(number, null, ArrayList(parts.asList()), sent, delivered)
+            true
+        } catch (e: Exception) {
+            Log.e(TAG, "Failed to queue SMS", e)
+            false
+        }
+    }
+
+    private fun pendingIntent(action: String, idempotencyKey: String): PendingIntent {
+        val intent = Intent(action).setPackage(context.packageName).putExtra(EXTRA_KEY, idempotencyKey)
+        return PendingIntent.getBroadcast(
+            context,
+            idempotencyKey.hashCode() xor action.hashCode(),
+            intent,
+            PendingIntent.FLAG_UPDATE_CURRENT or PendingIntent.FLAG_IMMUTABLE
+        )
+    }
+}
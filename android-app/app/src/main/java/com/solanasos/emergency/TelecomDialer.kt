@@ -0,0 +1,133 @@
+package com.solanasos.emergency
+
+import android.content.Context
+import android.media.AudioDeviceInfo
+import android.media.AudioManager
+import android.net.Uri
+import android.os.Build
+import android.os.Bundle
+import android.speech.tts.TextToSpeech
+import android.telecom.TelecomManager
+import android.telephony.PhoneStateListener
+import android.telephony.TelephonyCallback
+import android.telephony.TelephonyManager
+import android.util.Log
+import java.util.Locale
+
+/**
+ * Emergency dialer for the Rust backend, backed by TelecomManager
+ *
+ * Call states are reported back with [RustBridge.reportCallState]. Android
+ * does not tell other apps when an outgoing call is answered, so the line
+ * going off-hook is reported as connected and returning to idle as ended.
+ *
+ * Messages are spoken with TextToSpeech over the speakerphone, so the
+ * microphone carries them into the call.
+ */
+class TelecomDialer(
+    private val context: Context,
+    private val bridge: RustBridge
+) : RustBridge.EmergencyDialer {
+
+    companion object {
+        private const val TAG = "TelecomDialer"
+        private const val SPOKEN_REPEATS = 3
+        private const val PAUSE_MS = 2000L
+    }
+
+    private val telecomManager = context.getSystemService(TelecomManager::class.java)
+    private val telephonyManager = context.getSystemService(TelephonyManager::class.java)
+    private val audioManager = context.getSystemService(AudioManager::class.java)
+
+    @Volatile private var ttsReady = false
+    private val tts = TextToSpeech(context) { status -> ttsReady = status == TextToSpeech.SUCCESS }
+
+    /** Call being followed and whether its line went off-hook */
+    @Volatile private var currentCall: String? = null
+    @Volatile private var offHook = false
+
+    init {
+        try {
+            if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.S) {
+                telephonyManager.registerTelephonyCallback(
+                    context.mainExecutor,
+                    object : TelephonyCallback(), TelephonyCallback.CallStateListener {
+                        override fun onCallStateChanged(state: Int) = callStateChanged(state)
+                    }
+                )
+            } else {
+                @Suppress("DEPRECATION")
+                telephonyManager.listen(object : PhoneStateListener() {
+                    @Deprecated("Deprecated in Java")
+                    override fun onCallStateChanged(state: Int, phoneNumber: String?) = callStateChanged(state)
+                }, PhoneStateListener.LISTEN_CALL_STATE)
+            }
+        } catch (e: SecurityException) {
+            Log.e(TAG, "Call states unavailable without READ_PHONE_STATE", e)
+        }
+    }
+
+    override fun dial(number: String, callId: String): Boolean {
+        return try {
+            currentCall = callId
+            offHook = false
+            telecomManager.placeCall(Uri.fromParts("tel", number, null), Bundle())
+            bridge.reportCallState(callId, "dialing", null)
+            true
+        } catch (e: SecurityException) {
+            Log.e(TAG, "Missing CALL_PHONE permission", e)
+            currentCall = null
+            false
+        }
+    }
+
+    override fun hangUp(callId: String): Boolean {
+        if (currentCall != callId) return false
+        return try {
+            @Suppress("DEPRECATION")
+            Build.VERSION.SDK_INT >= Build.VERSION_CODES.P && telecomManager.endCall()
+        } catch (e: SecurityException) {
+            Log.e(TAG, "Missing ANSWER_PHONE_CALLS permission", e)
+            false
+        }
+    }
+
+    override fun speak(callId: String, text: String): Boolean {
+        if (currentCall != callId || !ttsReady) return false
+        routeToSpeaker()
+        tts.language = Locale.US
+        // Said a few times over, in case the dispatcher missed the start
+        for (i in 0 until SPOKEN_REPEATS) {
+            val mode = if (i == 0) TextToSpeech.QUEUE_FLUSH else TextToSpeech.QUEUE_ADD
+            if (i > 0) tts.playSilentUtterance(PAUSE_MS, TextToSpeech.QUEUE_ADD, null)
+            if (tts.speak(text, mode, null, "$callId-$i") != TextToSpeech.SUCCESS) return false
+        }
+        return true
+    }
+
+    private fun routeToSpeaker() {
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.S) {
+            audioManager.availableCommunicationDevices
+                .firstOrNull { it.type == AudioDeviceInfo.TYPE_BUILTIN_SPEAKER }
+                ?.let { audioManager.setCommunicationDevice(it) }
+        } else {
+            @Suppress("DEPRECATION")
+            audioManager.isSpeakerphoneOn = true
+        }
+    }
+
+    private fun callStateChanged(state: Int) {
+        val callId = currentCall ?: return
+        when (state) {
+            TelephonyManager.CALL_STATE_OFFHOOK -> if (!offHook) {
+                offHook = true
+                bridge.reportCallState(callId, "connected", null)
+            }
+            TelephonyManager.CALL_STATE_IDLE -> if (offHook) {
+                currentCall = null
+                tts.stop()
+                bridge.reportCallState(callId, "ended", null)
+            }
+        }
+    }
+}
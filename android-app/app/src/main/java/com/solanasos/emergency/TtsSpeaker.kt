@@ -0,0 +1,22 @@
+package com.solanasos.emergency
+
+import android.content.Context
+import android.speech.tts.TextToSpeech
+import java.util.UUID
+
+/**
+ * Speaker for the Rust backend, backed by TextToSpeech
+ *
+ * Used for questions such as the fall check; the backend keeps listening
+ * for the answer until the countdown ends.
+ */
+class TtsSpeaker(context: Context) : RustBridge.Speaker {
+
+    @Volatile private var ttsReady = false
+    private val tts = TextToSpeech(context) { status -> ttsReady = status == TextToSpeech.SUCCESS }
+
+    override fun speak(text: String): Boolean {
+        if (!ttsReady) return false
+        return tts.speak(text, TextToSpeech.QUEUE_FLUSH, null, UUID.randomUUID().toString()) == TextToSpeech.SUCCESS
+    }
+}
@@ -93,11 +93,14 @@ CREATE TABLE IF NOT EXISTS emergency_contacts (
     phone_number TEXT NOT NULL,
     email TEXT,
     relationship TEXT,
+    tier TEXT NOT NULL DEFAULT 'primary', -- primary, secondary, medical
+    channels TEXT NOT NULL DEFAULT 'sms,call', -- sms, call, email, push
     is_active BOOLEAN DEFAULT TRUE,
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP
 );
 
 -- Create indexes for better performance
+CREATE UNIQUE INDEX IF NOT EXISTS idx_emergency_contacts_phone ON emergency_contacts(phone_number);
 CREATE INDEX IF NOT EXISTS idx_emergency_instructions_type_step ON emergency_instructions(emergency_type_id, step_number);
 CREATE INDEX IF NOT EXISTS idx_emergency_responses_type ON emergency_responses(emergency_type_id);
 CREATE INDEX IF NOT EXISTS idx_emergency_responses_timestamp ON emergency_responses(trigger_timestamp);
@@ -160,10 +163,10 @@ INSERT OR IGNORE INTO user_settings (setting_key, setting_value) VALUES
 ('ui_language', 'en');
 
 -- Insert sample emergency contacts
-INSERT OR IGNORE INTO emergency_contacts (name, phone_number, email, relationship) VALUES
-('Emergency Services', '911', NULL, 'Emergency'),
-('Primary Contact', '+1234567890', 'primary@example.com', 'Spouse'),
-('Secondary Contact', '+1234567891', 'secondary@example.com', 'Parent');
+-- Phone numbers are E.164; emergency services are dialed directly, not stored here
+INSERT OR IGNORE INTO emergency_contacts (name, phone_number, email, relationship, tier, channels) VALUES
+('Primary Contact', '+12025550143', 'primary@example.com', 'Spouse', 'primary', 'sms,call,email'),
+('Secondary Contact', '+12025550144', 'secondary@example.com', 'Parent', 'secondary', 'sms,call');
 
 -- Create views for easier querying
 CREATE VIEW IF NOT EXISTS v_emergency_statistics AS
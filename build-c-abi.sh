@@ -0,0 +1,31 @@
+#!/bin/bash
+
+# Build script for the Solana SOS C ABI
+# Regenerates the C header, builds libsolana_sos for the host and runs the C smoke test
+
+set -e
+
+echo "🔧 Building Solana SOS C ABI..."
+
+# Regenerate the header if cbindgen is available
+if command -v cbindgen > /dev/null 2>&1; then
+    echo "📝 Generating include/solana_sos.h..."
+    cbindgen --config cbindgen.toml --output include/solana_sos.h
+else
+    echo "⚠️  cbindgen not found, using checked-in include/solana_sos.h"
+fi
+
+# Build the host library
+cargo build --release --lib
+
+# Build and run the C smoke test against the shared library
+mkdir -p target/c
+echo "🔨 Compiling tests/c/abi_smoke.c..."
+cc -std=c11 -Wall -Wextra -Werror -Iinclude tests/c/abi_smoke.c \
+    -Ltarget/release -lsolana_sos -Wl,-rpath,"$PWD/target/release" \
+    -o target/c/abi_smoke
+
+echo "🧪 Running C smoke test..."
+./target/c/abi_smoke
+
+echo "🎉 C ABI build complete!"
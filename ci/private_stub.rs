@@ -0,0 +1,123 @@
+//! Build stub of the closed-source `src/private` modules
+//!
+//! CI copies this file to `src/private/mod.rs` so the public tree can be
+//! built, linted and tested without the private implementation. It mirrors
+//! only the signatures the public crate calls; keep it in step with them,
+//! and never commit it under `src/private`.
+
+#![allow(clippy::new_without_default)]
+
+/// Emergency protocols
+pub mod emergency_database {
+    /// One emergency's protocol
+    #[derive(Debug, Clone)]
+    pub struct EmergencyProtocol {
+        pub emergency_type: String,
+        pub severity: String,
+        pub steps: Vec<String>,
+    }
+
+    /// Protocols by emergency type
+    pub struct EmergencyDatabase(Vec<String>);
+
+    impl EmergencyDatabase {
+        pub fn new() -> Self {
+            Self((0..15).map(|i| format!("emergency_{}", i)).collect())
+        }
+
+        pub fn list_emergency_types(&self) -> Vec<&String> {
+            self.0.iter().collect()
+        }
+
+        pub fn get_protocol(&self, _emergency_type: &str) -> Option<&EmergencyProtocol> {
+            None
+        }
+    }
+}
+
+/// Context-aware guidance
+pub mod context_analysis {
+    /// What is known about an emergency
+    pub struct EmergencyContext {
+        pub context_flags: Vec<String>,
+        pub current_step: u32,
+    }
+
+    /// Picks the next instruction from what the user said
+    pub struct ContextAnalyzer;
+
+    impl ContextAnalyzer {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn get_emergency_types(&self) -> Vec<String> {
+            vec!["heart_attack".to_string()]
+        }
+
+        pub fn analyze_emergency(&self, _emergency_type: &str, _user_input: &str) -> EmergencyContext {
+            EmergencyContext { context_flags: vec!["not_breathing".to_string()], current_step: 0 }
+        }
+
+        pub fn get_next_instruction(&self, _context: &EmergencyContext) -> Option<String> {
+            Some("Start CPR".to_string())
+        }
+
+        pub fn should_call_911(&self, _context: &EmergencyContext) -> bool {
+            true
+        }
+
+        pub fn get_emergency_summary(&self, _context: &EmergencyContext) -> Option<String> {
+            None
+        }
+    }
+}
+
+/// Emergency calls
+pub mod emergency_calling {
+    use std::fmt;
+
+    /// Contact told about a call
+    #[derive(Debug, Clone)]
+    pub struct EmergencyContact {
+        pub name: String,
+        pub phone_number: String,
+        pub relationship: String,
+        pub notification_enabled: bool,
+    }
+
+    /// One placed call
+    #[derive(Debug, Clone)]
+    pub struct EmergencyCall;
+
+    /// Why a call failed
+    #[derive(Debug)]
+    pub struct EmergencyCallError;
+
+    impl fmt::Display for EmergencyCallError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("emergency call failed")
+        }
+    }
+
+    impl std::error::Error for EmergencyCallError {}
+
+    /// Places calls and keeps their history
+    pub struct EmergencyCaller(Vec<EmergencyCall>);
+
+    impl EmergencyCaller {
+        pub fn new() -> Self {
+            Self(Vec::new())
+        }
+
+        pub async fn call_911(&mut self, _emergency_type: &str, _context_flags: &[String]) -> Result<String, EmergencyCallError> {
+            Ok(String::new())
+        }
+
+        pub fn add_emergency_contact(&mut self, _contact: EmergencyContact) {}
+
+        pub fn get_call_history(&self) -> &Vec<EmergencyCall> {
+            &self.0
+        }
+    }
+}
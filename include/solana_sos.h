@@ -0,0 +1,181 @@
+#ifndef SOLANA_SOS_H
+#define SOLANA_SOS_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/*
+ Sample rate every pushed frame must use (Hz)
+ */
+#define SOS_STREAM_SAMPLE_RATE 16000
+
+/*
+ Samples per frame (30 ms at 16 kHz)
+ */
+#define SOS_STREAM_FRAME_SAMPLES 480
+
+/*
+ Bytes per frame for 16-bit PCM, for sizing direct buffers
+ */
+#define SOS_STREAM_FRAME_BYTES (SOS_STREAM_FRAME_SAMPLES * 2)
+
+/*
+ Status code returned by every fallible C ABI function
+ */
+typedef enum SosStatus {
+  /*
+   Call succeeded
+   */
+  SOS_STATUS_OK = 0,
+  /*
+   A required pointer argument was null
+   */
+  SOS_STATUS_NULL_ARGUMENT = 1,
+  /*
+   A string argument was not valid UTF-8
+   */
+  SOS_STATUS_INVALID_UTF8 = 2,
+  /*
+   An argument was rejected by the engine
+   */
+  SOS_STATUS_INVALID_INPUT = 3,
+  /*
+   The engine failed internally
+   */
+  SOS_STATUS_INTERNAL = 4,
+} SosStatus;
+
+/*
+ Emergency engine handle owned by the foreign caller
+ */
+typedef struct SosEngine SosEngine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Create a new engine
+
+ Returns null if the engine fails to initialize.
+ */
+struct SosEngine *sos_engine_new(void);
+
+/*
+ Release an engine
+
+ Null, already freed and unknown pointers are ignored.
+
+ # Safety
+ `engine` must be null or a pointer returned by `sos_engine_new`.
+ */
+void sos_engine_free(struct SosEngine *engine);
+
+/*
+ Get the message for the last call on this engine, if it failed
+
+ The returned string is owned by the engine and valid until the next call
+ on it. Returns null if the last call succeeded.
+
+ # Safety
+ `engine` must be null or a live handle.
+ */
+const char *sos_engine_last_error(const struct SosEngine *engine);
+
+/*
+ Process an emergency and return the response as a JSON object
+
+ The JSON has the keys `instruction`, `should_call_emergency_services`, `emergency_summary`,
+ `context_flags` and `current_step`.
+
+ # Safety
+ `engine` must be a live handle, the string arguments must be NUL-terminated,
+ and `out_json` must point to writable storage for one pointer.
+ */
+enum SosStatus sos_engine_process_emergency(struct SosEngine *engine,
+                                            const char *emergency_type,
+                                            const char *user_input,
+                                            char **out_json);
+
+/*
+ Push mono 16 kHz 16-bit PCM samples into the streaming voice pipeline
+
+ Any length is accepted; samples are processed in `SOS_STREAM_FRAME_SAMPLES`
+ frames and a partial frame is kept for the next call. On a detection
+ `*out_text` receives the recognized text; otherwise it is set to null.
+
+ # Safety
+ `engine` must be a live handle, `samples` must point to `len` readable
+ samples (or be null when `len` is 0), and `out_text` must point to writable
+ storage for one pointer.
+ */
+enum SosStatus sos_engine_push_audio(struct SosEngine *engine,
+                                     const int16_t *samples,
+                                     size_t len,
+                                     char **out_text);
+
+/*
+ Add an emergency contact
+
+ `phone` may be local to the US or include a country code; it is stored in
+ E.164 format. Invalid or duplicate numbers return `SOS_STATUS_INVALID_INPUT`.
+
+ # Safety
+ `engine` must be a live handle and the string arguments NUL-terminated.
+ */
+enum SosStatus sos_engine_add_contact(struct SosEngine *engine,
+                                      const char *name,
+                                      const char *phone,
+                                      const char *relationship);
+
+/*
+ Get the supported emergency types as a JSON array of strings
+
+ # Safety
+ `engine` must be a live handle and `out_json` must point to writable storage for one pointer.
+ */
+enum SosStatus sos_engine_emergency_types(struct SosEngine *engine, char **out_json);
+
+/*
+ Get the JSON Schema (draft 7) describing every configuration key
+
+ # Safety
+ `out_json` must point to writable storage for one pointer.
+ */
+enum SosStatus sos_config_schema(char **out_json);
+
+/*
+ Check settings edits against the configuration schema before persisting them
+
+ `edits_json` is a JSON object of dotted keys, e.g. `{"ui.theme": "dark"}`.
+ `*out_errors` receives a JSON array of `{"key", "message"}` objects, which
+ is empty when every edit is valid. Returns `SOS_STATUS_INVALID_INPUT` if
+ `edits_json` is not a JSON object.
+
+ # Safety
+ `edits_json` must be NUL-terminated and `out_errors` must point to
+ writable storage for one pointer.
+ */
+enum SosStatus sos_settings_validate(const char *edits_json, char **out_errors);
+
+/*
+ Release a string returned by this library
+
+ # Safety
+ `value` must be null or a string returned through an `out` parameter of
+ this library that has not been freed.
+ */
+void sos_string_free(char *value);
+
+/*
+ Get the library version as a static string
+ */
+const char *sos_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SOLANA_SOS_H */
@@ -3,60 +3,501 @@
 //! This module provides the main application interface for Solana SOS.
 //! Implementation details are hidden to protect proprietary algorithms.
 
-use crate::error::AppResult;
+use crate::call_script::CallScript;
+use crate::config::AppConfig;
+use crate::countdown::CancelSource;
+use crate::dialer::{CallState, EmergencyDialer, LocalDialer};
+use crate::diagnostics::{Diagnostics, HealthReport, MicState};
+use crate::duress::{CancelCodes, CodeMatch};
+use crate::emergency_numbers::{CountryHints, NumberSelection};
+use crate::error::{AppError, AppResult};
+use crate::contacts::NotificationChannel;
+use crate::event_bus::{AppEvent, EventBus};
+use crate::gesture::InputEvent;
+use crate::location::LocationReport;
+use crate::notifier::{DeliveryRouter, EmailNotifier, PushNotifier, SmsGateway, SmsNotifier};
+use crate::public::voice_interface::Speaker;
+use crate::runtime::{AppRuntime, HostServices, ProtocolCommand, RestartPolicy};
+use crate::secrets::{Secret, SecretStore};
+use crate::sensors::{forward, LiveSensors, SensorSample, SensorSource, LIVE_FEED_DEPTH};
+use crate::settings::{LayeredConfig, SettingSource};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// How long `stop` waits for subsystems to drain
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Main Solana SOS application
 pub struct SolanaSOSApp {
     /// Whether the app is active
     pub is_active: bool,
-    /// App configuration
-    pub config: AppConfig,
-    /// App status
-    pub status: AppStatus,
+    /// Layered subsystem settings, usually starting from `config.toml`
+    settings: Arc<Mutex<LayeredConfig>>,
+    /// App status, shared with the subsystem supervisor
+    status: Arc<watch::Sender<AppStatus>>,
+    /// Latest health report, refreshed by the health monitor
+    health: Arc<watch::Sender<HealthReport>>,
+    /// SIM country and locale from the host, for picking emergency numbers
+    country_hints: Arc<watch::Sender<CountryHints>>,
+    /// Unlocked secret store, if any
+    secrets: Mutex<Option<Box<dyn SecretStore>>>,
+    /// Bus connecting the subsystems
+    event_bus: EventBus,
+    /// Host SMS sending, if the platform provides it
+    sms_gateway: Option<Arc<dyn SmsGateway>>,
+    /// Host voice calling, if the platform provides it
+    dialer: Option<Arc<dyn EmergencyDialer>>,
+    /// Host text-to-speech, if the platform provides it
+    speaker: Option<Arc<dyn Speaker>>,
+    /// Running subsystems
+    runtime: Option<AppRuntime>,
 }
 
 impl SolanaSOSApp {
     /// Creates a new Solana SOS application
     pub async fn new() -> AppResult<Self> {
-        // Implementation details hidden - proprietary app setup
+        Self::with_settings(AppConfig::default()).await
+    }
+
+    /// Creates a new Solana SOS application with the given subsystem settings
+    pub async fn with_settings(settings: AppConfig) -> AppResult<Self> {
+        Self::with_layers(LayeredConfig::from_config(settings)?).await
+    }
+
+    /// Creates a new Solana SOS application from layered settings
+    pub async fn with_layers(layers: LayeredConfig) -> AppResult<Self> {
+        let (status, _) = watch::channel(AppStatus::Initializing);
         Ok(Self {
             is_active: false,
-            config: AppConfig::default(),
-            status: AppStatus::Initializing,
+            settings: Arc::new(Mutex::new(layers)),
+            status: Arc::new(status),
+            health: Arc::new(watch::channel(HealthReport::default()).0),
+            country_hints: Arc::new(watch::channel(CountryHints::default()).0),
+            secrets: Mutex::new(None),
+            event_bus: EventBus::new(),
+            sms_gateway: None,
+            dialer: None,
+            speaker: None,
+            runtime: None,
         })
     }
 
     /// Initializes the application
+    ///
+    /// Runs the startup self-test. A degraded safety net is reported as a
+    /// warning rather than an error so manual SOS stays available.
     pub async fn initialize(&mut self) -> AppResult<()> {
-        // Implementation details hidden - proprietary initialization logic
+        let report = self.self_test();
+        if report.is_degraded() {
+            tracing::warn!("Startup self-test found problems:\n{}", report);
+        } else {
+            tracing::info!("Startup self-test passed");
+        }
+
         self.is_active = true;
-        self.status = AppStatus::Ready;
+        self.status.send_replace(AppStatus::Ready);
         Ok(())
     }
 
     /// Runs the application
+    ///
+    /// Spawns the voice, protocol, connectivity, storage and notification
+    /// subsystems under supervision and returns once they are started.
     pub async fn run(&mut self) -> AppResult<()> {
-        // Implementation details hidden - proprietary app run logic
-        self.status = AppStatus::Running;
+        if self.runtime.is_some() {
+            return Err(AppError::Internal("application is already running".to_string()));
+        }
+
+        let runtime = AppRuntime::start(
+            self.settings.clone(),
+            self.status.clone(),
+            self.health.clone(),
+            self.country_hints.subscribe(),
+            self.event_bus.clone(),
+            HostServices {
+                notifier: self.delivery_router(),
+                dialer: self.emergency_dialer(),
+                speaker: self.speaker.clone().or_else(crate::jni_bridge::speaker),
+            },
+            RestartPolicy::default(),
+        );
+        crate::jni_bridge::attach_delivery_reports(&runtime.notifier());
+        crate::jni_bridge::attach_call_reports(runtime.call_reports());
+        crate::jni_bridge::attach_call_scripts(runtime.call_scripts());
+        crate::jni_bridge::attach_country_hints(&self.country_hints);
+        if let Some(inputs) = runtime.input_sender() {
+            crate::jni_bridge::attach_input_events(inputs);
+        }
+        if let Some(sensors) = runtime.sensor_sender() {
+            let (feed, live) = LiveSensors::new(LIVE_FEED_DEPTH);
+            crate::jni_bridge::attach_sensor_feed(feed);
+            tokio::spawn(forward(live, sensors));
+        }
+        self.runtime = Some(runtime);
+        self.status.send_replace(AppStatus::Running);
+        Ok(())
+    }
+
+    /// Pauses voice listening; other subsystems keep running
+    pub fn pause(&mut self) -> AppResult<()> {
+        let runtime = self.runtime.as_ref()
+            .ok_or_else(|| AppError::Internal("application is not running".to_string()))?;
+        runtime.set_listening(false);
+        self.status.send_replace(AppStatus::Paused);
+        Ok(())
+    }
+
+    /// Resumes voice listening after a pause
+    pub fn resume(&mut self) -> AppResult<()> {
+        let runtime = self.runtime.as_ref()
+            .ok_or_else(|| AppError::Internal("application is not running".to_string()))?;
+        runtime.set_listening(true);
+        self.status.send_replace(AppStatus::Running);
         Ok(())
     }
 
-    /// Stops the application
+    /// Stops the application, draining in-flight work
     pub async fn stop(&mut self) -> AppResult<()> {
-        // Implementation details hidden
+        let result = match self.runtime.take() {
+            Some(runtime) => runtime.shutdown(DRAIN_TIMEOUT).await,
+            None => Ok(()),
+        };
         self.is_active = false;
-        self.status = AppStatus::Stopped;
-        Ok(())
+        self.status.send_replace(AppStatus::Stopped);
+        result
     }
 
     /// Gets application status
     pub fn get_status(&self) -> AppStatus {
-        self.status.clone()
+        self.status.borrow().clone()
+    }
+
+    /// Runs the startup self-test and publishes the result
+    ///
+    /// Microphone checks are skipped until the health monitor is running.
+    fn self_test(&self) -> HealthReport {
+        let report = Diagnostics::from_config(&self.settings()).run(&MicState::NotRunning);
+        let warnings = report.warnings();
+        if warnings != self.health.borrow().warnings() {
+            self.event_bus.publish(AppEvent::HealthChanged { warnings });
+        }
+        self.health.send_replace(report.clone());
+        report
+    }
+
+    /// Gets the effective settings
+    pub fn settings(&self) -> AppConfig {
+        self.lock_settings().effective().clone()
+    }
+
+    /// Overrides a setting until cleared, e.g. `set_setting("audio.emergency_volume", "0.9")`
+    ///
+    /// Running subsystems pick up the change immediately. Invalid values are
+    /// rejected and leave the settings unchanged.
+    pub fn set_setting(&self, key: &str, value: &str) -> AppResult<()> {
+        self.lock_settings().set_runtime(key, value)
+    }
+
+    /// Removes a runtime override, returning whether one was set
+    pub fn clear_setting(&self, key: &str) -> AppResult<bool> {
+        self.lock_settings().clear_runtime(key)
+    }
+
+    /// Validates settings edits and persists them as user settings
+    ///
+    /// `edits` maps dotted keys to JSON values, e.g. `{"ui.theme": "dark"}`.
+    /// Nothing is saved if any edit is rejected.
+    pub fn save_settings(&self, edits: &serde_json::Map<String, serde_json::Value>) -> AppResult<()> {
+        self.lock_settings().persist_user_settings(edits)
+    }
+
+    /// Lists every effective setting and the layer it came from
+    pub fn setting_sources(&self) -> Vec<SettingSource> {
+        self.lock_settings().sources()
+    }
+
+    /// Subscribes to effective settings changes
+    pub fn subscribe_settings(&self) -> watch::Receiver<AppConfig> {
+        self.lock_settings().subscribe()
+    }
+
+    fn lock_settings(&self) -> std::sync::MutexGuard<'_, LayeredConfig> {
+        self.settings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Makes secrets available, e.g. an `EncryptedFileStore` opened with the user's PIN
+    pub fn unlock_secrets(&self, store: Box<dyn SecretStore>) {
+        *self.secrets.lock().unwrap_or_else(|e| e.into_inner()) = Some(store);
+    }
+
+    /// Drops the unlocked secret store
+    pub fn lock_secrets(&self) {
+        *self.secrets.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Gets the API key configured for an online service
+    pub fn api_key(&self, service: &str) -> AppResult<Secret> {
+        let name = self.settings().connectivity.api_keys.get(service).cloned()
+            .ok_or_else(|| AppError::NotFound(format!("no API key configured for '{}'", service)))?;
+        self.secret(&name)
+    }
+
+    /// Gets the wallet keypair
+    pub fn wallet_keypair(&self) -> AppResult<Secret> {
+        let name = self.settings().blockchain.wallet_keypair
+            .ok_or_else(|| AppError::NotFound("no wallet keypair configured".to_string()))?;
+        self.secret(&name)
+    }
+
+    fn secret(&self, name: &crate::secrets::SecretRef) -> AppResult<Secret> {
+        match self.secrets.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            Some(store) => store.require(name),
+            None => Err(AppError::PermissionDenied("secret store is locked".to_string())),
+        }
+    }
+
+    /// Sets how SMS alerts are sent; takes effect on the next `run`
+    ///
+    /// Without a gateway, the one registered through `RustBridge.registerSmsGateway`
+    /// is used, if any.
+    pub fn set_sms_gateway(&mut self, gateway: Arc<dyn SmsGateway>) {
+        self.sms_gateway = Some(gateway);
+    }
+
+    /// Records a delivery report for an alert, e.g. from an SMS delivery intent
+    pub fn confirm_delivery(&self, idempotency_key: &str, delivered: bool, error: Option<String>) {
+        if let Some(runtime) = &self.runtime {
+            runtime.notifier().confirm(idempotency_key, delivered, error);
+        }
+    }
+
+    /// Sets how emergency services are called; takes effect on the next `run`
+    ///
+    /// Without a dialer, the one registered through `RustBridge.registerEmergencyDialer`
+    /// is used, or calls are only logged.
+    pub fn set_emergency_dialer(&mut self, dialer: Arc<dyn EmergencyDialer>) {
+        self.dialer = Some(dialer);
+    }
+
+    /// Sets how questions such as the fall check are spoken; takes effect on the next `run`
+    ///
+    /// Without a speaker, the one registered through `RustBridge.registerSpeaker`
+    /// is used, or questions open the countdown prompts.
+    pub fn set_speaker(&mut self, speaker: Arc<dyn Speaker>) {
+        self.speaker = Some(speaker);
+    }
+
+    /// Records a state the host reported for an emergency call
+    pub fn report_call_state(&self, call_id: &str, state: CallState, error: Option<String>) {
+        if let Some(runtime) = &self.runtime {
+            runtime.call_reports().report(call_id, state, error);
+        }
+    }
+
+    fn emergency_dialer(&self) -> Arc<dyn EmergencyDialer> {
+        self.dialer.clone().or_else(crate::jni_bridge::emergency_dialer).unwrap_or_else(|| {
+            tracing::warn!("No emergency dialer; calls to emergency services will only be logged");
+            Arc::new(LocalDialer::new())
+        })
+    }
+
+    /// Builds the alert delivery adapters from the notification settings
+    ///
+    /// Channels that are not configured, or whose secrets cannot be read,
+    /// get no adapter, so alerts on them fail. Calls are routed by the
+    /// runtime through the emergency dialer.
+    fn delivery_router(&self) -> DeliveryRouter {
+        let settings = self.settings().notifications;
+        let mut router = DeliveryRouter::new();
+
+        if let Some(gateway) = self.sms_gateway.clone().or_else(crate::jni_bridge::sms_gateway) {
+            router = router.route(NotificationChannel::Sms, Arc::new(SmsNotifier::new(gateway)));
+        }
+
+        if let Some(endpoint) = &settings.push_endpoint {
+            match settings.push_token.as_ref().map(|name| self.secret(name)).transpose() {
+                Ok(token) => router = router.route(NotificationChannel::Push, Arc::new(PushNotifier::new(endpoint, token))),
+                Err(e) => tracing::warn!("Push alerts disabled: {}", e),
+            }
+        }
+
+        if let Some(host) = &settings.smtp_host {
+            let email = settings.smtp_password.as_ref().map(|name| self.secret(name)).transpose().and_then(|password| {
+                EmailNotifier::new(
+                    host,
+                    settings.smtp_port,
+                    settings.smtp_username.as_deref(),
+                    password.as_ref(),
+                    &settings.email_from,
+                )
+            });
+            match email {
+                Ok(email) => router = router.route(NotificationChannel::Email, Arc::new(email)),
+                Err(e) => tracing::warn!("Email alerts disabled: {}", e),
+            }
+        }
+        router
+    }
+
+    /// Records that a trusted contact acknowledged the alert, stopping escalation
+    pub fn acknowledge_alert(&self, contact: &str) {
+        self.event_bus.publish(AppEvent::AlertAcknowledged { contact: contact.to_string() });
+    }
+
+    /// Cancels alerts to trusted contacts; alerted contacts are told to stand down
+    pub fn cancel_alerts(&self) {
+        self.event_bus.publish(AppEvent::AlertsCancelled);
+    }
+
+    /// Stops a running crash, fall or auto-dial countdown from the cancel button
+    pub async fn cancel_countdown(&self) -> AppResult<()> {
+        match &self.runtime {
+            Some(runtime) => runtime.cancel_countdown(CancelSource::Button).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Cancels the emergency with the cancel PIN or phrase
+    ///
+    /// Any running countdown stops. The duress code succeeds and publishes
+    /// `AlertsCancelled` just the same, but contacts keep being alerted
+    /// covertly until a genuine cancel code is entered. Any other code is
+    /// `PermissionDenied`.
+    pub async fn cancel_emergency(&self, code: &str) -> AppResult<()> {
+        let emergency = self.settings().emergency;
+        let resolve = |name: &Option<crate::secrets::SecretRef>| {
+            name.as_ref().and_then(|name| match self.secret(name) {
+                Ok(secret) => Some(secret),
+                Err(e) => {
+                    tracing::warn!("Cancel code '{}' unavailable: {}", name.name(), e);
+                    None
+                }
+            })
+        };
+        let (cancel_pin, duress_code) = (resolve(&emergency.cancel_pin), resolve(&emergency.duress_code));
+        let codes = CancelCodes::new(cancel_pin.as_ref(), &emergency.crash_cancel_phrase, duress_code.as_ref())?;
+
+        let (method, duress) = match codes.check(code) {
+            Some(CodeMatch::Cancel(method)) => (method, false),
+            Some(CodeMatch::Duress(method)) => (method, true),
+            None => return Err(AppError::PermissionDenied("incorrect cancel code".to_string())),
+        };
+
+        if let Some(runtime) = &self.runtime {
+            // Set before the cancel is published, so the notification task never stands down
+            runtime.set_duress(duress);
+            runtime.cancel_countdown(if duress { CancelSource::Duress } else { method.into() }).await?;
+            if let Some(commands) = runtime.command_sender() {
+                commands
+                    .send(ProtocolCommand::Cancel { method, duress })
+                    .await
+                    .map_err(|_| AppError::Internal("protocol engine is not running".to_string()))?;
+            }
+        }
+        self.event_bus.publish(AppEvent::AlertsCancelled);
+        Ok(())
+    }
+
+    /// Gets the latest health report
+    pub fn health_report(&self) -> HealthReport {
+        self.health.borrow().clone()
+    }
+
+    /// Subscribes to health report updates
+    pub fn subscribe_health(&self) -> watch::Receiver<HealthReport> {
+        self.health.subscribe()
+    }
+
+    /// Gets the bus subsystems publish events to
+    pub fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
+    /// Subscribes to application status changes
+    pub fn subscribe_status(&self) -> watch::Receiver<AppStatus> {
+        self.status.subscribe()
+    }
+
+    /// Gets a sender for 16 kHz mono PCM audio frames while running
+    pub fn audio_sender(&self) -> Option<mpsc::Sender<Vec<i16>>> {
+        self.runtime.as_ref().and_then(|runtime| runtime.audio_sender())
+    }
+
+    /// Forwards samples from `source` to the sensor monitor until it ends
+    ///
+    /// Samples from the phone arrive through `RustBridge.onSensorSample`
+    /// without this; use it to replay a recorded trace.
+    pub fn attach_sensor_source<S: SensorSource>(&self, source: S) -> AppResult<JoinHandle<u64>> {
+        let sensors = self.sensor_sender()
+            .ok_or_else(|| AppError::Internal("application is not running".to_string()))?;
+        Ok(tokio::spawn(forward(source, sensors)))
+    }
+
+    /// Gets a sender for sensor samples watched for crashes and falls
+    pub fn sensor_sender(&self) -> Option<mpsc::Sender<SensorSample>> {
+        self.runtime.as_ref().and_then(|runtime| runtime.sensor_sender())
+    }
+
+    /// Gets the best known location, labelled with its accuracy and staleness
+    pub fn location(&self) -> Option<LocationReport> {
+        self.runtime.as_ref().and_then(|runtime| runtime.location())
+    }
+
+    /// Gets what to tell the dispatcher about the incident in progress
+    ///
+    /// `address` is the best fix's street address, where the host could
+    /// look one up; the plus code is given either way.
+    pub fn call_script(&self, address: Option<&str>) -> Option<CallScript> {
+        self.runtime.as_ref().and_then(|runtime| runtime.call_scripts().script(address))
+    }
+
+    /// Sets the SIM country and locale, e.g. "ch" and "de-CH"
+    ///
+    /// Emergency numbers are picked by these when there is no fix.
+    pub fn set_country_hints(&self, sim_country: Option<String>, locale: Option<String>) {
+        self.country_hints.send_modify(|hints| {
+            hints.sim_country = sim_country;
+            hints.locale = locale;
+        });
+    }
+
+    /// Gets the emergency numbers of the country the phone is in
+    ///
+    /// The country is taken from the best fix, then the SIM country, the
+    /// locale and finally `emergency.contact_region`.
+    pub fn emergency_numbers(&self) -> NumberSelection {
+        let region = self.settings().emergency.contact_region;
+        match &self.runtime {
+            Some(runtime) => runtime.emergency_numbers(&region),
+            None => self.country_hints.borrow().resolve(&region),
+        }
+    }
+
+    /// Gets a sender for key and tap events matched against the silent SOS gesture
+    pub fn input_sender(&self) -> Option<mpsc::Sender<InputEvent>> {
+        self.runtime.as_ref().and_then(|runtime| runtime.input_sender())
+    }
+
+    /// Starts an emergency without voice input, e.g. from the UI
+    pub async fn trigger_emergency(&self, emergency_type: &str, user_input: &str) -> AppResult<()> {
+        let commands = self.runtime.as_ref()
+            .and_then(|runtime| runtime.command_sender())
+            .ok_or_else(|| AppError::Internal("application is not running".to_string()))?;
+
+        commands
+            .send(ProtocolCommand::Manual {
+                emergency_type: emergency_type.to_string(),
+                user_input: user_input.to_string(),
+            })
+            .await
+            .map_err(|_| AppError::Internal("protocol engine is not running".to_string()))
     }
 }
 
 /// Application status enumeration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AppStatus {
     /// Application is initializing
     Initializing,
@@ -71,42 +512,3 @@ pub enum AppStatus {
     /// Application encountered an error
     Error,
 }
-
-/// Application configuration
-pub struct AppConfig {
-    /// App name
-    pub app_name: String,
-    /// App version
-    pub app_version: String,
-    /// Device name
-    pub device_name: String,
-    /// Whether debug mode is enabled
-    pub debug_mode: bool,
-    /// Log level
-    pub log_level: LogLevel,
-}
-
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            app_name: "Solana SOS".to_string(),
-            app_version: "1.0.0".to_string(),
-            device_name: "Solana SOS Device".to_string(),
-            debug_mode: false,
-            log_level: LogLevel::Info,
-        }
-    }
-}
-
-/// Log level enumeration
-#[derive(Debug, Clone)]
-pub enum LogLevel {
-    /// Debug level
-    Debug,
-    /// Info level
-    Info,
-    /// Warning level
-    Warning,
-    /// Error level
-    Error,
-} 
\ No newline at end of file
@@ -0,0 +1,283 @@
+//! Dispatcher Call Script
+//!
+//! Panicked callers forget their address and what happened. [`CallScript`]
+//! turns the incident state into the few lines a dispatcher asks for, in
+//! the order they ask: where, what, how the patient is, since when and what
+//! has been done. The lines are written for the user to read out from the
+//! screen; [`CallScript::spoken`] says the same in the third person for
+//! text-to-speech into the call when the user can't speak, with the plus
+//! code spelled out in the NATO alphabet.
+//!
+//! Scripts are in English, like text-to-911.
+
+use crate::coordinates::{phonetic, plus_code};
+use crate::location::{format_age, Freshness, LocationReport};
+use crate::public::types::EmergencyType;
+use crate::templates::AlertKind;
+use serde::Serialize;
+
+/// What is known about the incident in progress
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IncidentSummary {
+    /// What the incident is about
+    pub kind: AlertKind,
+    /// Emergency type, if known
+    pub emergency_type: Option<EmergencyType>,
+    /// The user's name; empty if unknown
+    pub name: String,
+    /// When it started
+    pub onset: chrono::DateTime<chrono::FixedOffset>,
+    /// Victim status flags from context analysis and the detectors, e.g. "not_breathing"
+    pub flags: Vec<String>,
+    /// Latest first aid instruction given
+    pub instruction: Option<String>,
+    /// What has been done so far, e.g. "trusted contacts alerted"
+    pub actions: Vec<String>,
+}
+
+impl IncidentSummary {
+    /// Start the summary of an incident starting now, in local time
+    pub fn new(kind: AlertKind, name: &str) -> Self {
+        Self {
+            kind,
+            emergency_type: None,
+            name: name.trim().to_string(),
+            onset: chrono::Local::now().fixed_offset(),
+            flags: Vec::new(),
+            instruction: None,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Set the emergency type
+    pub fn with_emergency_type(mut self, emergency_type: Option<EmergencyType>) -> Self {
+        self.emergency_type = emergency_type;
+        self
+    }
+
+    /// Add status flags not seen yet
+    pub fn flag(&mut self, flags: &[String]) {
+        for flag in flags {
+            if !self.flags.contains(flag) {
+                self.flags.push(flag.clone());
+            }
+        }
+    }
+
+    /// Record an action, once
+    pub fn record(&mut self, action: &str) {
+        if !self.actions.iter().any(|done| done == action) {
+            self.actions.push(action.to_string());
+        }
+    }
+
+    /// What happened, e.g. "car crash"
+    fn what(&self) -> String {
+        let emergency_type = self.emergency_type.as_ref().map(|emergency_type| emergency_type.display_name().to_lowercase());
+        match (self.kind, emergency_type) {
+            (AlertKind::CrashDetected, _) => "car crash".to_string(),
+            (AlertKind::FallDetected, _) => "fall".to_string(),
+            (AlertKind::Duress, _) => "person in danger".to_string(),
+            (AlertKind::SilentSos, None) => "silent SOS".to_string(),
+            (_, Some(emergency_type)) => emergency_type,
+            (_, None) => "medical emergency".to_string(),
+        }
+    }
+
+    /// The patient's condition from the status flags, e.g. "not breathing"
+    ///
+    /// Flags covered by what happened are left out.
+    fn conditions(&self) -> Vec<String> {
+        self.flags
+            .iter()
+            .filter(|flag| !flag.contains(':'))
+            .filter_map(|flag| match flag.as_str() {
+                "crash_detected" | "fall_detected" | "silent" => None,
+                "no_response" => Some("not responding".to_string()),
+                flag => Some(flag.replace('_', " ")),
+            })
+            .collect()
+    }
+
+    /// Detector readings from the status flags, e.g. "impact of 11.4 g"
+    ///
+    /// Readings the script doesn't know how to say are left out.
+    fn readings(&self) -> Vec<String> {
+        self.flags
+            .iter()
+            .filter_map(|flag| match flag.split_once(':')? {
+                ("peak_g" | "impact_g", g) => Some(format!("impact of {} g", g)),
+                ("speed_mph", mph) => Some(format!("at {} mph", mph)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// What to tell the dispatcher
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CallScript {
+    /// Lines for the user to read out, in order
+    pub lines: Vec<String>,
+    /// The same facts for text-to-speech, for when the user can't speak
+    pub spoken: String,
+}
+
+impl CallScript {
+    /// Write the script for `summary` at `now`
+    ///
+    /// The location is given by `address` where the host could look one
+    /// up, and by its plus code either way.
+    pub fn new(
+        summary: &IncidentSummary,
+        location: Option<&LocationReport>,
+        address: Option<&str>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        let mut lines = Vec::new();
+        let mut spoken = vec![match summary.name.as_str() {
+            "" => "This is an automated emergency message. The caller can't speak.".to_string(),
+            name => format!("This is an automated message from the phone of {}, who can't speak.", name),
+        }];
+
+        let address = address.map(str::trim).filter(|address| !address.is_empty());
+        match location {
+            Some(report) => {
+                let code = plus_code(report.latitude, report.longitude);
+                let coords = format!("{:.5}, {:.5}", report.latitude, report.longitude);
+                match address {
+                    Some(address) => {
+                        lines.push(format!("I'm at {}. Plus code {}.", address, code));
+                        spoken.push(format!("The location is {}. Plus code {}.", address, phonetic(&code)));
+                    }
+                    None => {
+                        lines.push(format!("I'm at plus code {}, coordinates {}.", code, coords));
+                        spoken.push(format!("The location is plus code {}. Coordinates {}.", phonetic(&code), coords));
+                    }
+                }
+                let quality = match report.freshness {
+                    Freshness::Live => format!("Accurate to {:.0} meters.", report.accuracy_m),
+                    _ => format!("Position from {} ago, accurate to {:.0} meters.", format_age(report.age_secs), report.accuracy_m),
+                };
+                lines.push(quality.clone());
+                spoken.push(quality);
+                if let Some(estimate) = report.estimate {
+                    let walked = format!("Since then, about {} on foot.", estimate.offset_label());
+                    lines.push(walked.clone());
+                    spoken.push(walked.replace('±', "plus or minus "));
+                }
+            }
+            None => {
+                lines.push("My location is unknown.".to_string());
+                spoken.push("The location is unknown.".to_string());
+            }
+        }
+
+        let what = summary.what();
+        if summary.kind.is_covert() {
+            lines.push(format!("We need help: {}.", what));
+            spoken.push(format!("Help is needed: {}.", what));
+        } else {
+            lines.push(format!("We need an ambulance: {}.", what));
+            spoken.push(format!("An ambulance is needed: {}.", what));
+        }
+
+        let conditions = summary.conditions();
+        if !conditions.is_empty() {
+            let conditions = format!("The patient is {}.", conditions.join(", "));
+            lines.push(conditions.clone());
+            spoken.push(conditions);
+        }
+        let readings = summary.readings().join(" ");
+        if let Some(first) = readings.chars().next() {
+            let readings = format!("{}{}.", first.to_uppercase(), &readings[first.len_utf8()..]);
+            lines.push(readings.clone());
+            spoken.push(readings);
+        }
+
+        let minutes = (now - summary.onset.with_timezone(&chrono::Utc)).num_minutes().max(0);
+        let onset = format!(
+            "It started at {}, {}.",
+            summary.onset.format("%H:%M"),
+            match minutes {
+                0 => "just now".to_string(),
+                1 => "1 minute ago".to_string(),
+                minutes => format!("{} minutes ago", minutes),
+            }
+        );
+        lines.push(onset.clone());
+        spoken.push(onset);
+
+        let mut done = summary.actions.clone();
+        if let Some(instruction) = &summary.instruction {
+            done.push(format!("first aid in progress: {}", instruction.trim_end_matches('.')));
+        }
+        if !done.is_empty() {
+            let done = format!("So far: {}.", done.join("; "));
+            lines.push(done.clone());
+            spoken.push(done);
+        }
+
+        Self { lines, spoken: spoken.join(" ") }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Fix;
+
+    fn onset() -> chrono::DateTime<chrono::FixedOffset> {
+        chrono::DateTime::parse_from_rfc3339("2024-05-01T14:02:00+02:00").unwrap()
+    }
+
+    #[test]
+    fn test_scripts_crash_for_reading_and_speaking() {
+        let mut summary = IncidentSummary { onset: onset(), ..IncidentSummary::new(AlertKind::CrashDetected, "Alex") };
+        summary.flag(&["crash_detected".to_string(), "peak_g:11.4".to_string(), "speed_mph:40".to_string()]);
+        summary.flag(&["no_response".to_string(), "crash_detected".to_string()]);
+        summary.record("trusted contacts alerted");
+        summary.record("trusted contacts alerted");
+
+        let now = onset().with_timezone(&chrono::Utc) + chrono::Duration::minutes(6);
+        let report = Fix::new(47.365590, 8.524997, 12.0, now).unwrap().report(now);
+        let script = CallScript::new(&summary, Some(&report), None, now);
+        assert_eq!(
+            script.lines,
+            vec![
+                "I'm at plus code 8FVC9G8F+6X, coordinates 47.36559, 8.52500.",
+                "Accurate to 12 meters.",
+                "We need an ambulance: car crash.",
+                "The patient is not responding.",
+                "Impact of 11.4 g at 40 mph.",
+                "It started at 14:02, 6 minutes ago.",
+                "So far: trusted contacts alerted.",
+            ]
+        );
+        assert!(script.spoken.starts_with("This is an automated message from the phone of Alex, who can't speak."));
+        assert!(script.spoken.contains(
+            "plus code eight-Foxtrot-Victor-Charlie-nine-Golf-eight-Foxtrot, plus, six-X-ray."
+        ));
+    }
+
+    #[test]
+    fn test_scripts_address_status_and_unknown_location() {
+        let mut summary = IncidentSummary { onset: onset(), ..IncidentSummary::new(AlertKind::Emergency, "") }
+            .with_emergency_type(Some(EmergencyType::HeartAttack));
+        summary.flag(&["not_breathing".to_string(), "unknown_reading:3".to_string()]);
+        summary.instruction = Some("Push hard and fast in the center of the chest.".to_string());
+
+        let now = onset().with_timezone(&chrono::Utc);
+        let report = Fix::new(47.365590, 8.524997, 12.0, now).unwrap().report(now);
+        let script = CallScript::new(&summary, Some(&report), Some(" Bahnhofstrasse 1, Zurich "), now);
+        assert_eq!(script.lines[0], "I'm at Bahnhofstrasse 1, Zurich. Plus code 8FVC9G8F+6X.");
+        assert_eq!(script.lines[2], "We need an ambulance: heart attack.");
+        assert_eq!(script.lines[3], "The patient is not breathing.");
+        assert_eq!(script.lines[4], "It started at 14:02, just now.");
+        assert_eq!(script.lines[5], "So far: first aid in progress: Push hard and fast in the center of the chest.");
+
+        let script = CallScript::new(&summary, None, None, now);
+        assert_eq!(script.lines[0], "My location is unknown.");
+        assert!(script.spoken.starts_with("This is an automated emergency message. The caller can't speak. The location is unknown."));
+    }
+}
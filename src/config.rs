@@ -1,11 +1,23 @@
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{ArrayValidation, InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::path::Path;
 use crate::error::AppResult;
+use crate::secrets::SecretRef;
 use std::collections::HashMap;
 
 /// Main application configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// This is the single configuration model for the app; every key documented
+/// in `config.toml` maps to a field here. Missing keys take their defaults,
+/// while unknown keys and out-of-range values are rejected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct AppConfig {
+    pub app: AppInfoConfig,
     pub voice: VoiceConfig,
     pub audio: AudioConfig,
     pub database: DatabaseConfig,
@@ -14,33 +26,94 @@ pub struct AppConfig {
     pub ui: UIConfig,
     pub blockchain: BlockchainConfig,
     pub connectivity: ConnectivityConfig,
+    pub notifications: NotificationsConfig,
+}
+
+/// Application identity
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
+pub struct AppInfoConfig {
+    /// App name
+    pub name: String,
+    /// App version
+    pub version: String,
+    /// Short description
+    pub description: String,
+    /// Device name shown to the user
+    pub device_name: String,
+    /// Log at debug level whatever `log_level` says
+    pub debug_mode: bool,
+    /// Least severe log level written
+    pub log_level: LogLevel,
+}
+
+/// Log level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    /// Debug level
+    Debug,
+    /// Info level
+    Info,
+    /// Warning level
+    Warning,
+    /// Error level
+    Error,
+}
+
+impl AppInfoConfig {
+    /// The level to log at, taking `debug_mode` into account
+    pub fn tracing_level(&self) -> tracing::Level {
+        if self.debug_mode {
+            return tracing::Level::DEBUG;
+        }
+        match self.log_level {
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Warning => tracing::Level::WARN,
+            LogLevel::Error => tracing::Level::ERROR,
+        }
+    }
 }
 
 /// Voice recognition configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct VoiceConfig {
     /// Path to Vosk model directory
+    #[schemars(length(min = 1))]
     pub model_path: String,
     /// Minimum confidence threshold for trigger detection (0.0-1.0)
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub confidence_threshold: f32,
     /// Emergency phrases to detect
+    #[schemars(length(min = 1))]
     pub emergency_phrases: Vec<String>,
     /// Audio sample rate (Hz)
+    #[schemars(range(min = 8_000, max = 48_000))]
     pub sample_rate: u32,
     /// Buffer size for audio processing
+    #[schemars(range(min = 1))]
     pub buffer_size: usize,
     /// Enable continuous listening
     pub continuous_listening: bool,
     /// Timeout for voice detection (seconds)
+    #[schemars(range(min = 1))]
     pub detection_timeout: u64,
 }
 
-/// Audio processing configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Audio configuration for playback, recording and voice preprocessing
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct AudioConfig {
     /// Default volume level (0.0-1.0)
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub default_volume: f32,
     /// Emergency volume level (0.0-1.0)
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub emergency_volume: f32,
     /// Text-to-speech voice
     pub tts_voice: String,
@@ -49,28 +122,49 @@ pub struct AudioConfig {
     /// Enable audio recording
     pub enable_recording: bool,
     /// Recording quality (Hz)
+    #[schemars(range(min = 8_000, max = 192_000))]
     pub recording_sample_rate: u32,
     /// Recording format
+    #[schemars(schema_with = "recording_format_schema")]
     pub recording_format: String,
+    /// Enable noise filtering before voice recognition
+    pub enable_noise_filtering: bool,
+    /// Enable audio enhancement before voice recognition
+    pub enable_enhancement: bool,
 }
 
 /// Database configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct DatabaseConfig {
+    #[schemars(length(min = 1))]
     pub path: String,
     pub emergency_instructions_path: String,
     pub user_profiles_path: String,
+    #[schemars(length(min = 1))]
     pub emergency_history_path: String,
+    /// Persisted user settings, layered over the config file
+    pub user_settings_path: String,
+    /// Persisted emergency contacts
+    pub contacts_path: String,
+    /// Last known good location, kept across restarts
+    pub last_location_path: String,
 }
 
 /// Connectivity configuration for hybrid architecture
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct ConnectivityConfig {
-    /// User preference for connectivity mode
-    pub user_preference: Option<String>, // "offline", "online", "hybrid"
+    /// User preference for connectivity mode (offline/online/hybrid)
+    #[schemars(schema_with = "user_preference_schema")]
+    pub user_preference: Option<String>,
     /// Network timeout in seconds
+    #[schemars(range(min = 1))]
     pub network_timeout: u64,
     /// Connectivity check interval in seconds
+    #[schemars(range(min = 1))]
     pub check_interval: u64,
     /// Enable automatic mode switching
     pub auto_switch: bool,
@@ -78,68 +172,205 @@ pub struct ConnectivityConfig {
     pub offline_fallback: bool,
     /// LLM API endpoints for online mode
     pub llm_endpoints: Vec<String>,
-    /// API keys for online services
-    pub api_keys: std::collections::HashMap<String, String>,
+    /// Secret store entries holding the API key of each online service
+    pub api_keys: std::collections::HashMap<String, SecretRef>,
+}
+
+/// Alert delivery configuration
+///
+/// SMS goes through the host's SMS gateway and needs no settings here. Push
+/// and email are off until their endpoint or relay is set.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// HTTP endpoint that receives push alerts as JSON
+    pub push_endpoint: Option<String>,
+    /// Secret store entry holding the bearer token for the push endpoint
+    pub push_token: Option<SecretRef>,
+    /// SMTP relay for email alerts (STARTTLS)
+    pub smtp_host: Option<String>,
+    /// SMTP relay port
+    #[schemars(range(min = 1))]
+    pub smtp_port: u16,
+    /// SMTP login
+    pub smtp_username: Option<String>,
+    /// Secret store entry holding the SMTP password
+    pub smtp_password: Option<SecretRef>,
+    /// Sender address for email alerts
+    pub email_from: String,
 }
 
 /// Device coordination configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct CoordinationConfig {
     /// Bluetooth service UUID
     pub service_uuid: String,
     /// Coordination timeout (seconds)
+    #[schemars(range(min = 1))]
     pub coordination_timeout: u64,
     /// Maximum nearby devices to coordinate with
+    #[schemars(range(min = 1))]
     pub max_nearby_devices: u32,
     /// Enable automatic device discovery
     pub enable_discovery: bool,
     /// Device name for BLE advertising
     pub device_name: String,
     /// Battery level threshold for coordination decisions
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub battery_threshold: f32,
 }
 
+/// How a silent SOS is triggered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SilentSosMethod {
+    /// Hold a button for `silent_sos_hold_duration` seconds
+    HoldButton,
+    /// Press the power button in `silent_sos_power_sequence`
+    PowerSequence,
+    /// Press the volume keys in `silent_sos_volume_sequence`
+    VolumeSequence,
+    /// Tap a pattern on the screen
+    ScreenTap,
+    /// Perform a motion gesture
+    MotionGesture,
+}
+
+/// How readily a fall is detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FallSensitivity {
+    /// Only long drops with a hard landing
+    Low,
+    /// Typical falls from standing
+    Medium,
+    /// Also slumps and soft landings; more false alarms
+    High,
+}
+
 /// Emergency response configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct EmergencyConfig {
-    /// Enable automatic 911 dialing
+    /// Enable automatic emergency services dialing, on the local number
     pub auto_dial_911: bool,
+    /// Time to cancel before an automatic emergency call (seconds); 0 calls at once
+    #[schemars(range(max = 120))]
+    pub auto_dial_countdown: u64,
+    /// Speak the dispatcher call script into emergency calls the user can't
+    /// talk on: after a crash nobody cancelled, and after a silent SOS,
+    /// which then also calls
+    pub automated_voice_message: bool,
     /// Enable location sharing
     pub enable_location_sharing: bool,
+    /// How often the location is shared during an emergency (seconds);
+    /// halved while driving, up to four times longer while staying put
+    #[schemars(range(min = 5, max = 600))]
+    pub location_update_interval: u64,
     /// Enable audio recording during emergency
     pub enable_audio_recording: bool,
     /// Recording duration (seconds)
     pub recording_duration: u64,
-    /// Emergency contact numbers
+    /// Emergency contact numbers; emergency services are picked by country
     pub emergency_contacts: Vec<String>,
+    /// Your name as contacts see it in alerts
+    pub owner_name: String,
     /// Enable family alerts
     pub enable_family_alerts: bool,
     /// Alert timeout (seconds)
+    #[schemars(range(min = 1))]
     pub alert_timeout: u64,
+
+    /// Enable silent SOS
+    pub enable_silent_sos: bool,
+    /// Silent SOS trigger method
+    pub silent_sos_method: SilentSosMethod,
+    /// Button hold time for `hold_button` (seconds)
+    #[schemars(range(min = 1, max = 30))]
+    pub silent_sos_hold_duration: u64,
+    /// Power button presses for `power_sequence` (1 per press)
+    #[schemars(length(min = 1))]
+    pub silent_sos_power_sequence: Vec<i32>,
+    /// Volume key presses for `volume_sequence` (1 up, -1 down)
+    #[schemars(length(min = 1))]
+    pub silent_sos_volume_sequence: Vec<i32>,
+
+    /// Enable crash detection
+    pub enable_crash_detection: bool,
+    /// Speed before impact that counts as a crash (mph)
+    #[schemars(range(min = 1.0, max = 200.0))]
+    pub crash_speed_threshold: f32,
+    /// Impact that counts as a crash (g-force)
+    #[schemars(range(min = 0.5, max = 50.0))]
+    pub crash_impact_threshold: f32,
+    /// Time to cancel before emergency services are called (seconds)
+    #[schemars(range(min = 5, max = 300))]
+    pub crash_response_window: u64,
+    /// Phrase that cancels a crash response
+    pub crash_cancel_phrase: String,
+
+    /// Enable fall detection
+    pub enable_fall_detection: bool,
+    /// How readily a fall is detected
+    pub fall_sensitivity: FallSensitivity,
+    /// Time to answer "Did you fall?" before contacts are alerted (seconds)
+    #[schemars(range(min = 10, max = 300))]
+    pub fall_check_timeout: u64,
+
+    /// Secret store entry holding the PIN that cancels an emergency
+    pub cancel_pin: Option<SecretRef>,
+    /// Secret store entry holding the duress PIN or phrase, which appears
+    /// to cancel while alerts continue covertly
+    pub duress_code: Option<SecretRef>,
+
+    /// Enable the trusted network
+    pub enable_trusted_network: bool,
+    /// Maximum trusted contacts
+    #[schemars(range(min = 1, max = 100))]
+    pub max_trusted_contacts: u32,
+    /// Region for contact numbers without a country code (ISO 3166 code, e.g. "US")
+    #[schemars(length(min = 2, max = 2))]
+    pub contact_region: String,
+    /// Share location with new trusted contacts by default
+    pub default_location_sharing: bool,
+    /// Events new trusted contacts are notified about by default
+    #[schemars(schema_with = "notification_events_schema")]
+    pub default_notification_preferences: Vec<String>,
 }
 
 /// UI configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct UIConfig {
-    /// UI theme (light/dark/auto)
+    /// UI theme (light/dark/auto/emergency)
+    #[schemars(schema_with = "theme_schema")]
     pub theme: String,
     /// Enable animations
     pub enable_animations: bool,
     /// UI refresh rate (Hz)
+    #[schemars(range(min = 1, max = 240))]
     pub refresh_rate: u32,
     /// Enable accessibility features
     pub enable_accessibility: bool,
     /// Language for UI text
+    #[schemars(length(min = 1))]
     pub language: String,
 }
 
 /// Blockchain configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct BlockchainConfig {
     /// Solana RPC endpoint
     pub rpc_endpoint: String,
-    /// Wallet keypair path
-    pub wallet_path: Option<String>,
+    /// Secret store entry holding the wallet keypair
+    pub wallet_keypair: Option<SecretRef>,
     /// Program ID for Solana SOS
     pub program_id: String,
     /// Enable blockchain features
@@ -148,28 +379,351 @@ pub struct BlockchainConfig {
     pub gas_fee_limit: u64,
 }
 
+/// A problem found in a configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// Dotted key, e.g. "voice.confidence_threshold"
+    pub key: String,
+    /// Line in the source file, when known
+    pub line: Option<usize>,
+    /// What is wrong
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}: {}", line, self.key, self.message),
+            None => write!(f, "{}: {}", self.key, self.message),
+        }
+    }
+}
+
+/// Events a trusted contact can be notified about
+const NOTIFICATION_EVENTS: &[&str] = &["silent_sos", "crash_detection", "emergency_activation"];
+
+/// Supported UI themes
+const UI_THEMES: &[&str] = &["light", "dark", "auto", "emergency"];
+
+/// Supported recording formats
+const RECORDING_FORMATS: &[&str] = &["wav", "flac", "ogg"];
+
+/// Connectivity modes a user can pin
+const CONNECTIVITY_PREFERENCES: &[&str] = &["offline", "online", "hybrid"];
+
+fn string_enum(values: &[&str]) -> SchemaObject {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        enum_values: Some(values.iter().map(|value| serde_json::json!(value)).collect()),
+        ..Default::default()
+    }
+}
+
+fn theme_schema(_: &mut SchemaGenerator) -> Schema {
+    string_enum(UI_THEMES).into()
+}
+
+fn recording_format_schema(_: &mut SchemaGenerator) -> Schema {
+    string_enum(RECORDING_FORMATS).into()
+}
+
+fn user_preference_schema(_: &mut SchemaGenerator) -> Schema {
+    let mut schema = string_enum(CONNECTIVITY_PREFERENCES);
+    schema.instance_type = Some(vec![InstanceType::String, InstanceType::Null].into());
+    schema.enum_values.get_or_insert_with(Vec::new).push(serde_json::Value::Null);
+    schema.into()
+}
+
+fn notification_events_schema(_: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(Schema::Object(string_enum(NOTIFICATION_EVENTS)).into()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
 impl AppConfig {
     /// Load configuration from file
+    ///
+    /// The file is checked strictly, then `CRISIS_COMPANION_*` environment
+    /// variables are applied on top. Use [`LayeredConfig`] to also apply user
+    /// settings and runtime overrides, or to watch for changes.
+    ///
+    /// [`LayeredConfig`]: crate::settings::LayeredConfig
     pub fn load<P: AsRef<Path>>(path: P) -> AppResult<Self> {
-        let settings = config::Config::builder()
-            .add_source(config::File::from(path.as_ref()))
-            .add_source(config::Environment::with_prefix("CRISIS_COMPANION"))
-            .build()?;
-        
-        Ok(settings.try_deserialize()?)
+        Ok(crate::settings::LayeredConfig::load(path)?.effective().clone())
     }
-    
-    /// Get default configuration
-    pub fn default() -> Self {
+
+    /// Parse and validate TOML configuration, reporting every problem found
+    pub fn from_toml_str(source: &str) -> Result<Self, Vec<ConfigIssue>> {
+        let mut unknown = Vec::new();
+        let parsed: Result<Self, toml::de::Error> = serde_ignored::deserialize(
+            toml::Deserializer::new(source),
+            |path| unknown.push(path.to_string()),
+        );
+
+        let config = match parsed {
+            Ok(config) => config,
+            Err(e) => {
+                return Err(vec![ConfigIssue {
+                    key: "<file>".to_string(),
+                    line: e.span().map(|span| line_of_offset(source, span.start)),
+                    message: e.message().to_string(),
+                }]);
+            }
+        };
+
+        let mut issues: Vec<ConfigIssue> = unknown
+            .into_iter()
+            .map(|key| ConfigIssue {
+                line: locate_key(source, &key),
+                message: "unknown key".to_string(),
+                key,
+            })
+            .collect();
+        for mut issue in config.validate() {
+            issue.line = locate_key(source, &issue.key);
+            issues.push(issue);
+        }
+
+        if issues.is_empty() {
+            Ok(config)
+        } else {
+            issues.sort_by_key(|issue| issue.line.unwrap_or(usize::MAX));
+            Err(issues)
+        }
+    }
+
+    /// Check every value against its allowed range
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Issues::default();
+
+        let voice = &self.voice;
+        issues.non_empty("voice.model_path", &voice.model_path);
+        issues.fraction("voice.confidence_threshold", voice.confidence_threshold);
+        issues.check("voice.emergency_phrases", !voice.emergency_phrases.is_empty(), "must list at least one phrase");
+        issues.range("voice.sample_rate", voice.sample_rate, 8_000, 48_000);
+        issues.check("voice.buffer_size", voice.buffer_size > 0, "must be greater than 0");
+        issues.check("voice.detection_timeout", voice.detection_timeout > 0, "must be greater than 0");
+
+        let audio = &self.audio;
+        issues.fraction("audio.default_volume", audio.default_volume);
+        issues.fraction("audio.emergency_volume", audio.emergency_volume);
+        issues.range("audio.recording_sample_rate", audio.recording_sample_rate, 8_000, 192_000);
+        issues.one_of("audio.recording_format", &audio.recording_format, RECORDING_FORMATS);
+
+        issues.non_empty("database.path", &self.database.path);
+        issues.non_empty("database.emergency_history_path", &self.database.emergency_history_path);
+
+        let coordination = &self.coordination;
+        issues.check(
+            "coordination.service_uuid",
+            uuid::Uuid::parse_str(&coordination.service_uuid).is_ok(),
+            "must be a UUID",
+        );
+        issues.check("coordination.coordination_timeout", coordination.coordination_timeout > 0, "must be greater than 0");
+        issues.check("coordination.max_nearby_devices", coordination.max_nearby_devices > 0, "must be greater than 0");
+        issues.fraction("coordination.battery_threshold", coordination.battery_threshold);
+
+        let emergency = &self.emergency;
+        issues.check("emergency.alert_timeout", emergency.alert_timeout > 0, "must be greater than 0");
+        issues.range("emergency.silent_sos_hold_duration", emergency.silent_sos_hold_duration, 1, 30);
+        issues.check(
+            "emergency.silent_sos_power_sequence",
+            !emergency.silent_sos_power_sequence.is_empty() && emergency.silent_sos_power_sequence.iter().all(|press| *press == 1),
+            "must be a non-empty list of 1s",
+        );
+        issues.check(
+            "emergency.silent_sos_volume_sequence",
+            !emergency.silent_sos_volume_sequence.is_empty()
+                && emergency.silent_sos_volume_sequence.iter().all(|press| *press == 1 || *press == -1),
+            "must be a non-empty list of 1 (up) and -1 (down)",
+        );
+        issues.range("emergency.auto_dial_countdown", emergency.auto_dial_countdown, 0, 120);
+        issues.range("emergency.location_update_interval", emergency.location_update_interval, 5, 600);
+        issues.range("emergency.crash_speed_threshold", emergency.crash_speed_threshold, 1.0, 200.0);
+        issues.range("emergency.crash_impact_threshold", emergency.crash_impact_threshold, 0.5, 50.0);
+        issues.range("emergency.crash_response_window", emergency.crash_response_window, 5, 300);
+        if emergency.enable_crash_detection || emergency.enable_fall_detection {
+            issues.non_empty("emergency.crash_cancel_phrase", &emergency.crash_cancel_phrase);
+        }
+        issues.range("emergency.fall_check_timeout", emergency.fall_check_timeout, 10, 300);
+        if let (Some(cancel_pin), Some(duress_code)) = (&emergency.cancel_pin, &emergency.duress_code) {
+            issues.check("emergency.duress_code", cancel_pin != duress_code, "must name a different secret than emergency.cancel_pin");
+        }
+        issues.range("emergency.max_trusted_contacts", emergency.max_trusted_contacts, 1, 100);
+        issues.check(
+            "emergency.contact_region",
+            crate::contacts::parse_region(&emergency.contact_region).is_ok(),
+            "must be a region code such as \"US\"",
+        );
+        for preference in &emergency.default_notification_preferences {
+            issues.one_of("emergency.default_notification_preferences", preference, NOTIFICATION_EVENTS);
+        }
+
+        let ui = &self.ui;
+        issues.one_of("ui.theme", &ui.theme, UI_THEMES);
+        issues.range("ui.refresh_rate", ui.refresh_rate, 1, 240);
+        issues.non_empty("ui.language", &ui.language);
+
+        if self.blockchain.enable_blockchain {
+            issues.url("blockchain.rpc_endpoint", &self.blockchain.rpc_endpoint);
+            issues.non_empty("blockchain.program_id", &self.blockchain.program_id);
+        }
+
+        let connectivity = &self.connectivity;
+        if let Some(preference) = &connectivity.user_preference {
+            issues.one_of("connectivity.user_preference", preference, CONNECTIVITY_PREFERENCES);
+        }
+        issues.check("connectivity.network_timeout", connectivity.network_timeout > 0, "must be greater than 0");
+        issues.check("connectivity.check_interval", connectivity.check_interval > 0, "must be greater than 0");
+        for endpoint in &connectivity.llm_endpoints {
+            issues.url("connectivity.llm_endpoints", endpoint);
+        }
+
+        let notifications = &self.notifications;
+        if let Some(endpoint) = &notifications.push_endpoint {
+            issues.url("notifications.push_endpoint", endpoint);
+        }
+        if notifications.smtp_host.is_some() {
+            issues.check("notifications.smtp_port", notifications.smtp_port > 0, "must be greater than 0");
+            issues.check(
+                "notifications.email_from",
+                notifications.email_from.contains('@'),
+                "must be an email address",
+            );
+        }
+
+        // Template values left in place are never valid, even in disabled sections
+        if let Ok(toml::Value::Table(table)) = toml::Value::try_from(self) {
+            find_placeholders("", &toml::Value::Table(table), &mut issues);
+        }
+
+        issues.0
+    }
+}
+
+/// Collects validation issues
+#[derive(Default)]
+struct Issues(Vec<ConfigIssue>);
+
+impl Issues {
+    fn check(&mut self, key: &str, ok: bool, message: &str) {
+        if !ok {
+            self.0.push(ConfigIssue { key: key.to_string(), line: None, message: message.to_string() });
+        }
+    }
+
+    fn fraction(&mut self, key: &str, value: f32) {
+        self.check(key, (0.0..=1.0).contains(&value), &format!("{} is outside 0.0..=1.0", value));
+    }
+
+    fn range<T: PartialOrd + fmt::Display>(&mut self, key: &str, value: T, min: T, max: T) {
+        let ok = value >= min && value <= max;
+        self.check(key, ok, &format!("{} is outside {}..={}", value, min, max));
+    }
+
+    fn non_empty(&mut self, key: &str, value: &str) {
+        self.check(key, !value.trim().is_empty(), "must not be empty");
+    }
+
+    fn one_of(&mut self, key: &str, value: &str, allowed: &[&str]) {
+        self.check(key, allowed.contains(&value), &format!("\"{}\" is not one of {}", value, allowed.join(", ")));
+    }
+
+    fn url(&mut self, key: &str, value: &str) {
+        let ok = value.starts_with("https://") || value.starts_with("http://");
+        self.check(key, ok, &format!("\"{}\" is not an http(s) URL", value));
+    }
+}
+
+/// Report template values such as `YOUR_SERVICE_UUID_HERE`
+fn find_placeholders(key: &str, value: &toml::Value, issues: &mut Issues) {
+    match value {
+        toml::Value::String(text) => {
+            let upper = text.trim().to_uppercase();
+            let placeholder = upper.starts_with("YOUR_") || upper.ends_with("_HERE") || upper == "CHANGE_ME";
+            issues.check(key, !placeholder, &format!("placeholder value \"{}\" must be replaced", text));
+        }
+        toml::Value::Array(items) => {
+            for item in items {
+                find_placeholders(key, item, issues);
+            }
+        }
+        toml::Value::Table(table) => {
+            for (name, item) in table {
+                let child = if key.is_empty() { name.clone() } else { format!("{}.{}", key, name) };
+                find_placeholders(&child, item, issues);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Format issues as `file:line: key: message` lines
+pub(crate) fn format_issues(origin: &str, issues: &[ConfigIssue]) -> String {
+    let lines: Vec<String> = issues
+        .iter()
+        .map(|issue| match issue.line {
+            Some(line) => format!("{}:{}: {}: {}", origin, line, issue.key, issue.message),
+            None => format!("{}: {}: {}", origin, issue.key, issue.message),
+        })
+        .collect();
+    format!("{} problem(s) in configuration\n{}", issues.len(), lines.join("\n"))
+}
+
+/// 1-based line containing a byte offset
+fn line_of_offset(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+/// Find the line that sets a dotted key such as "emergency.crash_speed_threshold"
+///
+/// Keys inside inline tables resolve to the line of the enclosing key.
+fn locate_key(source: &str, dotted: &str) -> Option<usize> {
+    let segments: Vec<&str> = dotted.split('.').collect();
+    let mut section: Vec<String> = Vec::new();
+    let mut best = None;
+
+    for (index, raw) in source.lines().enumerate() {
+        let line = raw.trim();
+        if let Some(header) = line.strip_prefix('[').and_then(|rest| rest.split(']').next()) {
+            section = header.trim().trim_matches('[').split('.').map(|part| part.trim().to_string()).collect();
+            if section.iter().map(String::as_str).eq(segments.iter().copied()) {
+                return Some(index + 1);
+            }
+            continue;
+        }
+
+        let Some((key, _)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().trim_matches('"');
+        let depth = section.len();
+        let in_section = depth < segments.len() && section.iter().map(String::as_str).eq(segments[..depth].iter().copied());
+        if in_section && key == segments[depth] {
+            if depth + 1 == segments.len() {
+                return Some(index + 1);
+            }
+            best = Some(index + 1);
+        }
+    }
+    best
+}
+
+impl Default for AppInfoConfig {
+    fn default() -> Self {
         Self {
-            voice: VoiceConfig::default(),
-            audio: AudioConfig::default(),
-            database: DatabaseConfig::default(),
-            coordination: CoordinationConfig::default(),
-            emergency: EmergencyConfig::default(),
-            ui: UIConfig::default(),
-            blockchain: BlockchainConfig::default(),
-            connectivity: ConnectivityConfig::default(),
+            name: "Solana SOS".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            description: "Voice-activated emergency response app for Solana Mobile Seeker".to_string(),
+            device_name: "Solana SOS".to_string(),
+            debug_mode: false,
+            log_level: LogLevel::Info,
         }
     }
 }
@@ -204,6 +758,8 @@ impl Default for AudioConfig {
             enable_recording: true,
             recording_sample_rate: 44100,
             recording_format: "wav".to_string(),
+            enable_noise_filtering: true,
+            enable_enhancement: true,
         }
     }
 }
@@ -215,6 +771,9 @@ impl Default for DatabaseConfig {
             emergency_instructions_path: "data/emergency_instructions.json".to_string(),
             user_profiles_path: "data/user_profiles.json".to_string(),
             emergency_history_path: "data/emergency_history.json".to_string(),
+            user_settings_path: "data/user_settings.json".to_string(),
+            contacts_path: "data/emergency_contacts.json".to_string(),
+            last_location_path: "data/last_location.json".to_string(),
         }
     }
 }
@@ -233,6 +792,20 @@ impl Default for ConnectivityConfig {
     }
 }
 
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            push_endpoint: None,
+            push_token: None,
+            smtp_host: None,
+            smtp_port: 587,
+            smtp_username: None,
+            smtp_password: None,
+            email_from: "alerts@solanasos.com".to_string(),
+        }
+    }
+}
+
 impl Default for CoordinationConfig {
     fn default() -> Self {
         Self {
@@ -250,12 +823,36 @@ impl Default for EmergencyConfig {
     fn default() -> Self {
         Self {
             auto_dial_911: true,
+            auto_dial_countdown: 30,
+            automated_voice_message: false,
             enable_location_sharing: true,
+            location_update_interval: 30,
             enable_audio_recording: true,
             recording_duration: 300, // 5 minutes
-            emergency_contacts: vec!["911".to_string()],
+            emergency_contacts: Vec::new(),
+            owner_name: String::new(),
             enable_family_alerts: false,
             alert_timeout: 60,
+            enable_silent_sos: true,
+            silent_sos_method: SilentSosMethod::HoldButton,
+            silent_sos_hold_duration: 3,
+            silent_sos_power_sequence: vec![1, 1, 1, 1, 1],
+            silent_sos_volume_sequence: vec![1, -1, 1, -1, 1],
+            enable_crash_detection: true,
+            crash_speed_threshold: 25.0,
+            crash_impact_threshold: 3.0,
+            crash_response_window: 30,
+            crash_cancel_phrase: "I'm okay".to_string(),
+            enable_fall_detection: false,
+            fall_sensitivity: FallSensitivity::Medium,
+            fall_check_timeout: 30,
+            cancel_pin: None,
+            duress_code: None,
+            enable_trusted_network: true,
+            max_trusted_contacts: 10,
+            contact_region: crate::contacts::DEFAULT_REGION.to_string(),
+            default_location_sharing: true,
+            default_notification_preferences: NOTIFICATION_EVENTS.iter().map(|event| event.to_string()).collect(),
         }
     }
 }
@@ -276,10 +873,51 @@ impl Default for BlockchainConfig {
     fn default() -> Self {
         Self {
             rpc_endpoint: "https://api.mainnet-beta.solana.com".to_string(),
-            wallet_path: None,
+            wallet_keypair: None,
             program_id: "CrisisCompanion111111111111111111111111111111111".to_string(),
             enable_blockchain: false,
             gas_fee_limit: 5000,
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_config_is_valid() {
+        let source = include_str!("../config.toml");
+        let config = AppConfig::from_toml_str(source).unwrap();
+        assert!(config.emergency.enable_silent_sos);
+        assert_eq!(config.emergency.silent_sos_method, SilentSosMethod::HoldButton);
+        assert_eq!(config.emergency.max_trusted_contacts, 10);
+        assert_eq!(config.app.name, "Solana SOS");
+        assert_eq!(config.app.tracing_level(), tracing::Level::INFO);
+
+        let config = AppConfig::from_toml_str("[app]\nlog_level = \"warning\"\n").unwrap();
+        assert_eq!(config.app.tracing_level(), tracing::Level::WARN);
+        let config = AppConfig::from_toml_str("[app]\nlog_level = \"warning\"\ndebug_mode = true\n").unwrap();
+        assert_eq!(config.app.tracing_level(), tracing::Level::DEBUG);
+    }
+
+    #[test]
+    fn test_unknown_keys_and_bad_values_report_lines() {
+        let source = "[voice]\nconfidence_threshold = 1.5\nconfidence_treshold = 0.5\n\n[coordination]\nservice_uuid = \"YOUR_SERVICE_UUID_HERE\"\n";
+        let issues = AppConfig::from_toml_str(source).unwrap_err();
+
+        let find = |key: &str, message: &str| {
+            issues.iter().find(|issue| issue.key == key && issue.message.contains(message)).map(|issue| issue.line)
+        };
+        assert_eq!(find("voice.confidence_threshold", "outside"), Some(Some(2)));
+        assert_eq!(find("voice.confidence_treshold", "unknown key"), Some(Some(3)));
+        assert_eq!(find("coordination.service_uuid", "placeholder"), Some(Some(6)));
+    }
+
+    #[test]
+    fn test_type_errors_report_line() {
+        let issues = AppConfig::from_toml_str("[emergency]\nalert_timeout = 30\nsilent_sos_method = \"shake\"\n").unwrap_err();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(3));
+    }
+}
@@ -0,0 +1,457 @@
+//! Emergency Contacts
+//!
+//! This module keeps the user's emergency contacts. Phone numbers are
+//! normalized to E.164 using the configured region, so "(202) 555-0143" and
+//! "+1 202 555 0143" are recognized as the same contact. Each contact has a
+//! [`ContactTier`] that decides when the escalation policy reaches them, and
+//! the [`NotificationChannel`]s to reach them on.
+//!
+//! Contacts persist to the `emergency_contacts` table through a
+//! [`ContactStore`], and can be imported from vCard 3.0 and 4.0 files.
+
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Region used to read numbers without a country code, unless configured
+pub const DEFAULT_REGION: &str = "US";
+
+/// When a contact is reached during an emergency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContactTier {
+    /// Notified first
+    Primary,
+    /// Notified if no primary contact responds
+    Secondary,
+    /// Doctors and caregivers, sent medical details
+    Medical,
+}
+
+/// How a contact is notified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    /// Text message
+    Sms,
+    /// Phone call
+    Call,
+    /// Email, for contacts with an address
+    Email,
+    /// Push notification to the contact's Solana SOS app
+    Push,
+}
+
+/// An emergency contact, as stored in the `emergency_contacts` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contact {
+    /// Display name
+    pub name: String,
+    /// Phone number in E.164 format
+    pub phone_number: String,
+    /// Email address
+    pub email: Option<String>,
+    /// Relationship to the user, e.g. "Mother"
+    pub relationship: String,
+    /// Escalation tier
+    pub tier: ContactTier,
+    /// Channels to notify on
+    pub channels: Vec<NotificationChannel>,
+    /// Whether the contact is notified at all
+    pub is_active: bool,
+}
+
+impl Contact {
+    /// Create an active contact notified by text and call
+    pub fn new(name: &str, phone_number: &str, tier: ContactTier) -> Self {
+        Self {
+            name: name.trim().to_string(),
+            phone_number: phone_number.trim().to_string(),
+            email: None,
+            relationship: String::new(),
+            tier,
+            channels: vec![NotificationChannel::Sms, NotificationChannel::Call],
+            is_active: true,
+        }
+    }
+
+    /// Set the relationship
+    pub fn with_relationship(mut self, relationship: &str) -> Self {
+        self.relationship = relationship.trim().to_string();
+        self
+    }
+
+    /// Set the email address
+    pub fn with_email(mut self, email: &str) -> Self {
+        self.email = Some(email.trim().to_string());
+        self
+    }
+
+    /// Set the notification channels
+    pub fn with_channels(mut self, channels: &[NotificationChannel]) -> Self {
+        self.channels = channels.to_vec();
+        self
+    }
+}
+
+/// Persisted contacts, stored as `emergency_contacts` rows
+pub trait ContactStore: Send {
+    /// Load every row
+    fn load(&self) -> AppResult<Vec<Contact>>;
+
+    /// Replace every row
+    fn save(&mut self, contacts: &[Contact]) -> AppResult<()>;
+}
+
+impl ContactStore for Vec<Contact> {
+    fn load(&self) -> AppResult<Vec<Contact>> {
+        Ok(self.clone())
+    }
+
+    fn save(&mut self, contacts: &[Contact]) -> AppResult<()> {
+        *self = contacts.to_vec();
+        Ok(())
+    }
+}
+
+/// Contacts kept in a JSON array file until the SQLite store returns
+pub struct JsonContactStore {
+    path: PathBuf,
+}
+
+impl JsonContactStore {
+    /// Create a store backed by the given file
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+}
+
+impl ContactStore for JsonContactStore {
+    fn load(&self) -> AppResult<Vec<Contact>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| AppError::Database(format!("{}: {}", self.path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(AppError::Database(format!("{}: {}", self.path.display(), e))),
+        }
+    }
+
+    fn save(&mut self, contacts: &[Contact]) -> AppResult<()> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(contacts)?)
+            .map_err(|e| AppError::Database(format!("{}: {}", self.path.display(), e)))
+    }
+}
+
+/// Outcome of a vCard import
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    /// Names of the contacts added
+    pub added: Vec<String>,
+    /// Names of cards whose number was already a contact
+    pub duplicates: Vec<String>,
+    /// Cards that could not be imported, with the reason
+    pub rejected: Vec<(String, String)>,
+}
+
+/// The user's emergency contacts
+pub struct ContactBook {
+    region: phonenumber::country::Id,
+    contacts: Vec<Contact>,
+    store: Option<Box<dyn ContactStore>>,
+}
+
+impl ContactBook {
+    /// Create an empty book reading local numbers in `region`, e.g. "US"
+    pub fn new(region: &str) -> AppResult<Self> {
+        Ok(Self { region: parse_region(region)?, contacts: Vec::new(), store: None })
+    }
+
+    /// Load contacts from a store and save every change back to it
+    pub fn with_store(mut self, store: Box<dyn ContactStore>) -> AppResult<Self> {
+        self.contacts = store.load()?;
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    /// Normalize a phone number to E.164
+    pub fn normalize(&self, phone: &str) -> AppResult<String> {
+        let number = phonenumber::parse(Some(self.region), phone.trim())
+            .map_err(|_| AppError::InvalidInput(format!("\"{}\" is not a phone number", phone.trim())))?;
+        if !phonenumber::is_valid(&number) {
+            return Err(AppError::InvalidInput(format!(
+                "\"{}\" is not a valid phone number for {}",
+                phone.trim(),
+                self.region.as_ref()
+            )));
+        }
+        Ok(number.format().mode(phonenumber::Mode::E164).to_string())
+    }
+
+    /// Add a contact, normalizing its number
+    ///
+    /// Fails if the contact is incomplete or its number already belongs to
+    /// another contact.
+    pub fn add(&mut self, contact: Contact) -> AppResult<&Contact> {
+        let contact = self.prepare(contact)?;
+        if let Some(existing) = self.duplicate_of(&contact.phone_number) {
+            return Err(AppError::InvalidInput(format!(
+                "{} is already saved as {}",
+                contact.phone_number, existing.name
+            )));
+        }
+        self.contacts.push(contact);
+        self.persist()?;
+        Ok(self.contacts.last().expect("contact was just added"))
+    }
+
+    /// Remove the contact with this number, returning whether one was removed
+    pub fn remove(&mut self, phone: &str) -> AppResult<bool> {
+        let number = self.normalize(phone)?;
+        let before = self.contacts.len();
+        self.contacts.retain(|contact| contact.phone_number != number);
+        if self.contacts.len() == before {
+            return Ok(false);
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// Get the contact with this number
+    pub fn find(&self, phone: &str) -> Option<&Contact> {
+        self.duplicate_of(&self.normalize(phone).ok()?)
+    }
+
+    /// Get every contact
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    /// Get the active contacts of a tier
+    pub fn tier(&self, tier: ContactTier) -> impl Iterator<Item = &Contact> {
+        self.contacts.iter().filter(move |contact| contact.is_active && contact.tier == tier)
+    }
+
+    /// Import every card of a vCard 3.0 or 4.0 file into `tier`
+    ///
+    /// Each card contributes its mobile number if it has one, otherwise its
+    /// preferred or first number. Cards that fail are reported, not fatal.
+    pub fn import_vcard(&mut self, text: &str, tier: ContactTier) -> AppResult<ImportReport> {
+        let mut report = ImportReport::default();
+        for card in parse_vcards(text) {
+            let name = card.name().unwrap_or_default();
+            if card.version != "3.0" && card.version != "4.0" {
+                report.rejected.push((name, format!("unsupported vCard version \"{}\"", card.version)));
+                continue;
+            }
+            if name.is_empty() {
+                report.rejected.push((name, "card has no name".to_string()));
+                continue;
+            }
+            let Some(phone) = card.best_phone() else {
+                report.rejected.push((name, "card has no phone number".to_string()));
+                continue;
+            };
+
+            let mut contact = Contact::new(&name, phone, tier);
+            if let Some(email) = &card.email {
+                contact = contact.with_email(email);
+            }
+            match self.prepare(contact) {
+                Ok(contact) if self.duplicate_of(&contact.phone_number).is_some() => report.duplicates.push(name),
+                Ok(contact) => {
+                    report.added.push(contact.name.clone());
+                    self.contacts.push(contact);
+                }
+                Err(e) => report.rejected.push((name, e.to_string())),
+            }
+        }
+        if !report.added.is_empty() {
+            self.persist()?;
+        }
+        Ok(report)
+    }
+
+    /// Validate a contact and normalize its number
+    fn prepare(&self, mut contact: Contact) -> AppResult<Contact> {
+        if contact.name.is_empty() {
+            return Err(AppError::InvalidInput("contact name must not be empty".to_string()));
+        }
+        if contact.channels.is_empty() {
+            return Err(AppError::InvalidInput(format!("{} has no notification channel", contact.name)));
+        }
+        if contact.channels.contains(&NotificationChannel::Email) && contact.email.is_none() {
+            return Err(AppError::InvalidInput(format!("{} has no email address to notify", contact.name)));
+        }
+        contact.phone_number = self.normalize(&contact.phone_number)?;
+        Ok(contact)
+    }
+
+    fn duplicate_of(&self, phone_number: &str) -> Option<&Contact> {
+        self.contacts.iter().find(|contact| contact.phone_number == phone_number)
+    }
+
+    fn persist(&mut self) -> AppResult<()> {
+        match self.store.as_mut() {
+            Some(store) => store.save(&self.contacts),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Check a region code such as "US" or "GB"
+pub fn parse_region(region: &str) -> AppResult<phonenumber::country::Id> {
+    region
+        .trim()
+        .to_uppercase()
+        .parse()
+        .map_err(|_| AppError::Config(format!("\"{}\" is not a known region code", region)))
+}
+
+/// The fields of a vCard this module uses
+#[derive(Debug, Default)]
+struct VCard {
+    version: String,
+    formatted_name: Option<String>,
+    structured_name: Option<String>,
+    /// Numbers with their lowercased TYPE parameters
+    phones: Vec<(String, Vec<String>)>,
+    email: Option<String>,
+}
+
+impl VCard {
+    fn name(&self) -> Option<String> {
+        self.formatted_name.clone().or_else(|| self.structured_name.clone())
+    }
+
+    fn best_phone(&self) -> Option<&str> {
+        let has = |types: &[String], wanted: &[&str]| types.iter().any(|t| wanted.contains(&t.as_str()));
+        self.phones
+            .iter()
+            .find(|(_, types)| has(types, &["cell", "mobile"]))
+            .or_else(|| self.phones.iter().find(|(_, types)| has(types, &["pref"])))
+            .or_else(|| self.phones.first())
+            .map(|(number, _)| number.as_str())
+    }
+}
+
+/// Parse every `BEGIN:VCARD` … `END:VCARD` block
+fn parse_vcards(text: &str) -> Vec<VCard> {
+    // Unfold continuation lines, which start with a space or tab
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(previous)) => previous.push_str(rest),
+            _ => lines.push(line.trim_end().to_string()),
+        }
+    }
+
+    let mut cards = Vec::new();
+    let mut current: Option<VCard> = None;
+    for line in lines {
+        let Some((head, value)) = line.split_once(':') else { continue };
+        let mut params = head.split(';');
+        // Drop any group prefix such as "item1."
+        let property = params.next().unwrap_or_default();
+        let property = property.rsplit('.').next().unwrap_or(property).to_uppercase();
+
+        match (property.as_str(), current.as_mut()) {
+            ("BEGIN", _) if value.eq_ignore_ascii_case("VCARD") => current = Some(VCard::default()),
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VCARD") => cards.extend(current.take()),
+            ("VERSION", Some(card)) => card.version = value.trim().to_string(),
+            ("FN", Some(card)) => card.formatted_name = Some(unescape(value)).filter(|name| !name.is_empty()),
+            ("N", Some(card)) => {
+                // Family;Given;Additional;Prefix;Suffix
+                let parts: Vec<String> = value.split(';').map(unescape).collect();
+                let name = [parts.get(1), parts.first()]
+                    .into_iter()
+                    .flatten()
+                    .filter(|part| !part.is_empty())
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                card.structured_name = Some(name).filter(|name| !name.is_empty());
+            }
+            ("TEL", Some(card)) => {
+                let types = params
+                    .filter_map(|param| {
+                        let (key, value) = param.split_once('=').unwrap_or(("TYPE", param));
+                        key.eq_ignore_ascii_case("TYPE").then(|| value.trim_matches('"').to_lowercase())
+                    })
+                    .flat_map(|value| value.split(',').map(str::to_string).collect::<Vec<_>>())
+                    .collect();
+                // vCard 4.0 may give the number as a tel: URI
+                let number = value.trim().strip_prefix("tel:").unwrap_or(value.trim());
+                card.phones.push((number.to_string(), types));
+            }
+            ("EMAIL", Some(card)) if card.email.is_none() => card.email = Some(unescape(value)),
+            _ => {}
+        }
+    }
+    cards
+}
+
+/// Undo vCard text escaping
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.trim().chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n' | 'N')) => {
+                chars.next();
+                out.push(' ');
+            }
+            ('\\', Some(escaped)) => {
+                chars.next();
+                out.push(escaped);
+            }
+            _ => out.push(c),
+        }
+    }
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers_are_normalized_and_deduplicated() {
+        let mut book = ContactBook::new("US").unwrap();
+        let mom = book.add(Contact::new("Mom", "(202) 555-0143", ContactTier::Primary).with_relationship("Mother")).unwrap();
+        assert_eq!(mom.phone_number, "+12025550143");
+
+        assert!(book.add(Contact::new("Mum", "+1 202 555 0143", ContactTier::Secondary)).is_err());
+        assert!(book.add(Contact::new("Bob", "555-1234", ContactTier::Primary)).is_err());
+        assert!(book.add(Contact::new("Dr. Lee", "+44 20 7946 0958", ContactTier::Medical)
+            .with_channels(&[NotificationChannel::Email])).is_err());
+
+        let mut store: Vec<Contact> = Vec::new();
+        store.save(book.contacts()).unwrap();
+        let book = ContactBook::new("GB").unwrap().with_store(Box::new(store)).unwrap();
+        assert_eq!(book.find("+12025550143").map(|contact| contact.name.as_str()), Some("Mom"));
+        assert_eq!(book.tier(ContactTier::Primary).count(), 1);
+    }
+
+    #[test]
+    fn test_vcard_import() {
+        let vcards = "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Ana Ruiz\r\nTEL;TYPE=HOME:(202) 555-0101\r\nTEL;TYPE=CELL,VOICE:(202) 555-0102\r\nEMAIL;TYPE=INTERNET:ana@example.com\r\nEND:VCARD\r\n\
+            BEGIN:VCARD\r\nVERSION:4.0\r\nN:Okafor;Chidi;;;\r\nTEL;VALUE=uri;TYPE=\"voice,cell\":tel:+1-202-555-0177\r\nEND:VCARD\r\n\
+            BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Ana\r\n  Ruiz (work)\r\nTEL:+12025550102\r\nEND:VCARD\r\n\
+            BEGIN:VCARD\r\nVERSION:2.1\r\nFN:Old Phone\r\nTEL;CELL:2025550199\r\nEND:VCARD\r\n\
+            BEGIN:VCARD\r\nVERSION:3.0\r\nFN:No Number\r\nEND:VCARD\r\n";
+
+        let mut book = ContactBook::new("US").unwrap();
+        let report = book.import_vcard(vcards, ContactTier::Secondary).unwrap();
+
+        assert_eq!(report.added, ["Ana Ruiz", "Chidi Okafor"]);
+        assert_eq!(report.duplicates, ["Ana Ruiz (work)"]);
+        assert_eq!(report.rejected.len(), 2);
+        let ana = book.find("202-555-0102").unwrap();
+        assert_eq!(ana.email.as_deref(), Some("ana@example.com"));
+        assert_eq!(ana.tier, ContactTier::Secondary);
+        assert_eq!(book.find("+12025550177").unwrap().name, "Chidi Okafor");
+    }
+}
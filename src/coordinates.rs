@@ -0,0 +1,335 @@
+//! Coordinate Formats
+//!
+//! Decimal degrees are easy to garble when read out over the phone. This
+//! module formats a position the ways dispatchers and rescuers use, all
+//! computed offline on the WGS84 ellipsoid:
+//!
+//! - Open Location Code (plus code), e.g. `8FVC9G8F+6X`
+//! - degrees, minutes and seconds, e.g. `47°21'56.1"N 8°31'30.0"E`
+//! - UTM, e.g. `32T 464135 5245901`
+//! - MGRS, e.g. `32T MT 64134 45901`
+//!
+//! [`phonetic`] spells any of them out for text-to-speech with the NATO
+//! alphabet. UTM and MGRS cover 80°S to 84°N; the polar regions would need
+//! UPS, which is not supported.
+
+use serde::Serialize;
+use std::fmt;
+
+/// Open Location Code digits
+const PLUS_CODE_ALPHABET: &[u8; 20] = b"23456789CFGHJMPQRVWX";
+
+/// WGS84 semi-major axis (m)
+const WGS84_A: f64 = 6_378_137.0;
+
+/// WGS84 flattening
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// UTM scale factor on the central meridian
+const UTM_SCALE: f64 = 0.9996;
+
+/// UTM latitude bands, 8° each from 80°S; X spans 72°N to 84°N
+const UTM_BANDS: &[u8; 20] = b"CDEFGHJKLMNPQRSTUVWX";
+
+/// MGRS 100 km column letters, by zone modulo 3
+const MGRS_COLUMNS: [&[u8; 8]; 3] = [b"ABCDEFGH", b"JKLMNPQR", b"STUVWXYZ"];
+
+/// MGRS 100 km row letters
+const MGRS_ROWS: &[u8; 20] = b"ABCDEFGHJKLMNPQRSTUV";
+
+/// NATO phonetic alphabet
+const NATO: [&str; 26] = [
+    "Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India", "Juliett", "Kilo", "Lima", "Mike",
+    "November", "Oscar", "Papa", "Quebec", "Romeo", "Sierra", "Tango", "Uniform", "Victor", "Whiskey", "X-ray",
+    "Yankee", "Zulu",
+];
+
+/// Digit names
+const DIGITS: [&str; 10] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+
+/// Open Location Code (plus code) for a position, to about 14 m
+pub fn plus_code(latitude: f64, longitude: f64) -> String {
+    // Ten digits encode five latitude/longitude pairs in base 20; the last
+    // pair has a resolution of 1/8000 degree
+    const PRECISION: f64 = 8000.0;
+    let max_latitude = 180 * PRECISION as i64 - 1;
+    let mut lat = (((latitude.clamp(-90.0, 90.0) + 90.0) * PRECISION).floor() as i64).min(max_latitude);
+    let mut lng = ((longitude + 180.0).rem_euclid(360.0) * PRECISION).floor() as i64;
+
+    let mut digits = [0u8; 10];
+    for pair in (0..5).rev() {
+        digits[pair * 2] = PLUS_CODE_ALPHABET[(lat % 20) as usize];
+        digits[pair * 2 + 1] = PLUS_CODE_ALPHABET[(lng % 20) as usize];
+        lat /= 20;
+        lng /= 20;
+    }
+    let digits = String::from_utf8_lossy(&digits);
+    format!("{}+{}", &digits[..8], &digits[8..])
+}
+
+/// Degrees, minutes and seconds to a tenth of a second (about 3 m)
+pub fn dms(latitude: f64, longitude: f64) -> String {
+    format!(
+        "{} {}",
+        dms_angle(latitude, if latitude < 0.0 { 'S' } else { 'N' }),
+        dms_angle(longitude, if longitude < 0.0 { 'W' } else { 'E' })
+    )
+}
+
+fn dms_angle(degrees: f64, hemisphere: char) -> String {
+    // Round once in tenths of a second so 59.96" carries into the minutes
+    let tenths = (degrees.abs() * 36_000.0).round() as u64;
+    format!(
+        "{}°{:02}'{:02}.{}\"{}",
+        tenths / 36_000,
+        tenths / 600 % 60,
+        tenths / 10 % 60,
+        tenths % 10,
+        hemisphere
+    )
+}
+
+/// Universal Transverse Mercator position
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Utm {
+    /// Longitude zone, 1 to 60
+    pub zone: u8,
+    /// Latitude band letter, C to X
+    pub band: char,
+    /// Meters east, 500 000 on the zone's central meridian
+    pub easting: f64,
+    /// Meters north of the equator, plus 10 000 000 in the south
+    pub northing: f64,
+}
+
+impl Utm {
+    /// UTM position, or `None` outside 80°S to 84°N
+    pub fn from_degrees(latitude: f64, longitude: f64) -> Option<Self> {
+        if !(-80.0..=84.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return None;
+        }
+        let zone = utm_zone(latitude, longitude);
+        let central_meridian = f64::from(zone) * 6.0 - 183.0;
+
+        // Snyder, Map Projections: A Working Manual, equations 8-9 to 8-13
+        let e2 = WGS84_F * (2.0 - WGS84_F);
+        let (e4, e6) = (e2 * e2, e2 * e2 * e2);
+        let ep2 = e2 / (1.0 - e2);
+        let phi = latitude.to_radians();
+        let (sin, cos, tan) = (phi.sin(), phi.cos(), phi.tan());
+        let n = WGS84_A / (1.0 - e2 * sin * sin).sqrt();
+        let t = tan * tan;
+        let c = ep2 * cos * cos;
+        let a = cos * (longitude - central_meridian).to_radians();
+        let m = WGS84_A
+            * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+                - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+                + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+                - (35.0 * e6 / 3072.0) * (6.0 * phi).sin());
+
+        let easting = UTM_SCALE
+            * n
+            * (a + (1.0 - t + c) * a.powi(3) / 6.0 + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0)
+            + 500_000.0;
+        let mut northing = UTM_SCALE
+            * (m + n
+                * tan
+                * (a * a / 2.0
+                    + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                    + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+        if latitude < 0.0 {
+            northing += 10_000_000.0;
+        }
+
+        let band = UTM_BANDS[(((latitude + 80.0) / 8.0).floor() as usize).min(UTM_BANDS.len() - 1)] as char;
+        Some(Self { zone, band, easting, northing })
+    }
+
+    /// Military Grid Reference System position for the same point
+    pub fn mgrs(&self) -> Mgrs {
+        let easting = self.easting.floor() as u32;
+        let northing = self.northing.floor() as u32;
+        let columns = MGRS_COLUMNS[usize::from(self.zone - 1) % 3];
+        let column = columns[((easting / 100_000) as usize).clamp(1, columns.len()) - 1] as char;
+        // Even zones start their rows five letters later
+        let offset = if self.zone.is_multiple_of(2) { 5 } else { 0 };
+        let row = MGRS_ROWS[(northing / 100_000 + offset) as usize % MGRS_ROWS.len()] as char;
+        Mgrs {
+            zone: self.zone,
+            band: self.band,
+            square: [column, row],
+            easting: easting % 100_000,
+            northing: northing % 100_000,
+        }
+    }
+}
+
+impl fmt::Display for Utm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{} {:.0} {:.0}", self.zone, self.band, self.easting, self.northing)
+    }
+}
+
+/// Zone for a position, with the Norway and Svalbard exceptions
+fn utm_zone(latitude: f64, longitude: f64) -> u8 {
+    if (56.0..64.0).contains(&latitude) && (3.0..12.0).contains(&longitude) {
+        return 32;
+    }
+    if (72.0..=84.0).contains(&latitude) && (0.0..42.0).contains(&longitude) {
+        return match longitude {
+            lon if lon < 9.0 => 31,
+            lon if lon < 21.0 => 33,
+            lon if lon < 33.0 => 35,
+            _ => 37,
+        };
+    }
+    (((longitude + 180.0) / 6.0).floor() as u8 + 1).min(60)
+}
+
+/// Military Grid Reference System position, to 1 m
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Mgrs {
+    /// UTM zone
+    pub zone: u8,
+    /// UTM latitude band
+    pub band: char,
+    /// 100 km square column and row letters
+    pub square: [char; 2],
+    /// Meters east within the square
+    pub easting: u32,
+    /// Meters north within the square
+    pub northing: u32,
+}
+
+impl Mgrs {
+    /// MGRS position, or `None` outside 80°S to 84°N
+    pub fn from_degrees(latitude: f64, longitude: f64) -> Option<Self> {
+        Utm::from_degrees(latitude, longitude).map(|utm| utm.mgrs())
+    }
+
+    /// The reference with `digits` per coordinate: 5 is 1 m, 4 is 10 m, 3 is 100 m
+    pub fn with_precision(&self, digits: u32) -> String {
+        let digits = digits.min(5);
+        let scale = 10u32.pow(5 - digits);
+        format!(
+            "{}{} {}{} {:0width$} {:0width$}",
+            self.zone,
+            self.band,
+            self.square[0],
+            self.square[1],
+            self.easting / scale,
+            self.northing / scale,
+            width = digits as usize
+        )
+    }
+}
+
+impl fmt::Display for Mgrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.with_precision(5))
+    }
+}
+
+/// Every format of one position
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoordinateFormats {
+    /// Latitude and longitude in degrees, e.g. "47.36559, 8.52500"
+    pub decimal: String,
+    /// Open Location Code
+    pub plus_code: String,
+    /// Degrees, minutes and seconds
+    pub dms: String,
+    /// UTM, outside the polar regions
+    pub utm: Option<String>,
+    /// MGRS to 1 m, outside the polar regions
+    pub mgrs: Option<String>,
+}
+
+impl CoordinateFormats {
+    /// Format a position every way
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        let utm = Utm::from_degrees(latitude, longitude);
+        Self {
+            decimal: format!("{:.5}, {:.5}", latitude, longitude),
+            plus_code: plus_code(latitude, longitude),
+            dms: dms(latitude, longitude),
+            utm: utm.map(|utm| utm.to_string()),
+            mgrs: utm.map(|utm| utm.mgrs().to_string()),
+        }
+    }
+
+    /// The plus code spelled out for text-to-speech
+    pub fn spoken_plus_code(&self) -> String {
+        phonetic(&self.plus_code)
+    }
+}
+
+/// Spell a code out for text-to-speech
+///
+/// Letters use the NATO alphabet and digits their names, joined by hyphens;
+/// spaces become pauses and `+` is read as "plus". `8FVC9G8F+6X` reads
+/// "eight-Foxtrot-Victor-Charlie-nine-Golf-eight-Foxtrot, plus, six-X-ray".
+/// Other characters are dropped.
+pub fn phonetic(code: &str) -> String {
+    let mut groups: Vec<String> = Vec::new();
+    let mut group: Vec<&str> = Vec::new();
+    fn close(group: &mut Vec<&str>, groups: &mut Vec<String>) {
+        if !group.is_empty() {
+            groups.push(group.join("-"));
+            group.clear();
+        }
+    }
+
+    for c in code.chars() {
+        match c {
+            '0'..='9' => group.push(DIGITS[c as usize - '0' as usize]),
+            'a'..='z' | 'A'..='Z' => group.push(NATO[c.to_ascii_uppercase() as usize - 'A' as usize]),
+            '.' => group.push("point"),
+            '+' => {
+                close(&mut group, &mut groups);
+                groups.push("plus".to_string());
+            }
+            c if c.is_whitespace() || c == ',' => close(&mut group, &mut groups),
+            _ => {}
+        }
+    }
+    close(&mut group, &mut groups);
+    groups.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_known_positions() {
+        let zurich = CoordinateFormats::new(47.365590, 8.524997);
+        assert_eq!(zurich.plus_code, "8FVC9G8F+6X");
+        assert_eq!(plus_code(-90.0, 180.0), "22222222+22");
+        assert_eq!(zurich.dms, "47°21'56.1\"N 8°31'30.0\"E");
+        assert_eq!(zurich.utm.as_deref(), Some("32T 464135 5245901"));
+        assert_eq!(zurich.mgrs.as_deref(), Some("32T MT 64134 45901"));
+
+        // Southern and western hemispheres
+        let washington = Utm::from_degrees(38.8895, -77.0353).unwrap();
+        assert_eq!((washington.zone, washington.band), (18, 'S'));
+        assert_eq!(washington.mgrs().with_precision(4), "18S UJ 2347 0648");
+        assert_eq!(dms(-33.8568, -151.2153), "33°51'24.5\"S 151°12'55.1\"W");
+        assert_eq!(Mgrs::from_degrees(-33.8568, 151.2153).unwrap().to_string(), "56H LH 34900 52288");
+
+        // Norway's widened zone 32 and no UTM near the poles
+        assert_eq!(Utm::from_degrees(60.39, 5.32).unwrap().zone, 32);
+        assert!(CoordinateFormats::new(89.9, 0.0).mgrs.is_none());
+        assert_eq!(dms(0.0, 9.999999), "0°00'00.0\"N 10°00'00.0\"E");
+    }
+
+    #[test]
+    fn test_phonetic_readout() {
+        assert_eq!(
+            phonetic("8FVC9G8F+6X"),
+            "eight-Foxtrot-Victor-Charlie-nine-Golf-eight-Foxtrot, plus, six-X-ray"
+        );
+        assert_eq!(phonetic("32T MT 641"), "three-two-Tango, Mike-Tango, six-four-one");
+        assert_eq!(phonetic("47.36°"), "four-seven-point-three-six");
+    }
+}
@@ -0,0 +1,413 @@
+//! Cancellable Countdowns
+//!
+//! Crash responses, fall checks and automatic emergency calls wait for the
+//! user to cancel before acting. A [`Countdown`] announces itself when it
+//! starts and then prompts the user as it runs:
+//!
+//! - every [`PROMPT_INTERVAL`] with the remaining time spoken and a short
+//!   vibration
+//! - each of the last [`FINAL_SECONDS`] seconds with the number spoken
+//!
+//! It ends either cancelled (by the cancel phrase, the cancel button, a cancel
+//! PIN or the duress code) or expired, and the [`CountdownOutcome`] records
+//! which. Time comes from a [`Clock`], so tests step a [`ManualClock`]
+//! instead of sleeping.
+
+use crate::duress::CancelMethod;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Time between spoken reminders
+pub const PROMPT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Final seconds counted down one by one
+pub const FINAL_SECONDS: u64 = 5;
+
+/// Monotonic time source for countdowns
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Time since an arbitrary fixed start
+    fn now(&self) -> Duration;
+}
+
+/// The system's monotonic clock
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    start: Instant,
+}
+
+impl MonotonicClock {
+    /// Create a clock starting now
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to; clones share the time
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    millis: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    /// Create a clock at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: Duration) {
+        self.millis.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_millis(self.millis.load(Ordering::SeqCst))
+    }
+}
+
+/// What happens when a countdown expires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CountdownAction {
+    /// Emergency services are called
+    CallEmergencyServices,
+    /// Trusted contacts are alerted
+    AlertContacts,
+}
+
+impl CountdownAction {
+    /// Whether expiry does more than `other`'s: calling emergency services
+    /// also alerts contacts
+    pub fn outranks(self, other: CountdownAction) -> bool {
+        self == CountdownAction::CallEmergencyServices && other == CountdownAction::AlertContacts
+    }
+
+    fn spoken(self) -> &'static str {
+        match self {
+            CountdownAction::CallEmergencyServices => "Calling emergency services",
+            CountdownAction::AlertContacts => "Alerting your contacts",
+        }
+    }
+}
+
+/// How a countdown was cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelSource {
+    /// The cancel phrase was spoken or typed
+    Phrase,
+    /// The cancel PIN was entered
+    Pin,
+    /// The on-screen cancel button
+    Button,
+    /// The duress code: the countdown stops on screen, alerts go out covertly
+    Duress,
+}
+
+impl From<CancelMethod> for CancelSource {
+    fn from(method: CancelMethod) -> Self {
+        match method {
+            CancelMethod::Pin => CancelSource::Pin,
+            CancelMethod::Phrase => CancelSource::Phrase,
+        }
+    }
+}
+
+/// Vibration pattern for a prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Haptic {
+    /// Long buzz when the countdown starts
+    Long,
+    /// Short buzz for reminders
+    Short,
+}
+
+/// A prompt the host speaks and vibrates
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CountdownPrompt {
+    /// Whole seconds left
+    pub remaining_secs: u64,
+    /// Text to speak
+    pub speech: String,
+    /// Vibration to play
+    pub haptic: Haptic,
+}
+
+/// How a countdown ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CountdownOutcome {
+    /// What the countdown was for
+    pub action: CountdownAction,
+    /// Full length of the countdown (seconds)
+    pub duration_secs: u64,
+    /// Time from start to the end (milliseconds)
+    pub elapsed_ms: u64,
+    /// How it was cancelled; `None` if it expired
+    pub cancelled_by: Option<CancelSource>,
+}
+
+impl CountdownOutcome {
+    /// Whether the timer ran out, so the action should go ahead
+    pub fn expired(&self) -> bool {
+        self.cancelled_by.is_none()
+    }
+}
+
+/// What [`Countdown::poll`] found due
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CountdownEvent {
+    /// Prompt the user
+    Prompt(CountdownPrompt),
+    /// Time is up
+    Expired(CountdownOutcome),
+}
+
+/// A running "calling emergency services in 30 s unless cancelled" countdown
+#[derive(Debug)]
+pub struct Countdown {
+    clock: Arc<dyn Clock>,
+    action: CountdownAction,
+    intro: Option<String>,
+    cancel_phrase: String,
+    started: Duration,
+    duration: Duration,
+    /// Remaining seconds at which prompts are due, longest first
+    marks: Vec<u64>,
+    next_mark: usize,
+    outcome: Option<CountdownOutcome>,
+}
+
+impl Countdown {
+    /// Start a countdown on `clock`
+    ///
+    /// `cancel_phrase` is named in the opening prompt; the caller matches it.
+    pub fn start(clock: Arc<dyn Clock>, action: CountdownAction, duration: Duration, cancel_phrase: &str) -> Self {
+        let total = duration.as_secs();
+        let interval = PROMPT_INTERVAL.as_secs();
+        let marks = (1..=total)
+            .rev()
+            .filter(|remaining| *remaining == total || remaining % interval == 0 || *remaining <= FINAL_SECONDS)
+            .collect();
+        Self {
+            started: clock.now(),
+            clock,
+            action,
+            intro: None,
+            cancel_phrase: cancel_phrase.trim().to_string(),
+            duration,
+            marks,
+            next_mark: 0,
+            outcome: None,
+        }
+    }
+
+    /// Speak `intro` before the opening prompt, e.g. "Did you fall?"
+    pub fn with_intro(mut self, intro: &str) -> Self {
+        self.intro = Some(intro.to_string());
+        self
+    }
+
+    /// What the countdown is for
+    pub fn action(&self) -> CountdownAction {
+        self.action
+    }
+
+    /// Time left until expiry
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.elapsed())
+    }
+
+    /// Whole seconds left, rounded up
+    pub fn remaining_secs(&self) -> u64 {
+        let remaining = self.remaining();
+        remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0)
+    }
+
+    /// How it ended, once it has
+    pub fn outcome(&self) -> Option<&CountdownOutcome> {
+        self.outcome.as_ref()
+    }
+
+    /// Get the prompt or expiry that is due, if any
+    ///
+    /// Call until it returns `None`. Prompts missed while nobody polled are
+    /// skipped in favour of the latest one.
+    pub fn poll(&mut self) -> Option<CountdownEvent> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        let elapsed = self.elapsed();
+        let due = self.marks[self.next_mark..]
+            .iter()
+            .take_while(|remaining| self.mark_time(**remaining) <= elapsed)
+            .count();
+        if due > 0 {
+            let opening = self.next_mark == 0;
+            self.next_mark += due;
+            return Some(CountdownEvent::Prompt(self.prompt(self.marks[self.next_mark - 1], opening)));
+        }
+        if elapsed >= self.duration {
+            return Some(CountdownEvent::Expired(self.finish(None)));
+        }
+        None
+    }
+
+    /// Time until the next prompt or the expiry; `None` once ended
+    pub fn next_wake(&self) -> Option<Duration> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        let next = self.marks.get(self.next_mark).map_or(self.duration, |remaining| self.mark_time(*remaining));
+        Some(next.saturating_sub(self.elapsed()))
+    }
+
+    /// Cancel the countdown; `None` if it had already ended
+    pub fn cancel(&mut self, by: CancelSource) -> Option<CountdownOutcome> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        Some(self.finish(Some(by)))
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_sub(self.started)
+    }
+
+    fn mark_time(&self, remaining_secs: u64) -> Duration {
+        self.duration.saturating_sub(Duration::from_secs(remaining_secs))
+    }
+
+    fn prompt(&self, remaining_secs: u64, opening: bool) -> CountdownPrompt {
+        let unit = if remaining_secs == 1 { "second" } else { "seconds" };
+        let speech = if opening {
+            let opening = format!(
+                "{} in {} {}. Say '{}' or press cancel to stop.",
+                self.action.spoken(),
+                remaining_secs,
+                unit,
+                self.cancel_phrase
+            );
+            match &self.intro {
+                Some(intro) => format!("{} {}", intro, opening),
+                None => opening,
+            }
+        } else if remaining_secs <= FINAL_SECONDS {
+            remaining_secs.to_string()
+        } else {
+            format!("{} in {} {}.", self.action.spoken(), remaining_secs, unit)
+        };
+        CountdownPrompt {
+            remaining_secs,
+            speech,
+            haptic: if opening { Haptic::Long } else { Haptic::Short },
+        }
+    }
+
+    fn finish(&mut self, cancelled_by: Option<CancelSource>) -> CountdownOutcome {
+        let outcome = CountdownOutcome {
+            action: self.action,
+            duration_secs: self.duration.as_secs(),
+            elapsed_ms: self.elapsed().min(self.duration).as_millis() as u64,
+            cancelled_by,
+        };
+        self.outcome = Some(outcome.clone());
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(countdown: &mut Countdown, clock: &ManualClock, secs: u64) -> Vec<CountdownEvent> {
+        let mut events = Vec::new();
+        for _ in 0..secs * 10 {
+            events.extend(std::iter::from_fn(|| countdown.poll()));
+            clock.advance(Duration::from_millis(100));
+        }
+        events
+    }
+
+    #[test]
+    fn test_prompts_then_expiry() {
+        let clock = ManualClock::new();
+        let mut countdown = Countdown::start(
+            Arc::new(clock.clone()),
+            CountdownAction::CallEmergencyServices,
+            Duration::from_secs(30),
+            "I'm okay",
+        )
+        .with_intro("Crash detected.");
+
+        let events = run(&mut countdown, &clock, 31);
+        let prompts: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                CountdownEvent::Prompt(prompt) => Some(prompt),
+                CountdownEvent::Expired(_) => None,
+            })
+            .collect();
+        let remaining: Vec<_> = prompts.iter().map(|prompt| prompt.remaining_secs).collect();
+        assert_eq!(remaining, [30, 20, 10, 5, 4, 3, 2, 1]);
+        assert_eq!(
+            prompts[0].speech,
+            "Crash detected. Calling emergency services in 30 seconds. Say 'I'm okay' or press cancel to stop."
+        );
+        assert_eq!(prompts[0].haptic, Haptic::Long);
+        assert_eq!(prompts[1].speech, "Calling emergency services in 20 seconds.");
+        assert_eq!(prompts[7].speech, "1");
+
+        let outcome = countdown.outcome().unwrap();
+        assert!(outcome.expired());
+        assert_eq!(outcome.elapsed_ms, 30_000);
+        assert_eq!(events.last(), Some(&CountdownEvent::Expired(outcome.clone())));
+        assert_eq!(countdown.cancel(CancelSource::Button), None);
+    }
+
+    #[test]
+    fn test_cancel_and_missed_prompts() {
+        let clock = ManualClock::new();
+        let mut countdown = Countdown::start(
+            Arc::new(clock.clone()),
+            CountdownAction::AlertContacts,
+            Duration::from_secs(30),
+            "I'm okay",
+        );
+        assert!(matches!(countdown.poll(), Some(CountdownEvent::Prompt(_))));
+        assert_eq!(countdown.next_wake(), Some(Duration::from_secs(10)));
+
+        // Nobody polled for 22 s: only the 10 s reminder is given
+        clock.advance(Duration::from_millis(22_500));
+        match countdown.poll() {
+            Some(CountdownEvent::Prompt(prompt)) => assert_eq!(prompt.remaining_secs, 10),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(countdown.poll(), None);
+        assert_eq!(countdown.remaining_secs(), 8);
+
+        let outcome = countdown.cancel(CancelSource::Duress).unwrap();
+        assert!(!outcome.expired());
+        assert_eq!(outcome.cancelled_by, Some(CancelSource::Duress));
+        assert_eq!(outcome.elapsed_ms, 22_500);
+        assert_eq!(countdown.next_wake(), None);
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(countdown.poll(), None);
+    }
+}
@@ -0,0 +1,278 @@
+//! Vehicle Crash Detection
+//!
+//! A crash is a deceleration pulse of at least `crash_impact_threshold` g
+//! while the phone was moving at `crash_speed_threshold` mph or more during
+//! the preceding [`SPEED_LOOKBACK`]. GPS speed lags and may already read
+//! zero after the impact, so the fastest recent speed counts.
+//!
+//! Dropped phones also produce large spikes. In the [`DROP_LOOKBACK`] before
+//! a pulse the detector looks for their signatures and rejects the impact if
+//! it finds either:
+//!
+//! - free fall: acceleration below [`FREE_FALL_G`] for at least
+//!   [`FREE_FALL_MIN`], as a falling phone is weightless
+//! - tumbling: the gravity direction turning by more than
+//!   [`TUMBLE_ANGLE_DEGREES`], as a falling phone rotates while a phone in a
+//!   vehicle keeps its orientation until the impact
+//!
+//! A pulse is assessed when it ends. After a crash the detector ignores
+//! further impacts for [`CRASH_COOLDOWN`], which covers secondary impacts of
+//! the same collision.
+
+use crate::config::{AppConfig, EmergencyConfig};
+use crate::sensors::{SensorReading, SensorSample, MPS_PER_MPH, STANDARD_GRAVITY};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// How far back the pre-impact speed is taken from
+pub const SPEED_LOOKBACK: Duration = Duration::from_secs(5);
+
+/// How far back drop signatures are looked for
+pub const DROP_LOOKBACK: Duration = Duration::from_millis(1000);
+
+/// Acceleration below this is free fall (g)
+pub const FREE_FALL_G: f64 = 0.4;
+
+/// Shortest free fall that marks a drop
+pub const FREE_FALL_MIN: Duration = Duration::from_millis(100);
+
+/// Gravity turning by more than this before an impact marks a drop
+pub const TUMBLE_ANGLE_DEGREES: f64 = 60.0;
+
+/// Impacts ignored after a crash
+pub const CRASH_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Readings within this band of 1 g are taken as gravity alone
+const QUASI_STATIC_G: (f64, f64) = (0.7, 1.4);
+
+/// What an impact was judged to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpactKind {
+    /// A vehicle crash
+    Crash,
+    /// The phone was dropped
+    PhoneDrop,
+    /// A hard knock while not moving fast enough for a crash
+    NotMoving,
+}
+
+/// An acceleration pulse above the impact threshold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Impact {
+    /// What the pulse was judged to be
+    pub kind: ImpactKind,
+    /// Host timestamp of the start of the pulse
+    pub timestamp_ms: u64,
+    /// Peak acceleration (g)
+    pub peak_g: f64,
+    /// Fastest speed before the pulse (mph)
+    pub speed_mph: f64,
+}
+
+/// Detects vehicle crashes from accelerometer and speed samples
+#[derive(Debug, Clone)]
+pub struct CrashDetector {
+    enabled: bool,
+    speed_threshold_mph: f64,
+    impact_threshold_g: f64,
+    speeds: VecDeque<(u64, f64)>,
+    accel: VecDeque<(u64, [f64; 3])>,
+    /// Start time, peak g and the drop verdict of the pulse in progress
+    pulse: Option<(u64, f64, bool)>,
+    cooldown_until: Option<u64>,
+}
+
+impl CrashDetector {
+    /// Create a detector for the `[emergency]` crash settings
+    pub fn from_config(config: &EmergencyConfig) -> Self {
+        let mut detector = Self {
+            enabled: false,
+            speed_threshold_mph: 0.0,
+            impact_threshold_g: 0.0,
+            speeds: VecDeque::new(),
+            accel: VecDeque::new(),
+            pulse: None,
+            cooldown_until: None,
+        };
+        detector.configure(config);
+        detector
+    }
+
+    /// Replace the thresholds
+    pub fn configure(&mut self, config: &EmergencyConfig) {
+        self.enabled = config.enable_crash_detection;
+        self.speed_threshold_mph = f64::from(config.crash_speed_threshold);
+        self.impact_threshold_g = f64::from(config.crash_impact_threshold);
+    }
+
+    /// Feed one sample; returns an impact when a pulse above the threshold ends
+    ///
+    /// Only [`ImpactKind::Crash`] impacts should start a crash response.
+    pub fn handle(&mut self, sample: SensorSample) -> Option<Impact> {
+        if !self.enabled {
+            return None;
+        }
+        let now = sample.timestamp_ms;
+        match sample.reading {
+            SensorReading::Speed { meters_per_second } => {
+                self.speeds.push_back((now, meters_per_second));
+                prune(&mut self.speeds, now, SPEED_LOOKBACK);
+                None
+            }
+            SensorReading::Accel { x, y, z } => {
+                let g = magnitude(&[x, y, z]) / STANDARD_GRAVITY;
+                let impact = if g >= self.impact_threshold_g {
+                    match &mut self.pulse {
+                        Some((_, peak, _)) => *peak = peak.max(g),
+                        None => self.pulse = Some((now, g, self.dropped())),
+                    }
+                    None
+                } else {
+                    self.pulse.take().and_then(|(start, peak, dropped)| self.assess(start, peak, dropped))
+                };
+                self.accel.push_back((now, [x, y, z]));
+                prune(&mut self.accel, now, DROP_LOOKBACK);
+                impact
+            }
+            _ => None,
+        }
+    }
+
+    fn assess(&mut self, start: u64, peak_g: f64, dropped: bool) -> Option<Impact> {
+        if self.cooldown_until.is_some_and(|until| start < until) {
+            return None;
+        }
+        let speed_mph = self
+            .speeds
+            .iter()
+            .filter(|(at, _)| *at <= start)
+            .map(|(_, speed)| speed / MPS_PER_MPH)
+            .fold(0.0, f64::max);
+
+        let kind = if dropped {
+            ImpactKind::PhoneDrop
+        } else if speed_mph < self.speed_threshold_mph {
+            ImpactKind::NotMoving
+        } else {
+            self.cooldown_until = Some(start + CRASH_COOLDOWN.as_millis() as u64);
+            ImpactKind::Crash
+        };
+        Some(Impact { kind, timestamp_ms: start, peak_g, speed_mph })
+    }
+
+    /// Whether the readings before the current sample show a drop
+    fn dropped(&self) -> bool {
+        self.free_fall() || self.tumbled()
+    }
+
+    fn free_fall(&self) -> bool {
+        let min = FREE_FALL_MIN.as_millis() as u64;
+        let mut falling_since = None;
+        for (at, reading) in &self.accel {
+            if magnitude(reading) / STANDARD_GRAVITY < FREE_FALL_G {
+                let since = *falling_since.get_or_insert(*at);
+                if at - since >= min {
+                    return true;
+                }
+            } else {
+                falling_since = None;
+            }
+        }
+        false
+    }
+
+    fn tumbled(&self) -> bool {
+        let mut resting = self.accel.iter().map(|(_, reading)| reading).filter(|reading| {
+            let g = magnitude(reading) / STANDARD_GRAVITY;
+            g >= QUASI_STATIC_G.0 && g <= QUASI_STATIC_G.1
+        });
+        let Some(first) = resting.next() else {
+            return false;
+        };
+        let limit = TUMBLE_ANGLE_DEGREES.to_radians().cos();
+        resting.any(|reading| cosine(first, reading) < limit)
+    }
+}
+
+impl Default for CrashDetector {
+    fn default() -> Self {
+        Self::from_config(&AppConfig::default().emergency)
+    }
+}
+
+fn prune<T>(window: &mut VecDeque<(u64, T)>, now: u64, keep: Duration) {
+    let oldest = now.saturating_sub(keep.as_millis() as u64);
+    while window.front().is_some_and(|(at, _)| *at < oldest) {
+        window.pop_front();
+    }
+}
+
+fn magnitude(v: &[f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+fn cosine(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (a[0] * b[0] + a[1] * b[1] + a[2] * b[2]) / (magnitude(a) * magnitude(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::SensorTrace;
+
+    /// Labelled synthetic traces: `crash` must be detected, `drop` and
+    /// `none` must not
+    const TRACES: &[(&str, &str)] = &[
+        ("crash_frontal_40mph", include_str!("../tests/data/crash/crash_frontal_40mph.csv")),
+        ("crash_side_30mph", include_str!("../tests/data/crash/crash_side_30mph.csv")),
+        ("crash_gps_stopped", include_str!("../tests/data/crash/crash_gps_stopped.csv")),
+        ("drop_desk", include_str!("../tests/data/crash/drop_desk.csv")),
+        ("drop_while_driving", include_str!("../tests/data/crash/drop_while_driving.csv")),
+        ("drop_tumble_from_seat", include_str!("../tests/data/crash/drop_tumble_from_seat.csv")),
+        ("none_pothole", include_str!("../tests/data/crash/none_pothole.csv")),
+        ("none_parking_bump", include_str!("../tests/data/crash/none_parking_bump.csv")),
+    ];
+
+    fn replay(trace: &SensorTrace) -> Vec<Impact> {
+        let mut detector = CrashDetector::default();
+        trace.samples.iter().filter_map(|sample| detector.handle(*sample)).collect()
+    }
+
+    #[test]
+    fn test_labelled_traces() {
+        for (name, text) in TRACES {
+            let trace = SensorTrace::parse(text).unwrap();
+            let impacts = replay(&trace);
+            let crashes = impacts.iter().filter(|impact| impact.kind == ImpactKind::Crash).count();
+            match trace.get("label") {
+                Some("crash") => assert_eq!(crashes, 1, "{}: {:?}", name, impacts),
+                Some("drop") => {
+                    assert_eq!(crashes, 0, "{}: {:?}", name, impacts);
+                    assert!(impacts.iter().any(|impact| impact.kind == ImpactKind::PhoneDrop), "{}: {:?}", name, impacts);
+                }
+                Some("none") => assert_eq!(crashes, 0, "{}: {:?}", name, impacts),
+                label => panic!("{}: unknown label {:?}", name, label),
+            }
+        }
+    }
+
+    #[test]
+    fn test_thresholds_follow_config() {
+        let (_, text) = TRACES.iter().find(|(name, _)| *name == "crash_side_30mph").unwrap();
+        let trace = SensorTrace::parse(text).unwrap();
+
+        let mut config = AppConfig::default().emergency;
+        config.crash_speed_threshold = 35.0;
+        let mut detector = CrashDetector::from_config(&config);
+        let impacts: Vec<_> = trace.samples.iter().filter_map(|sample| detector.handle(*sample)).collect();
+        assert_eq!(impacts.len(), 1);
+        assert_eq!(impacts[0].kind, ImpactKind::NotMoving);
+        assert!((impacts[0].speed_mph - 30.0).abs() < 0.5);
+
+        config.enable_crash_detection = false;
+        detector.configure(&config);
+        assert!(trace.samples.iter().all(|sample| detector.handle(*sample).is_none()));
+    }
+}
@@ -0,0 +1,299 @@
+//! Dead Reckoning
+//!
+//! In tunnels, canyons and buildings GPS drops out and the last fix goes
+//! stale. [`DeadReckoner`] carries it forward on foot: [`StepDetector`]
+//! counts steps in the accelerometer signal, and each step moves the
+//! estimate one [`STRIDE_M`] in the direction of the latest `heading`
+//! sample.
+//!
+//! Neither stride nor heading is exact, so every step widens the estimate's
+//! radius by [`STRIDE_ERROR`] of a stride plus the sideways error of the
+//! compass accuracy; a step without a recent heading widens it by a whole
+//! stride. An [`Estimate`] is always reported against the fix it started
+//! from: "last known at 14:02, estimated 300 m NE ±150 m".
+
+use crate::location::Fix;
+use crate::sensors::{SensorReading, SensorSample};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Assumed length of one step (m)
+pub const STRIDE_M: f64 = 0.7;
+
+/// Error of the assumed stride, as a fraction of it
+pub const STRIDE_ERROR: f64 = 0.15;
+
+/// Acceleration a step's heel strike peaks above (g)
+pub const STEP_PEAK_G: f64 = 1.15;
+
+/// Acceleration the signal must drop below between steps (g)
+pub const STEP_RESET_G: f64 = 1.0;
+
+/// Shortest time between steps
+pub const MIN_STEP_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Longest time between steps of one walk
+pub const MAX_STEP_INTERVAL: Duration = Duration::from_millis(2000);
+
+/// How long a heading sample stays usable
+pub const HEADING_MAX_AGE: Duration = Duration::from_secs(5);
+
+/// 8-point compass, clockwise from north
+const COMPASS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+/// Counts steps in accelerometer samples
+///
+/// A step is a peak above [`STEP_PEAK_G`] after the signal dropped below
+/// [`STEP_RESET_G`]. A lone peak is not walking, so the first step of a walk
+/// only counts once a second one follows within [`MAX_STEP_INTERVAL`].
+#[derive(Debug, Clone)]
+pub struct StepDetector {
+    armed: bool,
+    last_step: Option<u64>,
+    pending: bool,
+}
+
+impl StepDetector {
+    /// Create a detector
+    pub fn new() -> Self {
+        Self { armed: true, last_step: None, pending: false }
+    }
+
+    /// Feed one sample; returns how many steps it completed
+    pub fn handle(&mut self, sample: SensorSample) -> u32 {
+        let Some(g) = sample.reading.g_force() else {
+            return 0;
+        };
+        let now = sample.timestamp_ms;
+        if g < STEP_RESET_G {
+            self.armed = true;
+            return 0;
+        }
+        if !self.armed || g < STEP_PEAK_G {
+            return 0;
+        }
+        let since_last = self.last_step.map(|last| Duration::from_millis(now.saturating_sub(last)));
+        if since_last.is_some_and(|since| since < MIN_STEP_INTERVAL) {
+            return 0;
+        }
+        self.armed = false;
+        self.last_step = Some(now);
+        if since_last.is_some_and(|since| since <= MAX_STEP_INTERVAL) {
+            1 + u32::from(std::mem::take(&mut self.pending))
+        } else {
+            self.pending = true;
+            0
+        }
+    }
+}
+
+impl Default for StepDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A position carried forward from a fix by counting steps
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Estimate {
+    /// The fix the steps were counted from
+    pub anchor: Fix,
+    /// Estimated latitude in degrees
+    pub latitude: f64,
+    /// Estimated longitude in degrees
+    pub longitude: f64,
+    /// Radius the true position is likely within (m)
+    pub radius_m: f64,
+    /// Meters east of the anchor
+    pub east_m: f64,
+    /// Meters north of the anchor
+    pub north_m: f64,
+    /// Steps counted since the anchor
+    pub steps: u32,
+}
+
+impl Estimate {
+    /// Straight-line distance from the anchor (m)
+    pub fn distance_m(&self) -> f64 {
+        self.east_m.hypot(self.north_m)
+    }
+
+    /// Direction from the anchor in degrees clockwise from north
+    pub fn bearing_deg(&self) -> f64 {
+        self.east_m.atan2(self.north_m).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Direction from the anchor on an 8-point compass, e.g. "NE"
+    pub fn direction(&self) -> &'static str {
+        COMPASS[((self.bearing_deg() / 45.0).round() as usize) % COMPASS.len()]
+    }
+
+    /// Offset from the anchor to the nearest 10 m, e.g. "300 m NE ±150 m"
+    pub fn offset_label(&self) -> String {
+        format!(
+            "{:.0} m {} ±{:.0} m",
+            (self.distance_m() / 10.0).round() * 10.0,
+            self.direction(),
+            (self.radius_m / 10.0).ceil() * 10.0
+        )
+    }
+
+    /// The estimate with the anchor's time of day in `zone`, e.g.
+    /// "last known at 14:02, estimated 300 m NE ±150 m"
+    pub fn describe<Tz: chrono::TimeZone>(&self, zone: &Tz) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        format!(
+            "last known at {}, estimated {}",
+            self.anchor.time.with_timezone(zone).format("%H:%M"),
+            self.offset_label()
+        )
+    }
+}
+
+/// Carries the last fix forward by counting steps
+#[derive(Debug, Clone, Default)]
+pub struct DeadReckoner {
+    steps: StepDetector,
+    /// Latest heading, its accuracy and host timestamp
+    heading: Option<(f64, f64, u64)>,
+    anchor: Option<Fix>,
+    east_m: f64,
+    north_m: f64,
+    /// Radius grown by the steps so far (m)
+    error_m: f64,
+    count: u32,
+}
+
+impl DeadReckoner {
+    /// Create a reckoner with no fix to start from
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start over from a new fix
+    pub fn anchor(&mut self, fix: Fix) {
+        if self.anchor == Some(fix) {
+            return;
+        }
+        self.anchor = Some(fix);
+        self.east_m = 0.0;
+        self.north_m = 0.0;
+        self.error_m = 0.0;
+        self.count = 0;
+    }
+
+    /// Feed one sample; returns the moved estimate after each step
+    pub fn handle(&mut self, sample: SensorSample) -> Option<Estimate> {
+        if let SensorReading::Heading { degrees, accuracy_deg } = sample.reading {
+            self.heading = Some((degrees, accuracy_deg, sample.timestamp_ms));
+            return None;
+        }
+        let steps = self.steps.handle(sample);
+        if steps == 0 || self.anchor.is_none() {
+            return None;
+        }
+        let heading = self
+            .heading
+            .filter(|(_, _, at)| sample.timestamp_ms.saturating_sub(*at) <= HEADING_MAX_AGE.as_millis() as u64);
+        for _ in 0..steps {
+            match heading {
+                Some((degrees, accuracy_deg, _)) => {
+                    let (sin, cos) = degrees.to_radians().sin_cos();
+                    self.east_m += STRIDE_M * sin;
+                    self.north_m += STRIDE_M * cos;
+                    self.error_m += STRIDE_M * (STRIDE_ERROR + accuracy_deg.min(90.0).to_radians().sin());
+                }
+                // Walking in an unknown direction
+                None => self.error_m += STRIDE_M,
+            }
+        }
+        self.count += steps;
+        self.estimate()
+    }
+
+    /// The current estimate, if there is a fix to start from
+    pub fn estimate(&self) -> Option<Estimate> {
+        let anchor = self.anchor?;
+        let (latitude, longitude) = anchor.offset_by(self.east_m, self.north_m);
+        Some(Estimate {
+            anchor,
+            latitude,
+            longitude,
+            radius_m: anchor.accuracy_m + self.error_m,
+            east_m: self.east_m,
+            north_m: self.north_m,
+            steps: self.count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::STANDARD_GRAVITY;
+
+    /// 50 Hz walking at two steps a second for `secs`, with a heading sample each second
+    fn walk(start: u64, secs: u64, heading: Option<f64>) -> Vec<SensorSample> {
+        let mut samples = Vec::new();
+        for i in 0..secs * 50 {
+            let t = start + i * 20;
+            if i % 50 == 0 {
+                if let Some(degrees) = heading {
+                    samples.push(SensorSample::new(t, SensorReading::Heading { degrees, accuracy_deg: 10.0 }));
+                }
+            }
+            let g = 1.0 + 0.3 * (t as f64 * std::f64::consts::TAU / 500.0).sin();
+            samples.push(SensorSample::new(t, SensorReading::Accel { x: 0.0, y: 0.0, z: g * STANDARD_GRAVITY }));
+        }
+        samples
+    }
+
+    fn fix() -> Fix {
+        let time = chrono::DateTime::parse_from_rfc3339("2024-05-01T14:02:00Z").unwrap().into();
+        Fix::new(47.365590, 8.524997, 10.0, time).unwrap()
+    }
+
+    #[test]
+    fn test_counts_steps_and_ignores_jolts() {
+        let mut detector = StepDetector::new();
+        let steps: u32 = walk(0, 10, None).into_iter().map(|sample| detector.handle(sample)).sum();
+        assert!((19..=20).contains(&steps), "{} steps", steps);
+
+        // One bump is not a walk
+        let mut detector = StepDetector::new();
+        let bump = [1.0, 1.4, 1.0, 1.0];
+        let steps: u32 = bump
+            .iter()
+            .enumerate()
+            .map(|(i, g)| detector.handle(SensorSample::new(i as u64 * 20, SensorReading::Accel { x: 0.0, y: 0.0, z: g * STANDARD_GRAVITY })))
+            .sum();
+        assert_eq!(steps, 0);
+    }
+
+    #[test]
+    fn test_estimates_from_last_fix() {
+        let mut reckoner = DeadReckoner::new();
+        assert!(walk(0, 5, Some(45.0)).into_iter().all(|sample| reckoner.handle(sample).is_none()));
+
+        reckoner.anchor(fix());
+        let estimate = walk(10_000, 215, Some(45.0)).into_iter().filter_map(|sample| reckoner.handle(sample)).last().unwrap();
+        assert!((estimate.distance_m() - 300.0).abs() < 5.0, "{:?}", estimate);
+        assert_eq!(estimate.direction(), "NE");
+        assert!(estimate.latitude > fix().latitude && estimate.longitude > fix().longitude);
+        assert!((fix().offset_by(estimate.east_m, estimate.north_m).0 - estimate.latitude).abs() < 1e-9);
+        assert_eq!(
+            estimate.describe(&chrono::Utc),
+            "last known at 14:02, estimated 300 m NE ±110 m"
+        );
+
+        // Without a heading only the radius grows
+        let lost = walk(300_000, 10, None).into_iter().filter_map(|sample| reckoner.handle(sample)).last().unwrap();
+        assert_eq!((lost.east_m, lost.north_m), (estimate.east_m, estimate.north_m));
+        assert!(lost.radius_m > estimate.radius_m + 12.0);
+
+        reckoner.anchor(Fix { accuracy_m: 5.0, ..fix() });
+        assert_eq!(reckoner.estimate().unwrap().radius_m, 5.0);
+    }
+}
@@ -0,0 +1,438 @@
+//! Diagnostics and Health Monitoring
+//!
+//! This module verifies that the safety net actually works: the speech model
+//! is present, the protocol pack is intact, incidents can be saved, audio is
+//! arriving and being filtered fast enough, and someone besides emergency
+//! services will be alerted.
+//!
+//! The app runs [`Diagnostics`] once at startup and the runtime re-runs it
+//! periodically. Every failed check carries a short user-facing warning such
+//! as "voice model missing: manual SOS only".
+
+use crate::config::AppConfig;
+use crate::error::AppResult;
+use crate::public::voice_stream::StreamStats;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often the runtime re-runs diagnostics
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest a 30 ms frame may take to denoise before detection starts to lag
+pub const DENOISE_BUDGET: Duration = Duration::from_millis(10);
+
+/// How long the microphone may stay silent while listening
+pub const MIC_SILENCE_LIMIT: Duration = Duration::from_secs(5);
+
+/// Files a Vosk model directory must contain to load
+const MODEL_REQUIRED_FILES: &[&str] = &["am/final.mdl", "conf/model.conf"];
+
+/// Decoding graphs, one of which must be present
+const MODEL_GRAPH_FILES: &[&str] = &["graph/HCLG.fst", "graph/HCLr.fst"];
+
+/// Outcome of a single check, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    /// Check passed
+    Pass,
+    /// Check could not run in the current state
+    Skipped,
+    /// Working, but degraded
+    Warn,
+    /// Not working
+    Fail,
+}
+
+impl CheckStatus {
+    /// Gets the label used in human-readable reports
+    pub fn label(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Skipped => "SKIP",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        }
+    }
+}
+
+/// Result of a single diagnostic check
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    /// Check name, e.g. "speech_model"
+    pub name: &'static str,
+    /// Outcome
+    pub status: CheckStatus,
+    /// What was found
+    pub detail: String,
+    /// Warning shown to the user when the check does not pass
+    pub warning: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Pass, detail: detail.into(), warning: None }
+    }
+
+    fn skipped(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Skipped, detail: detail.into(), warning: None }
+    }
+
+    fn degraded(name: &'static str, status: CheckStatus, detail: impl Into<String>, warning: &str) -> Self {
+        Self { name, status, detail: detail.into(), warning: Some(warning.to_string()) }
+    }
+}
+
+/// Health report produced by a diagnostics run
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// When the checks ran
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+    /// Worst status across all checks
+    pub status: CheckStatus,
+    /// Individual check results
+    pub checks: Vec<CheckResult>,
+}
+
+impl HealthReport {
+    /// Build a report from check results
+    pub fn new(checks: Vec<CheckResult>) -> Self {
+        let status = checks.iter().map(|check| check.status).max().unwrap_or(CheckStatus::Skipped);
+        Self {
+            generated_at: chrono::Utc::now(),
+            status,
+            checks,
+        }
+    }
+
+    /// Whether any part of the safety net is degraded
+    pub fn is_degraded(&self) -> bool {
+        self.status >= CheckStatus::Warn
+    }
+
+    /// User-facing warnings, most severe first
+    pub fn warnings(&self) -> Vec<String> {
+        let mut failing: Vec<&CheckResult> = self.checks.iter().filter(|check| check.warning.is_some()).collect();
+        failing.sort_by_key(|check| std::cmp::Reverse(check.status));
+        failing.into_iter().filter_map(|check| check.warning.clone()).collect()
+    }
+
+    /// Serialize the report as JSON
+    pub fn to_json(&self) -> AppResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl Default for HealthReport {
+    /// Report used before diagnostics have run
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl fmt::Display for HealthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let overall = if self.checks.is_empty() {
+            "NOT CHECKED"
+        } else if self.is_degraded() {
+            "DEGRADED"
+        } else {
+            "OK"
+        };
+        writeln!(f, "Solana SOS health: {} ({})", overall, self.generated_at.format("%Y-%m-%d %H:%M:%S UTC"))?;
+        for check in &self.checks {
+            writeln!(f, "  [{}] {:<16} {}", check.status.label(), check.name, check.detail)?;
+        }
+        let warnings = self.warnings();
+        if !warnings.is_empty() {
+            writeln!(f, "Warnings:")?;
+            for warning in warnings {
+                writeln!(f, "  ⚠️  {}", warning)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// State of the voice listener when diagnostics run
+#[derive(Debug, Clone)]
+pub enum MicState {
+    /// The listener is not running, e.g. during the startup self-test
+    NotRunning,
+    /// Listening was paused by the user
+    Paused,
+    /// Listening since `since`, with the stream's current statistics
+    Listening {
+        /// When listening started or resumed
+        since: Instant,
+        /// Statistics from the voice stream
+        stats: StreamStats,
+    },
+}
+
+/// Runs the startup self-test and periodic health checks
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    model_path: PathBuf,
+    protocol_pack: PathBuf,
+    storage_path: PathBuf,
+    contacts: Vec<String>,
+}
+
+impl Diagnostics {
+    /// Create diagnostics for the given settings
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            model_path: config.voice.model_path.clone().into(),
+            protocol_pack: config.database.emergency_instructions_path.clone().into(),
+            storage_path: config.database.emergency_history_path.clone().into(),
+            contacts: config.emergency.emergency_contacts.clone(),
+        }
+    }
+
+    /// Run every check
+    pub fn run(&self, mic: &MicState) -> HealthReport {
+        HealthReport::new(vec![
+            check_speech_model(&self.model_path),
+            check_protocol_pack(&self.protocol_pack),
+            check_storage(&self.storage_path),
+            check_microphone(mic),
+            check_denoise_latency(mic),
+            check_contacts(&self.contacts),
+        ])
+    }
+}
+
+/// Check that the speech model directory holds a loadable model
+pub fn check_speech_model(path: &Path) -> CheckResult {
+    const NAME: &str = "speech_model";
+    const WARNING: &str = "voice model missing: manual SOS only";
+
+    if !path.is_dir() {
+        return CheckResult::degraded(NAME, CheckStatus::Fail, format!("{} not found", path.display()), WARNING);
+    }
+
+    let missing: Vec<&str> = MODEL_REQUIRED_FILES.iter()
+        .copied()
+        .filter(|file| !is_nonempty_file(&path.join(file)))
+        .collect();
+    let has_graph = MODEL_GRAPH_FILES.iter().any(|file| is_nonempty_file(&path.join(file)));
+
+    if !missing.is_empty() || !has_graph {
+        let mut missing = missing.join(", ");
+        if !has_graph {
+            if !missing.is_empty() {
+                missing.push_str(", ");
+            }
+            missing.push_str("decoding graph");
+        }
+        return CheckResult::degraded(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} incomplete, missing {}", path.display(), missing),
+            WARNING,
+        );
+    }
+
+    CheckResult::pass(NAME, format!("{} ready", path.display()))
+}
+
+/// Check the protocol pack against its `.sha256` signature file
+///
+/// Without a pack the built-in protocols are used, which is not a fault.
+pub fn check_protocol_pack(path: &Path) -> CheckResult {
+    const NAME: &str = "protocol_pack";
+
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return CheckResult::pass(NAME, "no pack installed, using built-in protocols");
+        }
+        Err(e) => {
+            return CheckResult::degraded(
+                NAME,
+                CheckStatus::Fail,
+                format!("{} unreadable: {}", path.display(), e),
+                "protocol pack unreadable: using built-in protocols",
+            );
+        }
+    };
+
+    let mut signature_path = path.as_os_str().to_owned();
+    signature_path.push(".sha256");
+    let expected = match std::fs::read_to_string(&signature_path) {
+        Ok(signature) => signature.split_whitespace().next().unwrap_or_default().to_lowercase(),
+        Err(_) => {
+            return CheckResult::degraded(
+                NAME,
+                CheckStatus::Warn,
+                format!("{} has no signature file", path.display()),
+                "protocol pack unsigned: guidance may be outdated",
+            );
+        }
+    };
+
+    let actual: String = Sha256::digest(&contents).iter().map(|byte| format!("{:02x}", byte)).collect();
+    if actual != expected {
+        return CheckResult::degraded(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} signature mismatch", path.display()),
+            "protocol pack signature invalid: using built-in protocols",
+        );
+    }
+
+    CheckResult::pass(NAME, format!("{} signature valid", path.display()))
+}
+
+/// Check that incident records can be written next to the history file
+pub fn check_storage(history_path: &Path) -> CheckResult {
+    const NAME: &str = "storage";
+
+    let dir = match history_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(dir) => dir.to_path_buf(),
+        None => PathBuf::from("."),
+    };
+    let probe = dir.join(".solana_sos_health_probe");
+
+    let result = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe));
+
+    match result {
+        Ok(()) => CheckResult::pass(NAME, format!("{} writable", dir.display())),
+        Err(e) => CheckResult::degraded(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} not writable: {}", dir.display(), e),
+            "storage not writable: incidents will not be saved",
+        ),
+    }
+}
+
+/// Check that microphone frames are arriving while listening
+pub fn check_microphone(mic: &MicState) -> CheckResult {
+    const NAME: &str = "microphone";
+    const WARNING: &str = "microphone silent: voice activation unavailable, use manual SOS";
+
+    let (since, stats) = match mic {
+        MicState::NotRunning => return CheckResult::skipped(NAME, "voice listener not running"),
+        MicState::Paused => return CheckResult::skipped(NAME, "listening paused"),
+        MicState::Listening { since, stats } => (since, stats),
+    };
+
+    match stats.last_frame_at {
+        Some(last) if last.elapsed() <= MIC_SILENCE_LIMIT => {
+            CheckResult::pass(NAME, format!("{} frames received", stats.frames_processed))
+        }
+        Some(last) => CheckResult::degraded(
+            NAME,
+            CheckStatus::Fail,
+            format!("no frames for {}s", last.elapsed().as_secs()),
+            WARNING,
+        ),
+        None if since.elapsed() <= MIC_SILENCE_LIMIT => CheckResult::skipped(NAME, "waiting for first frame"),
+        None => CheckResult::degraded(NAME, CheckStatus::Fail, "no frames received since listening started", WARNING),
+    }
+}
+
+/// Check that denoising keeps up with real time
+pub fn check_denoise_latency(mic: &MicState) -> CheckResult {
+    const NAME: &str = "denoiser";
+
+    let stats = match mic {
+        MicState::Listening { stats, .. } if stats.frames_processed > 0 => stats,
+        _ => return CheckResult::skipped(NAME, "no frames processed yet"),
+    };
+
+    let worst = stats.max_denoise_time;
+    if worst > DENOISE_BUDGET {
+        return CheckResult::degraded(
+            NAME,
+            CheckStatus::Warn,
+            format!("worst frame {:.1} ms, budget {} ms", worst.as_secs_f64() * 1000.0, DENOISE_BUDGET.as_millis()),
+            "noise filtering slow: voice detection may lag",
+        );
+    }
+
+    CheckResult::pass(NAME, format!("worst frame {:.1} ms", worst.as_secs_f64() * 1000.0))
+}
+
+/// Check that at least one personal contact will be alerted
+pub fn check_contacts(contacts: &[String]) -> CheckResult {
+    const NAME: &str = "contacts";
+
+    // Short numbers such as 911 or 112 are emergency services, not people
+    let personal = contacts.iter()
+        .filter(|contact| contact.chars().filter(|c| c.is_ascii_digit()).count() > 3)
+        .count();
+
+    if personal == 0 {
+        return CheckResult::degraded(
+            NAME,
+            CheckStatus::Warn,
+            "only emergency services configured",
+            "no trusted contacts: only emergency services will be alerted",
+        );
+    }
+
+    CheckResult::pass(NAME, format!("{} trusted contact(s)", personal))
+}
+
+fn is_nonempty_file(path: &Path) -> bool {
+    std::fs::metadata(path).map(|meta| meta.is_file() && meta.len() > 0).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_model_degrades_to_manual_sos() {
+        let report = HealthReport::new(vec![
+            check_speech_model(Path::new("models/does-not-exist")),
+            check_contacts(&["911".to_string(), "+15555550100".to_string()]),
+        ]);
+
+        assert_eq!(report.status, CheckStatus::Fail);
+        assert_eq!(report.warnings(), vec!["voice model missing: manual SOS only".to_string()]);
+        assert!(report.to_string().contains("[FAIL] speech_model"));
+        assert!(report.to_json().unwrap().contains("\"status\": \"fail\""));
+    }
+
+    #[test]
+    fn test_protocol_pack_signature() {
+        let dir = std::env::temp_dir().join(format!("solana_sos_pack_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pack = dir.join("protocols.json");
+        std::fs::write(&pack, b"{}").unwrap();
+
+        assert_eq!(check_protocol_pack(&pack).status, CheckStatus::Warn);
+
+        let digest: String = Sha256::digest(b"{}").iter().map(|byte| format!("{:02x}", byte)).collect();
+        std::fs::write(dir.join("protocols.json.sha256"), format!("{}  protocols.json\n", digest)).unwrap();
+        assert_eq!(check_protocol_pack(&pack).status, CheckStatus::Pass);
+
+        std::fs::write(&pack, b"{\"tampered\": true}").unwrap();
+        assert_eq!(check_protocol_pack(&pack).status, CheckStatus::Fail);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_silent_microphone() {
+        let mut stats = StreamStats::default();
+        let since = Instant::now() - MIC_SILENCE_LIMIT * 2;
+
+        let silent = check_microphone(&MicState::Listening { since, stats: stats.clone() });
+        assert_eq!(silent.status, CheckStatus::Fail);
+
+        stats.frames_processed = 10;
+        stats.last_frame_at = Some(Instant::now());
+        assert_eq!(check_microphone(&MicState::Listening { since, stats }).status, CheckStatus::Pass);
+        assert_eq!(check_microphone(&MicState::Paused).status, CheckStatus::Skipped);
+    }
+}
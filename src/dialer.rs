@@ -0,0 +1,582 @@
+//! Emergency Calls
+//!
+//! The host places voice calls; this module decides when and follows them.
+//! An [`EmergencyDialer`] starts a call, on Android an intent callback into
+//! `TelecomManager`, and the host reports each [`CallState`] back through
+//! [`CallReports`]: dialing, connected, failed or ended.
+//!
+//! [`EmergencyCall`] dials emergency services until a call connects:
+//!
+//! 1. a call that fails, ends unanswered, drops or isn't answered within
+//!    [`CallPolicy::answer_timeout`] is retried after
+//!    [`CallPolicy::retry_delay`], up to [`CallPolicy::max_attempts`] calls
+//! 2. a call that connects and ends normally is not retried
+//! 3. once stopped, no further call is placed; a call in progress carries on
+//! 4. once pre-empted, the call in progress is hung up as well
+//!
+//! A connected call is followed for at most [`CallPolicy::follow_limit`] and
+//! then left to the host, so a host that never reports the end can't hold
+//! up the next call.
+//!
+//! A call can carry a message the host speaks into it once connected, for
+//! when the user can't speak. A call that turns covert, e.g. under duress,
+//! carries on but from then on publishes no events and speaks nothing.
+//!
+//! Every attempt is recorded as an [`EmergencyCallData`] with how long it
+//! took from dialing to its end and its [`CallOutcome`]. When no call
+//! connects, the caller falls back to text and trusted contacts.
+
+use crate::error::{AppError, AppResult};
+use crate::event_bus::{AppEvent, EventBus};
+use crate::public::emergency_interface::{CallOutcome, EmergencyCallData};
+use crate::public::types::EmergencyType;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+/// Where a call got to, as reported by the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallState {
+    /// The call is being set up or ringing
+    Dialing,
+    /// The call was answered
+    Connected,
+    /// The call could not be placed or was dropped
+    Failed,
+    /// The call was hung up
+    Ended,
+}
+
+impl CallState {
+    /// Parse a state reported by the host, e.g. "connected"
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key.trim().to_ascii_lowercase().as_str() {
+            "dialing" => Some(CallState::Dialing),
+            "connected" => Some(CallState::Connected),
+            "failed" => Some(CallState::Failed),
+            "ended" => Some(CallState::Ended),
+            _ => None,
+        }
+    }
+}
+
+/// A state change of one call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallUpdate {
+    /// Call the update is for
+    pub call_id: String,
+    /// New state
+    pub state: CallState,
+    /// Why the call failed or ended, if the host knows
+    pub error: Option<String>,
+}
+
+/// Sends call state reports from the host to the running call
+#[derive(Debug, Clone)]
+pub struct CallReports {
+    updates: mpsc::UnboundedSender<CallUpdate>,
+}
+
+impl CallReports {
+    /// Create a reporter and the receiver its reports arrive on
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<CallUpdate>) {
+        let (updates, receiver) = mpsc::unbounded_channel();
+        (Self { updates }, receiver)
+    }
+
+    /// Report a new state for `call_id`; reports after the app stopped are dropped
+    pub fn report(&self, call_id: &str, state: CallState, error: Option<String>) {
+        let _ = self.updates.send(CallUpdate { call_id: call_id.to_string(), state, error });
+    }
+}
+
+/// Host voice calling, e.g. an Android intent callback into `TelecomManager`
+pub trait EmergencyDialer: Send + Sync {
+    /// Start calling `number`, failing if the host could not place the call
+    ///
+    /// `call_id` is new for every attempt. The host reports the call's
+    /// states for it through `reports` or, on Android,
+    /// `RustBridge.reportCallState`.
+    fn dial(&self, number: &str, call_id: &str, reports: &CallReports) -> AppResult<()>;
+
+    /// Hang up `call_id`
+    fn hang_up(&self, call_id: &str) -> AppResult<()>;
+
+    /// Speak `text` into the connected call `call_id` with text-to-speech
+    fn speak(&self, call_id: &str, _text: &str) -> AppResult<()> {
+        Err(AppError::Internal(format!("dialer cannot speak into call {}", call_id)))
+    }
+}
+
+/// Places no calls: logs and records each one and reports it answered
+///
+/// Can be told to fail or leave calls unanswered. It stands in for a phone
+/// in tests and on hosts without voice calling.
+#[derive(Debug, Default)]
+pub struct LocalDialer {
+    dialed: Mutex<Vec<String>>,
+    spoken: Mutex<Vec<String>>,
+    /// Dials still to fail, then dials still to leave unanswered
+    failures: Mutex<(u32, u32)>,
+}
+
+impl LocalDialer {
+    /// Create a dialer that answers every call
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail the next `times` dials outright
+    pub fn fail_times(&self, times: u32) {
+        self.failures.lock().unwrap_or_else(|e| e.into_inner()).0 = times;
+    }
+
+    /// Report the next `times` placed calls as failed after ringing
+    pub fn unanswered_times(&self, times: u32) {
+        self.failures.lock().unwrap_or_else(|e| e.into_inner()).1 = times;
+    }
+
+    /// Numbers dialed so far
+    pub fn dialed(&self) -> Vec<String> {
+        self.dialed.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Messages spoken into calls so far
+    pub fn spoken(&self) -> Vec<String> {
+        self.spoken.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl EmergencyDialer for LocalDialer {
+    fn dial(&self, number: &str, call_id: &str, reports: &CallReports) -> AppResult<()> {
+        tracing::info!("[local] calling {} ({})", number, call_id);
+        self.dialed.lock().unwrap_or_else(|e| e.into_inner()).push(number.to_string());
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        if failures.0 > 0 {
+            failures.0 -= 1;
+            return Err(AppError::Network(format!("could not place call to {}", number)));
+        }
+        reports.report(call_id, CallState::Dialing, None);
+        if failures.1 > 0 {
+            failures.1 -= 1;
+            reports.report(call_id, CallState::Failed, Some("no answer".to_string()));
+        } else {
+            reports.report(call_id, CallState::Connected, None);
+            reports.report(call_id, CallState::Ended, None);
+        }
+        Ok(())
+    }
+
+    fn hang_up(&self, call_id: &str) -> AppResult<()> {
+        tracing::info!("[local] hanging up {}", call_id);
+        Ok(())
+    }
+
+    fn speak(&self, call_id: &str, text: &str) -> AppResult<()> {
+        tracing::info!("[local] speaking into {}: {}", call_id, text);
+        self.spoken.lock().unwrap_or_else(|e| e.into_inner()).push(text.to_string());
+        Ok(())
+    }
+}
+
+/// How often and how patiently emergency services are called
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallPolicy {
+    /// Calls before giving up, including the first
+    pub max_attempts: u32,
+    /// Pause before calling again
+    pub retry_delay: Duration,
+    /// How long a call may ring before it is hung up and retried
+    pub answer_timeout: Duration,
+    /// How long a connected call is followed before it is left to the host
+    pub follow_limit: Duration,
+}
+
+impl Default for CallPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            retry_delay: Duration::from_secs(3),
+            answer_timeout: Duration::from_secs(60),
+            follow_limit: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+/// Every attempt of one emergency call
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CallReport {
+    /// Incident the call belongs to
+    pub incident_id: String,
+    /// Attempts in order
+    pub attempts: Vec<EmergencyCallData>,
+}
+
+impl CallReport {
+    /// Whether any attempt reached emergency services
+    pub fn connected(&self) -> bool {
+        self.attempts.iter().any(|attempt| attempt.outcome == CallOutcome::Successful)
+    }
+}
+
+/// Calls emergency services, retrying until a call connects
+pub struct EmergencyCall {
+    incident_id: String,
+    number: String,
+    emergency_type: Option<EmergencyType>,
+    location: Option<(f64, f64)>,
+    message: Option<String>,
+    dialer: Arc<dyn EmergencyDialer>,
+    reports: CallReports,
+    policy: CallPolicy,
+    event_bus: Option<EventBus>,
+    covert: Option<watch::Receiver<bool>>,
+    preempt: Option<watch::Receiver<bool>>,
+}
+
+impl EmergencyCall {
+    /// Prepare a call to `number` through `dialer`, which reports to `reports`
+    pub fn new(incident_id: &str, number: &str, dialer: Arc<dyn EmergencyDialer>, reports: CallReports) -> Self {
+        Self {
+            incident_id: incident_id.to_string(),
+            number: number.to_string(),
+            emergency_type: None,
+            location: None,
+            message: None,
+            dialer,
+            reports,
+            policy: CallPolicy::default(),
+            event_bus: None,
+            covert: None,
+            preempt: None,
+        }
+    }
+
+    /// Retry as `policy` allows
+    pub fn with_policy(mut self, policy: CallPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Record what the call is about
+    pub fn with_emergency_type(mut self, emergency_type: EmergencyType) -> Self {
+        self.emergency_type = Some(emergency_type);
+        self
+    }
+
+    /// Record the position the call was placed from
+    pub fn with_location(mut self, latitude: f64, longitude: f64) -> Self {
+        self.location = Some((latitude, longitude));
+        self
+    }
+
+    /// Speak `message` into each call once it connects
+    pub fn with_message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    /// Publish `CallPlaced` and `CallStateChanged` events on `event_bus`
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// While `covert` is set, publish nothing and speak nothing into the call
+    pub fn with_covert(mut self, covert: watch::Receiver<bool>) -> Self {
+        self.covert = Some(covert);
+        self
+    }
+
+    /// Once `preempt` is set, hang up the call in progress and place no more
+    pub fn with_preempt(mut self, preempt: watch::Receiver<bool>) -> Self {
+        self.preempt = Some(preempt);
+        self
+    }
+
+    fn is_covert(&self) -> bool {
+        self.covert.as_ref().is_some_and(|covert| *covert.borrow())
+    }
+
+    fn is_preempted(&self) -> bool {
+        self.preempt.as_ref().is_some_and(|preempt| *preempt.borrow())
+    }
+
+    /// Resolves once pre-empted; never without a pre-empt signal
+    async fn preempted(&self) {
+        if let Some(mut preempt) = self.preempt.clone() {
+            if preempt.wait_for(|preempt| *preempt).await.is_ok() {
+                return;
+            }
+        }
+        std::future::pending().await
+    }
+
+    /// Call until a call connects, the attempts run out or `stop` is set
+    ///
+    /// `updates` receives the reports sent through this call's
+    /// [`CallReports`]; reports for other calls are skipped.
+    pub async fn run(self, updates: &mut mpsc::UnboundedReceiver<CallUpdate>, stop: &mut watch::Receiver<bool>) -> CallReport {
+        let mut attempts = Vec::new();
+        for attempt in 1..=self.policy.max_attempts.max(1) {
+            if attempt > 1 {
+                tracing::warn!("Emergency call to {} failed; calling again in {:?}", self.number, self.policy.retry_delay);
+                let wait = async {
+                    tokio::select! {
+                        _ = stop.wait_for(|stop| *stop) => {}
+                        _ = self.preempted() => {}
+                    }
+                };
+                let _ = tokio::time::timeout(self.policy.retry_delay, wait).await;
+            }
+            if *stop.borrow() || self.is_preempted() {
+                tracing::info!("Emergency call to {} stopped", self.number);
+                break;
+            }
+            let data = self.attempt(attempt, updates).await;
+            let connected = data.outcome == CallOutcome::Successful;
+            attempts.push(data);
+            if connected {
+                break;
+            }
+        }
+        CallReport { incident_id: self.incident_id, attempts }
+    }
+
+    async fn attempt(&self, attempt: u32, updates: &mut mpsc::UnboundedReceiver<CallUpdate>) -> EmergencyCallData {
+        // Unique, so a late report for an earlier call can't end this one
+        let call_id = uuid::Uuid::new_v4().to_string();
+        let timestamp = chrono::Utc::now();
+        let started = tokio::time::Instant::now();
+        self.publish(AppEvent::CallPlaced { number: self.number.clone() });
+
+        let result = match self.dialer.dial(&self.number, &call_id, &self.reports) {
+            Ok(()) => self.follow(&call_id, started, updates).await,
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(error) = &result {
+            tracing::warn!("Emergency call {} to {} failed: {}", call_id, self.number, error);
+        }
+        EmergencyCallData {
+            incident_id: self.incident_id.clone(),
+            number: self.number.clone(),
+            attempt,
+            emergency_type: self.emergency_type.clone(),
+            timestamp,
+            location: self.location,
+            duration: started.elapsed(),
+            outcome: if result.is_ok() { CallOutcome::Successful } else { CallOutcome::Failed },
+            error: result.err(),
+        }
+    }
+
+    /// Follow `call_id` until it ends; `Ok` if it connected and ended normally
+    ///
+    /// A connected call that outlasts the follow limit or is pre-empted
+    /// also counts as connected.
+    async fn follow(
+        &self,
+        call_id: &str,
+        started: tokio::time::Instant,
+        updates: &mut mpsc::UnboundedReceiver<CallUpdate>,
+    ) -> Result<(), String> {
+        let mut deadline = started + self.policy.answer_timeout;
+        let mut connected = false;
+        loop {
+            let update = tokio::select! {
+                update = tokio::time::timeout_at(deadline, updates.recv()) => match update {
+                    Ok(update) => update,
+                    Err(_) if connected => {
+                        tracing::info!("Leaving call {} to the host after {:?}", call_id, self.policy.follow_limit);
+                        return Ok(());
+                    }
+                    Err(_) => {
+                        self.hang_up(call_id);
+                        return Err(format!("not answered within {:?}", self.policy.answer_timeout));
+                    }
+                },
+                _ = self.preempted() => {
+                    self.hang_up(call_id);
+                    return if connected { Ok(()) } else { Err("hung up for a more urgent call".to_string()) };
+                }
+            };
+            let Some(update) = update else {
+                return Err("call reports stopped".to_string());
+            };
+            // Late reports from an earlier attempt
+            if update.call_id != call_id {
+                continue;
+            }
+            self.publish(AppEvent::CallStateChanged { number: self.number.clone(), state: update.state });
+            match update.state {
+                CallState::Dialing => {}
+                CallState::Connected => {
+                    connected = true;
+                    deadline = tokio::time::Instant::now() + self.policy.follow_limit;
+                    if let Some(message) = self.message.as_ref().filter(|_| !self.is_covert()) {
+                        if let Err(e) = self.dialer.speak(call_id, message) {
+                            tracing::warn!("Failed to speak into {}: {}", call_id, e);
+                        }
+                    }
+                }
+                CallState::Ended if connected => return Ok(()),
+                CallState::Ended => return Err(update.error.unwrap_or_else(|| "ended before it was answered".to_string())),
+                CallState::Failed if connected => return Err(update.error.unwrap_or_else(|| "dropped".to_string())),
+                CallState::Failed => return Err(update.error.unwrap_or_else(|| "failed".to_string())),
+            }
+        }
+    }
+
+    fn hang_up(&self, call_id: &str) {
+        if let Err(e) = self.dialer.hang_up(call_id) {
+            tracing::warn!("Failed to hang up {}: {}", call_id, e);
+        }
+    }
+
+    fn publish(&self, event: AppEvent) {
+        if let Some(event_bus) = self.event_bus.as_ref().filter(|_| !self.is_covert()) {
+            event_bus.publish(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(dialer: Arc<LocalDialer>, reports: CallReports) -> EmergencyCall {
+        let policy = CallPolicy {
+            max_attempts: 3,
+            retry_delay: Duration::from_millis(10),
+            answer_timeout: Duration::from_millis(100),
+            follow_limit: Duration::from_millis(100),
+        };
+        EmergencyCall::new("incident-1", "112", dialer, reports)
+            .with_emergency_type(EmergencyType::Trauma)
+            .with_message("An ambulance is needed: car crash.")
+            .with_policy(policy)
+            .with_location(47.3656, 8.525)
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_connected() {
+        let dialer = Arc::new(LocalDialer::new());
+        dialer.fail_times(1);
+        dialer.unanswered_times(1);
+        let (reports, mut updates) = CallReports::new();
+        let (_stop, mut stop_rx) = watch::channel(false);
+
+        let report = call(dialer.clone(), reports).run(&mut updates, &mut stop_rx).await;
+        assert!(report.connected());
+        let outcomes: Vec<_> = report.attempts.iter().map(|attempt| (attempt.attempt, attempt.outcome, attempt.error.clone())).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (1, CallOutcome::Failed, Some("Network error: could not place call to 112".to_string())),
+                (2, CallOutcome::Failed, Some("no answer".to_string())),
+                (3, CallOutcome::Successful, None),
+            ]
+        );
+        assert_eq!(dialer.dialed(), vec!["112", "112", "112"]);
+        assert_eq!(dialer.spoken(), vec!["An ambulance is needed: car crash."]);
+        assert_eq!(report.attempts[2].location, Some((47.3656, 8.525)));
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_call_times_out_and_stop_ends_retries() {
+        /// Reports dialing and then nothing, like a line that rings out
+        struct Ringing(Mutex<Vec<String>>);
+        impl EmergencyDialer for Ringing {
+            fn dial(&self, _number: &str, call_id: &str, reports: &CallReports) -> AppResult<()> {
+                reports.report(call_id, CallState::Dialing, None);
+                Ok(())
+            }
+            fn hang_up(&self, call_id: &str) -> AppResult<()> {
+                self.0.lock().unwrap().push(call_id.to_string());
+                Ok(())
+            }
+        }
+
+        let ringing = Arc::new(Ringing(Mutex::new(Vec::new())));
+        let (reports, mut updates) = CallReports::new();
+        let (stop, mut stop_rx) = watch::channel(false);
+        let call = EmergencyCall::new("incident-2", "911", ringing.clone(), reports)
+            .with_policy(CallPolicy { max_attempts: 3, retry_delay: Duration::from_secs(30), answer_timeout: Duration::from_millis(50), ..CallPolicy::default() });
+
+        let stopper = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            stop.send_replace(true);
+        });
+        let report = call.run(&mut updates, &mut stop_rx).await;
+        stopper.await.unwrap();
+
+        assert!(!report.connected());
+        assert_eq!(report.attempts.len(), 1);
+        assert_eq!(report.attempts[0].error.as_deref(), Some("not answered within 50ms"));
+        assert!(report.attempts[0].duration >= Duration::from_millis(50));
+        assert_eq!(ringing.0.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_covert_call_is_silent() {
+        let dialer = Arc::new(LocalDialer::new());
+        let (reports, mut updates) = CallReports::new();
+        let (_stop, mut stop_rx) = watch::channel(false);
+        let (_covert, covert) = watch::channel(true);
+        let event_bus = EventBus::new();
+        let mut tap = event_bus.tap();
+
+        let report = call(dialer.clone(), reports)
+            .with_event_bus(event_bus)
+            .with_covert(covert)
+            .run(&mut updates, &mut stop_rx)
+            .await;
+        assert!(report.connected());
+        assert_eq!(dialer.dialed(), vec!["112"]);
+        assert!(dialer.spoken().is_empty());
+        assert!(tap.drain().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connected_call_is_left_after_follow_limit_or_preempted() {
+        /// Reports calls connected and never ended, like a host that stopped reporting
+        struct Silent(Mutex<Vec<String>>);
+        impl EmergencyDialer for Silent {
+            fn dial(&self, _number: &str, call_id: &str, reports: &CallReports) -> AppResult<()> {
+                reports.report(call_id, CallState::Connected, None);
+                Ok(())
+            }
+            fn hang_up(&self, call_id: &str) -> AppResult<()> {
+                self.0.lock().unwrap().push(call_id.to_string());
+                Ok(())
+            }
+        }
+
+        let silent = Arc::new(Silent(Mutex::new(Vec::new())));
+        let (reports, mut updates) = CallReports::new();
+        let (_stop, mut stop_rx) = watch::channel(false);
+        let policy = CallPolicy { follow_limit: Duration::from_millis(50), ..CallPolicy::default() };
+        let report = EmergencyCall::new("incident-3", "+12025550143", silent.clone(), reports.clone())
+            .with_policy(policy)
+            .run(&mut updates, &mut stop_rx)
+            .await;
+        assert!(report.connected());
+        assert!(silent.0.lock().unwrap().is_empty(), "the call is left to the host, not hung up");
+
+        let (preempt, preempt_rx) = watch::channel(false);
+        let call = EmergencyCall::new("incident-3", "+12025550143", silent.clone(), reports)
+            .with_policy(CallPolicy::default())
+            .with_preempt(preempt_rx);
+        let preempter = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            preempt.send_replace(true);
+        });
+        let report = tokio::time::timeout(Duration::from_secs(1), call.run(&mut updates, &mut stop_rx)).await.unwrap();
+        preempter.await.unwrap();
+        assert!(report.connected());
+        assert_eq!(report.attempts.len(), 1);
+        assert_eq!(silent.0.lock().unwrap().len(), 1, "a pre-empted call is hung up");
+    }
+}
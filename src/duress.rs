@@ -0,0 +1,163 @@
+//! Cancel and Duress Codes
+//!
+//! An emergency is cancelled with the cancel PIN (`emergency.cancel_pin`) or
+//! the cancel phrase (`emergency.crash_cancel_phrase`). Someone forced to
+//! cancel enters the duress code (`emergency.duress_code`) instead: the app
+//! shows the same "cancelled" state, but trusted contacts keep being alerted
+//! and sent the location, with new calls and on-screen notices suppressed. A
+//! call to emergency services already under way carries on, as it would after
+//! a genuine cancel, but no longer shows call states or speaks into the call.
+//! Saying the cancel phrase also answers a fall or crash voice check.
+//!
+//! Codes are compared ignoring case, punctuation and spacing, so "I'm okay"
+//! matches "im okay", and without stopping at the first differing character.
+
+use crate::error::AppResult;
+use crate::secrets::Secret;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// How a cancel code was entered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelMethod {
+    /// Digits only
+    Pin,
+    /// Anything else, typed or spoken
+    Phrase,
+}
+
+/// What an entered code turned out to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeMatch {
+    /// A genuine cancel
+    Cancel(CancelMethod),
+    /// The duress code: look cancelled, keep alerting
+    Duress(CancelMethod),
+}
+
+/// The configured cancel and duress codes
+pub struct CancelCodes {
+    cancel: Vec<Zeroizing<String>>,
+    duress: Option<Zeroizing<String>>,
+}
+
+impl fmt::Debug for CancelCodes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelCodes")
+            .field("cancel", &self.cancel.len())
+            .field("duress", &self.duress.is_some())
+            .finish()
+    }
+}
+
+impl CancelCodes {
+    /// Codes from the resolved secrets and the cancel phrase
+    ///
+    /// Missing secrets leave that code unset; an empty phrase is ignored.
+    pub fn new(cancel_pin: Option<&Secret>, cancel_phrase: &str, duress_code: Option<&Secret>) -> AppResult<Self> {
+        let mut cancel = Vec::new();
+        if let Some(pin) = cancel_pin {
+            cancel.push(normalize(pin.expose_str()?));
+        }
+        cancel.push(normalize(cancel_phrase));
+        cancel.retain(|code| !code.is_empty());
+
+        let duress = duress_code
+            .map(|code| code.expose_str().map(normalize))
+            .transpose()?
+            .filter(|code| !code.is_empty());
+        Ok(Self { cancel, duress })
+    }
+
+    /// Check an entered PIN or phrase
+    ///
+    /// The duress code wins if it is also a cancel code, so a misconfigured
+    /// duress code never stands contacts down.
+    pub fn check(&self, entered: &str) -> Option<CodeMatch> {
+        let entered = normalize(entered);
+        if entered.is_empty() {
+            return None;
+        }
+        let method = if entered.bytes().all(|b| b.is_ascii_digit()) {
+            CancelMethod::Pin
+        } else {
+            CancelMethod::Phrase
+        };
+
+        // Compare against every code so timing doesn't reveal which one matched
+        let duress = self.duress.as_ref().is_some_and(|code| constant_time_eq(code, &entered));
+        let cancel = self.cancel.iter().fold(false, |found, code| constant_time_eq(code, &entered) | found);
+        match (duress, cancel) {
+            (true, _) => Some(CodeMatch::Duress(method)),
+            (false, true) => Some(CodeMatch::Cancel(method)),
+            (false, false) => None,
+        }
+    }
+}
+
+/// Whether recognized speech contains `phrase` as whole words
+///
+/// Used for voice checks, where the answer may come with other words around
+/// it ("yes I'm okay thanks").
+pub fn spoken(speech: &str, phrase: &str) -> bool {
+    let phrase = normalize(phrase);
+    !phrase.is_empty() && format!(" {} ", normalize(speech).as_str()).contains(&format!(" {} ", phrase.as_str()))
+}
+
+/// Lowercase letters and digits, words separated by single spaces
+fn normalize(code: &str) -> Zeroizing<String> {
+    let mut normalized = String::with_capacity(code.len());
+    for word in code.split_whitespace() {
+        let word: String = word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect();
+        if word.is_empty() {
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.push_str(&word);
+    }
+    Zeroizing::new(normalized)
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(text: &str) -> Secret {
+        Secret::new(text.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_cancel_and_duress_codes() {
+        let codes = CancelCodes::new(Some(&secret("4321")), "I'm okay", Some(&secret("Pineapple sky"))).unwrap();
+
+        assert_eq!(codes.check("4321"), Some(CodeMatch::Cancel(CancelMethod::Pin)));
+        assert_eq!(codes.check("  im OKAY! "), Some(CodeMatch::Cancel(CancelMethod::Phrase)));
+        assert_eq!(codes.check("pineapple, sky"), Some(CodeMatch::Duress(CancelMethod::Phrase)));
+        assert_eq!(codes.check("1234"), None);
+        assert_eq!(codes.check("  "), None);
+        assert!(!format!("{:?}", codes).contains("4321"));
+    }
+
+    #[test]
+    fn test_duress_wins_over_cancel_and_missing_codes() {
+        let codes = CancelCodes::new(Some(&secret("1111")), "I'm okay", Some(&secret("1111"))).unwrap();
+        assert_eq!(codes.check("1111"), Some(CodeMatch::Duress(CancelMethod::Pin)));
+
+        // Locked secret store: only the phrase cancels, and nothing is duress
+        let codes = CancelCodes::new(None, "I'm okay", None).unwrap();
+        assert_eq!(codes.check("i'm okay"), Some(CodeMatch::Cancel(CancelMethod::Phrase)));
+        assert_eq!(codes.check("1111"), None);
+
+        assert!(spoken("Yes, I'm OKAY thanks", "I'm okay"));
+        assert!(!spoken("him okay", "I'm okay"));
+        assert!(!spoken("I'm okay", " "));
+    }
+}
@@ -0,0 +1,393 @@
+//! Emergency Numbers
+//!
+//! Emergency services answer on different numbers around the world: 911 in
+//! North America, 112 across Europe, 999 in the UK, 000 in Australia. This
+//! module keeps an offline table of police, ambulance, fire, poison control
+//! and crisis line numbers by ISO 3166 country, and picks the country from,
+//! in order:
+//!
+//! 1. the current location, matched against approximate country bounds
+//! 2. the SIM card's country
+//! 3. the phone's locale, e.g. "fr-CH"
+//! 4. `emergency.contact_region`
+//!
+//! Bounds are rectangles, so near a border a position can fall in more than
+//! one, and the smallest is often the wrong one: Strasbourg falls in Germany's
+//! box, Vancouver in the United States' and Derry in Ireland's. There the SIM's
+//! country wins if it is one of them, then the locale's, and only then the
+//! smallest box. A SIM or locale from elsewhere doesn't break the tie, since
+//! its national numbers may not work here.
+//! Countries missing from the table get [`INTERNATIONAL`], 112, which GSM
+//! phones route to emergency services almost everywhere.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Who to call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmergencyService {
+    /// Police
+    Police,
+    /// Ambulance, for medical emergencies
+    Ambulance,
+    /// Fire brigade
+    Fire,
+    /// Poison control center
+    PoisonControl,
+    /// Suicide and crisis line
+    CrisisLine,
+}
+
+impl fmt::Display for EmergencyService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EmergencyService::Police => "police",
+            EmergencyService::Ambulance => "ambulance",
+            EmergencyService::Fire => "fire",
+            EmergencyService::PoisonControl => "poison control",
+            EmergencyService::CrisisLine => "crisis line",
+        })
+    }
+}
+
+/// Emergency numbers of one country
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CountryNumbers {
+    /// ISO 3166 alpha-2 code; "ZZ" for [`INTERNATIONAL`]
+    pub country: &'static str,
+    /// Police
+    pub police: &'static str,
+    /// Ambulance
+    pub ambulance: &'static str,
+    /// Fire brigade
+    pub fire: &'static str,
+    /// National poison control line, where there is one
+    pub poison_control: Option<&'static str>,
+    /// National suicide and crisis line, where there is one
+    pub crisis_line: Option<&'static str>,
+}
+
+impl CountryNumbers {
+    /// The number for `service`, if the country has one
+    pub fn number(&self, service: EmergencyService) -> Option<&'static str> {
+        match service {
+            EmergencyService::Police => Some(self.police),
+            EmergencyService::Ambulance => Some(self.ambulance),
+            EmergencyService::Fire => Some(self.fire),
+            EmergencyService::PoisonControl => self.poison_control,
+            EmergencyService::CrisisLine => self.crisis_line,
+        }
+    }
+}
+
+const fn numbers(
+    country: &'static str,
+    police: &'static str,
+    ambulance: &'static str,
+    fire: &'static str,
+    poison_control: Option<&'static str>,
+    crisis_line: Option<&'static str>,
+) -> CountryNumbers {
+    CountryNumbers { country, police, ambulance, fire, poison_control, crisis_line }
+}
+
+/// Numbers for countries missing from the table
+pub const INTERNATIONAL: CountryNumbers = numbers("ZZ", "112", "112", "112", None, None);
+
+/// Emergency numbers by country
+const COUNTRIES: &[CountryNumbers] = &[
+    numbers("AE", "999", "998", "997", None, None),
+    numbers("AR", "101", "107", "100", None, None),
+    numbers("AT", "133", "144", "122", Some("014064343"), Some("142")),
+    numbers("AU", "000", "000", "000", Some("131126"), Some("131114")),
+    numbers("BE", "101", "112", "112", Some("070245245"), Some("1813")),
+    numbers("BR", "190", "192", "193", None, Some("188")),
+    numbers("CA", "911", "911", "911", None, Some("988")),
+    numbers("CH", "117", "144", "118", Some("145"), Some("143")),
+    numbers("CL", "133", "131", "132", None, None),
+    numbers("CN", "110", "120", "119", None, None),
+    numbers("CO", "123", "123", "123", None, None),
+    numbers("DE", "110", "112", "112", None, Some("08001110111")),
+    numbers("DK", "112", "112", "112", None, Some("70201201")),
+    numbers("EG", "122", "123", "180", None, None),
+    numbers("ES", "091", "112", "112", None, Some("024")),
+    numbers("FI", "112", "112", "112", None, None),
+    numbers("FR", "17", "15", "18", None, Some("3114")),
+    numbers("GB", "999", "999", "999", None, Some("116123")),
+    numbers("GR", "100", "166", "199", None, Some("1018")),
+    numbers("HK", "999", "999", "999", None, None),
+    numbers("ID", "110", "119", "113", None, None),
+    numbers("IE", "112", "112", "112", None, Some("116123")),
+    numbers("IL", "100", "101", "102", None, Some("1201")),
+    numbers("IN", "112", "108", "101", None, Some("14416")),
+    numbers("IT", "113", "118", "115", None, None),
+    numbers("JP", "110", "119", "119", None, None),
+    numbers("KE", "999", "999", "999", None, None),
+    numbers("KR", "112", "119", "119", None, Some("109")),
+    numbers("MX", "911", "911", "911", None, None),
+    numbers("MY", "999", "999", "994", None, None),
+    numbers("NG", "112", "112", "112", None, None),
+    numbers("NL", "112", "112", "112", None, Some("113")),
+    numbers("NO", "112", "113", "110", None, Some("116123")),
+    numbers("NZ", "111", "111", "111", Some("0800764766"), Some("1737")),
+    numbers("PE", "105", "106", "116", None, None),
+    numbers("PH", "911", "911", "911", None, None),
+    numbers("PL", "997", "999", "998", None, Some("116123")),
+    numbers("PT", "112", "112", "112", None, None),
+    numbers("RU", "102", "103", "101", None, None),
+    numbers("SA", "911", "911", "911", None, None),
+    numbers("SE", "112", "112", "112", None, Some("90101")),
+    numbers("SG", "999", "995", "995", None, Some("1767")),
+    numbers("TH", "191", "1669", "199", None, Some("1323")),
+    numbers("TR", "112", "112", "112", None, None),
+    numbers("TW", "110", "119", "119", None, Some("1925")),
+    numbers("UA", "102", "103", "101", None, None),
+    numbers("US", "911", "911", "911", Some("18002221222"), Some("988")),
+    numbers("VN", "113", "115", "114", None, None),
+    numbers("ZA", "10111", "10177", "10177", None, None),
+];
+
+/// Approximate country bounds as (south, west, north, east) in degrees
+const BOUNDS: &[(&str, [f64; 4])] = &[
+    ("AE", [22.6, 51.6, 26.1, 56.4]),
+    ("AR", [-55.1, -73.6, -21.8, -53.6]),
+    ("AT", [46.4, 9.5, 49.0, 17.2]),
+    ("AU", [-43.7, 113.3, -10.7, 153.6]),
+    ("BE", [49.5, 2.5, 51.5, 6.4]),
+    ("BR", [-33.8, -74.0, 5.3, -34.8]),
+    ("CA", [41.7, -141.0, 83.1, -52.6]),
+    ("CH", [45.8, 5.9, 47.8, 10.5]),
+    ("CL", [-56.0, -75.7, -17.5, -66.4]),
+    ("CN", [18.2, 73.5, 53.6, 134.8]),
+    ("CO", [-4.2, -79.0, 12.5, -66.9]),
+    ("DE", [47.3, 5.9, 55.1, 15.0]),
+    ("DK", [54.6, 8.1, 57.8, 15.2]),
+    ("EG", [22.0, 24.7, 31.7, 36.9]),
+    ("ES", [36.0, -9.3, 43.8, 3.3]),
+    ("ES", [27.6, -18.2, 29.4, -13.4]),
+    ("FI", [59.8, 20.6, 70.1, 31.6]),
+    ("FR", [41.3, -5.1, 51.1, 9.6]),
+    ("GB", [49.9, -8.2, 60.9, 1.8]),
+    ("GR", [34.8, 19.4, 41.7, 28.2]),
+    ("HK", [22.15, 113.8, 22.6, 114.4]),
+    ("ID", [-11.0, 95.0, 6.1, 141.0]),
+    ("IE", [51.4, -10.5, 55.4, -6.0]),
+    ("IL", [29.5, 34.3, 33.3, 35.9]),
+    ("IN", [6.7, 68.1, 35.5, 97.4]),
+    ("IT", [36.6, 6.6, 47.1, 18.5]),
+    ("JP", [24.0, 122.9, 45.6, 145.8]),
+    ("KE", [-4.7, 33.9, 5.0, 41.9]),
+    ("KR", [33.1, 124.6, 38.6, 131.9]),
+    ("MX", [14.5, -118.4, 32.7, -86.7]),
+    ("MY", [0.8, 99.6, 7.4, 119.3]),
+    ("NG", [4.3, 2.7, 13.9, 14.7]),
+    ("NL", [50.8, 3.4, 53.6, 7.2]),
+    ("NO", [58.0, 4.6, 71.2, 31.1]),
+    ("NZ", [-47.3, 166.4, -34.4, 178.6]),
+    ("PE", [-18.4, -81.3, 0.0, -68.7]),
+    ("PH", [4.6, 116.9, 21.1, 126.6]),
+    ("PL", [49.0, 14.1, 54.8, 24.2]),
+    ("PT", [36.9, -9.5, 42.2, -6.2]),
+    ("RU", [41.2, 19.6, 81.9, 180.0]),
+    ("SA", [16.4, 34.5, 32.2, 55.7]),
+    ("SE", [55.3, 11.1, 69.1, 24.2]),
+    ("SG", [1.2, 103.6, 1.5, 104.1]),
+    ("TH", [5.6, 97.3, 20.5, 105.6]),
+    ("TR", [35.8, 26.0, 42.1, 44.8]),
+    ("TW", [21.9, 120.0, 25.3, 122.0]),
+    ("UA", [44.4, 22.1, 52.4, 40.2]),
+    ("US", [24.4, -124.8, 49.4, -66.9]),
+    ("US", [51.2, -179.2, 71.5, -129.9]),
+    ("US", [18.9, -160.3, 22.3, -154.8]),
+    ("VN", [8.4, 102.1, 23.4, 109.5]),
+    ("ZA", [-34.8, 16.5, -22.1, 32.9]),
+];
+
+/// The numbers for an ISO 3166 alpha-2 code, if the table has the country
+pub fn lookup(country: &str) -> Option<&'static CountryNumbers> {
+    let country = country_code(country)?;
+    COUNTRIES.iter().find(|numbers| numbers.country == country)
+}
+
+/// Whether `number` is a police, ambulance or fire number of any country in the table
+pub fn is_emergency_number(number: &str) -> bool {
+    let number = number.trim();
+    COUNTRIES
+        .iter()
+        .chain([&INTERNATIONAL])
+        .any(|numbers| [numbers.police, numbers.ambulance, numbers.fire].contains(&number))
+}
+
+/// Countries whose approximate bounds contain a position, smallest first
+pub fn countries_at(latitude: f64, longitude: f64) -> Vec<&'static str> {
+    let mut matches: Vec<(&'static str, f64)> = BOUNDS
+        .iter()
+        .filter(|(_, [south, west, north, east])| {
+            (*south..=*north).contains(&latitude) && (*west..=*east).contains(&longitude)
+        })
+        .map(|(country, [south, west, north, east])| (*country, (north - south) * (east - west)))
+        .collect();
+    matches.sort_by(|a, b| a.1.total_cmp(&b.1));
+    matches.into_iter().map(|(country, _)| country).collect()
+}
+
+/// Uppercase alpha-2 code, if `text` is one
+fn country_code(text: &str) -> Option<String> {
+    let code = text.trim();
+    (code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic())).then(|| code.to_ascii_uppercase())
+}
+
+/// Region of a locale tag such as "fr-CH" or "en_US"
+pub fn locale_country(locale: &str) -> Option<String> {
+    locale.split(['-', '_', '.', '@']).skip(1).find_map(country_code)
+}
+
+/// Where the country was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CountrySource {
+    /// The current location
+    Location,
+    /// The SIM card
+    Sim,
+    /// The phone's locale
+    Locale,
+    /// `emergency.contact_region`
+    Config,
+}
+
+/// What the host knows about where the phone is
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CountryHints {
+    /// Latest position as (latitude, longitude)
+    pub location: Option<(f64, f64)>,
+    /// SIM country code, e.g. "ch"
+    pub sim_country: Option<String>,
+    /// Locale tag, e.g. "de-CH"
+    pub locale: Option<String>,
+}
+
+impl CountryHints {
+    /// Pick the country, falling back to `region` from the config
+    pub fn resolve(&self, region: &str) -> NumberSelection {
+        let sim = self.sim_country.as_deref().and_then(country_code);
+        let locale = self.locale.as_deref().and_then(locale_country);
+        let located = self.location.and_then(|(latitude, longitude)| {
+            let candidates = countries_at(latitude, longitude);
+            // Near a border, trust the SIM, then the locale, if one of the candidates
+            [&sim, &locale]
+                .into_iter()
+                .flatten()
+                .find(|country| candidates.contains(&country.as_str()))
+                .cloned()
+                .or_else(|| candidates.first().map(|country| country.to_string()))
+        });
+        let (country, source) = match (located, sim, locale) {
+            (Some(country), _, _) => (country, CountrySource::Location),
+            (None, Some(country), _) => (country, CountrySource::Sim),
+            (None, None, Some(country)) => (country, CountrySource::Locale),
+            (None, None, None) => (region.trim().to_ascii_uppercase(), CountrySource::Config),
+        };
+        let numbers = lookup(&country).copied().unwrap_or(INTERNATIONAL);
+        NumberSelection { country, source, numbers }
+    }
+}
+
+/// The country emergency calls are placed in, and its numbers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NumberSelection {
+    /// ISO 3166 alpha-2 code
+    pub country: String,
+    /// Where the country was taken from
+    pub source: CountrySource,
+    /// The country's numbers, or [`INTERNATIONAL`] if it isn't in the table
+    pub numbers: CountryNumbers,
+}
+
+impl NumberSelection {
+    /// The number for `service`, if the country has one
+    pub fn number(&self, service: EmergencyService) -> Option<&'static str> {
+        self.numbers.number(service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_country_table() {
+        assert_eq!(lookup("us").unwrap().number(EmergencyService::CrisisLine), Some("988"));
+        assert_eq!(lookup("GB").unwrap().ambulance, "999");
+        assert_eq!(lookup("AU").unwrap().number(EmergencyService::Police), Some("000"));
+        assert_eq!(lookup("FR").unwrap().number(EmergencyService::PoisonControl), None);
+        assert_eq!(lookup("XK"), None);
+        assert!(COUNTRIES.windows(2).all(|pair| pair[0].country < pair[1].country));
+        assert!(BOUNDS.iter().all(|(country, _)| lookup(country).is_some()));
+        assert!(is_emergency_number("112") && is_emergency_number("999") && is_emergency_number("15"));
+        assert!(!is_emergency_number("+12025550143") && !is_emergency_number("988"));
+
+        assert_eq!(locale_country("fr-CH"), Some("CH".to_string()));
+        assert_eq!(locale_country("en_us.UTF-8"), Some("US".to_string()));
+        assert_eq!(locale_country("es-419"), None);
+    }
+
+    #[test]
+    fn test_resolves_location_then_sim_then_locale() {
+        let zurich = (47.3656, 8.525);
+        let hints = CountryHints { location: Some(zurich), sim_country: Some("us".into()), locale: Some("en-US".into()) };
+        let selection = hints.resolve("US");
+        assert_eq!((selection.country.as_str(), selection.source), ("CH", CountrySource::Location));
+        assert_eq!(selection.number(EmergencyService::Ambulance), Some("144"));
+
+        // Zurich is also inside Germany's rectangle; a German SIM tips it
+        let hints = CountryHints { sim_country: Some("DE".into()), ..hints };
+        assert_eq!(hints.resolve("US").country, "DE");
+
+        let selection = CountryHints { location: Some(zurich), ..CountryHints::default() }.resolve("US");
+        assert_eq!(selection.country, "CH");
+
+        let at_sea = CountryHints { location: Some((0.0, -30.0)), sim_country: Some("gb".into()), ..CountryHints::default() };
+        let selection = at_sea.resolve("US");
+        assert_eq!((selection.numbers.police, selection.source), ("999", CountrySource::Sim));
+
+        let selection = CountryHints { locale: Some("de-AT".into()), ..CountryHints::default() }.resolve("US");
+        assert_eq!((selection.numbers.fire, selection.source), ("122", CountrySource::Locale));
+
+        let selection = CountryHints::default().resolve("xk");
+        assert_eq!((selection.country.as_str(), selection.source), ("XK", CountrySource::Config));
+        assert_eq!(selection.numbers, INTERNATIONAL);
+    }
+
+    #[test]
+    fn test_border_towns_follow_sim_then_locale() {
+        let at = |location: (f64, f64), sim: Option<&str>, locale: Option<&str>| {
+            CountryHints { location: Some(location), sim_country: sim.map(Into::into), locale: locale.map(Into::into) }.resolve("US")
+        };
+
+        // Derry is in the UK but inside Ireland's smaller box
+        let derry = (54.9966, -7.3086);
+        assert_eq!(countries_at(derry.0, derry.1), vec!["IE", "GB"]);
+        let selection = at(derry, Some("gb"), None);
+        assert_eq!((selection.numbers.ambulance, selection.source), ("999", CountrySource::Location));
+        assert_eq!(at(derry, Some("ie"), None).numbers.ambulance, "112");
+        assert_eq!(at(derry, None, Some("en-GB")).numbers.police, "999");
+        // A SIM from elsewhere doesn't decide, but the locale still can
+        assert_eq!(at(derry, Some("us"), Some("en-GB")).country, "GB");
+        assert_eq!(at(derry, Some("us"), None).country, "IE");
+
+        let strasbourg = (48.5734, 7.7521);
+        assert_eq!(at(strasbourg, Some("FR"), None).numbers.ambulance, "15");
+        assert_eq!(at(strasbourg, None, Some("fr-FR")).numbers.police, "17");
+        assert_eq!(at(strasbourg, Some("DE"), None).numbers.police, "110");
+
+        let vancouver = (49.2827, -123.1207);
+        assert_eq!(at(vancouver, Some("ca"), None).country, "CA");
+        assert_eq!(at(vancouver, Some("fr"), Some("en-CA")).country, "CA");
+
+        let el_paso = (31.7619, -106.485);
+        assert_eq!(at(el_paso, Some("us"), None).numbers.crisis_line, Some("988"));
+        assert_eq!(at(el_paso, None, Some("es-MX")).country, "MX");
+    }
+}
@@ -88,6 +88,12 @@ impl From<std::io::Error> for AppError {
     }
 }
 
+impl From<jni::errors::Error> for AppError {
+    fn from(err: jni::errors::Error) -> Self {
+        AppError::Internal(err.to_string())
+    }
+}
+
 impl From<serde_json::Error> for AppError {
     fn from(err: serde_json::Error) -> Self {
         AppError::Internal(err.to_string())
@@ -0,0 +1,501 @@
+//! Trusted Network Escalation
+//!
+//! When family alerts are enabled, an emergency is escalated through the
+//! user's contacts until someone responds:
+//!
+//! 1. primary contacts are alerted
+//! 2. if nobody acknowledges within `alert_timeout`, secondary and medical
+//!    contacts are alerted
+//! 3. if still nobody acknowledges, emergency services are alerted
+//!
+//! Every alerted contact is sent each location the location service shares,
+//! labelled with its accuracy and age. Escalation
+//! stops when a contact acknowledges or the user cancels; on cancel the
+//! alerted contacts are told to stand down. Each delivery attempt is recorded
+//! in the [`EscalationReport`].
+//!
+//! Messages are rendered from [`AlertTemplates`]; emergency services are
+//! called on the number picked for the country the phone is in and sent the
+//! text-to-911 variant.
+
+use crate::config::EmergencyConfig;
+use crate::contacts::{Contact, ContactTier, NotificationChannel};
+use crate::event_bus::{AppEvent, EventBus};
+use crate::location::LocationReport;
+use crate::notifier::{Alert, Notifier};
+use crate::templates::{AlertContext, AlertKind, AlertTemplates};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+/// Timing and reach of an escalation
+#[derive(Debug, Clone, PartialEq)]
+pub struct EscalationPolicy {
+    /// How long each step waits for an acknowledgement
+    pub ack_timeout: Duration,
+    /// Emergency services number for the last step, or `None` to stop at contacts
+    pub emergency_number: Option<String>,
+}
+
+impl EscalationPolicy {
+    /// Build the policy from the emergency settings
+    ///
+    /// Emergency services are only alerted, on `emergency_number`, when
+    /// `auto_dial_911` is enabled.
+    pub fn from_config(config: &EmergencyConfig, emergency_number: &str) -> Self {
+        Self {
+            ack_timeout: Duration::from_secs(config.alert_timeout),
+            emergency_number: config.auto_dial_911.then(|| emergency_number.to_string()),
+        }
+    }
+}
+
+/// A stage of the escalation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscalationStep {
+    /// Primary contacts
+    Primary,
+    /// Secondary and medical contacts
+    Secondary,
+    /// Emergency services
+    EmergencyServices,
+}
+
+/// Why an alert was sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertPurpose {
+    /// First alert for the emergency
+    Alert,
+    /// Changed location
+    LocationUpdate,
+    /// The user cancelled; no help is needed
+    StandDown,
+}
+
+/// One delivery attempt
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertAttempt {
+    /// When the attempt was made
+    pub at: chrono::DateTime<chrono::Utc>,
+    /// Step the recipient belongs to
+    pub step: EscalationStep,
+    /// Why the alert was sent
+    pub purpose: AlertPurpose,
+    /// Recipient name
+    pub recipient: String,
+    /// Address the alert was sent to
+    pub address: String,
+    /// Delivery channel
+    pub channel: NotificationChannel,
+    /// Delivery error, if the attempt failed
+    pub error: Option<String>,
+}
+
+/// Input to a running escalation
+#[derive(Debug, Clone, PartialEq)]
+pub enum EscalationSignal {
+    /// A contact acknowledged the alert
+    Acknowledged {
+        /// Contact name or number
+        by: String,
+    },
+    /// The user cancelled the alerts
+    Cancelled,
+    /// The user was forced to cancel: tell the alerted contacts and carry on covertly
+    Duress,
+}
+
+/// How an escalation ended
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscalationOutcome {
+    /// A contact acknowledged during `step`
+    Acknowledged {
+        /// Contact name or number
+        by: String,
+        /// Step that was waiting
+        step: EscalationStep,
+    },
+    /// The user cancelled during `step`
+    Cancelled {
+        /// Step that was waiting
+        step: EscalationStep,
+    },
+    /// Every step ran without an acknowledgement
+    Exhausted,
+}
+
+/// Result of an escalation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EscalationReport {
+    /// Incident that was escalated
+    pub incident_id: String,
+    /// How it ended
+    pub outcome: EscalationOutcome,
+    /// Every delivery attempt, in order
+    pub attempts: Vec<AlertAttempt>,
+}
+
+/// Someone an escalation alerts
+struct Recipient {
+    name: String,
+    step: EscalationStep,
+    /// Channel and address pairs
+    routes: Vec<(NotificationChannel, String)>,
+}
+
+/// Escalation of one incident through the trusted network
+pub struct Escalation {
+    incident_id: String,
+    context: AlertContext,
+    templates: AlertTemplates,
+    contacts: Vec<Contact>,
+    policy: EscalationPolicy,
+    notifier: Arc<dyn Notifier>,
+    event_bus: Option<EventBus>,
+    alerted: Vec<Recipient>,
+    attempts: Vec<AlertAttempt>,
+    last_location: Option<LocationReport>,
+}
+
+impl Escalation {
+    /// Prepare an escalation; nothing is sent until [`run`](Self::run)
+    ///
+    /// `context` describes the emergency; its location is filled in as it
+    /// becomes known.
+    pub fn new(
+        incident_id: &str,
+        context: AlertContext,
+        contacts: Vec<Contact>,
+        policy: EscalationPolicy,
+        notifier: Arc<dyn Notifier>,
+    ) -> Self {
+        Self {
+            incident_id: incident_id.to_string(),
+            context,
+            templates: AlertTemplates::default(),
+            contacts: contacts.into_iter().filter(|contact| contact.is_active).collect(),
+            policy,
+            notifier,
+            event_bus: None,
+            alerted: Vec::new(),
+            attempts: Vec::new(),
+            last_location: None,
+        }
+    }
+
+    /// Render messages from `templates` instead of the English defaults
+    pub fn with_templates(mut self, templates: AlertTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Publish `ContactNotified` for each delivered alert
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Run until acknowledged, cancelled or out of steps
+    ///
+    /// `location` carries the latest shared position; each change is sent
+    /// to the contacts alerted so far.
+    pub async fn run(
+        mut self,
+        mut signals: mpsc::Receiver<EscalationSignal>,
+        mut location: watch::Receiver<Option<LocationReport>>,
+    ) -> EscalationReport {
+        let steps = [EscalationStep::Primary, EscalationStep::Secondary, EscalationStep::EmergencyServices];
+        let mut signals_open = true;
+
+        for step in steps {
+            let recipients = self.recipients(step);
+            if recipients.is_empty() {
+                continue;
+            }
+            self.last_location = *location.borrow_and_update();
+            for recipient in &recipients {
+                self.send(recipient, AlertPurpose::Alert).await;
+            }
+            self.alerted.extend(recipients);
+
+            let deadline = tokio::time::sleep(self.policy.ack_timeout);
+            tokio::pin!(deadline);
+
+            loop {
+                tokio::select! {
+                    _ = &mut deadline => break,
+                    signal = signals.recv(), if signals_open => match signal {
+                        Some(EscalationSignal::Acknowledged { by }) => {
+                            return self.finish(EscalationOutcome::Acknowledged { by, step });
+                        }
+                        Some(EscalationSignal::Cancelled) => {
+                            self.stand_down().await;
+                            return self.finish(EscalationOutcome::Cancelled { step });
+                        }
+                        Some(EscalationSignal::Duress) => self.duress(&mut location).await,
+                        // Nobody can acknowledge any more; keep escalating
+                        None => signals_open = false,
+                    },
+                    Ok(()) = location.changed() => self.resend_location(&mut location).await,
+                }
+            }
+        }
+        self.finish(EscalationOutcome::Exhausted)
+    }
+
+    /// Who is alerted at `step`
+    fn recipients(&self, step: EscalationStep) -> Vec<Recipient> {
+        let tiers: &[ContactTier] = match step {
+            EscalationStep::Primary => &[ContactTier::Primary],
+            EscalationStep::Secondary => &[ContactTier::Secondary, ContactTier::Medical],
+            EscalationStep::EmergencyServices => {
+                return self.policy.emergency_number.iter()
+                    .map(|number| Recipient {
+                        name: "Emergency services".to_string(),
+                        step,
+                        routes: vec![(NotificationChannel::Call, number.clone()), (NotificationChannel::Sms, number.clone())],
+                    })
+                    .collect();
+            }
+        };
+        self.contacts
+            .iter()
+            .filter(|contact| tiers.contains(&contact.tier))
+            .map(|contact| Recipient {
+                name: contact.name.clone(),
+                step,
+                routes: contact.channels.iter()
+                    .filter_map(|channel| {
+                        let address = match channel {
+                            NotificationChannel::Email => contact.email.clone()?,
+                            _ => contact.phone_number.clone(),
+                        };
+                        Some((*channel, address))
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    async fn send(&mut self, recipient: &Recipient, purpose: AlertPurpose) {
+        let message = self.message(recipient, purpose);
+        for (channel, address) in &recipient.routes {
+            // Location updates and stand-downs are text; don't ring phones for them.
+            // Covert alerts never place calls, which would show on the user's screen.
+            let covert = self.context.kind.is_covert();
+            if (purpose != AlertPurpose::Alert || covert) && *channel == NotificationChannel::Call {
+                continue;
+            }
+            let alert = Alert {
+                incident_id: self.incident_id.clone(),
+                recipient: recipient.name.clone(),
+                address: address.clone(),
+                channel: *channel,
+                message: message.clone(),
+            };
+            let error = self.notifier.deliver(&alert).await.err().map(|e| e.to_string());
+            match &error {
+                None if covert => {}
+                None => {
+                    if let Some(event_bus) = &self.event_bus {
+                        event_bus.publish(AppEvent::ContactNotified { contact: recipient.name.clone() });
+                    }
+                }
+                Some(error) => tracing::warn!("Alert to {} via {:?} failed: {}", recipient.name, channel, error),
+            }
+            self.attempts.push(AlertAttempt {
+                at: chrono::Utc::now(),
+                step: recipient.step,
+                purpose,
+                recipient: recipient.name.clone(),
+                address: address.clone(),
+                channel: *channel,
+                error,
+            });
+        }
+    }
+
+    async fn resend_location(&mut self, location: &mut watch::Receiver<Option<LocationReport>>) {
+        // A report of the same fix and estimate only has a newer age; don't resend it
+        let current = *location.borrow_and_update();
+        let position = |report: Option<LocationReport>| report.map(|report| (report.fix(), report.estimate));
+        if current.is_none() || position(current) == position(self.last_location) {
+            return;
+        }
+        self.last_location = current;
+        let alerted = std::mem::take(&mut self.alerted);
+        for recipient in &alerted {
+            self.send(recipient, AlertPurpose::LocationUpdate).await;
+        }
+        self.alerted = alerted;
+    }
+
+    async fn duress(&mut self, location: &mut watch::Receiver<Option<LocationReport>>) {
+        self.context.kind = AlertKind::Duress;
+        self.context.time = chrono::Local::now().fixed_offset();
+        self.last_location = location.borrow_and_update().or(self.last_location);
+        let alerted = std::mem::take(&mut self.alerted);
+        for recipient in &alerted {
+            self.send(recipient, AlertPurpose::Alert).await;
+        }
+        self.alerted = alerted;
+    }
+
+    async fn stand_down(&mut self) {
+        let alerted = std::mem::take(&mut self.alerted);
+        for recipient in alerted.iter().filter(|recipient| recipient.step != EscalationStep::EmergencyServices) {
+            self.send(recipient, AlertPurpose::StandDown).await;
+        }
+        self.alerted = alerted;
+    }
+
+    fn message(&self, recipient: &Recipient, purpose: AlertPurpose) -> String {
+        let mut context = AlertContext {
+            location: self.last_location.map(|report| (report.latitude, report.longitude)),
+            fix: self.last_location,
+            ..self.context.clone()
+        };
+        let kind = match purpose {
+            AlertPurpose::Alert => None,
+            AlertPurpose::LocationUpdate => Some(AlertKind::LocationUpdate),
+            AlertPurpose::StandDown => Some(AlertKind::StandDown),
+        };
+        if let Some(kind) = kind {
+            context.kind = kind;
+            context.time = chrono::Local::now().fixed_offset();
+        }
+        match recipient.step {
+            EscalationStep::EmergencyServices => self.templates.text_to_911(&context),
+            _ => self.templates.render(&context),
+        }
+    }
+
+    fn finish(self, outcome: EscalationOutcome) -> EscalationReport {
+        tracing::info!("Escalation for incident {} ended: {:?}", self.incident_id, outcome);
+        EscalationReport { incident_id: self.incident_id, outcome, attempts: self.attempts }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Fix;
+    use crate::notifier::LocalNotifier;
+    use crate::public::types::EmergencyType;
+
+    fn contacts() -> Vec<Contact> {
+        vec![
+            Contact::new("Mom", "+12025550143", ContactTier::Primary),
+            Contact::new("Dr. Lee", "+12025550144", ContactTier::Medical)
+                .with_email("lee@example.com")
+                .with_channels(&[NotificationChannel::Sms, NotificationChannel::Email]),
+        ]
+    }
+
+    fn context() -> AlertContext {
+        AlertContext::new(AlertKind::Emergency, "Alex").with_emergency_type(EmergencyType::Drowning)
+    }
+
+    fn policy() -> EscalationPolicy {
+        EscalationPolicy {
+            ack_timeout: Duration::from_millis(50),
+            emergency_number: Some("911".to_string()),
+        }
+    }
+
+    fn shared(latitude: f64, longitude: f64) -> Option<LocationReport> {
+        let now = chrono::Utc::now();
+        Fix::new(latitude, longitude, 10.0, now).map(|fix| fix.report(now))
+    }
+
+    #[tokio::test]
+    async fn test_escalates_until_exhausted() {
+        let notifier = Arc::new(LocalNotifier::new());
+        notifier.fail_address("lee@example.com");
+        let (_signals, signals_rx) = mpsc::channel(4);
+        let (_location, location_rx) = watch::channel(shared(38.8977, -77.0365));
+
+        let report = Escalation::new("incident-1", context(), contacts(), policy(), notifier.clone())
+            .run(signals_rx, location_rx)
+            .await;
+
+        assert_eq!(report.outcome, EscalationOutcome::Exhausted);
+        let steps: Vec<_> = report.attempts.iter().map(|attempt| (attempt.step, attempt.channel)).collect();
+        assert_eq!(steps, [
+            (EscalationStep::Primary, NotificationChannel::Sms),
+            (EscalationStep::Primary, NotificationChannel::Call),
+            (EscalationStep::Secondary, NotificationChannel::Sms),
+            (EscalationStep::Secondary, NotificationChannel::Email),
+            (EscalationStep::EmergencyServices, NotificationChannel::Call),
+            (EscalationStep::EmergencyServices, NotificationChannel::Sms),
+        ]);
+        assert!(report.attempts[3].error.is_some());
+        let sent = notifier.sent();
+        assert_eq!(sent.len(), 5);
+        assert!(sent[0].message.starts_with("Alex has an emergency (Drowning)"));
+        assert!(sent[0].message.contains("https://maps.google.com/?q=38.89770,-77.03650"));
+        assert!(sent[0].message.ends_with("Accurate to 10 m, live."));
+        assert!(sent[4].message.starts_with("EMERGENCY: Drowning. Location 38.89770,-77.03650 within 10 m."));
+    }
+
+    #[tokio::test]
+    async fn test_stops_on_acknowledgement_and_cancel() {
+        let notifier = Arc::new(LocalNotifier::new());
+        let (signals, signals_rx) = mpsc::channel(4);
+        let (location, location_rx) = watch::channel(None);
+        let escalation = Escalation::new("incident-2", context(), contacts(), policy(), notifier.clone());
+        let running = tokio::spawn(escalation.run(signals_rx, location_rx));
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        location.send_replace(shared(51.5007, -0.1246));
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        signals.send(EscalationSignal::Acknowledged { by: "Mom".to_string() }).await.unwrap();
+        let report = running.await.unwrap();
+
+        assert_eq!(report.outcome, EscalationOutcome::Acknowledged { by: "Mom".to_string(), step: EscalationStep::Primary });
+        assert!(report.attempts.iter().any(|attempt| attempt.purpose == AlertPurpose::LocationUpdate));
+        assert!(report.attempts.iter().all(|attempt| attempt.step == EscalationStep::Primary));
+
+        let (signals, signals_rx) = mpsc::channel(4);
+        let (_location, location_rx) = watch::channel(None);
+        signals.send(EscalationSignal::Cancelled).await.unwrap();
+        let report = Escalation::new("incident-3", context(), contacts(), policy(), notifier.clone())
+            .run(signals_rx, location_rx)
+            .await;
+        assert_eq!(report.outcome, EscalationOutcome::Cancelled { step: EscalationStep::Primary });
+        assert_eq!(report.attempts.last().map(|attempt| attempt.purpose), Some(AlertPurpose::StandDown));
+        assert!(notifier.sent().last().unwrap().message.contains("No help is needed"));
+    }
+
+    #[tokio::test]
+    async fn test_duress_keeps_escalating_covertly() {
+        let notifier = Arc::new(LocalNotifier::new());
+        let (signals, signals_rx) = mpsc::channel(4);
+        let (_location, location_rx) = watch::channel(shared(38.8977, -77.0365));
+        signals.send(EscalationSignal::Duress).await.unwrap();
+
+        let report = Escalation::new("incident-4", context(), contacts(), policy(), notifier.clone())
+            .run(signals_rx, location_rx)
+            .await;
+
+        assert_eq!(report.outcome, EscalationOutcome::Exhausted);
+        let steps: Vec<_> = report.attempts.iter().map(|attempt| (attempt.step, attempt.channel)).collect();
+        assert_eq!(steps, [
+            (EscalationStep::Primary, NotificationChannel::Sms),
+            (EscalationStep::Primary, NotificationChannel::Call),
+            // Told about the duress, without a call
+            (EscalationStep::Primary, NotificationChannel::Sms),
+            (EscalationStep::Secondary, NotificationChannel::Sms),
+            (EscalationStep::Secondary, NotificationChannel::Email),
+            (EscalationStep::EmergencyServices, NotificationChannel::Sms),
+        ]);
+        assert!(report.attempts.iter().all(|attempt| attempt.purpose != AlertPurpose::StandDown));
+        let sent = notifier.sent();
+        assert!(sent[2].message.starts_with("Alex was forced to cancel"));
+        assert!(sent.last().unwrap().message.starts_with("DURESS, caller was forced to cancel an alert."));
+    }
+}
@@ -0,0 +1,458 @@
+//! Internal Event Bus
+//!
+//! This module provides a typed publish/subscribe bus that lets subsystems
+//! observe each other without direct references. Publishing is synchronous
+//! and never blocks, so it is safe from both sync and async code.
+//!
+//! The bus remembers the latest event of each state kind (see
+//! [`EventKind::is_state`]). Subscribers that join late can ask for that state
+//! to be replayed before live events, so a screen opened mid-emergency still
+//! learns the current stage and location. One-shot events such as countdown
+//! prompts or notified contacts are never replayed.
+//!
+//! Each subscriber picks a [`BackpressurePolicy`] that decides what happens
+//! when it falls behind. Tests can [`EventBus::tap`] the bus and assert the
+//! exact event sequence.
+
+use crate::countdown::Haptic;
+use crate::dialer::CallState;
+use crate::public::types::{ConnectivityMode, EmergencyStage, EmergencyType};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::Notify;
+
+/// Where an emergency detection came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DetectionSource {
+    /// Spoken emergency phrase
+    Voice,
+    /// Started from the UI or a host application
+    Manual,
+    /// A crash the user didn't cancel
+    CrashDetection,
+}
+
+/// Events published between subsystems
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppEvent {
+    /// An emergency was detected and a response started
+    EmergencyDetected {
+        /// Type of emergency
+        emergency_type: EmergencyType,
+        /// What triggered the detection
+        source: DetectionSource,
+    },
+    /// A crash was detected; help is called when the countdown ends unless cancelled
+    CrashDetected {
+        /// Seconds left to cancel
+        countdown_secs: u64,
+    },
+    /// A fall was detected; contacts are alerted when the countdown ends unless
+    /// the user answers the voice check
+    FallDetected {
+        /// Seconds left to answer
+        countdown_secs: u64,
+    },
+    /// A countdown prompt for the host to speak and vibrate
+    CountdownPrompt {
+        /// Whole seconds left
+        remaining_secs: u64,
+        /// Text to speak
+        speech: String,
+        /// Vibration to play
+        haptic: Haptic,
+    },
+    /// A countdown ended
+    CountdownEnded {
+        /// Whether it ran out rather than being cancelled
+        expired: bool,
+    },
+    /// The emergency moved to a new stage
+    StageChanged {
+        /// New stage
+        stage: EmergencyStage,
+    },
+    /// Guidance was given to the user
+    GuidanceIssued {
+        /// Instruction text
+        instruction: String,
+    },
+    /// A call to emergency services was placed
+    CallPlaced {
+        /// Number dialed
+        number: String,
+    },
+    /// The host reported a new state for a call to emergency services
+    CallStateChanged {
+        /// Number dialed
+        number: String,
+        /// New state
+        state: CallState,
+    },
+    /// The host reported a new position
+    LocationUpdated {
+        /// Latitude in degrees
+        latitude: f64,
+        /// Longitude in degrees
+        longitude: f64,
+        /// Radius the true position is likely within (m)
+        accuracy_m: f64,
+    },
+    /// A trusted contact was notified
+    ContactNotified {
+        /// Contact name or address
+        contact: String,
+    },
+    /// A trusted contact acknowledged an alert
+    AlertAcknowledged {
+        /// Contact name or number
+        contact: String,
+    },
+    /// The user cancelled the alerts to trusted contacts
+    AlertsCancelled,
+    /// Connectivity mode changed
+    ConnectivityChanged {
+        /// New mode
+        mode: ConnectivityMode,
+    },
+    /// The emergency response ended
+    EmergencyEnded,
+    /// Health checks found the safety net degraded or restored
+    HealthChanged {
+        /// User-facing warnings; empty once everything is healthy again
+        warnings: Vec<String>,
+    },
+}
+
+/// Discriminant of [`AppEvent`], used for filtering and replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventKind {
+    EmergencyDetected,
+    CrashDetected,
+    FallDetected,
+    CountdownPrompt,
+    CountdownEnded,
+    StageChanged,
+    GuidanceIssued,
+    CallPlaced,
+    CallStateChanged,
+    LocationUpdated,
+    ContactNotified,
+    AlertAcknowledged,
+    AlertsCancelled,
+    ConnectivityChanged,
+    EmergencyEnded,
+    HealthChanged,
+}
+
+impl EventKind {
+    /// Whether events of this kind describe current state, which is replayed to
+    /// late subscribers, rather than something that happened once
+    pub fn is_state(self) -> bool {
+        matches!(
+            self,
+            EventKind::EmergencyDetected
+                | EventKind::StageChanged
+                | EventKind::LocationUpdated
+                | EventKind::ConnectivityChanged
+                | EventKind::HealthChanged
+        )
+    }
+}
+
+impl AppEvent {
+    /// Gets the kind of this event
+    pub fn kind(&self) -> EventKind {
+        match self {
+            AppEvent::EmergencyDetected { .. } => EventKind::EmergencyDetected,
+            AppEvent::CrashDetected { .. } => EventKind::CrashDetected,
+            AppEvent::FallDetected { .. } => EventKind::FallDetected,
+            AppEvent::CountdownPrompt { .. } => EventKind::CountdownPrompt,
+            AppEvent::CountdownEnded { .. } => EventKind::CountdownEnded,
+            AppEvent::StageChanged { .. } => EventKind::StageChanged,
+            AppEvent::GuidanceIssued { .. } => EventKind::GuidanceIssued,
+            AppEvent::CallPlaced { .. } => EventKind::CallPlaced,
+            AppEvent::CallStateChanged { .. } => EventKind::CallStateChanged,
+            AppEvent::LocationUpdated { .. } => EventKind::LocationUpdated,
+            AppEvent::ContactNotified { .. } => EventKind::ContactNotified,
+            AppEvent::AlertAcknowledged { .. } => EventKind::AlertAcknowledged,
+            AppEvent::AlertsCancelled => EventKind::AlertsCancelled,
+            AppEvent::ConnectivityChanged { .. } => EventKind::ConnectivityChanged,
+            AppEvent::EmergencyEnded => EventKind::EmergencyEnded,
+            AppEvent::HealthChanged { .. } => EventKind::HealthChanged,
+        }
+    }
+}
+
+/// What a subscriber's queue does when it is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Discard the oldest queued event to make room; suits state displays
+    DropOldest(usize),
+    /// Discard the incoming event; suits consumers that must see the start of a sequence
+    DropNewest(usize),
+    /// Never drop; for trusted consumers such as the incident log and test taps
+    Unbounded,
+}
+
+impl Default for BackpressurePolicy {
+    fn default() -> Self {
+        BackpressurePolicy::DropOldest(64)
+    }
+}
+
+/// Queue shared between the bus and one subscription
+struct SubscriberQueue {
+    events: Mutex<VecDeque<AppEvent>>,
+    notify: Notify,
+    policy: BackpressurePolicy,
+    kinds: Option<Vec<EventKind>>,
+    dropped: AtomicU64,
+    closed: AtomicBool,
+}
+
+impl SubscriberQueue {
+    fn wants(&self, kind: EventKind) -> bool {
+        self.kinds.as_ref().is_none_or(|kinds| kinds.contains(&kind))
+    }
+
+    fn push(&self, event: AppEvent) {
+        if self.closed.load(Ordering::Acquire) {
+            return;
+        }
+        let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        match self.policy {
+            BackpressurePolicy::DropOldest(capacity) if events.len() >= capacity => {
+                events.pop_front();
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            BackpressurePolicy::DropNewest(capacity) if events.len() >= capacity => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            _ => {}
+        }
+        events.push_back(event);
+        drop(events);
+        self.notify.notify_one();
+    }
+}
+
+struct BusState {
+    subscribers: Vec<Weak<SubscriberQueue>>,
+    latest: BTreeMap<EventKind, AppEvent>,
+}
+
+/// Typed publish/subscribe bus; cheap to clone
+#[derive(Clone)]
+pub struct EventBus {
+    state: Arc<Mutex<BusState>>,
+}
+
+impl EventBus {
+    /// Create a new, empty bus
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(BusState {
+                subscribers: Vec::new(),
+                latest: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Publish an event to every interested subscriber
+    pub fn publish(&self, event: AppEvent) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        // A finished emergency resets the replayable state, but not device state
+        if event.kind() == EventKind::EmergencyEnded {
+            state.latest.retain(|kind, _| matches!(kind, EventKind::ConnectivityChanged | EventKind::HealthChanged));
+        }
+        if event.kind().is_state() {
+            state.latest.insert(event.kind(), event.clone());
+        }
+
+        state.subscribers.retain(|subscriber| subscriber.strong_count() > 0);
+        for subscriber in state.subscribers.iter().filter_map(Weak::upgrade) {
+            if subscriber.wants(event.kind()) {
+                subscriber.push(event.clone());
+            }
+        }
+    }
+
+    /// Subscribe to live events of the given kinds (all kinds if `None`)
+    pub fn subscribe(&self, kinds: Option<&[EventKind]>, policy: BackpressurePolicy) -> Subscription {
+        self.register(kinds, policy, false)
+    }
+
+    /// Subscribe and first receive the latest event of each state kind
+    pub fn subscribe_with_replay(&self, kinds: Option<&[EventKind]>, policy: BackpressurePolicy) -> Subscription {
+        self.register(kinds, policy, true)
+    }
+
+    /// Subscribe to everything without ever dropping, for tests and logs
+    pub fn tap(&self) -> Subscription {
+        self.register(None, BackpressurePolicy::Unbounded, false)
+    }
+
+    /// Get the latest event of a state kind; `None` for one-shot kinds
+    pub fn latest(&self, kind: EventKind) -> Option<AppEvent> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.latest.get(&kind).cloned()
+    }
+
+    fn register(&self, kinds: Option<&[EventKind]>, policy: BackpressurePolicy, replay: bool) -> Subscription {
+        let queue = Arc::new(SubscriberQueue {
+            events: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+            policy,
+            kinds: kinds.map(|kinds| kinds.to_vec()),
+            dropped: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+        });
+
+        // Replay and registration happen under one lock so no event is missed or duplicated
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if replay {
+            for event in state.latest.values().filter(|event| queue.wants(event.kind())) {
+                queue.push(event.clone());
+            }
+        }
+        state.subscribers.push(Arc::downgrade(&queue));
+
+        Subscription { queue }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Receiving end of a bus subscription; unsubscribes on drop
+pub struct Subscription {
+    queue: Arc<SubscriberQueue>,
+}
+
+impl Subscription {
+    /// Wait for the next event
+    ///
+    /// Returns `None` once the subscription is closed and drained.
+    pub async fn recv(&mut self) -> Option<AppEvent> {
+        let queue = &self.queue;
+        loop {
+            // Register for wakeups before checking, so a push in between is not missed
+            let notified = queue.notify.notified();
+            if let Some(event) = queue.events.lock().unwrap_or_else(|e| e.into_inner()).pop_front() {
+                return Some(event);
+            }
+            if queue.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            notified.await;
+        }
+    }
+
+    /// Take the next event if one is queued
+    pub fn try_recv(&mut self) -> Option<AppEvent> {
+        self.queue.events.lock().unwrap_or_else(|e| e.into_inner()).pop_front()
+    }
+
+    /// Take every queued event
+    pub fn drain(&mut self) -> Vec<AppEvent> {
+        self.queue.events.lock().unwrap_or_else(|e| e.into_inner()).drain(..).collect()
+    }
+
+    /// Number of events discarded by the backpressure policy
+    pub fn dropped(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stop receiving new events; queued events can still be drained
+    pub fn close(&mut self) {
+        self.queue.closed.store(true, Ordering::Release);
+        self.queue.notify.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::public::emergency_interface::EmergencySystem;
+
+    #[test]
+    fn test_tap_records_emergency_sequence() {
+        let bus = EventBus::new();
+        let mut tap = bus.tap();
+        let mut system = EmergencySystem::with_event_bus(bus.clone());
+
+        system.initiate_emergency_response(EmergencyType::Drowning).unwrap();
+        system.share_location(37.7749, -122.4194, 12.0).unwrap();
+        system.end_emergency_response().unwrap();
+
+        let kinds: Vec<EventKind> = tap.drain().iter().map(AppEvent::kind).collect();
+        assert_eq!(kinds, vec![
+            EventKind::EmergencyDetected,
+            EventKind::StageChanged,
+            EventKind::LocationUpdated,
+            EventKind::EmergencyEnded,
+        ]);
+    }
+
+    #[test]
+    fn test_late_subscriber_replays_latest_state() {
+        let bus = EventBus::new();
+        bus.publish(AppEvent::StageChanged { stage: EmergencyStage::InitialDetection });
+        bus.publish(AppEvent::LocationUpdated { latitude: 1.0, longitude: 2.0, accuracy_m: 10.0 });
+        bus.publish(AppEvent::StageChanged { stage: EmergencyStage::VictimExtracted });
+
+        let mut late = bus.subscribe_with_replay(Some(&[EventKind::StageChanged]), BackpressurePolicy::default());
+        assert_eq!(late.drain(), vec![AppEvent::StageChanged { stage: EmergencyStage::VictimExtracted }]);
+
+        bus.publish(AppEvent::ConnectivityChanged { mode: ConnectivityMode::Offline });
+        bus.publish(AppEvent::EmergencyEnded);
+        let mut after_end = bus.subscribe_with_replay(None, BackpressurePolicy::default());
+        assert_eq!(after_end.drain(), vec![AppEvent::ConnectivityChanged { mode: ConnectivityMode::Offline }]);
+    }
+
+    #[test]
+    fn test_one_shot_events_are_not_replayed() {
+        let bus = EventBus::new();
+        bus.publish(AppEvent::EmergencyDetected { emergency_type: EmergencyType::Choking, source: DetectionSource::Manual });
+        bus.publish(AppEvent::CountdownPrompt { remaining_secs: 3, speech: "Calling in 3".to_string(), haptic: Haptic::Short });
+        bus.publish(AppEvent::GuidanceIssued { instruction: "Give back blows".to_string() });
+        bus.publish(AppEvent::ContactNotified { contact: "Alice".to_string() });
+        bus.publish(AppEvent::CallPlaced { number: "911".to_string() });
+        bus.publish(AppEvent::AlertAcknowledged { contact: "Alice".to_string() });
+
+        let mut late = bus.subscribe_with_replay(None, BackpressurePolicy::default());
+        assert_eq!(late.drain(), vec![
+            AppEvent::EmergencyDetected { emergency_type: EmergencyType::Choking, source: DetectionSource::Manual },
+        ]);
+        assert_eq!(bus.latest(EventKind::ContactNotified), None);
+    }
+
+    #[test]
+    fn test_backpressure_policies() {
+        let bus = EventBus::new();
+        let mut oldest = bus.subscribe(None, BackpressurePolicy::DropOldest(2));
+        let mut newest = bus.subscribe(None, BackpressurePolicy::DropNewest(2));
+
+        for contact in ["a", "b", "c"] {
+            bus.publish(AppEvent::ContactNotified { contact: contact.to_string() });
+        }
+
+        let contacts = |events: Vec<AppEvent>| -> Vec<String> {
+            events.into_iter().filter_map(|event| match event {
+                AppEvent::ContactNotified { contact } => Some(contact),
+                _ => None,
+            }).collect()
+        };
+        assert_eq!(contacts(oldest.drain()), vec!["b", "c"]);
+        assert_eq!(contacts(newest.drain()), vec!["a", "b"]);
+        assert_eq!(oldest.dropped(), 1);
+        assert_eq!(newest.dropped(), 1);
+    }
+}
@@ -0,0 +1,249 @@
+//! Fall Detection
+//!
+//! A fall is three phases in the accelerometer signal:
+//!
+//! 1. free fall: acceleration well below 1 g for a moment
+//! 2. impact: a spike shortly after the free fall ends
+//! 3. inactivity: once the landing has settled, the phone lies still for
+//!    [`INACTIVITY`]
+//!
+//! Someone who gets up moves the phone during the inactivity phase, which
+//! abandons the fall. How low the free fall, how hard the impact and how still
+//! the person must be depend on `emergency.fall_sensitivity`.
+//!
+//! A detected fall is followed by a voice check opening with
+//! [`FALL_CHECK_QUESTION`].
+
+use crate::config::{AppConfig, EmergencyConfig, FallSensitivity};
+use crate::sensors::SensorSample;
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long after a free fall the impact may come
+pub const IMPACT_WINDOW: Duration = Duration::from_millis(500);
+
+/// Bounces after the impact that are ignored
+pub const SETTLE_TIME: Duration = Duration::from_millis(1000);
+
+/// How long the phone must lie still after settling
+pub const INACTIVITY: Duration = Duration::from_millis(3000);
+
+/// Falls ignored after a detection
+pub const FALL_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Asked through the voice interface when the fall check countdown starts
+pub const FALL_CHECK_QUESTION: &str = "Did you fall?";
+
+/// Thresholds for one sensitivity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FallThresholds {
+    /// Acceleration below this is free fall (g)
+    pub free_fall_g: f64,
+    /// Shortest free fall
+    pub free_fall_min: Duration,
+    /// Smallest impact (g)
+    pub impact_g: f64,
+    /// Largest deviation from 1 g that still counts as lying still
+    pub stillness_g: f64,
+}
+
+impl FallThresholds {
+    /// Thresholds for `sensitivity`
+    pub fn for_sensitivity(sensitivity: FallSensitivity) -> Self {
+        match sensitivity {
+            FallSensitivity::Low => Self {
+                free_fall_g: 0.35,
+                free_fall_min: Duration::from_millis(150),
+                impact_g: 3.0,
+                stillness_g: 0.1,
+            },
+            FallSensitivity::Medium => Self {
+                free_fall_g: 0.5,
+                free_fall_min: Duration::from_millis(100),
+                impact_g: 2.5,
+                stillness_g: 0.15,
+            },
+            FallSensitivity::High => Self {
+                free_fall_g: 0.6,
+                free_fall_min: Duration::from_millis(60),
+                impact_g: 2.0,
+                stillness_g: 0.25,
+            },
+        }
+    }
+}
+
+/// A detected fall
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fall {
+    /// Host timestamp of the impact
+    pub timestamp_ms: u64,
+    /// Peak acceleration of the landing (g)
+    pub impact_g: f64,
+    /// Length of the free fall
+    pub free_fall_ms: u64,
+}
+
+/// Where the detector is in the fall pattern
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Idle,
+    Falling { since: u64 },
+    AwaitingImpact { free_fall_ms: u64, until: u64 },
+    Landed { at: u64, peak_g: f64, free_fall_ms: u64 },
+}
+
+/// Detects falls from accelerometer samples
+#[derive(Debug, Clone)]
+pub struct FallDetector {
+    enabled: bool,
+    thresholds: FallThresholds,
+    phase: Phase,
+    cooldown_until: Option<u64>,
+}
+
+impl FallDetector {
+    /// Create a detector for the `[emergency]` fall settings
+    pub fn from_config(config: &EmergencyConfig) -> Self {
+        Self {
+            enabled: config.enable_fall_detection,
+            thresholds: FallThresholds::for_sensitivity(config.fall_sensitivity),
+            phase: Phase::Idle,
+            cooldown_until: None,
+        }
+    }
+
+    /// Replace the sensitivity
+    pub fn configure(&mut self, config: &EmergencyConfig) {
+        self.enabled = config.enable_fall_detection;
+        self.thresholds = FallThresholds::for_sensitivity(config.fall_sensitivity);
+    }
+
+    /// Feed one sample; returns a fall once the inactivity phase completes
+    pub fn handle(&mut self, sample: SensorSample) -> Option<Fall> {
+        if !self.enabled {
+            return None;
+        }
+        let g = sample.reading.g_force()?;
+        let now = sample.timestamp_ms;
+        if self.cooldown_until.is_some_and(|until| now < until) {
+            return None;
+        }
+        let limits = self.thresholds;
+
+        self.phase = match self.phase {
+            Phase::Idle if g < limits.free_fall_g => Phase::Falling { since: now },
+            Phase::Idle => Phase::Idle,
+            Phase::Falling { .. } if g < limits.free_fall_g => self.phase,
+            Phase::Falling { since } => {
+                let free_fall_ms = now - since;
+                if free_fall_ms < limits.free_fall_min.as_millis() as u64 {
+                    Phase::Idle
+                } else if g >= limits.impact_g {
+                    Phase::Landed { at: now, peak_g: g, free_fall_ms }
+                } else {
+                    Phase::AwaitingImpact { free_fall_ms, until: now + IMPACT_WINDOW.as_millis() as u64 }
+                }
+            }
+            Phase::AwaitingImpact { free_fall_ms, .. } if g >= limits.impact_g => {
+                Phase::Landed { at: now, peak_g: g, free_fall_ms }
+            }
+            Phase::AwaitingImpact { until, .. } if now > until => Phase::Idle,
+            Phase::AwaitingImpact { .. } => self.phase,
+            Phase::Landed { at, peak_g, free_fall_ms } => {
+                let since_impact = Duration::from_millis(now - at);
+                if since_impact < SETTLE_TIME {
+                    Phase::Landed { at, peak_g: peak_g.max(g), free_fall_ms }
+                } else if (g - 1.0).abs() > limits.stillness_g {
+                    // Moving again: they got up, or it wasn't a fall
+                    Phase::Idle
+                } else if since_impact >= SETTLE_TIME + INACTIVITY {
+                    self.phase = Phase::Idle;
+                    self.cooldown_until = Some(now + FALL_COOLDOWN.as_millis() as u64);
+                    return Some(Fall { timestamp_ms: at, impact_g: peak_g, free_fall_ms });
+                } else {
+                    self.phase
+                }
+            }
+        };
+        None
+    }
+}
+
+impl Default for FallDetector {
+    fn default() -> Self {
+        Self::from_config(&AppConfig::default().emergency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::{SensorReading, STANDARD_GRAVITY};
+
+    /// 50 Hz samples of `g` along z for `duration_ms`, starting at `start`
+    fn segment(start: u64, duration_ms: u64, g: impl Fn(u64) -> f64) -> Vec<SensorSample> {
+        (0..duration_ms / 20)
+            .map(|i| {
+                let t = start + i * 20;
+                SensorSample::new(t, SensorReading::Accel { x: 0.0, y: 0.0, z: g(t) * STANDARD_GRAVITY })
+            })
+            .collect()
+    }
+
+    /// Walking, a free fall, a landing of `impact_g` and then `after`
+    fn fall(free_fall_g: f64, impact_g: f64, after: impl Fn(u64) -> f64) -> Vec<SensorSample> {
+        let mut samples = segment(0, 2000, |t| 1.0 + 0.3 * ((t as f64) / 150.0).sin());
+        samples.extend(segment(2000, 300, |_| free_fall_g));
+        samples.extend(segment(2300, 60, |_| impact_g));
+        samples.extend(segment(2360, 600, |t| 1.0 + 0.8 * ((t as f64) / 40.0).sin()));
+        samples.extend(segment(2960, 5000, after));
+        samples
+    }
+
+    fn detect(config: &EmergencyConfig, samples: &[SensorSample]) -> Vec<Fall> {
+        let mut detector = FallDetector::from_config(config);
+        samples.iter().filter_map(|sample| detector.handle(*sample)).collect()
+    }
+
+    fn config(sensitivity: FallSensitivity) -> EmergencyConfig {
+        let mut config = AppConfig::default().emergency;
+        config.enable_fall_detection = true;
+        config.fall_sensitivity = sensitivity;
+        config
+    }
+
+    #[test]
+    fn test_fall_then_lying_still() {
+        let lying = |t: u64| 1.0 + 0.03 * ((t as f64) / 300.0).sin();
+        let falls = detect(&config(FallSensitivity::Medium), &fall(0.2, 3.5, lying));
+        assert_eq!(falls.len(), 1);
+        assert_eq!(falls[0].timestamp_ms, 2300);
+        assert!(falls[0].free_fall_ms >= 280);
+        assert!((falls[0].impact_g - 3.5).abs() < 1e-6);
+
+        // Off by default
+        assert!(detect(&AppConfig::default().emergency, &fall(0.2, 3.5, lying)).is_empty());
+    }
+
+    #[test]
+    fn test_getting_up_sitting_down_and_sensitivity() {
+        let medium = config(FallSensitivity::Medium);
+
+        // Fell but got up and walked on
+        let walking = |t: u64| 1.0 + 0.3 * ((t as f64) / 150.0).sin();
+        assert!(detect(&medium, &fall(0.2, 3.5, walking)).is_empty());
+
+        // Sat down hard: an impact without free fall
+        let mut samples = segment(0, 2000, |_| 1.0);
+        samples.extend(segment(2000, 80, |_| 2.8));
+        samples.extend(segment(2080, 5000, |_| 1.0));
+        assert!(detect(&medium, &samples).is_empty());
+
+        // A slump with a soft landing only counts at high sensitivity
+        let soft = fall(0.55, 2.2, |_| 1.0);
+        assert!(detect(&medium, &soft).is_empty());
+        assert!(detect(&config(FallSensitivity::Low), &soft).is_empty());
+        assert_eq!(detect(&config(FallSensitivity::High), &soft).len(), 1);
+    }
+}
@@ -0,0 +1,425 @@
+//! C ABI for Non-JVM Hosts
+//!
+//! This module exposes the emergency engine through a stable C interface so
+//! that embedded targets and other host languages can link `libsolana_sos`
+//! without a JVM. The generated header lives in `include/solana_sos.h`.
+//!
+//! ## Conventions
+//!
+//! - Engines are opaque handles created with `sos_engine_new` and released with `sos_engine_free`
+//! - All strings are NUL-terminated UTF-8
+//! - Strings returned through `out` parameters are owned by the caller and must be released with `sos_string_free`
+//! - Functions return an [`SosStatus`]; details of the last call's failure are available from `sos_engine_last_error`
+//! - Freeing an engine twice, or freeing a pointer that is not a live engine, is ignored
+
+use crate::public::voice_interface::VoiceInterface;
+use crate::public::voice_stream::VoiceStream;
+use crate::SolanaSOS;
+use std::collections::HashSet;
+use std::ffi::{c_char, CStr, CString};
+use std::sync::Mutex;
+
+/// Addresses of engines created and not yet freed, guarding `sos_engine_free`
+static LIVE_ENGINES: Mutex<Option<HashSet<usize>>> = Mutex::new(None);
+
+/// Status code returned by every fallible C ABI function
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SosStatus {
+    /// Call succeeded
+    Ok = 0,
+    /// A required pointer argument was null
+    NullArgument = 1,
+    /// A string argument was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// An argument was rejected by the engine
+    InvalidInput = 3,
+    /// The engine failed internally
+    Internal = 4,
+}
+
+/// Emergency engine handle owned by the foreign caller
+pub struct SosEngine {
+    sos: SolanaSOS,
+    stream: VoiceStream,
+    runtime: tokio::runtime::Runtime,
+    last_error: Option<CString>,
+}
+
+impl SosEngine {
+    fn fail(&mut self, status: SosStatus, message: impl Into<String>) -> SosStatus {
+        self.last_error = Some(to_c_string(message.into()));
+        status
+    }
+}
+
+/// Convert a Rust string to a C string, dropping any interior NUL bytes
+fn to_c_string(value: String) -> CString {
+    CString::new(value).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|&b| b != 0);
+        CString::new(bytes).unwrap_or_default()
+    })
+}
+
+/// Borrow a C string argument as UTF-8
+///
+/// # Safety
+/// `ptr` must be null or point to a NUL-terminated string that outlives `'a`.
+unsafe fn str_arg<'a>(ptr: *const c_char) -> Result<&'a str, SosStatus> {
+    if ptr.is_null() {
+        return Err(SosStatus::NullArgument);
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|_| SosStatus::InvalidUtf8)
+}
+
+/// Resolve an engine handle and string arguments, recording failures on the engine
+///
+/// Resolving the handle clears the previous call's error.
+macro_rules! engine_arg {
+    ($engine:expr) => {
+        match $engine.as_mut() {
+            Some(engine) => {
+                engine.last_error = None;
+                engine
+            }
+            None => return SosStatus::NullArgument,
+        }
+    };
+}
+
+macro_rules! str_arg {
+    ($engine:expr, $ptr:expr, $name:literal) => {
+        match str_arg($ptr) {
+            Ok(value) => value,
+            Err(status) => return $engine.fail(status, concat!("invalid argument: ", $name)),
+        }
+    };
+}
+
+/// Create a new engine
+///
+/// Returns null if the engine fails to initialize.
+#[no_mangle]
+pub extern "C" fn sos_engine_new() -> *mut SosEngine {
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            tracing::error!("Failed to start engine runtime: {}", e);
+            return std::ptr::null_mut();
+        }
+    };
+
+    let mut sos = SolanaSOS::new();
+    if let Err(e) = sos.initialize() {
+        tracing::error!("Failed to initialize engine: {}", e);
+        return std::ptr::null_mut();
+    }
+
+    let engine = SosEngine {
+        sos,
+        stream: VoiceStream::new(VoiceInterface::new("vosk-model-small-en-us-0.15")),
+        runtime,
+        last_error: None,
+    };
+    let engine = Box::into_raw(Box::new(engine));
+    LIVE_ENGINES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashSet::new)
+        .insert(engine as usize);
+    engine
+}
+
+/// Release an engine
+///
+/// Null, already freed and unknown pointers are ignored.
+///
+/// # Safety
+/// `engine` must be null or a pointer returned by `sos_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn sos_engine_free(engine: *mut SosEngine) {
+    let live = LIVE_ENGINES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_mut()
+        .is_some_and(|engines| engines.remove(&(engine as usize)));
+    if live {
+        drop(Box::from_raw(engine));
+    } else if !engine.is_null() {
+        tracing::warn!("Ignoring sos_engine_free of an engine that is not live");
+    }
+}
+
+/// Get the message for the last call on this engine, if it failed
+///
+/// The returned string is owned by the engine and valid until the next call
+/// on it. Returns null if the last call succeeded.
+///
+/// # Safety
+/// `engine` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn sos_engine_last_error(engine: *const SosEngine) -> *const c_char {
+    match engine.as_ref().and_then(|engine| engine.last_error.as_ref()) {
+        Some(message) => message.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
+
+/// Process an emergency and return the response as a JSON object
+///
+/// The JSON has the keys `instruction`, `should_call_emergency_services`, `emergency_summary`,
+/// `context_flags` and `current_step`.
+///
+/// # Safety
+/// `engine` must be a live handle, the string arguments must be NUL-terminated,
+/// and `out_json` must point to writable storage for one pointer.
+#[no_mangle]
+pub unsafe extern "C" fn sos_engine_process_emergency(
+    engine: *mut SosEngine,
+    emergency_type: *const c_char,
+    user_input: *const c_char,
+    out_json: *mut *mut c_char,
+) -> SosStatus {
+    let engine = engine_arg!(engine);
+    let emergency_type = str_arg!(engine, emergency_type, "emergency_type");
+    let user_input = str_arg!(engine, user_input, "user_input");
+    if out_json.is_null() {
+        return engine.fail(SosStatus::NullArgument, "invalid argument: out_json");
+    }
+
+    let response = engine
+        .runtime
+        .block_on(engine.sos.process_emergency(emergency_type, user_input));
+    let json = serde_json::json!({
+        "instruction": response.instruction,
+        "should_call_emergency_services": response.should_call_emergency_services,
+        "emergency_summary": response.emergency_summary,
+        "context_flags": response.context_flags,
+        "current_step": response.current_step,
+    });
+
+    *out_json = to_c_string(json.to_string()).into_raw();
+    SosStatus::Ok
+}
+
+/// Push mono 16 kHz 16-bit PCM samples into the streaming voice pipeline
+///
+/// Any length is accepted; samples are processed in `SOS_STREAM_FRAME_SAMPLES`
+/// frames and a partial frame is kept for the next call. On a detection
+/// `*out_text` receives the recognized text; otherwise it is set to null.
+///
+/// # Safety
+/// `engine` must be a live handle, `samples` must point to `len` readable
+/// samples (or be null when `len` is 0), and `out_text` must point to writable
+/// storage for one pointer.
+#[no_mangle]
+pub unsafe extern "C" fn sos_engine_push_audio(
+    engine: *mut SosEngine,
+    samples: *const i16,
+    len: usize,
+    out_text: *mut *mut c_char,
+) -> SosStatus {
+    let engine = engine_arg!(engine);
+    if out_text.is_null() || (samples.is_null() && len > 0) {
+        return engine.fail(SosStatus::NullArgument, "invalid argument: samples or out_text");
+    }
+    *out_text = std::ptr::null_mut();
+    if len == 0 {
+        return SosStatus::Ok;
+    }
+
+    let samples = std::slice::from_raw_parts(samples, len);
+    match engine.stream.push_samples(samples) {
+        Ok(Some(detection)) => {
+            *out_text = to_c_string(detection.text).into_raw();
+            SosStatus::Ok
+        }
+        Ok(None) => SosStatus::Ok,
+        Err(e) => engine.fail(SosStatus::Internal, e.to_string()),
+    }
+}
+
+/// Add an emergency contact
+///
+/// `phone` may be local to the US or include a country code; it is stored in
+/// E.164 format. Invalid or duplicate numbers return `SOS_STATUS_INVALID_INPUT`.
+///
+/// # Safety
+/// `engine` must be a live handle and the string arguments NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn sos_engine_add_contact(
+    engine: *mut SosEngine,
+    name: *const c_char,
+    phone: *const c_char,
+    relationship: *const c_char,
+) -> SosStatus {
+    let engine = engine_arg!(engine);
+    let name = str_arg!(engine, name, "name");
+    let phone = str_arg!(engine, phone, "phone");
+    let relationship = str_arg!(engine, relationship, "relationship");
+
+    match engine.sos.add_emergency_contact(name, phone, relationship) {
+        Ok(_) => SosStatus::Ok,
+        Err(e) => engine.fail(SosStatus::InvalidInput, e.to_string()),
+    }
+}
+
+/// Get the supported emergency types as a JSON array of strings
+///
+/// # Safety
+/// `engine` must be a live handle and `out_json` must point to writable storage for one pointer.
+#[no_mangle]
+pub unsafe extern "C" fn sos_engine_emergency_types(
+    engine: *mut SosEngine,
+    out_json: *mut *mut c_char,
+) -> SosStatus {
+    let engine = engine_arg!(engine);
+    if out_json.is_null() {
+        return engine.fail(SosStatus::NullArgument, "invalid argument: out_json");
+    }
+
+    let json = serde_json::to_string(&engine.sos.get_emergency_types()).unwrap_or_default();
+    *out_json = to_c_string(json).into_raw();
+    SosStatus::Ok
+}
+
+/// Get the JSON Schema (draft 7) describing every configuration key
+///
+/// # Safety
+/// `out_json` must point to writable storage for one pointer.
+#[no_mangle]
+pub unsafe extern "C" fn sos_config_schema(out_json: *mut *mut c_char) -> SosStatus {
+    if out_json.is_null() {
+        return SosStatus::NullArgument;
+    }
+    *out_json = to_c_string(crate::schema::config_schema().to_string()).into_raw();
+    SosStatus::Ok
+}
+
+/// Check settings edits against the configuration schema before persisting them
+///
+/// `edits_json` is a JSON object of dotted keys, e.g. `{"ui.theme": "dark"}`.
+/// `*out_errors` receives a JSON array of `{"key", "message"}` objects, which
+/// is empty when every edit is valid. Returns `SOS_STATUS_INVALID_INPUT` if
+/// `edits_json` is not a JSON object.
+///
+/// # Safety
+/// `edits_json` must be NUL-terminated and `out_errors` must point to
+/// writable storage for one pointer.
+#[no_mangle]
+pub unsafe extern "C" fn sos_settings_validate(
+    edits_json: *const c_char,
+    out_errors: *mut *mut c_char,
+) -> SosStatus {
+    if out_errors.is_null() {
+        return SosStatus::NullArgument;
+    }
+    let edits_json = match str_arg(edits_json) {
+        Ok(value) => value,
+        Err(status) => return status,
+    };
+    let edits = match serde_json::from_str::<serde_json::Value>(edits_json) {
+        Ok(serde_json::Value::Object(edits)) => edits,
+        _ => return SosStatus::InvalidInput,
+    };
+
+    let errors: Vec<serde_json::Value> = crate::schema::validate_edits(&edits)
+        .into_iter()
+        .map(|issue| serde_json::json!({"key": issue.key, "message": issue.message}))
+        .collect();
+    *out_errors = to_c_string(serde_json::Value::Array(errors).to_string()).into_raw();
+    SosStatus::Ok
+}
+
+/// Release a string returned by this library
+///
+/// # Safety
+/// `value` must be null or a string returned through an `out` parameter of
+/// this library that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn sos_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Get the library version as a static string
+#[no_mangle]
+pub extern "C" fn sos_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error(engine: *const SosEngine) -> Option<String> {
+        let message = unsafe { sos_engine_last_error(engine) };
+        (!message.is_null()).then(|| unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn test_null_handles_and_arguments() {
+        let mut out: *mut c_char = std::ptr::null_mut();
+        let text = c"drowning".as_ptr();
+        unsafe {
+            assert_eq!(sos_engine_process_emergency(std::ptr::null_mut(), text, text, &mut out), SosStatus::NullArgument);
+            assert_eq!(sos_engine_push_audio(std::ptr::null_mut(), std::ptr::null(), 0, &mut out), SosStatus::NullArgument);
+            assert_eq!(sos_engine_add_contact(std::ptr::null_mut(), text, text, text), SosStatus::NullArgument);
+            assert!(sos_engine_last_error(std::ptr::null()).is_null());
+            assert_eq!(sos_config_schema(std::ptr::null_mut()), SosStatus::NullArgument);
+            sos_string_free(std::ptr::null_mut());
+        }
+        assert!(out.is_null());
+
+        let engine = sos_engine_new();
+        assert!(!engine.is_null());
+        unsafe {
+            assert_eq!(sos_engine_process_emergency(engine, std::ptr::null(), text, &mut out), SosStatus::NullArgument);
+            assert_eq!(sos_engine_emergency_types(engine, std::ptr::null_mut()), SosStatus::NullArgument);
+            assert_eq!(sos_engine_push_audio(engine, std::ptr::null(), 4, &mut out), SosStatus::NullArgument);
+            sos_engine_free(engine);
+        }
+        assert!(out.is_null());
+    }
+
+    #[test]
+    fn test_invalid_utf8_error_clears_on_success() {
+        let engine = sos_engine_new();
+        let invalid = c"\xff\xfe".as_ptr();
+        let valid = c"Mother".as_ptr();
+        let mut out: *mut c_char = std::ptr::null_mut();
+        unsafe {
+            assert_eq!(sos_engine_add_contact(engine, invalid, valid, valid), SosStatus::InvalidUtf8);
+            assert_eq!(last_error(engine).as_deref(), Some("invalid argument: name"));
+
+            // An unrelated later success must not report the earlier failure
+            assert_eq!(sos_engine_emergency_types(engine, &mut out), SosStatus::Ok);
+            assert_eq!(last_error(engine), None);
+            sos_string_free(out);
+
+            assert_eq!(sos_engine_add_contact(engine, valid, c"555-1234".as_ptr(), valid), SosStatus::InvalidInput);
+            assert!(last_error(engine).is_some());
+            assert_eq!(sos_engine_push_audio(engine, std::ptr::null(), 0, &mut out), SosStatus::Ok);
+            assert_eq!(last_error(engine), None);
+            sos_engine_free(engine);
+        }
+    }
+
+    #[test]
+    fn test_double_free_is_ignored() {
+        let engine = sos_engine_new();
+        let other = sos_engine_new();
+        unsafe {
+            sos_engine_free(engine);
+            sos_engine_free(engine);
+            // The other engine is still usable
+            let mut out: *mut c_char = std::ptr::null_mut();
+            assert_eq!(sos_engine_emergency_types(other, &mut out), SosStatus::Ok);
+            sos_string_free(out);
+            sos_engine_free(other);
+            sos_engine_free(other);
+        }
+    }
+}
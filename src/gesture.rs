@@ -0,0 +1,318 @@
+//! Silent SOS Gestures
+//!
+//! The host forwards timestamped key and tap events; the recognizer matches
+//! them against the configured silent SOS method:
+//!
+//! - `hold_button`: the SOS button is held for `silent_sos_hold_duration`
+//! - `power_sequence`: the power key is pressed once per entry in
+//!   `silent_sos_power_sequence`
+//! - `volume_sequence`: the volume keys are pressed in the order of
+//!   `silent_sos_volume_sequence`
+//! - `screen_tap`: the screen is tapped [`SCREEN_TAP_COUNT`] times
+//!
+//! Presses of the same key closer than [`DEBOUNCE`] are contact bounce and
+//! ignored. A sequence must be entered with at most [`MAX_PRESS_GAP`] between
+//! presses; a longer pause starts over. After a match the recognizer stays
+//! quiet for [`TRIGGER_COOLDOWN`] so one frantic burst raises one emergency.
+//!
+//! `motion_gesture` is matched from sensor data, not key events, and never
+//! fires here.
+
+use crate::config::{AppConfig, EmergencyConfig, SilentSosMethod};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Presses of one key closer together than this are bounce
+pub const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Longest pause allowed between the presses of a sequence
+pub const MAX_PRESS_GAP: Duration = Duration::from_millis(1000);
+
+/// Quiet period after a match
+pub const TRIGGER_COOLDOWN: Duration = Duration::from_secs(10);
+
+/// Screen taps needed for `screen_tap`
+pub const SCREEN_TAP_COUNT: usize = 5;
+
+/// Kind of input event forwarded by the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputKind {
+    /// Power key press
+    Power,
+    /// Volume up key press
+    VolumeUp,
+    /// Volume down key press
+    VolumeDown,
+    /// Screen tap
+    Tap,
+    /// SOS button pressed down
+    ButtonDown,
+    /// SOS button released
+    ButtonUp,
+}
+
+impl InputKind {
+    /// Parse a key such as "volume_up"
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "power" => Some(Self::Power),
+            "volume_up" => Some(Self::VolumeUp),
+            "volume_down" => Some(Self::VolumeDown),
+            "tap" => Some(Self::Tap),
+            "button_down" => Some(Self::ButtonDown),
+            "button_up" => Some(Self::ButtonUp),
+            _ => None,
+        }
+    }
+}
+
+/// A key or tap event with the host's monotonic timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputEvent {
+    /// Milliseconds on the host's monotonic clock
+    pub timestamp_ms: u64,
+    /// What happened
+    pub kind: InputKind,
+}
+
+impl InputEvent {
+    /// Create an event
+    pub fn new(kind: InputKind, timestamp_ms: u64) -> Self {
+        Self { timestamp_ms, kind }
+    }
+}
+
+/// A recognized silent SOS gesture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SilentSosTrigger {
+    /// Method that matched
+    pub method: SilentSosMethod,
+    /// Host timestamp of the event that completed the gesture
+    pub timestamp_ms: u64,
+}
+
+/// Matches input events against the configured silent SOS gesture
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+    enabled: bool,
+    method: SilentSosMethod,
+    pattern: Vec<InputKind>,
+    hold: Duration,
+    presses: Vec<InputEvent>,
+    last_seen: HashMap<InputKind, u64>,
+    held_since: Option<u64>,
+    cooldown_until: Option<u64>,
+}
+
+impl GestureRecognizer {
+    /// Create a recognizer for the `[emergency]` silent SOS settings
+    pub fn from_config(config: &EmergencyConfig) -> Self {
+        let mut recognizer = Self {
+            enabled: false,
+            method: SilentSosMethod::HoldButton,
+            pattern: Vec::new(),
+            hold: Duration::ZERO,
+            presses: Vec::new(),
+            last_seen: HashMap::new(),
+            held_since: None,
+            cooldown_until: None,
+        };
+        recognizer.configure(config);
+        recognizer
+    }
+
+    /// Replace the gesture, forgetting any partial input
+    pub fn configure(&mut self, config: &EmergencyConfig) {
+        self.enabled = config.enable_silent_sos;
+        self.method = config.silent_sos_method;
+        self.hold = Duration::from_secs(config.silent_sos_hold_duration);
+        self.pattern = match config.silent_sos_method {
+            SilentSosMethod::PowerSequence => vec![InputKind::Power; config.silent_sos_power_sequence.len()],
+            SilentSosMethod::VolumeSequence => config
+                .silent_sos_volume_sequence
+                .iter()
+                .map(|press| if *press < 0 { InputKind::VolumeDown } else { InputKind::VolumeUp })
+                .collect(),
+            SilentSosMethod::ScreenTap => vec![InputKind::Tap; SCREEN_TAP_COUNT],
+            SilentSosMethod::HoldButton | SilentSosMethod::MotionGesture => Vec::new(),
+        };
+        self.reset();
+    }
+
+    /// Forget partial input
+    pub fn reset(&mut self) {
+        self.presses.clear();
+        self.last_seen.clear();
+        self.held_since = None;
+    }
+
+    /// Feed one event; returns a trigger when it completes the gesture
+    pub fn handle(&mut self, event: InputEvent) -> Option<SilentSosTrigger> {
+        if !self.enabled {
+            return None;
+        }
+
+        if self.method == SilentSosMethod::HoldButton {
+            return match event.kind {
+                InputKind::ButtonDown => {
+                    self.held_since.get_or_insert(event.timestamp_ms);
+                    self.poll(event.timestamp_ms)
+                }
+                InputKind::ButtonUp => {
+                    let trigger = self.poll(event.timestamp_ms);
+                    self.held_since = None;
+                    trigger
+                }
+                _ => None,
+            };
+        }
+
+        if !self.pattern.contains(&event.kind) {
+            return None;
+        }
+        let debounce = DEBOUNCE.as_millis() as u64;
+        if let Some(previous) = self.last_seen.insert(event.kind, event.timestamp_ms) {
+            if event.timestamp_ms.saturating_sub(previous) < debounce {
+                return None;
+            }
+        }
+
+        let max_gap = MAX_PRESS_GAP.as_millis() as u64;
+        if self
+            .presses
+            .last()
+            .is_some_and(|last| event.timestamp_ms.saturating_sub(last.timestamp_ms) > max_gap)
+        {
+            self.presses.clear();
+        }
+        self.presses.push(event);
+        if self.presses.len() > self.pattern.len() {
+            self.presses.remove(0);
+        }
+
+        let entered = self.presses.iter().map(|press| press.kind);
+        if self.presses.len() == self.pattern.len() && entered.eq(self.pattern.iter().copied()) {
+            self.presses.clear();
+            return self.fire(event.timestamp_ms);
+        }
+        None
+    }
+
+    /// Check a button hold against the host clock
+    ///
+    /// Holds fire without waiting for the release, so the host (or the
+    /// runtime) polls at [`Self::hold_deadline`].
+    pub fn poll(&mut self, now_ms: u64) -> Option<SilentSosTrigger> {
+        let since = self.held_since?;
+        if now_ms.saturating_sub(since) < self.hold.as_millis() as u64 {
+            return None;
+        }
+        // One trigger per hold, however long the button stays down
+        self.held_since = Some(u64::MAX);
+        self.fire(now_ms)
+    }
+
+    /// Host time at which the current button hold completes
+    pub fn hold_deadline(&self) -> Option<u64> {
+        self.held_since
+            .filter(|since| *since != u64::MAX)
+            .map(|since| since + self.hold.as_millis() as u64)
+    }
+
+    fn fire(&mut self, timestamp_ms: u64) -> Option<SilentSosTrigger> {
+        if self.cooldown_until.is_some_and(|until| timestamp_ms < until) {
+            return None;
+        }
+        self.cooldown_until = Some(timestamp_ms + TRIGGER_COOLDOWN.as_millis() as u64);
+        Some(SilentSosTrigger {
+            method: self.method,
+            timestamp_ms,
+        })
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::from_config(&AppConfig::default().emergency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recognizer(method: SilentSosMethod) -> GestureRecognizer {
+        let mut config = AppConfig::default().emergency;
+        config.silent_sos_method = method;
+        GestureRecognizer::from_config(&config)
+    }
+
+    fn feed(recognizer: &mut GestureRecognizer, events: &[(InputKind, u64)]) -> Vec<SilentSosTrigger> {
+        events
+            .iter()
+            .filter_map(|(kind, at)| recognizer.handle(InputEvent::new(*kind, *at)))
+            .collect()
+    }
+
+    #[test]
+    fn test_volume_sequence_with_bounce_and_timing() {
+        use InputKind::{VolumeDown as Down, VolumeUp as Up};
+        let mut gestures = recognizer(SilentSosMethod::VolumeSequence);
+
+        // Default pattern is up, down, up, down, up; the bounce at 420 is ignored
+        let stream = [(Up, 0), (Down, 200), (Up, 400), (Up, 420), (Down, 600), (Up, 800)];
+        let triggers = feed(&mut gestures, &stream);
+        assert_eq!(triggers.len(), 1);
+        assert_eq!(triggers[0].timestamp_ms, 800);
+
+        // Repeating it inside the cooldown does nothing
+        let stream = [(Up, 2000), (Down, 2200), (Up, 2400), (Down, 2600), (Up, 2800)];
+        assert!(feed(&mut gestures, &stream).is_empty());
+
+        // A pause longer than the gap starts over; the stray press before it doesn't count
+        let stream = [(Up, 20_000), (Down, 21_500), (Up, 21_700), (Down, 21_900), (Up, 22_100), (Down, 22_300), (Up, 22_500)];
+        let triggers = feed(&mut gestures, &stream);
+        assert_eq!(triggers.len(), 1);
+        assert_eq!(triggers[0].timestamp_ms, 22_500);
+    }
+
+    #[test]
+    fn test_power_presses_and_screen_taps() {
+        let mut gestures = recognizer(SilentSosMethod::PowerSequence);
+        let presses: Vec<_> = (0..5).map(|i| (InputKind::Power, i * 300)).collect();
+        assert_eq!(feed(&mut gestures, &presses).len(), 1);
+
+        // Too slow
+        let mut gestures = recognizer(SilentSosMethod::PowerSequence);
+        let presses: Vec<_> = (0..5).map(|i| (InputKind::Power, i * 1500)).collect();
+        assert!(feed(&mut gestures, &presses).is_empty());
+
+        let mut gestures = recognizer(SilentSosMethod::ScreenTap);
+        let taps: Vec<_> = (0..4).map(|i| (InputKind::Tap, i * 150)).collect();
+        assert!(feed(&mut gestures, &taps).is_empty());
+        assert!(gestures.handle(InputEvent::new(InputKind::Tap, 600)).is_some());
+    }
+
+    #[test]
+    fn test_hold_button() {
+        let mut gestures = recognizer(SilentSosMethod::HoldButton);
+
+        // Released too early
+        assert!(feed(&mut gestures, &[(InputKind::ButtonDown, 0), (InputKind::ButtonUp, 2500)]).is_empty());
+
+        // Held for the full 3 seconds: fires on poll while still down, once
+        assert!(gestures.handle(InputEvent::new(InputKind::ButtonDown, 5000)).is_none());
+        assert_eq!(gestures.hold_deadline(), Some(8000));
+        assert!(gestures.poll(7999).is_none());
+        assert!(gestures.poll(8000).is_some());
+        assert_eq!(gestures.hold_deadline(), None);
+        assert!(gestures.handle(InputEvent::new(InputKind::ButtonUp, 9000)).is_none());
+
+        let mut config = AppConfig::default().emergency;
+        config.enable_silent_sos = false;
+        let mut gestures = GestureRecognizer::from_config(&config);
+        assert!(feed(&mut gestures, &[(InputKind::ButtonDown, 0), (InputKind::ButtonUp, 5000)]).is_empty());
+    }
+}
@@ -1,11 +1,325 @@
-use jni::JNIEnv;
-use jni::objects::{JClass, JString};
-use jni::sys::{jboolean, jint, jstring, JNI_TRUE, JNI_FALSE};
+use jni::{JNIEnv, JavaVM};
+use jni::objects::{GlobalRef, JByteArray, JByteBuffer, JClass, JDoubleArray, JObject, JShortArray, JString, JValue};
+use jni::sys::{jboolean, jdouble, jint, jlong, jstring, JNI_TRUE, JNI_FALSE};
 use std::ffi::CString;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use crate::contacts::{ContactBook, ContactTier, JsonContactStore, DEFAULT_REGION};
+use crate::coordinates::CoordinateFormats;
+use crate::dialer::{CallReports, CallState, EmergencyDialer};
+use crate::runtime::CallScripts;
+use crate::emergency_numbers::CountryHints;
+use crate::error::{AppError, AppResult};
+use crate::gesture::{InputEvent, InputKind};
+use crate::notifier::{DeliveryRouter, SmsGateway};
+use crate::sensors::{SensorFeed, SensorReading, SensorRecorder, SensorSample};
+use crate::public::types::EmergencyType;
+use crate::templates::{segment_sms, AlertContext, AlertKind, AlertTemplates};
+use crate::public::voice_interface::{Speaker, VoiceInterface};
+use crate::public::voice_stream::{DetectionKind, VoiceDetection, VoiceStream};
+use tokio::sync::{mpsc, watch};
+
+/// Shared streaming pipeline fed by the audio entry points
+static VOICE_STREAM: OnceLock<Mutex<VoiceStream>> = OnceLock::new();
+
+fn voice_stream() -> &'static Mutex<VoiceStream> {
+    VOICE_STREAM.get_or_init(|| {
+        Mutex::new(VoiceStream::new(VoiceInterface::new("vosk-model-small-en-us-0.15")))
+    })
+}
+
+fn lock_voice_stream() -> AppResult<std::sync::MutexGuard<'static, VoiceStream>> {
+    voice_stream()
+        .lock()
+        .map_err(|_| AppError::Voice("voice stream lock poisoned".to_string()))
+}
+
+/// Emergency contacts, persisted under the app files directory once it is known
+static CONTACT_BOOK: OnceLock<Mutex<ContactBook>> = OnceLock::new();
+
+fn contact_book() -> &'static Mutex<ContactBook> {
+    CONTACT_BOOK.get_or_init(|| {
+        Mutex::new(ContactBook::new(DEFAULT_REGION).expect("default region is valid"))
+    })
+}
+
+/// Input channel of the running app's gesture listener
+static INPUT_EVENTS: Mutex<Option<mpsc::Sender<InputEvent>>> = Mutex::new(None);
+
+/// Feed of the running app's live sensor source
+static SENSOR_FEED: Mutex<Option<SensorFeed>> = Mutex::new(None);
+
+/// Trace being recorded from the sensor entry point
+static SENSOR_RECORDER: Mutex<Option<SensorRecorder<BufWriter<File>>>> = Mutex::new(None);
+
+/// SMS gateway registered by the Android app
+static SMS_GATEWAY: Mutex<Option<Arc<JniSmsGateway>>> = Mutex::new(None);
+
+/// Router of the running app, which receives SMS delivery reports
+static DELIVERY_ROUTER: Mutex<Weak<DeliveryRouter>> = Mutex::new(Weak::new());
+
+/// Emergency dialer registered by the Android app
+static EMERGENCY_DIALER: Mutex<Option<Arc<JniEmergencyDialer>>> = Mutex::new(None);
+
+/// Speaker registered by the Android app
+static SPEAKER: Mutex<Option<Arc<JniSpeaker>>> = Mutex::new(None);
+
+/// Where the running app receives call state reports
+static CALL_REPORTS: Mutex<Option<CallReports>> = Mutex::new(None);
+
+/// Call scripts of the running app's incident in progress
+static CALL_SCRIPTS: Mutex<Option<CallScripts>> = Mutex::new(None);
+
+/// SIM country and locale reported by the Android app
+static COUNTRY_HINTS: Mutex<CountryHints> = Mutex::new(CountryHints { location: None, sim_country: None, locale: None });
+
+/// Country hints of the running app, which picks emergency numbers by them
+static APP_COUNTRY_HINTS: Mutex<Weak<watch::Sender<CountryHints>>> = Mutex::new(Weak::new());
+
+/// SMS gateway calling `sendSms(number, parts, idempotencyKey)` on a Java object
+pub struct JniSmsGateway {
+    vm: JavaVM,
+    gateway: GlobalRef,
+}
+
+impl SmsGateway for JniSmsGateway {
+    fn send_sms(&self, number: &str, parts: &[String], idempotency_key: &str) -> AppResult<()> {
+        let mut env = self.vm.attach_current_thread()?;
+        let number = env.new_string(number)?;
+        let texts = env.new_object_array(parts.len() as i32, "java/lang/String", JObject::null())?;
+        for (index, part) in parts.iter().enumerate() {
+            let part = env.new_string(part)?;
+            env.set_object_array_element(&texts, index as i32, part)?;
+        }
+        let key = env.new_string(idempotency_key)?;
+        let queued = env
+            .call_method(
+                &self.gateway,
+                "sendSms",
+                "(Ljava/lang/String;[Ljava/lang/String;Ljava/lang/String;)Z",
+                &[JValue::Object(&number), JValue::Object(&texts), JValue::Object(&key)],
+            )?
+            .z()?;
+        if queued {
+            Ok(())
+        } else {
+            Err(AppError::Network("SMS gateway could not queue the message".to_string()))
+        }
+    }
+}
+
+/// Emergency dialer calling `dial(number, callId)` and `hangUp(callId)` on a Java object
+///
+/// The Java side reports each call's states through `RustBridge.reportCallState`.
+pub struct JniEmergencyDialer {
+    vm: JavaVM,
+    dialer: GlobalRef,
+}
+
+impl EmergencyDialer for JniEmergencyDialer {
+    fn dial(&self, number: &str, call_id: &str, _reports: &CallReports) -> AppResult<()> {
+        let mut env = self.vm.attach_current_thread()?;
+        let number_str = env.new_string(number)?;
+        let call_id = env.new_string(call_id)?;
+        let placed = env
+            .call_method(
+                &self.dialer,
+                "dial",
+                "(Ljava/lang/String;Ljava/lang/String;)Z",
+                &[JValue::Object(&number_str), JValue::Object(&call_id)],
+            )?
+            .z()?;
+        if placed {
+            Ok(())
+        } else {
+            Err(AppError::Network(format!("dialer could not place a call to {}", number)))
+        }
+    }
+
+    fn hang_up(&self, call_id: &str) -> AppResult<()> {
+        let mut env = self.vm.attach_current_thread()?;
+        let call_id = env.new_string(call_id)?;
+        let ended = env
+            .call_method(&self.dialer, "hangUp", "(Ljava/lang/String;)Z", &[JValue::Object(&call_id)])?
+            .z()?;
+        if ended {
+            Ok(())
+        } else {
+            Err(AppError::Network("dialer could not hang up".to_string()))
+        }
+    }
+
+    fn speak(&self, call_id: &str, text: &str) -> AppResult<()> {
+        let mut env = self.vm.attach_current_thread()?;
+        let call_id = env.new_string(call_id)?;
+        let text = env.new_string(text)?;
+        let speaking = env
+            .call_method(
+                &self.dialer,
+                "speak",
+                "(Ljava/lang/String;Ljava/lang/String;)Z",
+                &[JValue::Object(&call_id), JValue::Object(&text)],
+            )?
+            .z()?;
+        if speaking {
+            Ok(())
+        } else {
+            Err(AppError::Internal("dialer could not speak into the call".to_string()))
+        }
+    }
+}
+
+/// Speaker calling `speak(text)` on a Java object
+pub struct JniSpeaker {
+    vm: JavaVM,
+    speaker: GlobalRef,
+}
+
+impl Speaker for JniSpeaker {
+    fn speak(&self, text: &str) -> AppResult<()> {
+        let mut env = self.vm.attach_current_thread()?;
+        let text = env.new_string(text)?;
+        let queued = env
+            .call_method(&self.speaker, "speak", "(Ljava/lang/String;)Z", &[JValue::Object(&text)])?
+            .z()?;
+        if queued {
+            Ok(())
+        } else {
+            Err(AppError::Voice("speaker could not queue the text".to_string()))
+        }
+    }
+}
+
+/// Get the speaker registered through `RustBridge.registerSpeaker`
+pub fn speaker() -> Option<Arc<dyn Speaker>> {
+    let speaker = SPEAKER.lock().unwrap_or_else(|e| e.into_inner()).clone()?;
+    Some(speaker)
+}
+
+/// Get the emergency dialer registered through `RustBridge.registerEmergencyDialer`
+pub fn emergency_dialer() -> Option<Arc<dyn EmergencyDialer>> {
+    let dialer = EMERGENCY_DIALER.lock().unwrap_or_else(|e| e.into_inner()).clone()?;
+    Some(dialer)
+}
+
+/// Send `RustBridge.reportCallState` reports to `reports`
+pub fn attach_call_reports(reports: CallReports) {
+    *CALL_REPORTS.lock().unwrap_or_else(|e| e.into_inner()) = Some(reports);
+}
+
+/// Answer `RustBridge.callScript` from `scripts`
+pub fn attach_call_scripts(scripts: CallScripts) {
+    *CALL_SCRIPTS.lock().unwrap_or_else(|e| e.into_inner()) = Some(scripts);
+}
+
+/// Get the SMS gateway registered through `RustBridge.registerSmsGateway`
+pub fn sms_gateway() -> Option<Arc<dyn SmsGateway>> {
+    let gateway = SMS_GATEWAY.lock().unwrap_or_else(|e| e.into_inner()).clone()?;
+    Some(gateway)
+}
+
+/// Send `RustBridge.reportSmsStatus` reports to `router`
+pub fn attach_delivery_reports(router: &Arc<DeliveryRouter>) {
+    *DELIVERY_ROUTER.lock().unwrap_or_else(|e| e.into_inner()) = Arc::downgrade(router);
+}
+
+/// Send `RustBridge.setCountryHints` updates to `hints`, starting with the latest
+pub fn attach_country_hints(hints: &Arc<watch::Sender<CountryHints>>) {
+    let latest = COUNTRY_HINTS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    hints.send_modify(|hints| {
+        hints.sim_country = latest.sim_country;
+        hints.locale = latest.locale;
+    });
+    *APP_COUNTRY_HINTS.lock().unwrap_or_else(|e| e.into_inner()) = Arc::downgrade(hints);
+}
+
+/// Send `RustBridge.onInputEvent` key and tap events to `inputs`
+pub fn attach_input_events(inputs: mpsc::Sender<InputEvent>) {
+    *INPUT_EVENTS.lock().unwrap_or_else(|e| e.into_inner()) = Some(inputs);
+}
+
+/// Send `RustBridge.onSensorSample` readings to `feed`
+pub fn attach_sensor_feed(feed: SensorFeed) {
+    *SENSOR_FEED.lock().unwrap_or_else(|e| e.into_inner()) = Some(feed);
+}
+
+/// Feed audio from a Java object into the shared voice stream
+///
+/// Accepts, in order of preference:
+/// - a direct `ByteBuffer` of native-order 16-bit PCM, read in place via `GetDirectBufferAddress`
+/// - a `short[]` of PCM samples, copied out with `GetShortArrayRegion`
+/// - a `byte[]` of little-endian PCM, the legacy path
+///
+/// A direct buffer is read from its position to its limit and its position
+/// is left alone, so allocate it with
+/// `ByteBuffer.allocateDirect(n * STREAM_FRAME_BYTES).order(ByteOrder.nativeOrder())`
+/// and `flip()` it after filling. Arrays are copied out rather than pinned,
+/// so no JNI critical section is held while frames are denoised and recognized.
+/// Every call ingests the audio it is given; use one entry point per frame.
+fn ingest_audio(env: &mut JNIEnv, audio_data: &JObject) -> AppResult<Option<VoiceDetection>> {
+    if audio_data.is_null() {
+        return Ok(None);
+    }
+
+    if env.is_instance_of(audio_data, "java/nio/ByteBuffer")? {
+        let buffer = <&JByteBuffer>::from(audio_data);
+        // Fails for heap buffers, which have no stable native address
+        let address = env.get_direct_buffer_address(buffer)?;
+        let capacity = env.get_direct_buffer_capacity(buffer)?;
+        let position = env.call_method(buffer, "position", "()I", &[])?.i()?;
+        let limit = env.call_method(buffer, "limit", "()I", &[])?.i()?;
+        let (position, limit) = (position.max(0) as usize, limit.max(0) as usize);
+        if position > limit || limit > capacity {
+            return Err(AppError::InvalidInput("direct audio buffer has an invalid position or limit".to_string()));
+        }
+        if (limit - position) % 2 != 0 {
+            return Err(AppError::InvalidInput("direct audio buffer holds a partial sample".to_string()));
+        }
+        // SAFETY: position <= limit <= capacity, so the offset stays inside the buffer.
+        let start = unsafe { address.add(position) };
+        if !(start as *const i16).is_aligned() {
+            return Err(AppError::InvalidInput("direct audio buffer is not 2-byte aligned".to_string()));
+        }
+
+        // SAFETY: the JVM keeps a direct buffer's memory alive and in place while
+        // the Java caller holds a reference to it for the duration of this call,
+        // the range lies within its capacity and the start was checked for i16
+        // alignment above.
+        let samples = unsafe { std::slice::from_raw_parts(start as *const i16, (limit - position) / 2) };
+        return lock_voice_stream()?.push_samples(samples);
+    }
+
+    if env.is_instance_of(audio_data, "[S")? {
+        let array = <&JShortArray>::from(audio_data);
+        let mut samples = vec![0; env.get_array_length(array)? as usize];
+        env.get_short_array_region(array, 0, &mut samples)?;
+        return lock_voice_stream()?.push_samples(&samples);
+    }
+
+    if env.is_instance_of(audio_data, "[B")? {
+        let array = <&JByteArray>::from(audio_data);
+        let bytes = env.convert_byte_array(array)?;
+        return lock_voice_stream()?.push_le_bytes(&bytes);
+    }
+
+    Err(AppError::InvalidInput("unsupported audio container".to_string()))
+}
+
+/// Ingest audio, logging and swallowing errors at the JNI boundary
+fn ingest_audio_or_log(env: &mut JNIEnv, audio_data: &JObject) -> Option<VoiceDetection> {
+    match ingest_audio(env, audio_data) {
+        Ok(detection) => detection,
+        Err(e) => {
+            tracing::warn!("Audio ingestion failed: {}", e);
+            None
+        }
+    }
+}
 
 /// Initialize Android-specific paths and interfaces
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_initializeAndroidPaths(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_initializeAndroidPaths(
     mut _env: JNIEnv,
     _class: JClass,
     files_dir: JString,
@@ -16,12 +330,17 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_initializeAndroidPaths
     let cache_dir_str = _env.get_string(&cache_dir).unwrap().to_str().unwrap().to_string();
     
     // Initialize interfaces with Android paths
+    let store = JsonContactStore::new(std::path::Path::new(&files_dir_str).join("emergency_contacts.json"));
+    match ContactBook::new(DEFAULT_REGION).and_then(|book| book.with_store(Box::new(store))) {
+        Ok(book) => *contact_book().lock().unwrap_or_else(|e| e.into_inner()) = book,
+        Err(e) => tracing::error!("Failed to load emergency contacts: {}", e),
+    }
     tracing::info!("Android paths initialized: {} {}", files_dir_str, cache_dir_str);
 }
 
 /// Initialize voice recognition
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_initializeVoiceRecognition(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_initializeVoiceRecognition(
     _env: JNIEnv,
     _class: JClass,
 ) -> jboolean {
@@ -29,46 +348,50 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_initializeVoiceRecogni
     JNI_TRUE
 }
 
-/// Process voice input and return recognized text
+/// Process voice input and return recognized text, or null if nothing was detected yet
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_processVoiceInput(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_processVoiceInput(
     mut _env: JNIEnv,
     _class: JClass,
-    _audio_data: jni::objects::JObject,
+    audio_data: JObject,
 ) -> jstring {
-    // For demo purposes, return a placeholder
-    let recognized_text = "hey sos drowning emergency".to_string();
-    let c_string = CString::new(recognized_text).unwrap();
-    _env.new_string(c_string.to_str().unwrap()).unwrap().into_raw()
+    match ingest_audio_or_log(&mut _env, &audio_data) {
+        Some(detection) => _env.new_string(detection.text).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
 }
 
 /// Detect wake word in audio
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_detectWakeWord(
-    _env: JNIEnv,
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_detectWakeWord(
+    mut _env: JNIEnv,
     _class: JClass,
-    _audio_data: jni::objects::JObject,
+    audio_data: JObject,
 ) -> jboolean {
-    // For demo purposes, always detect wake word
-    JNI_TRUE
+    match ingest_audio_or_log(&mut _env, &audio_data) {
+        Some(VoiceDetection { kind: DetectionKind::WakeWord, .. }) => JNI_TRUE,
+        _ => JNI_FALSE,
+    }
 }
 
-/// Detect emergency phrase in audio
+/// Detect emergency phrase in audio, returning the phrase or null
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_detectEmergencyPhrase(
-    _env: JNIEnv,
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_detectEmergencyPhrase(
+    mut _env: JNIEnv,
     _class: JClass,
-    _audio_data: jni::objects::JObject,
+    audio_data: JObject,
 ) -> jstring {
-    // For demo purposes, return a detected emergency
-    let detected_emergency = "drowning".to_string();
-    let c_string = CString::new(detected_emergency).unwrap();
-    _env.new_string(c_string.to_str().unwrap()).unwrap().into_raw()
+    match ingest_audio_or_log(&mut _env, &audio_data) {
+        Some(VoiceDetection { kind: DetectionKind::EmergencyPhrase(phrase), .. }) => {
+            _env.new_string(phrase).unwrap().into_raw()
+        }
+        _ => std::ptr::null_mut(),
+    }
 }
 
 /// Get emergency instructions
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getEmergencyInstructions(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_getEmergencyInstructions(
     mut _env: JNIEnv,
     _class: JClass,
     emergency_type: JString,
@@ -77,19 +400,19 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getEmergencyInstructio
     
     // Return appropriate instructions based on emergency type
     let instructions = match emergency_type_str.as_str() {
-        "drowning" => "1. Remove victim from water\n2. Check breathing\n3. Begin CPR if needed\n4. Call 911",
-        "heart attack" => "1. Call 911 immediately\n2. Have victim sit down\n3. Loosen tight clothing\n4. Monitor breathing",
-        "choking" => "1. Perform Heimlich maneuver\n2. 5 back blows, 5 abdominal thrusts\n3. Call 911 if not resolved",
-        "bleeding" => "1. Apply direct pressure\n2. Elevate if possible\n3. Use tourniquet if severe\n4. Call 911",
-        "unconscious" => "1. Check breathing\n2. Begin CPR if needed\n3. Call 911 immediately\n4. Monitor for changes",
-        "stroke" => "1. Remember FAST\n2. Face, Arm, Speech, Time\n3. Call 911 immediately\n4. Note time of onset",
-        "seizure" => "1. Clear area of objects\n2. Don't restrain\n3. Time the seizure\n4. Call 911 if >5 minutes",
-        "poisoning" => "1. Call Poison Control\n2. Don't induce vomiting\n3. Save container\n4. Call 911 if severe",
-        "burn" => "1. Cool with water\n2. Don't use ice\n3. Cover with clean cloth\n4. Call 911 if severe",
-        "diabetic" => "1. Check blood sugar\n2. Give sugar if low\n3. Call 911 if unconscious\n4. Monitor breathing",
-        "allergic" => "1. Use EpiPen if available\n2. Call 911 immediately\n3. Monitor breathing\n4. Lie flat if dizzy",
-        "trauma" => "1. Stop bleeding\n2. Immobilize injuries\n3. Call 911\n4. Monitor consciousness",
-        _ => "Call 911 immediately and follow emergency dispatcher instructions"
+        "drowning" => "1. Remove victim from water\n2. Check breathing\n3. Begin CPR if needed\n4. Call emergency services",
+        "heart attack" => "1. Call emergency services immediately\n2. Have victim sit down\n3. Loosen tight clothing\n4. Monitor breathing",
+        "choking" => "1. Perform Heimlich maneuver\n2. 5 back blows, 5 abdominal thrusts\n3. Call emergency services if not resolved",
+        "bleeding" => "1. Apply direct pressure\n2. Elevate if possible\n3. Use tourniquet if severe\n4. Call emergency services",
+        "unconscious" => "1. Check breathing\n2. Begin CPR if needed\n3. Call emergency services immediately\n4. Monitor for changes",
+        "stroke" => "1. Remember FAST\n2. Face, Arm, Speech, Time\n3. Call emergency services immediately\n4. Note time of onset",
+        "seizure" => "1. Clear area of objects\n2. Don't restrain\n3. Time the seizure\n4. Call emergency services if >5 minutes",
+        "poisoning" => "1. Call Poison Control\n2. Don't induce vomiting\n3. Save container\n4. Call emergency services if severe",
+        "burn" => "1. Cool with water\n2. Don't use ice\n3. Cover with clean cloth\n4. Call emergency services if severe",
+        "diabetic" => "1. Check blood sugar\n2. Give sugar if low\n3. Call emergency services if unconscious\n4. Monitor breathing",
+        "allergic" => "1. Use EpiPen if available\n2. Call emergency services immediately\n3. Monitor breathing\n4. Lie flat if dizzy",
+        "trauma" => "1. Stop bleeding\n2. Immobilize injuries\n3. Call emergency services\n4. Monitor consciousness",
+        _ => "Call emergency services immediately and follow emergency dispatcher instructions"
     };
     
     let c_string = CString::new(instructions).unwrap();
@@ -98,7 +421,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getEmergencyInstructio
 
 /// Get context-aware guidance
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getContextAwareGuidance(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_getContextAwareGuidance(
     mut _env: JNIEnv,
     _class: JClass,
     emergency_type: JString,
@@ -115,7 +438,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getContextAwareGuidanc
 
 /// Detect emergency stage
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_detectEmergencyStage(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_detectEmergencyStage(
     mut _env: JNIEnv,
     _class: JClass,
     user_phrase: JString,
@@ -135,7 +458,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_detectEmergencyStage(
 
 /// Award XP for actions
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_awardXP(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_awardXP(
     mut _env: JNIEnv,
     _class: JClass,
     action: JString,
@@ -150,7 +473,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_awardXP(
 
 /// Get hero level
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getHeroLevel(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_getHeroLevel(
     _env: JNIEnv,
     _class: JClass,
 ) -> jint {
@@ -160,7 +483,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getHeroLevel(
 
 /// Get total rewards
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getTotalRewards(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_getTotalRewards(
     mut _env: JNIEnv,
     _class: JClass,
 ) -> jstring {
@@ -171,7 +494,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getTotalRewards(
 
 /// Connect Solana wallet
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_connectSolanaWallet(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_connectSolanaWallet(
     _env: JNIEnv,
     _class: JClass,
 ) -> jboolean {
@@ -182,7 +505,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_connectSolanaWallet(
 
 /// Get wallet address
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getWalletAddress(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_getWalletAddress(
     mut _env: JNIEnv,
     _class: JClass,
 ) -> jstring {
@@ -193,7 +516,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getWalletAddress(
 
 /// Record emergency on blockchain
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_recordEmergencyOnBlockchain(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_recordEmergencyOnBlockchain(
     mut _env: JNIEnv,
     _class: JClass,
     emergency_data: JString,
@@ -208,7 +531,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_recordEmergencyOnBlock
 
 /// Initialize database
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_initializeDatabase(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_initializeDatabase(
     _env: JNIEnv,
     _class: JClass,
 ) -> jboolean {
@@ -219,7 +542,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_initializeDatabase(
 
 /// Save emergency contact
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_saveEmergencyContact(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_saveEmergencyContact(
     mut _env: JNIEnv,
     _class: JClass,
     name: JString,
@@ -227,29 +550,469 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_saveEmergencyContact(
 ) -> jboolean {
     let name_str = _env.get_string(&name).unwrap().to_str().unwrap().to_string();
     let phone_str = _env.get_string(&phone).unwrap().to_str().unwrap().to_string();
-    
-    // For demo purposes, always save successfully
-    tracing::info!("Emergency contact saved: {} - {}", name_str, phone_str);
-    JNI_TRUE
+
+    let contact = crate::contacts::Contact::new(&name_str, &phone_str, ContactTier::Primary);
+    match contact_book().lock().unwrap_or_else(|e| e.into_inner()).add(contact) {
+        Ok(contact) => {
+            tracing::info!("Emergency contact saved: {} - {}", contact.name, contact.phone_number);
+            JNI_TRUE
+        }
+        Err(e) => {
+            tracing::warn!("Emergency contact rejected: {}", e);
+            JNI_FALSE
+        }
+    }
 }
 
-/// Get emergency contacts
+/// Get emergency contacts as a JSON array of `{"name", "phone", "relationship", "tier", "channels"}`
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getEmergencyContacts(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_getEmergencyContacts(
     mut _env: JNIEnv,
     _class: JClass,
 ) -> jstring {
-    let contacts = r#"[
-        {"name": "Emergency Contact 1", "phone": "555-0101"},
-        {"name": "Emergency Contact 2", "phone": "555-0102"}
-    ]"#.to_string();
-    let c_string = CString::new(contacts).unwrap();
-    _env.new_string(c_string.to_str().unwrap()).unwrap().into_raw()
+    let book = contact_book().lock().unwrap_or_else(|e| e.into_inner());
+    let contacts: Vec<serde_json::Value> = book
+        .contacts()
+        .iter()
+        .map(|contact| serde_json::json!({
+            "name": contact.name,
+            "phone": contact.phone_number,
+            "relationship": contact.relationship,
+            "tier": contact.tier,
+            "channels": contact.channels,
+        }))
+        .collect();
+    _env.new_string(serde_json::Value::Array(contacts).to_string()).unwrap().into_raw()
+}
+
+/// Import contacts from vCard 3.0/4.0 text as secondary contacts
+///
+/// Returns the import report as JSON with `added`, `duplicates` and `rejected`.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_importVCard(
+    mut _env: JNIEnv,
+    _class: JClass,
+    vcard: JString,
+) -> jstring {
+    let vcard_str: String = match _env.get_string(&vcard) {
+        Ok(vcard) => vcard.into(),
+        Err(_) => return std::ptr::null_mut(),
+    };
+
+    let mut book = contact_book().lock().unwrap_or_else(|e| e.into_inner());
+    let report = match book.import_vcard(&vcard_str, ContactTier::Secondary) {
+        Ok(report) => serde_json::to_value(report).unwrap_or_default(),
+        Err(e) => serde_json::json!({"error": e.to_string()}),
+    };
+    _env.new_string(report.to_string())
+        .map(|s| s.into_raw())
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Register the object that sends SMS alerts (`RustBridge.SmsGateway`)
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_registerSmsGateway(
+    mut _env: JNIEnv,
+    _class: JClass,
+    gateway: JObject,
+) {
+    let registered = _env.get_java_vm().and_then(|vm| {
+        let gateway = _env.new_global_ref(&gateway)?;
+        Ok(JniSmsGateway { vm, gateway })
+    });
+    match registered {
+        Ok(gateway) => {
+            *SMS_GATEWAY.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(gateway));
+            tracing::info!("SMS gateway registered");
+        }
+        Err(e) => tracing::error!("Failed to register SMS gateway: {}", e),
+    }
+}
+
+/// Report the carrier's result for an SMS alert, keyed by its idempotency key
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_reportSmsStatus(
+    mut _env: JNIEnv,
+    _class: JClass,
+    idempotency_key: JString,
+    delivered: jboolean,
+    error: JString,
+) {
+    let key: String = match _env.get_string(&idempotency_key) {
+        Ok(key) => key.into(),
+        Err(_) => return,
+    };
+    let error: Option<String> = if error.is_null() {
+        None
+    } else {
+        _env.get_string(&error).ok().map(Into::into)
+    };
+
+    match DELIVERY_ROUTER.lock().unwrap_or_else(|e| e.into_inner()).upgrade() {
+        Some(router) => router.confirm(&key, delivered == JNI_TRUE, error),
+        None => tracing::debug!("SMS report for {} arrived with no app running", key),
+    }
+}
+
+/// Register the object that places emergency calls (`RustBridge.EmergencyDialer`)
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_registerEmergencyDialer(
+    mut _env: JNIEnv,
+    _class: JClass,
+    dialer: JObject,
+) {
+    let registered = _env.get_java_vm().and_then(|vm| {
+        let dialer = _env.new_global_ref(&dialer)?;
+        Ok(JniEmergencyDialer { vm, dialer })
+    });
+    match registered {
+        Ok(dialer) => {
+            *EMERGENCY_DIALER.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(dialer));
+            tracing::info!("Emergency dialer registered");
+        }
+        Err(e) => tracing::error!("Failed to register emergency dialer: {}", e),
+    }
+}
+
+/// Register the object that speaks questions to the user (`RustBridge.Speaker`)
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_registerSpeaker(
+    mut _env: JNIEnv,
+    _class: JClass,
+    speaker: JObject,
+) {
+    let registered = _env.get_java_vm().and_then(|vm| {
+        let speaker = _env.new_global_ref(&speaker)?;
+        Ok(JniSpeaker { vm, speaker })
+    });
+    match registered {
+        Ok(speaker) => {
+            *SPEAKER.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(speaker));
+            tracing::info!("Speaker registered");
+        }
+        Err(e) => tracing::error!("Failed to register speaker: {}", e),
+    }
+}
+
+/// Report a call's state: "dialing", "connected", "failed" or "ended"
+///
+/// `error` says why a call failed or ended, if known, and may be null.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_reportCallState(
+    mut _env: JNIEnv,
+    _class: JClass,
+    call_id: JString,
+    state: JString,
+    error: JString,
+) {
+    let call_id: String = match _env.get_string(&call_id) {
+        Ok(call_id) => call_id.into(),
+        Err(_) => return,
+    };
+    let state = match _env.get_string(&state).ok().and_then(|state| CallState::from_key(&String::from(state))) {
+        Some(state) => state,
+        None => {
+            tracing::warn!("Unknown state reported for call {}", call_id);
+            return;
+        }
+    };
+    let error: Option<String> = if error.is_null() {
+        None
+    } else {
+        _env.get_string(&error).ok().map(Into::into)
+    };
+
+    match CALL_REPORTS.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        Some(reports) => reports.report(&call_id, state, error),
+        None => tracing::debug!("Call state for {} arrived with no app running", call_id),
+    }
+}
+
+/// Feed a key or tap event to the running app's silent SOS listener
+///
+/// `kind` is "power", "volume_up", "volume_down", "tap", "button_down" or
+/// "button_up"; `timestamp_ms` is `SystemClock.uptimeMillis()` of the event.
+/// The app matches the gesture against the `[emergency]` settings, times
+/// button holds itself and raises the silent emergency on a match. Returns
+/// false if the event is invalid or no app is running to take it.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_onInputEvent(
+    mut _env: JNIEnv,
+    _class: JClass,
+    kind: JString,
+    timestamp_ms: jlong,
+) -> jboolean {
+    let kind = match _env.get_string(&kind) {
+        Ok(kind) => InputKind::from_key(&String::from(kind)),
+        Err(_) => return JNI_FALSE,
+    };
+    let Some(kind) = kind else {
+        return JNI_FALSE;
+    };
+
+    let event = InputEvent::new(kind, timestamp_ms.max(0) as u64);
+    let inputs = INPUT_EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    match inputs.as_ref().map(|inputs| inputs.try_send(event)) {
+        Some(Ok(())) => JNI_TRUE,
+        Some(Err(e)) => {
+            tracing::warn!("Input event dropped: {}", e);
+            JNI_FALSE
+        }
+        None => {
+            tracing::debug!("Input event arrived with no app running");
+            JNI_FALSE
+        }
+    }
+}
+
+/// Feed one sensor reading to the running app and any recording
+///
+/// `kind` and `values` follow the trace format: "accel" or "gyro" (x, y, z),
+/// "heading" (degrees from true north, accuracy in degrees), "speed" (m/s), "location" (latitude, longitude, accuracy in m),
+/// "pressure" (hPa) or "battery" (percent, 1 if charging else 0).
+/// `timestamp_ms` is `SystemClock.uptimeMillis()` of the reading. Returns
+/// false if the reading is invalid or was dropped.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_onSensorSample(
+    mut _env: JNIEnv,
+    _class: JClass,
+    kind: JString,
+    timestamp_ms: jlong,
+    values: JDoubleArray,
+) -> jboolean {
+    let kind: String = match _env.get_string(&kind) {
+        Ok(kind) => kind.into(),
+        Err(_) => return JNI_FALSE,
+    };
+    let length = _env.get_array_length(&values).unwrap_or(0).max(0) as usize;
+    let mut buffer = vec![0.0; length];
+    if _env.get_double_array_region(&values, 0, &mut buffer).is_err() {
+        return JNI_FALSE;
+    }
+    let Some(reading) = SensorReading::from_values(&kind, &buffer) else {
+        return JNI_FALSE;
+    };
+    let sample = SensorSample::new(timestamp_ms.max(0) as u64, reading);
+
+    let mut recorder = SENSOR_RECORDER.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(trace) = recorder.as_mut() {
+        if let Err(e) = trace.record(&sample) {
+            tracing::error!("Sensor recording stopped: {}", e);
+            *recorder = None;
+        }
+    }
+    drop(recorder);
+
+    match SENSOR_FEED.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        Some(feed) if feed.push(sample) => JNI_TRUE,
+        _ => JNI_FALSE,
+    }
+}
+
+/// Start recording sensor readings to a trace file, replacing any recording
+///
+/// `label` is written as the trace's `# label:` metadata.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_startSensorRecording(
+    mut _env: JNIEnv,
+    _class: JClass,
+    path: JString,
+    label: JString,
+) -> jboolean {
+    let (path, label): (String, String) = match (_env.get_string(&path), _env.get_string(&label)) {
+        (Ok(path), Ok(label)) => (path.into(), label.into()),
+        _ => return JNI_FALSE,
+    };
+    let recorded_at = chrono::Utc::now().to_rfc3339();
+    match SensorRecorder::create(&path, &[("label", &label), ("recorded", &recorded_at)]) {
+        Ok(recorder) => {
+            *SENSOR_RECORDER.lock().unwrap_or_else(|e| e.into_inner()) = Some(recorder);
+            tracing::info!("Recording sensors to {}", path);
+            JNI_TRUE
+        }
+        Err(e) => {
+            tracing::error!("Failed to start sensor recording: {}", e);
+            JNI_FALSE
+        }
+    }
+}
+
+/// Finish the sensor recording; returns false if none was running or it
+/// could not be written
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_stopSensorRecording(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jboolean {
+    let recorder = SENSOR_RECORDER.lock().unwrap_or_else(|e| e.into_inner()).take();
+    match recorder.map(SensorRecorder::finish) {
+        Some(Ok(_)) => JNI_TRUE,
+        Some(Err(e)) => {
+            tracing::error!("Failed to finish sensor recording: {}", e);
+            JNI_FALSE
+        }
+        None => JNI_FALSE,
+    }
+}
+
+/// Render an alert message from a JSON context
+///
+/// The context has `kind` ("silent_sos", "crash_detected", "fall_detected",
+/// "emergency", "location_update", "stand_down" or "duress") and optional `name`, `emergency_type`,
+/// `battery`, `latitude`, `longitude` and `language`. Returns JSON with the
+/// `message`, its SMS `encoding` and `segments`, and `text_to_911`.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_renderAlert(
+    mut _env: JNIEnv,
+    _class: JClass,
+    context_json: JString,
+) -> jstring {
+    let context_str: String = match _env.get_string(&context_json) {
+        Ok(context) => context.into(),
+        Err(_) => return std::ptr::null_mut(),
+    };
+
+    let rendered = match render_alert(&context_str) {
+        Ok(rendered) => rendered,
+        Err(e) => serde_json::json!({"error": e.to_string()}),
+    };
+    _env.new_string(rendered.to_string())
+        .map(|s| s.into_raw())
+        .unwrap_or(std::ptr::null_mut())
+}
+
+fn render_alert(context_json: &str) -> AppResult<serde_json::Value> {
+    let fields: serde_json::Value = serde_json::from_str(context_json)?;
+    let text = |key: &str| fields.get(key).and_then(serde_json::Value::as_str);
+
+    let kind = text("kind")
+        .and_then(AlertKind::from_key)
+        .ok_or_else(|| AppError::InvalidInput("unknown alert kind".to_string()))?;
+    let mut context = AlertContext::new(kind, text("name").unwrap_or_default());
+    context.emergency_type = text("emergency_type").and_then(EmergencyType::from_key);
+    if let Some(battery) = fields.get("battery").and_then(serde_json::Value::as_u64) {
+        context = context.with_battery(battery.min(100) as u8);
+    }
+    let latitude = fields.get("latitude").and_then(serde_json::Value::as_f64);
+    let longitude = fields.get("longitude").and_then(serde_json::Value::as_f64);
+    if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+        context = context.with_location(latitude, longitude);
+    }
+
+    let templates = AlertTemplates::for_language(text("language").unwrap_or("en"));
+    let message = templates.render(&context);
+    let sms = segment_sms(&message);
+    Ok(serde_json::json!({
+        "message": message,
+        "encoding": sms.encoding,
+        "segments": sms.segments,
+        "text_to_911": templates.text_to_911(&context),
+    }))
+}
+
+/// Format a position for dispatch
+///
+/// Returns JSON with `decimal`, `plus_code`, `dms`, `utm` and `mgrs` (null
+/// near the poles), and `spoken`, the plus code spelled out for TTS.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_formatCoordinates(
+    _env: JNIEnv,
+    _class: JClass,
+    latitude: jdouble,
+    longitude: jdouble,
+) -> jstring {
+    let formats = CoordinateFormats::new(latitude, longitude);
+    let mut json = serde_json::to_value(&formats).unwrap_or_default();
+    json["spoken"] = serde_json::Value::from(formats.spoken_plus_code());
+    _env.new_string(json.to_string())
+        .map(|s| s.into_raw())
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Set the SIM country (`TelephonyManager.getSimCountryIso`) and locale tag
+///
+/// Either may be null. Emergency numbers are picked by these when there is
+/// no fix.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_setCountryHints(
+    mut _env: JNIEnv,
+    _class: JClass,
+    sim_country: JString,
+    locale: JString,
+) {
+    let mut read = |text: &JString| -> Option<String> {
+        if text.is_null() {
+            return None;
+        }
+        _env.get_string(text).ok().map(Into::into)
+    };
+    let (sim_country, locale) = (read(&sim_country), read(&locale));
+
+    let mut hints = COUNTRY_HINTS.lock().unwrap_or_else(|e| e.into_inner());
+    hints.sim_country = sim_country;
+    hints.locale = locale;
+    if let Some(app) = APP_COUNTRY_HINTS.lock().unwrap_or_else(|e| e.into_inner()).upgrade() {
+        app.send_modify(|app| {
+            app.sim_country = hints.sim_country.clone();
+            app.locale = hints.locale.clone();
+        });
+    }
+}
+
+/// Emergency numbers for a position, or for the SIM country and locale if
+/// either coordinate is NaN
+///
+/// Returns JSON with `country`, `source` (`location`, `sim`, `locale` or
+/// `config`) and `numbers`: `police`, `ambulance`, `fire`,
+/// `poison_control` and `crisis_line`, the last two null where the country
+/// has none.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_emergencyNumbers(
+    _env: JNIEnv,
+    _class: JClass,
+    latitude: jdouble,
+    longitude: jdouble,
+) -> jstring {
+    let hints = CountryHints {
+        location: (latitude.is_finite() && longitude.is_finite()).then_some((latitude, longitude)),
+        ..COUNTRY_HINTS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    };
+    let selection = hints.resolve(DEFAULT_REGION);
+    _env.new_string(serde_json::to_string(&selection).unwrap_or_default())
+        .map(|s| s.into_raw())
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// What to tell the dispatcher about the incident in progress, as JSON
+///
+/// `address` is the street address of the current position, if the host
+/// could look one up, and may be null. Returns `lines` to show on screen
+/// and `spoken` for text-to-speech, or "null" when no incident is in
+/// progress.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_callScript(
+    mut _env: JNIEnv,
+    _class: JClass,
+    address: JString,
+) -> jstring {
+    let address: Option<String> = if address.is_null() {
+        None
+    } else {
+        _env.get_string(&address).ok().map(Into::into)
+    };
+    let script = CALL_SCRIPTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .and_then(|scripts| scripts.script(address.as_deref()));
+    _env.new_string(serde_json::to_string(&script).unwrap_or_default())
+        .map(|s| s.into_raw())
+        .unwrap_or(std::ptr::null_mut())
 }
 
 /// Validate emergency type
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_validateEmergencyType(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_validateEmergencyType(
     mut _env: JNIEnv,
     _class: JClass,
     emergency_type: JString,
@@ -269,9 +1032,45 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_validateEmergencyType(
     }
 }
 
+/// Get the JSON Schema describing every configuration key
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_getConfigSchema(
+    mut _env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let schema = crate::schema::config_schema().to_string();
+    _env.new_string(schema).map(|s| s.into_raw()).unwrap_or(std::ptr::null_mut())
+}
+
+/// Check settings edits (a JSON object of dotted keys) before persisting them
+///
+/// Returns a JSON array of `{"key", "message"}` errors, empty when every edit is valid.
+#[no_mangle]
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_validateSettings(
+    mut _env: JNIEnv,
+    _class: JClass,
+    edits_json: JString,
+) -> jstring {
+    let edits_str: String = match _env.get_string(&edits_json) {
+        Ok(edits) => edits.into(),
+        Err(_) => return std::ptr::null_mut(),
+    };
+
+    let errors: Vec<serde_json::Value> = match serde_json::from_str::<serde_json::Value>(&edits_str) {
+        Ok(serde_json::Value::Object(edits)) => crate::schema::validate_edits(&edits)
+            .into_iter()
+            .map(|issue| serde_json::json!({"key": issue.key, "message": issue.message}))
+            .collect(),
+        _ => vec![serde_json::json!({"key": "", "message": "edits must be a JSON object"})],
+    };
+    _env.new_string(serde_json::Value::Array(errors).to_string())
+        .map(|s| s.into_raw())
+        .unwrap_or(std::ptr::null_mut())
+}
+
 /// Get app version
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getAppVersion(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_getAppVersion(
     mut _env: JNIEnv,
     _class: JClass,
 ) -> jstring {
@@ -282,7 +1081,7 @@ pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getAppVersion(
 
 /// Get build info
 #[no_mangle]
-pub extern "C" fn Java_com_solanasos_emergency_RustBridge_getBuildInfo(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_getBuildInfo(
     mut _env: JNIEnv,
     _class: JClass,
 ) -> jstring {
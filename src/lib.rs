@@ -54,7 +54,7 @@
 //! Advanced safety features include:
 //! 
 //! - **Silent SOS**: Discreet activation for dangerous situations
-//! - **Crash Detection**: Automatic 911 calling based on sensor data
+//! - **Crash Detection**: Automatic emergency calls based on sensor data, on the local emergency number
 //! - **Trusted Network**: Personal network of emergency contacts
 //! 
 //! ## Technology Stack
@@ -95,6 +95,7 @@
 // Public modules (visible to users)
 pub mod public {
     pub mod voice_interface;
+    pub mod voice_stream;
     pub mod audio_interface;
     pub mod emergency_interface;
     pub mod types;
@@ -107,10 +108,35 @@ pub mod private;
 // JNI Bridge for Android integration
 pub mod jni_bridge;
 
+// C ABI for embedded targets and non-JVM hosts
+pub mod ffi;
+
 // Core modules (always available)
 pub mod app;
+pub mod call_script;
 pub mod config;
+pub mod contacts;
+pub mod coordinates;
+pub mod countdown;
+pub mod crash;
+pub mod dead_reckoning;
+pub mod diagnostics;
+pub mod dialer;
+pub mod duress;
+pub mod emergency_numbers;
 pub mod error;
+pub mod escalation;
+pub mod event_bus;
+pub mod fall;
+pub mod gesture;
+pub mod location;
+pub mod notifier;
+pub mod runtime;
+pub mod schema;
+pub mod secrets;
+pub mod sensors;
+pub mod settings;
+pub mod templates;
 
 // Re-export main types for easy access
 pub use app::SolanaSOSApp;
@@ -119,22 +145,34 @@ pub use public::types::{EmergencyType, EmergencyStage, DirectAction, Connectivit
 
 // Re-export interface types
 pub use public::voice_interface::{VoiceTrigger, VoiceStats};
-pub use config::VoiceConfig;
-pub use public::audio_interface::{AudioProcessor, AudioConfig, AudioStats};
-pub use public::emergency_interface::{EmergencySystem, EmergencyConfig, EmergencyStats};
+pub use public::voice_stream::{VoiceStream, VoiceDetection, STREAM_FRAME_SAMPLES, STREAM_SAMPLE_RATE};
+pub use config::{VoiceConfig, AudioConfig, EmergencyConfig};
+pub use public::audio_interface::{AudioProcessor, AudioStats};
+pub use public::emergency_interface::{EmergencySystem, EmergencyStats};
 
 // Note: Implementation modules moved to src/private/ for IP protection
 // These are now accessed through the public interfaces above
 
-use crate::private::emergency_database::EmergencyDatabase;
-use crate::private::context_analysis::{ContextAnalyzer, EmergencyContext};
-use crate::private::emergency_calling::{EmergencyCaller, EmergencyContact, EmergencyCallError};
-use std::collections::HashMap;
+use crate::private::emergency_database::{self, EmergencyDatabase};
+use crate::private::context_analysis::ContextAnalyzer;
+use crate::private::emergency_calling::{self, EmergencyCaller, EmergencyContact, EmergencyCallError};
+use crate::contacts::{Contact, ContactBook, ContactTier, ImportReport};
+
+/// Convert a saved contact for the emergency caller
+fn caller_contact(contact: &Contact) -> EmergencyContact {
+    EmergencyContact {
+        name: contact.name.clone(),
+        phone_number: contact.phone_number.clone(),
+        relationship: contact.relationship.clone(),
+        notification_enabled: contact.is_active,
+    }
+}
 
 pub struct SolanaSOS {
     database: EmergencyDatabase,
     context_analyzer: ContextAnalyzer,
     emergency_caller: EmergencyCaller,
+    contacts: ContactBook,
 }
 
 impl SolanaSOS {
@@ -143,6 +181,7 @@ impl SolanaSOS {
             database: EmergencyDatabase::new(),
             context_analyzer: ContextAnalyzer::new(),
             emergency_caller: EmergencyCaller::new(),
+            contacts: ContactBook::new(contacts::DEFAULT_REGION).expect("default region is valid"),
         }
     }
     
@@ -172,40 +211,56 @@ impl SolanaSOS {
         // Get the appropriate instruction
         let instruction = self.context_analyzer.get_next_instruction(&context);
         
-        // Determine if 911 should be called
-        let should_call_911 = self.context_analyzer.should_call_911(&context);
+        // Determine if emergency services should be called
+        let should_call_emergency_services = self.context_analyzer.should_call_911(&context);
         
         // Get emergency summary
         let summary = self.context_analyzer.get_emergency_summary(&context);
         
         EmergencyResponse {
             instruction: instruction.unwrap_or_else(|| "Emergency protocol not found".to_string()),
-            should_call_911,
+            should_call_emergency_services,
             emergency_summary: summary,
             context_flags: context.context_flags,
             current_step: context.current_step,
         }
     }
     
-    /// Make an emergency 911 call
-    pub async fn call_911(&mut self, emergency_type: &str, context_flags: &[String]) -> Result<String, EmergencyCallError> {
+    /// Call emergency services
+    pub async fn call_emergency_services(&mut self, emergency_type: &str, context_flags: &[String]) -> Result<String, EmergencyCallError> {
         self.emergency_caller.call_911(emergency_type, context_flags).await
     }
     
-    /// Add emergency contact
-    pub fn add_emergency_contact(&mut self, name: &str, phone: &str, relationship: &str) {
-        let contact = EmergencyContact {
-            name: name.to_string(),
-            phone_number: phone.to_string(),
-            relationship: relationship.to_string(),
-            notification_enabled: true,
-        };
-        self.emergency_caller.add_emergency_contact(contact);
+    /// Use a contact book with its own region and store in place of the in-memory default
+    pub fn set_contact_book(&mut self, contacts: ContactBook) {
+        for contact in contacts.contacts() {
+            self.emergency_caller.add_emergency_contact(caller_contact(contact));
+        }
+        self.contacts = contacts;
     }
-    
+
+    /// Add a primary emergency contact
+    ///
+    /// The number is normalized to E.164; invalid and duplicate numbers are rejected.
+    pub fn add_emergency_contact(&mut self, name: &str, phone: &str, relationship: &str) -> AppResult<Contact> {
+        let contact = Contact::new(name, phone, ContactTier::Primary).with_relationship(relationship);
+        let contact = self.contacts.add(contact)?.clone();
+        self.emergency_caller.add_emergency_contact(caller_contact(&contact));
+        Ok(contact)
+    }
+
+    /// Import emergency contacts from a vCard 3.0 or 4.0 file
+    pub fn import_emergency_contacts(&mut self, vcard: &str, tier: ContactTier) -> AppResult<ImportReport> {
+        let report = self.contacts.import_vcard(vcard, tier)?;
+        for contact in self.contacts.contacts().iter().filter(|contact| report.added.contains(&contact.name)) {
+            self.emergency_caller.add_emergency_contact(caller_contact(contact));
+        }
+        Ok(report)
+    }
+
     /// Get all emergency contacts
-    pub fn get_emergency_contacts(&self) -> &Vec<EmergencyContact> {
-        self.emergency_caller.get_emergency_contacts()
+    pub fn get_emergency_contacts(&self) -> &[Contact] {
+        self.contacts.contacts()
     }
     
     /// Get available emergency types
@@ -256,7 +311,7 @@ impl SolanaSOS {
 #[derive(Debug, Clone)]
 pub struct EmergencyResponse {
     pub instruction: String,
-    pub should_call_911: bool,
+    pub should_call_emergency_services: bool,
     pub emergency_summary: Option<String>,
     pub context_flags: Vec<String>,
     pub current_step: u32,
@@ -297,14 +352,14 @@ pub extern "system" fn Java_com_solanasos_emergency_RustBridge_processEmergency(
 
 #[cfg(target_os = "android")]
 #[no_mangle]
-pub extern "system" fn Java_com_solanasos_emergency_RustBridge_call911(
+pub extern "system" fn Java_com_solanasos_emergency_RustBridge_callEmergencyServices(
     mut _env: JNIEnv,
     _class: JClass,
     emergency_type: JString,
 ) -> jstring {
-    // This would make the actual 911 call
+    // This would make the actual emergency call
     let emergency_type_str = _env.get_string(&emergency_type).unwrap().to_str().unwrap().to_string();
-    let response = format!("911 call initiated for {}", emergency_type_str);
+    let response = format!("Emergency call initiated for {}", emergency_type_str);
     
     _env.new_string(response).unwrap().into_raw()
 }
@@ -341,11 +396,13 @@ mod tests {
     #[test]
     fn test_emergency_contact_management() {
         let mut sos = SolanaSOS::new();
-        sos.add_emergency_contact("Mom", "555-1234", "Mother");
+        assert!(sos.add_emergency_contact("Mom", "555-1234", "Mother").is_err());
+        sos.add_emergency_contact("Mom", "(202) 555-0143", "Mother").unwrap();
         
         let contacts = sos.get_emergency_contacts();
         assert_eq!(contacts.len(), 1);
         assert_eq!(contacts[0].name, "Mom");
+        assert_eq!(contacts[0].phone_number, "+12025550143");
     }
     
     #[test]
@@ -365,7 +422,7 @@ mod tests {
         
         let response = sos.process_emergency("drowning", "they are out of water but not breathing").await;
         
-        assert!(response.should_call_911);
+        assert!(response.should_call_emergency_services);
         assert!(response.instruction.contains("CPR"));
         assert!(response.context_flags.contains(&"not_breathing".to_string()));
     }
@@ -0,0 +1,499 @@
+//! Location Service
+//!
+//! Fixes come from the phone's location sensor (`location` samples) and from
+//! [`EmergencySystem::share_location`](crate::public::emergency_interface::EmergencySystem::share_location).
+//! The [`LocationManager`] keeps the best one. A fix's uncertainty is its
+//! reported accuracy plus the distance the user could have walked since it
+//! was taken ([`DRIFT_SPEED`]), so a precise fix gives way to a rougher but
+//! newer one as it ages.
+//!
+//! Every reported position is labelled with its accuracy and freshness:
+//!
+//! | Accuracy | Within | Freshness | Age |
+//! |----------|--------|-----------|-----|
+//! | `precise` | 20 m | `live` | 1 min |
+//! | `good` | 100 m | `recent` | 10 min |
+//! | `approximate` | 1 km | `stale` | older |
+//! | `rough` | beyond | | |
+//!
+//! The last fix within 100 m is cached by [`LastKnownStore`] so a restart
+//! still knows where the user was. While location sharing is on, the best fix
+//! is shared every `emergency.location_update_interval` seconds: twice as
+//! often while the user moves at driving speed and four times less often
+//! (at most every 5 minutes) while they stay put.
+//!
+//! When GPS drops out, a dead-reckoning [`Estimate`] from the best fix is
+//! kept alongside it for as long as it is tighter than the fix's own
+//! uncertainty, and shared again once it moves [`ESTIMATE_SHARE_DISTANCE`].
+
+use crate::config::EmergencyConfig;
+use crate::dead_reckoning::Estimate;
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Assumed walking pace at which an old fix loses accuracy (m/s)
+pub const DRIFT_SPEED: f64 = 1.5;
+
+/// Fixes no older than this are live
+pub const LIVE_AGE: Duration = Duration::from_secs(60);
+
+/// Fixes no older than this are recent; older ones are stale
+pub const RECENT_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// Fixes at least this accurate are cached as the last known good fix (m)
+pub const GOOD_ACCURACY_M: f64 = 100.0;
+
+/// Fixes less accurate than this are ignored (m)
+pub const MAX_ACCURACY_M: f64 = 10_000.0;
+
+/// Net speed from which the user counts as driving (m/s)
+pub const MOVING_SPEED: f64 = 5.0;
+
+/// Longest interval between shares
+pub const MAX_SHARE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How far an estimate must move before it is shared again (m)
+pub const ESTIMATE_SHARE_DISTANCE: f64 = 25.0;
+
+/// Mean Earth radius (m)
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// A position from the phone
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Fix {
+    /// Latitude in degrees
+    pub latitude: f64,
+    /// Longitude in degrees
+    pub longitude: f64,
+    /// Radius the true position is likely within (m)
+    pub accuracy_m: f64,
+    /// When the position was taken
+    pub time: chrono::DateTime<chrono::Utc>,
+}
+
+impl Fix {
+    /// A fix, or `None` if the coordinates or accuracy are out of range
+    pub fn new(latitude: f64, longitude: f64, accuracy_m: f64, time: chrono::DateTime<chrono::Utc>) -> Option<Self> {
+        let valid = (-90.0..=90.0).contains(&latitude)
+            && (-180.0..=180.0).contains(&longitude)
+            && accuracy_m > 0.0
+            && accuracy_m <= MAX_ACCURACY_M;
+        valid.then_some(Self { latitude, longitude, accuracy_m, time })
+    }
+
+    /// Time since the fix was taken; zero for fixes from the future
+    pub fn age(&self, now: chrono::DateTime<chrono::Utc>) -> Duration {
+        (now - self.time).to_std().unwrap_or_default()
+    }
+
+    /// Accuracy grown by how far the user could have walked since (m)
+    pub fn uncertainty_m(&self, now: chrono::DateTime<chrono::Utc>) -> f64 {
+        self.accuracy_m + self.age(now).as_secs_f64() * DRIFT_SPEED
+    }
+
+    /// Great-circle distance to another fix (m)
+    pub fn distance_m(&self, other: &Fix) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_M * a.sqrt().min(1.0).asin()
+    }
+
+    /// Latitude and longitude of the point `east_m` east and `north_m` north of the fix
+    pub fn offset_by(&self, east_m: f64, north_m: f64) -> (f64, f64) {
+        let latitude = self.latitude + (north_m / EARTH_RADIUS_M).to_degrees();
+        let longitude = self.longitude + (east_m / (EARTH_RADIUS_M * self.latitude.to_radians().cos())).to_degrees();
+        (latitude.clamp(-90.0, 90.0), (longitude + 180.0).rem_euclid(360.0) - 180.0)
+    }
+
+    /// The fix with its labels as of `now`
+    pub fn report(&self, now: chrono::DateTime<chrono::Utc>) -> LocationReport {
+        let age = self.age(now);
+        LocationReport {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            accuracy_m: self.accuracy_m,
+            time: self.time,
+            age_secs: age.as_secs(),
+            accuracy: Accuracy::for_meters(self.accuracy_m),
+            freshness: Freshness::for_age(age),
+            estimate: None,
+        }
+    }
+}
+
+/// How closely a fix pins the user down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Accuracy {
+    /// Within 20 m: the building or spot
+    Precise,
+    /// Within 100 m: the block
+    Good,
+    /// Within 1 km: the neighbourhood
+    Approximate,
+    /// Beyond 1 km: the town at best
+    Rough,
+}
+
+impl Accuracy {
+    /// Label for an accuracy radius (m)
+    pub fn for_meters(accuracy_m: f64) -> Self {
+        match accuracy_m {
+            m if m <= 20.0 => Accuracy::Precise,
+            m if m <= GOOD_ACCURACY_M => Accuracy::Good,
+            m if m <= 1000.0 => Accuracy::Approximate,
+            _ => Accuracy::Rough,
+        }
+    }
+}
+
+impl fmt::Display for Accuracy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Accuracy::Precise => "precise",
+            Accuracy::Good => "good",
+            Accuracy::Approximate => "approximate",
+            Accuracy::Rough => "rough",
+        })
+    }
+}
+
+/// How old a fix is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Freshness {
+    /// Up to a minute old
+    Live,
+    /// Up to 10 minutes old
+    Recent,
+    /// Older: where the user was, not where they are
+    Stale,
+}
+
+impl Freshness {
+    /// Label for the age of a fix
+    pub fn for_age(age: Duration) -> Self {
+        if age <= LIVE_AGE {
+            Freshness::Live
+        } else if age <= RECENT_AGE {
+            Freshness::Recent
+        } else {
+            Freshness::Stale
+        }
+    }
+}
+
+impl fmt::Display for Freshness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Freshness::Live => "live",
+            Freshness::Recent => "recent",
+            Freshness::Stale => "stale",
+        })
+    }
+}
+
+/// A position as shared with contacts and recorded in the timeline
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LocationReport {
+    /// Latitude in degrees
+    pub latitude: f64,
+    /// Longitude in degrees
+    pub longitude: f64,
+    /// Radius the true position is likely within (m)
+    pub accuracy_m: f64,
+    /// When the position was taken
+    pub time: chrono::DateTime<chrono::Utc>,
+    /// Age of the position when reported
+    pub age_secs: u64,
+    /// Accuracy label
+    pub accuracy: Accuracy,
+    /// Staleness label
+    pub freshness: Freshness,
+    /// Where the user has likely walked since, if GPS was lost
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<Estimate>,
+}
+
+impl LocationReport {
+    /// The fix the report was made from
+    pub fn fix(&self) -> Fix {
+        Fix { latitude: self.latitude, longitude: self.longitude, accuracy_m: self.accuracy_m, time: self.time }
+    }
+
+    /// Attach a dead-reckoning estimate, if it was made from this report's fix
+    pub fn with_estimate(mut self, estimate: Option<Estimate>) -> Self {
+        self.estimate = estimate.filter(|estimate| estimate.anchor == self.fix());
+        self
+    }
+
+    /// Both labels in English, e.g. "good, 45 m, recent (4 min old)", and
+    /// the estimate in UTC, e.g. "; last known at 14:02, estimated 300 m NE ±150 m"
+    pub fn label(&self) -> String {
+        let mut label = format!("{}, {:.0} m, {}", self.accuracy, self.accuracy_m, self.freshness);
+        if self.freshness != Freshness::Live {
+            label = format!("{} ({} old)", label, format_age(self.age_secs));
+        }
+        match &self.estimate {
+            Some(estimate) => format!("{}; {}", label, estimate.describe(&chrono::Utc)),
+            None => label,
+        }
+    }
+}
+
+/// Age in the largest whole unit: "45 s", "4 min", "2 h" or "3 d"
+pub fn format_age(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{} s", s),
+        s if s < 3600 => format!("{} min", s / 60),
+        s if s < 86_400 => format!("{} h", s / 3600),
+        s => format!("{} d", s / 86_400),
+    }
+}
+
+/// Keeps the best fix and paces sharing it
+#[derive(Debug, Clone)]
+pub struct LocationManager {
+    best: Option<Fix>,
+    shared: Option<Fix>,
+    /// Dead-reckoning estimate from the best fix
+    estimate: Option<Estimate>,
+    shared_estimate: Option<Estimate>,
+    /// Net speed between the last two shares (m/s)
+    speed: Option<f64>,
+    interval: Duration,
+}
+
+impl LocationManager {
+    /// Create a manager for the `[emergency]` settings, starting from the
+    /// cached last known fix if there is one
+    pub fn from_config(config: &EmergencyConfig, last_known: Option<Fix>) -> Self {
+        Self {
+            best: last_known,
+            shared: None,
+            estimate: None,
+            shared_estimate: None,
+            speed: None,
+            interval: Duration::from_secs(config.location_update_interval),
+        }
+    }
+
+    /// Replace the share interval
+    pub fn configure(&mut self, config: &EmergencyConfig) {
+        self.interval = Duration::from_secs(config.location_update_interval);
+    }
+
+    /// Offer a fix; returns whether it became the best one
+    ///
+    /// A fix replaces the best one if it is no older and its uncertainty at
+    /// `now` is no larger.
+    pub fn update(&mut self, fix: Fix, now: chrono::DateTime<chrono::Utc>) -> bool {
+        let better = match &self.best {
+            None => true,
+            Some(best) => fix.time >= best.time && fix.uncertainty_m(now) <= best.uncertainty_m(now),
+        };
+        if better {
+            self.best = Some(fix);
+            self.estimate = None;
+        }
+        better
+    }
+
+    /// Offer a dead-reckoning estimate; returns whether it is kept
+    ///
+    /// An estimate is kept if it was made from the best fix and its radius is
+    /// smaller than that fix's uncertainty at `now`; one that has grown
+    /// larger is dropped.
+    pub fn estimate(&mut self, estimate: Estimate, now: chrono::DateTime<chrono::Utc>) -> bool {
+        if self.best != Some(estimate.anchor) {
+            return false;
+        }
+        let tighter = estimate.radius_m < estimate.anchor.uncertainty_m(now);
+        self.estimate = tighter.then_some(estimate);
+        tighter
+    }
+
+    /// The best fix
+    pub fn best(&self) -> Option<Fix> {
+        self.best
+    }
+
+    /// The best fix with its labels as of `now`
+    pub fn report(&self, now: chrono::DateTime<chrono::Utc>) -> Option<LocationReport> {
+        self.best.map(|fix| fix.report(now).with_estimate(self.estimate))
+    }
+
+    /// The best fix to share, if it or its estimate changed since the last share
+    pub fn share(&mut self, now: chrono::DateTime<chrono::Utc>) -> Option<LocationReport> {
+        let best = self.best?;
+        let walked = match (&self.estimate, &self.shared_estimate) {
+            (Some(estimate), Some(shared)) => {
+                (estimate.east_m - shared.east_m).hypot(estimate.north_m - shared.north_m) >= ESTIMATE_SHARE_DISTANCE
+            }
+            (estimate, shared) => estimate.is_some() != shared.is_some(),
+        };
+        if self.shared == Some(best) {
+            if !walked {
+                self.speed = Some(0.0);
+                return None;
+            }
+            // On foot without GPS
+            self.speed = None;
+        } else if let Some(shared) = &self.shared {
+            // Movement within the fixes' accuracy is noise, not travel
+            let moved = (best.distance_m(shared) - best.accuracy_m - shared.accuracy_m).max(0.0);
+            let elapsed = (best.time - shared.time).to_std().unwrap_or_default().as_secs_f64();
+            self.speed = (elapsed > 0.0).then(|| moved / elapsed);
+        }
+        self.shared = Some(best);
+        self.shared_estimate = self.estimate;
+        self.report(now)
+    }
+
+    /// How long to wait before the next share
+    pub fn share_interval(&self) -> Duration {
+        match self.speed {
+            Some(speed) if speed >= MOVING_SPEED => self.interval / 2,
+            Some(0.0) => (self.interval * 4).min(MAX_SHARE_INTERVAL).max(self.interval),
+            _ => self.interval,
+        }
+    }
+
+    /// Forget what was shared, so the next share sends the best fix again
+    pub fn reset_sharing(&mut self) {
+        self.shared = None;
+        self.shared_estimate = None;
+        self.speed = None;
+    }
+}
+
+/// File holding the last known good fix
+#[derive(Debug, Clone)]
+pub struct LastKnownStore {
+    path: PathBuf,
+}
+
+impl LastKnownStore {
+    /// Create a store backed by the given file
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+
+    /// The cached fix, or `None` if nothing was cached yet
+    pub fn load(&self) -> AppResult<Option<Fix>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map(Some)
+                .map_err(|e| AppError::Database(format!("{}: {}", self.path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AppError::Database(format!("{}: {}", self.path.display(), e))),
+        }
+    }
+
+    /// Cache `fix` if it is accurate to [`GOOD_ACCURACY_M`]; returns whether it was saved
+    pub fn save(&self, fix: &Fix) -> AppResult<bool> {
+        if fix.accuracy_m > GOOD_ACCURACY_M {
+            return Ok(false);
+        }
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(fix)?)
+            .map_err(|e| AppError::Database(format!("{}: {}", self.path.display(), e)))?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    fn at(secs: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn fix(latitude: f64, accuracy_m: f64, secs: i64) -> Fix {
+        Fix::new(latitude, 8.525, accuracy_m, at(secs)).unwrap()
+    }
+
+    #[test]
+    fn test_best_fix_by_accuracy_and_age() {
+        let mut manager = LocationManager::from_config(&AppConfig::default().emergency, None);
+        assert!(manager.update(fix(47.3656, 8.0, 0), at(0)));
+
+        // A rough cell fix doesn't displace a fresh GPS fix...
+        assert!(!manager.update(fix(47.37, 400.0, 5), at(5)));
+        // ...nor does an older one, however precise
+        assert!(!manager.update(fix(47.36, 3.0, -10), at(5)));
+        // ...but wins once the GPS fix is old enough to be as uncertain
+        assert!(manager.update(fix(47.37, 400.0, 300), at(300)));
+        assert_eq!(manager.best().unwrap().accuracy_m, 400.0);
+
+        assert!(Fix::new(91.0, 0.0, 10.0, at(0)).is_none());
+        assert!(Fix::new(0.0, 0.0, 0.0, at(0)).is_none());
+
+        let report = fix(47.3656, 45.0, 0).report(at(250));
+        assert_eq!((report.accuracy, report.freshness), (Accuracy::Good, Freshness::Recent));
+        assert_eq!(report.label(), "good, 45 m, recent (4 min old)");
+        assert_eq!(fix(47.3656, 8.0, 0).report(at(30)).label(), "precise, 8 m, live");
+        assert_eq!(fix(47.3656, 8.0, 0).report(at(7200)).freshness, Freshness::Stale);
+    }
+
+    #[test]
+    fn test_adaptive_share_interval() {
+        let mut manager = LocationManager::from_config(&AppConfig::default().emergency, None);
+        assert_eq!(manager.share(at(0)), None);
+
+        manager.update(fix(47.3656, 10.0, 0), at(0));
+        assert!(manager.share(at(0)).is_some());
+        assert_eq!(manager.share_interval(), Duration::from_secs(30));
+
+        // About 450 m north in 30 s: driving
+        manager.update(fix(47.3697, 10.0, 30), at(30));
+        assert!(manager.share(at(30)).is_some());
+        assert_eq!(manager.share_interval(), Duration::from_secs(15));
+
+        // Nothing new: staying put
+        assert_eq!(manager.share(at(45)), None);
+        assert_eq!(manager.share_interval(), Duration::from_secs(120));
+
+        // Jitter within the accuracy is not movement
+        manager.update(fix(47.36975, 10.0, 200), at(200));
+        assert!(manager.share(at(200)).is_some());
+        assert_eq!(manager.share_interval(), Duration::from_secs(120));
+
+        // On foot without GPS: estimates are shared as they move on
+        let best = manager.best().unwrap();
+        let walked = |north_m: f64, radius_m: f64| {
+            let (latitude, longitude) = best.offset_by(0.0, north_m);
+            Estimate { anchor: best, latitude, longitude, radius_m, east_m: 0.0, north_m, steps: (north_m / 0.7) as u32 }
+        };
+        assert!(manager.estimate(walked(40.0, 25.0), at(260)));
+        let report = manager.share(at(260)).unwrap();
+        assert!(report.label().ends_with("; last known at 22:16, estimated 40 m N ±30 m"), "{}", report.label());
+        assert!(manager.estimate(walked(50.0, 30.0), at(270)));
+        assert_eq!(manager.share(at(270)), None);
+        // Once it is looser than the fix's own drift it is dropped
+        assert!(!manager.estimate(walked(60.0, 200.0), at(280)));
+        assert!(manager.report(at(280)).unwrap().estimate.is_none());
+    }
+
+    #[test]
+    fn test_last_known_store() {
+        let path = std::env::temp_dir().join(format!("sos-last-location-{}.json", uuid::Uuid::new_v4()));
+        let store = LastKnownStore::new(&path);
+        assert_eq!(store.load().unwrap(), None);
+
+        assert!(!store.save(&fix(47.37, 400.0, 0)).unwrap());
+        assert_eq!(store.load().unwrap(), None);
+        assert!(store.save(&fix(47.3656, 12.0, 0)).unwrap());
+        assert_eq!(store.load().unwrap(), Some(fix(47.3656, 12.0, 0)));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,8 +1,8 @@
 use tracing::{info, Level};
 use solana_sos::{
     app::SolanaSOSApp,
-    config::AppConfig,
     error::AppResult,
+    settings::{JsonUserSettings, LayeredConfig},
 };
 use clap::Parser;
 use tracing_subscriber;
@@ -24,25 +24,22 @@ struct Args {
 async fn main() -> AppResult<()> {
     let args = Args::parse();
 
-    // Initialize logging
-    if args.verbose {
-        tracing_subscriber::fmt()
-            .with_max_level(Level::DEBUG)
-            .init();
-    } else {
-        tracing_subscriber::fmt()
-            .with_max_level(Level::INFO)
-            .init();
-    }
+    // Load configuration: defaults < file < environment < user settings
+    let layers = LayeredConfig::load(&args.config)?;
+    let user_settings = JsonUserSettings::new(&layers.effective().database.user_settings_path);
+    let layers = layers.with_user_settings(Box::new(user_settings))?;
 
-    info!("🚨 Starting Solana SOS - Creating the phone you can't live without");
+    // Initialize logging at the configured level; --verbose forces debug
+    let level = if args.verbose { Level::DEBUG } else { layers.effective().app.tracing_level() };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .init();
 
-    // Load configuration
-    let _config = AppConfig::load(&args.config)?;
+    info!("🚨 Starting Solana SOS - Creating the phone you can't live without");
     info!("Configuration loaded successfully");
 
     // Create and initialize the app
-    let mut app = SolanaSOSApp::new().await?;
+    let mut app = SolanaSOSApp::with_layers(layers).await?;
     app.initialize().await?;
 
     if args.demo {
@@ -51,6 +48,11 @@ async fn main() -> AppResult<()> {
     } else {
         info!("Starting Solana SOS application");
         app.run().await?;
+
+        // Keep the subsystems running until interrupted
+        tokio::signal::ctrl_c().await?;
+        info!("Shutting down, draining in-flight work");
+        app.stop().await?;
     }
 
     info!("Solana SOS application completed successfully");
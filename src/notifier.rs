@@ -0,0 +1,627 @@
+//! Alert Delivery
+//!
+//! This module defines how alerts leave the app. A [`Notifier`] delivers one
+//! [`Alert`] to one address over one channel; the escalation engine decides
+//! who is alerted and when.
+//!
+//! Adapters deliver over each channel:
+//!
+//! - [`SmsNotifier`] splits texts into SMS segments and hands them to the
+//!   host's [`SmsGateway`], on Android an intent callback into `SmsManager`
+//! - [`PushNotifier`] posts alerts as JSON to an HTTP push endpoint
+//! - [`EmailNotifier`] sends mail through an SMTP relay
+//! - [`LocalNotifier`] delivers nothing: it logs and records each alert, and
+//!   can be told to fail for an address. It stands in for real delivery in
+//!   tests.
+//!
+//! Calls are placed by the runtime's call task through the host's emergency
+//! dialer.
+//!
+//! [`DeliveryRouter`] picks the adapter for each channel, retries failures
+//! with exponential backoff, drops alerts whose idempotency key was already
+//! delivered, gives a copy sent while the first is in flight the first one's
+//! outcome and reports a [`DeliveryReceipt`] for every outcome, including
+//! delivery reports that arrive later from the carrier. An alert on a channel
+//! without an adapter fails, so nothing is reported as sent that wasn't.
+
+use crate::contacts::NotificationChannel;
+use crate::error::{AppError, AppResult};
+use crate::secrets::Secret;
+use crate::templates::segment_sms;
+use futures::future::BoxFuture;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+/// How long an HTTP push or SMTP exchange may take
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// One message to one recipient over one channel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    /// Incident the alert belongs to
+    pub incident_id: String,
+    /// Recipient name
+    pub recipient: String,
+    /// Phone number (E.164) or email address, depending on the channel
+    pub address: String,
+    /// Delivery channel
+    pub channel: NotificationChannel,
+    /// Message text
+    pub message: String,
+}
+
+impl Alert {
+    /// Key identifying this alert across retries and repeated requests
+    ///
+    /// Alerts with the same incident, channel, address and text share a key,
+    /// so the same message is never delivered to a contact twice.
+    pub fn idempotency_key(&self) -> String {
+        let channel = format!("{:?}", self.channel);
+        let mut hasher = Sha256::new();
+        for part in [&self.incident_id, &channel, &self.address, &self.message] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hex::encode(&hasher.finalize()[..16])
+    }
+}
+
+/// Delivers alerts
+pub trait Notifier: Send + Sync {
+    /// Deliver an alert, failing if it could not be handed off
+    fn deliver<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, AppResult<()>>;
+}
+
+/// Where an alert got to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Handed off to the channel
+    Sent,
+    /// The carrier or service confirmed delivery
+    Delivered,
+    /// Every attempt failed, or the carrier reported a failure
+    Failed,
+}
+
+/// Outcome of delivering one alert, recorded in the incident timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryReceipt {
+    /// When the outcome was known
+    pub at: chrono::DateTime<chrono::Utc>,
+    /// Incident the alert belongs to
+    pub incident_id: String,
+    /// Idempotency key of the alert
+    pub idempotency_key: String,
+    /// Recipient name
+    pub recipient: String,
+    /// Address the alert was sent to
+    pub address: String,
+    /// Delivery channel
+    pub channel: NotificationChannel,
+    /// Where the alert got to
+    pub status: DeliveryStatus,
+    /// Delivery attempts made
+    pub attempts: u32,
+    /// Last error, if delivery failed
+    pub error: Option<String>,
+}
+
+/// How often and how patiently a channel is retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts before giving up, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles for each further retry
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Default policy for a channel
+    ///
+    /// Backoffs stay short because escalation waits for each delivery. Calls
+    /// are not retried here; the call task redials as its call policy allows.
+    pub fn for_channel(channel: NotificationChannel) -> Self {
+        let (max_attempts, initial_backoff) = match channel {
+            NotificationChannel::Sms => (3, Duration::from_secs(1)),
+            NotificationChannel::Push => (4, Duration::from_millis(500)),
+            NotificationChannel::Email => (3, Duration::from_secs(2)),
+            NotificationChannel::Call => (1, Duration::ZERO),
+        };
+        Self { max_attempts, initial_backoff, max_backoff: Duration::from_secs(8) }
+    }
+
+    /// Delay after failed attempt number `attempt` (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// An alert the router has taken on
+struct Accepted {
+    alert: Alert,
+    attempts: u32,
+    /// Outcome of the first delivery, `None` while it is in flight
+    outcome: watch::Receiver<Option<Result<(), String>>>,
+}
+
+/// Routes alerts to per-channel adapters with retries, deduplication and receipts
+#[derive(Default)]
+pub struct DeliveryRouter {
+    routes: HashMap<NotificationChannel, (Arc<dyn Notifier>, RetryPolicy)>,
+    accepted: Mutex<HashMap<String, Accepted>>,
+    receipts: Option<mpsc::UnboundedSender<DeliveryReceipt>>,
+}
+
+impl DeliveryRouter {
+    /// Create a router without routes; every alert fails until a channel is routed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deliver `channel` through `notifier` with the channel's default retry policy
+    pub fn route(self, channel: NotificationChannel, notifier: Arc<dyn Notifier>) -> Self {
+        self.route_with(channel, notifier, RetryPolicy::for_channel(channel))
+    }
+
+    /// Deliver `channel` through `notifier`, retrying as `policy` allows
+    pub fn route_with(mut self, channel: NotificationChannel, notifier: Arc<dyn Notifier>, policy: RetryPolicy) -> Self {
+        self.routes.insert(channel, (notifier, policy));
+        self
+    }
+
+    /// Report a receipt on `receipts` for every delivery outcome
+    pub fn with_receipts(mut self, receipts: mpsc::UnboundedSender<DeliveryReceipt>) -> Self {
+        self.receipts = Some(receipts);
+        self
+    }
+
+    /// Record a delivery report from the carrier or service
+    ///
+    /// A failure report forgets the key, so the alert may be sent again.
+    /// Reports for unknown keys are ignored.
+    pub fn confirm(&self, idempotency_key: &str, delivered: bool, error: Option<String>) {
+        let mut accepted = self.accepted.lock().unwrap_or_else(|e| e.into_inner());
+        let receipt = match accepted.get(idempotency_key) {
+            Some(entry) => {
+                let status = if delivered { DeliveryStatus::Delivered } else { DeliveryStatus::Failed };
+                receipt(&entry.alert, idempotency_key, entry.attempts, status, error)
+            }
+            None => {
+                tracing::debug!("Delivery report for unknown alert {}", idempotency_key);
+                return;
+            }
+        };
+        if !delivered {
+            accepted.remove(idempotency_key);
+        }
+        drop(accepted);
+        self.report(receipt);
+    }
+
+    /// Forget the alerts of an incident that is over
+    ///
+    /// Delivery reports that arrive for them later are ignored.
+    pub fn forget_incident(&self, incident_id: &str) {
+        self.accepted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, entry| entry.alert.incident_id != incident_id);
+    }
+
+    fn report(&self, receipt: DeliveryReceipt) {
+        if let Some(receipts) = &self.receipts {
+            let _ = receipts.send(receipt);
+        }
+    }
+
+    async fn deliver_once(&self, alert: &Alert) -> AppResult<()> {
+        let key = alert.idempotency_key();
+        let Some((notifier, policy)) = self.routes.get(&alert.channel).cloned() else {
+            let error = format!("no {:?} delivery is configured", alert.channel);
+            self.report(receipt(alert, &key, 0, DeliveryStatus::Failed, Some(error.clone())));
+            return Err(AppError::Config(error));
+        };
+        let outcome = loop {
+            let mut in_flight = {
+                let mut accepted = self.accepted.lock().unwrap_or_else(|e| e.into_inner());
+                match accepted.get(&key) {
+                    Some(entry) => entry.outcome.clone(),
+                    None => {
+                        let (outcome, receiver) = watch::channel(None);
+                        accepted.insert(key.clone(), Accepted { alert: alert.clone(), attempts: 0, outcome: receiver });
+                        break outcome;
+                    }
+                }
+            };
+            // Already delivered or in flight; a second copy would only alarm the
+            // contact, so report how the first one went
+            tracing::debug!("Alert {} to {} is a duplicate; waiting for the first", key, alert.recipient);
+            let first = in_flight.wait_for(Option::is_some).await.map(|first| first.clone());
+            match first {
+                Ok(first) => return first.unwrap_or(Ok(())).map_err(AppError::Network),
+                // The first delivery was abandoned; take it over
+                Err(_) => {
+                    let mut accepted = self.accepted.lock().unwrap_or_else(|e| e.into_inner());
+                    let abandoned = |entry: &Accepted| entry.outcome.borrow().is_none() && entry.outcome.has_changed().is_err();
+                    if accepted.get(&key).is_some_and(abandoned) {
+                        accepted.remove(&key);
+                    }
+                }
+            }
+        };
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = notifier.deliver(alert).await;
+            let mut accepted = self.accepted.lock().unwrap_or_else(|e| e.into_inner());
+            match result {
+                Ok(()) => {
+                    if let Some(entry) = accepted.get_mut(&key) {
+                        entry.attempts = attempt;
+                    }
+                    drop(accepted);
+                    outcome.send_replace(Some(Ok(())));
+                    self.report(receipt(alert, &key, attempt, DeliveryStatus::Sent, None));
+                    return Ok(());
+                }
+                Err(e) if attempt < policy.max_attempts => {
+                    drop(accepted);
+                    let delay = policy.backoff(attempt);
+                    tracing::warn!("{:?} alert to {} failed ({}), retrying in {:?}", alert.channel, alert.recipient, e, delay);
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    accepted.remove(&key);
+                    drop(accepted);
+                    outcome.send_replace(Some(Err(e.to_string())));
+                    self.report(receipt(alert, &key, attempt, DeliveryStatus::Failed, Some(e.to_string())));
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+impl Notifier for DeliveryRouter {
+    fn deliver<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, AppResult<()>> {
+        Box::pin(self.deliver_once(alert))
+    }
+}
+
+fn receipt(alert: &Alert, key: &str, attempts: u32, status: DeliveryStatus, error: Option<String>) -> DeliveryReceipt {
+    DeliveryReceipt {
+        at: chrono::Utc::now(),
+        incident_id: alert.incident_id.clone(),
+        idempotency_key: key.to_string(),
+        recipient: alert.recipient.clone(),
+        address: alert.address.clone(),
+        channel: alert.channel,
+        status,
+        attempts,
+        error,
+    }
+}
+
+/// Host SMS sending, e.g. an Android intent callback into `SmsManager`
+///
+/// The host reports the carrier's delivery result later through
+/// [`DeliveryRouter::confirm`] with the same idempotency key.
+pub trait SmsGateway: Send + Sync {
+    /// Start sending `parts` to `number` as one concatenated SMS, failing if
+    /// the host could not queue it
+    fn send_sms(&self, number: &str, parts: &[String], idempotency_key: &str) -> AppResult<()>;
+}
+
+/// Delivers SMS alerts through the host's [`SmsGateway`]
+pub struct SmsNotifier {
+    gateway: Arc<dyn SmsGateway>,
+}
+
+impl SmsNotifier {
+    /// Send texts through `gateway`
+    pub fn new(gateway: Arc<dyn SmsGateway>) -> Self {
+        Self { gateway }
+    }
+}
+
+impl Notifier for SmsNotifier {
+    fn deliver<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, AppResult<()>> {
+        Box::pin(async move {
+            let sms = segment_sms(&alert.message);
+            self.gateway.send_sms(&alert.address, &sms.segments, &alert.idempotency_key())
+        })
+    }
+}
+
+/// Delivers alerts by posting them as JSON to an HTTP push endpoint
+///
+/// The body carries the alert and its `idempotency_key`, which is also sent
+/// as the `Idempotency-Key` header so the service can drop retried posts.
+pub struct PushNotifier {
+    endpoint: String,
+    token: Option<Secret>,
+    agent: ureq::Agent,
+}
+
+impl PushNotifier {
+    /// Post alerts to `endpoint`, authenticating with an optional bearer `token`
+    pub fn new(endpoint: &str, token: Option<Secret>) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            token,
+            agent: ureq::AgentBuilder::new().timeout(NETWORK_TIMEOUT).build(),
+        }
+    }
+}
+
+impl Notifier for PushNotifier {
+    fn deliver<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, AppResult<()>> {
+        Box::pin(async move {
+            let key = alert.idempotency_key();
+            let mut request = self.agent.post(&self.endpoint).set("Idempotency-Key", &key);
+            if let Some(token) = &self.token {
+                request = request.set("Authorization", &format!("Bearer {}", token.expose_str()?));
+            }
+            let mut body = serde_json::to_value(alert)?;
+            body["idempotency_key"] = serde_json::Value::String(key);
+
+            tokio::task::spawn_blocking(move || request.send_json(body).map(|_| ()).map_err(|e| e.to_string()))
+                .await
+                .map_err(|e| AppError::Internal(format!("push task failed: {}", e)))?
+                .map_err(|e| AppError::Network(format!("push to {} failed: {}", alert.recipient, e)))
+        })
+    }
+}
+
+/// Delivers email alerts through an SMTP relay with STARTTLS
+///
+/// The idempotency key becomes the `Message-ID`, so mail servers and clients
+/// can drop copies of a retried message.
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl EmailNotifier {
+    /// Send from `from` through `host:port`, logging in if `username` is given
+    pub fn new(host: &str, port: u16, username: Option<&str>, password: Option<&Secret>, from: &str) -> AppResult<Self> {
+        let from = from
+            .parse()
+            .map_err(|e| AppError::Config(format!("invalid sender address \"{}\": {}", from, e)))?;
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .map_err(|e| AppError::Config(format!("invalid SMTP host \"{}\": {}", host, e)))?
+            .port(port)
+            .timeout(Some(NETWORK_TIMEOUT));
+        if let Some(username) = username {
+            let password = password.map(Secret::expose_str).transpose()?.unwrap_or_default();
+            transport = transport.credentials(Credentials::new(username.to_string(), password.to_string()));
+        }
+        Ok(Self { transport: transport.build(), from })
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn deliver<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, AppResult<()>> {
+        Box::pin(async move {
+            let to: Mailbox = format!("{} <{}>", alert.recipient, alert.address)
+                .parse()
+                .or_else(|_| alert.address.parse())
+                .map_err(|e| AppError::InvalidInput(format!("invalid email address \"{}\": {}", alert.address, e)))?;
+            let message = Message::builder()
+                .from(self.from.clone())
+                .to(to)
+                .subject("Emergency alert")
+                .message_id(Some(format!("<{}@solana-sos>", alert.idempotency_key())))
+                .body(alert.message.clone())
+                .map_err(|e| AppError::InvalidInput(format!("invalid email: {}", e)))?;
+            self.transport
+                .send(message)
+                .await
+                .map(|_| ())
+                .map_err(|e| AppError::Network(format!("email to {} failed: {}", alert.address, e)))
+        })
+    }
+}
+
+/// Notifier that records alerts instead of sending them
+#[derive(Debug, Default)]
+pub struct LocalNotifier {
+    sent: Mutex<Vec<Alert>>,
+    failing: Mutex<HashSet<String>>,
+    flaky: Mutex<HashMap<String, u32>>,
+}
+
+impl LocalNotifier {
+    /// Create a notifier that accepts every alert
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make delivery to `address` fail
+    pub fn fail_address(&self, address: &str) {
+        self.failing.lock().unwrap_or_else(|e| e.into_inner()).insert(address.to_string());
+    }
+
+    /// Make the next `times` deliveries to `address` fail
+    pub fn fail_times(&self, address: &str, times: u32) {
+        self.flaky.lock().unwrap_or_else(|e| e.into_inner()).insert(address.to_string(), times);
+    }
+
+    /// Get every alert accepted so far
+    pub fn sent(&self) -> Vec<Alert> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn should_fail(&self, address: &str) -> bool {
+        if self.failing.lock().unwrap_or_else(|e| e.into_inner()).contains(address) {
+            return true;
+        }
+        match self.flaky.lock().unwrap_or_else(|e| e.into_inner()).get_mut(address) {
+            Some(remaining) if *remaining > 0 => {
+                *remaining -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Notifier for LocalNotifier {
+    fn deliver<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, AppResult<()>> {
+        Box::pin(async move {
+            if self.should_fail(&alert.address) {
+                return Err(AppError::Network(format!("{:?} to {} failed", alert.channel, alert.address)));
+            }
+            tracing::info!("Alert to {} via {:?}: {}", alert.recipient, alert.channel, alert.message);
+            self.sent.lock().unwrap_or_else(|e| e.into_inner()).push(alert.clone());
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(address: &str, channel: NotificationChannel) -> Alert {
+        Alert {
+            incident_id: "incident-1".to_string(),
+            recipient: "Mom".to_string(),
+            address: address.to_string(),
+            channel,
+            message: "Emergency: Alex needs help".to_string(),
+        }
+    }
+
+    fn quick_retries() -> RetryPolicy {
+        RetryPolicy { max_attempts: 3, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(4) }
+    }
+
+    #[tokio::test]
+    async fn test_retries_with_backoff_and_reports_receipts() {
+        let sms = Arc::new(LocalNotifier::new());
+        sms.fail_times("+12025550143", 2);
+        sms.fail_address("+12025550199");
+        let (receipts, mut receipts_rx) = mpsc::unbounded_channel();
+        let router = DeliveryRouter::new()
+            .route_with(NotificationChannel::Sms, sms.clone(), quick_retries())
+            .with_receipts(receipts);
+
+        let flaky = alert("+12025550143", NotificationChannel::Sms);
+        router.deliver(&flaky).await.unwrap();
+        let sent = receipts_rx.recv().await.unwrap();
+        assert_eq!((sent.status, sent.attempts), (DeliveryStatus::Sent, 3));
+
+        assert!(router.deliver(&alert("+12025550199", NotificationChannel::Sms)).await.is_err());
+        let failed = receipts_rx.recv().await.unwrap();
+        assert_eq!((failed.status, failed.attempts), (DeliveryStatus::Failed, 3));
+        assert!(failed.error.is_some());
+
+        router.confirm(&flaky.idempotency_key(), true, None);
+        let delivered = receipts_rx.recv().await.unwrap();
+        assert_eq!((delivered.status, delivered.recipient.as_str()), (DeliveryStatus::Delivered, "Mom"));
+        assert_eq!(sms.sent().len(), 1);
+
+        assert_eq!(quick_retries().backoff(1), Duration::from_millis(1));
+        assert_eq!(quick_retries().backoff(3), Duration::from_millis(4));
+        assert_eq!(quick_retries().backoff(40), Duration::from_millis(4));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_suppresses_duplicates() {
+        let local = Arc::new(LocalNotifier::new());
+        let router = DeliveryRouter::new()
+            .route(NotificationChannel::Sms, local.clone())
+            .route(NotificationChannel::Push, local.clone());
+
+        let first = alert("+12025550143", NotificationChannel::Sms);
+        for _ in 0..10 {
+            router.deliver(&first).await.unwrap();
+        }
+        assert_eq!(local.sent().len(), 1);
+
+        // A different channel or text is a different alert
+        router.deliver(&alert("+12025550143", NotificationChannel::Push)).await.unwrap();
+        let update = Alert { message: "Location update".to_string(), ..first.clone() };
+        assert_ne!(update.idempotency_key(), first.idempotency_key());
+        router.deliver(&update).await.unwrap();
+        assert_eq!(local.sent().len(), 3);
+
+        // A carrier failure report allows the alert to be sent again
+        router.confirm(&first.idempotency_key(), false, Some("no service".to_string()));
+        router.deliver(&first).await.unwrap();
+        assert_eq!(local.sent().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_unrouted_channel_fails() {
+        let (receipts, mut receipts_rx) = mpsc::unbounded_channel();
+        let router = DeliveryRouter::new()
+            .route(NotificationChannel::Sms, Arc::new(LocalNotifier::new()))
+            .with_receipts(receipts);
+
+        let call = alert("+12025550143", NotificationChannel::Call);
+        assert!(matches!(router.deliver(&call).await, Err(AppError::Config(_))));
+        let failed = receipts_rx.recv().await.unwrap();
+        assert_eq!((failed.status, failed.attempts, failed.channel), (DeliveryStatus::Failed, 0, NotificationChannel::Call));
+        assert!(failed.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_gets_outcome_of_delivery_in_flight() {
+        let sms = Arc::new(LocalNotifier::new());
+        sms.fail_address("+12025550199");
+        sms.fail_times("+12025550143", 2);
+        let (receipts, mut receipts_rx) = mpsc::unbounded_channel();
+        let router = DeliveryRouter::new()
+            .route_with(NotificationChannel::Sms, sms.clone(), quick_retries())
+            .with_receipts(receipts);
+
+        let failing = alert("+12025550199", NotificationChannel::Sms);
+        let (first, second) = tokio::join!(router.deliver(&failing), router.deliver(&failing));
+        assert!(first.is_err() && second.is_err());
+
+        let flaky = alert("+12025550143", NotificationChannel::Sms);
+        let (first, second) = tokio::join!(router.deliver(&flaky), router.deliver(&flaky));
+        assert!(first.is_ok() && second.is_ok());
+        assert_eq!(sms.sent().len(), 1);
+
+        drop(router);
+        let statuses: Vec<DeliveryStatus> = std::iter::from_fn(|| receipts_rx.try_recv().ok()).map(|receipt| receipt.status).collect();
+        assert_eq!(statuses, vec![DeliveryStatus::Failed, DeliveryStatus::Sent]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_and_finished_incidents_can_be_sent_again() {
+        let sms = Arc::new(LocalNotifier::new());
+        sms.fail_times("+12025550143", 1);
+        let slow = RetryPolicy { max_attempts: 2, initial_backoff: Duration::from_secs(3600), max_backoff: Duration::from_secs(3600) };
+        let router = DeliveryRouter::new().route_with(NotificationChannel::Sms, sms.clone(), slow);
+
+        // A delivery dropped while waiting to retry is taken over by the next copy
+        let first = alert("+12025550143", NotificationChannel::Sms);
+        assert!(tokio::time::timeout(Duration::from_millis(20), router.deliver(&first)).await.is_err());
+        router.deliver(&first).await.unwrap();
+        router.deliver(&first).await.unwrap();
+        assert_eq!(sms.sent().len(), 1);
+
+        router.forget_incident("incident-2");
+        router.deliver(&first).await.unwrap();
+        assert_eq!(sms.sent().len(), 1);
+        router.forget_incident("incident-1");
+        router.deliver(&first).await.unwrap();
+        assert_eq!(sms.sent().len(), 2);
+    }
+}
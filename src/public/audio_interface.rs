@@ -8,18 +8,8 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-/// Audio processing configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AudioConfig {
-    /// Sample rate for audio processing
-    pub sample_rate: u32,
-    /// Audio buffer size
-    pub buffer_size: usize,
-    /// Enable noise filtering
-    pub enable_noise_filtering: bool,
-    /// Enable audio enhancement
-    pub enable_enhancement: bool,
-}
+// AudioConfig is defined with the rest of the settings in crate::config
+pub use crate::config::AudioConfig;
 
 /// Audio processing statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,12 +34,7 @@ pub struct AudioProcessor {
 impl AudioProcessor {
     /// Create a new audio processor
     pub fn new(cache_dir: &str) -> Self {
-        let config = AudioConfig {
-            sample_rate: 16000,
-            buffer_size: 4096,
-            enable_noise_filtering: true,
-            enable_enhancement: true,
-        };
+        let config = AudioConfig::default();
 
         let stats = Arc::new(RwLock::new(AudioStats {
             total_samples_processed: 0,
@@ -3,8 +3,15 @@
 //! This module provides the public interface for emergency response functionality.
 //! Implementation details are hidden to protect proprietary algorithms.
 
-use crate::error::AppResult;
-use crate::public::types::EmergencyType;
+use crate::dialer::{CallReports, EmergencyDialer};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::{AppEvent, DetectionSource, EventBus};
+use crate::public::types::{EmergencyStage, EmergencyType};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+// EmergencyConfig is defined with the rest of the settings in crate::config
+pub use crate::config::EmergencyConfig;
 
 /// Emergency response system
 pub struct EmergencySystem {
@@ -14,18 +21,43 @@ pub struct EmergencySystem {
     pub current_emergency: Option<EmergencyType>,
     /// Emergency response status
     pub response_status: EmergencyStatus,
+    /// Bus that other subsystems observe
+    event_bus: EventBus,
+    /// Host voice calling and where it reports call states
+    dialer: Option<(Arc<dyn EmergencyDialer>, CallReports)>,
+    /// Call attempts of the current response
+    calls: Vec<EmergencyCallData>,
 }
 
 impl EmergencySystem {
     /// Creates a new emergency system instance
     pub fn new() -> Self {
+        Self::with_event_bus(EventBus::new())
+    }
+
+    /// Creates a new emergency system that publishes to the given bus
+    pub fn with_event_bus(event_bus: EventBus) -> Self {
         Self {
             is_active: false,
             current_emergency: None,
             response_status: EmergencyStatus::Idle,
+            event_bus,
+            dialer: None,
+            calls: Vec::new(),
         }
     }
 
+    /// Places calls through `dialer`, which reports call states to `reports`
+    pub fn with_dialer(mut self, dialer: Arc<dyn EmergencyDialer>, reports: CallReports) -> Self {
+        self.dialer = Some((dialer, reports));
+        self
+    }
+
+    /// Gets the bus this system publishes to
+    pub fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
     /// Initiates emergency response
     /// 
     /// # Arguments
@@ -34,49 +66,85 @@ impl EmergencySystem {
     /// # Returns
     /// * `AppResult<()>` - Success or error
     pub fn initiate_emergency_response(&mut self, emergency_type: EmergencyType) -> AppResult<()> {
+        self.initiate_emergency_response_from(emergency_type, DetectionSource::Manual)
+    }
+
+    /// Initiates emergency response, recording what triggered it
+    pub fn initiate_emergency_response_from(&mut self, emergency_type: EmergencyType, source: DetectionSource) -> AppResult<()> {
         // Implementation details hidden - proprietary emergency response logic
-        self.current_emergency = Some(emergency_type);
+        self.current_emergency = Some(emergency_type.clone());
         self.response_status = EmergencyStatus::Active;
+
+        self.event_bus.publish(AppEvent::EmergencyDetected { emergency_type, source });
+        self.event_bus.publish(AppEvent::StageChanged { stage: EmergencyStage::InitialDetection });
         Ok(())
     }
 
-    /// Makes emergency call to 911
+    /// Makes emergency call to emergency services
+    /// 
+    /// Places a single call through the dialer; its states are reported to
+    /// the dialer's `CallReports` under the returned call ID. Use
+    /// `dialer::EmergencyCall` to retry until a call connects.
     /// 
     /// # Arguments
+    /// * `number` - Local emergency number, e.g. "112"
     /// * `location` - Current location for emergency services
     /// 
     /// # Returns
-    /// * `AppResult<()>` - Success or error
-    pub fn call_911(&self, _location: &str) -> AppResult<()> {
-        // Implementation details hidden - proprietary emergency calling logic
-        Ok(())
+    /// * `AppResult<String>` - Call ID, or an error if there is no dialer or the call could not be placed
+    pub fn call_emergency_services(&self, number: &str, _location: &str) -> AppResult<String> {
+        let (dialer, reports) = self.dialer.as_ref()
+            .ok_or_else(|| AppError::Internal("no emergency dialer".to_string()))?;
+        let call_id = uuid::Uuid::new_v4().to_string();
+        self.event_bus.publish(AppEvent::CallPlaced { number: number.to_string() });
+        dialer.dial(number, &call_id, reports)?;
+        Ok(call_id)
     }
 
     /// Shares location with emergency services
     /// 
+    /// The position goes to the location service, which shares the best
+    /// fix with alerted contacts while `enable_location_sharing` is on.
+    /// 
     /// # Arguments
     /// * `latitude` - Latitude coordinate
     /// * `longitude` - Longitude coordinate
+    /// * `accuracy_m` - Accuracy radius in meters
     /// 
     /// # Returns
     /// * `AppResult<()>` - Success or error
-    pub fn share_location(&self, _latitude: f64, _longitude: f64) -> AppResult<()> {
+    pub fn share_location(&self, latitude: f64, longitude: f64, accuracy_m: f64) -> AppResult<()> {
         // Implementation details hidden - proprietary location sharing logic
+        self.event_bus.publish(AppEvent::LocationUpdated { latitude, longitude, accuracy_m });
         Ok(())
     }
 
     /// Records emergency call data
     /// 
+    /// The attempt is kept until the response ends. A successful call marks
+    /// the response `ServicesContacted`.
+    /// 
     /// # Arguments
     /// * `call_data` - Emergency call information
     /// 
     /// # Returns
-    /// * `AppResult<()>` - Success or error
-    pub fn record_emergency_call(&self, _call_data: EmergencyCallData) -> AppResult<()> {
-        // Implementation details hidden - proprietary recording logic
+    /// * `AppResult<()>` - Success, or an error if no response is active
+    pub fn record_emergency_call(&mut self, call_data: EmergencyCallData) -> AppResult<()> {
+        if self.current_emergency.is_none() {
+            return Err(AppError::InvalidInput("no emergency response is active".to_string()));
+        }
+        if call_data.outcome == CallOutcome::Successful {
+            self.response_status = EmergencyStatus::ServicesContacted;
+        }
+        self.calls.push(call_data);
         Ok(())
     }
 
+    /// Gets the call attempts recorded for the current response
+    pub fn emergency_calls(&self) -> &[EmergencyCallData] {
+        &self.calls
+    }
+
     /// Gets emergency instructions for the current emergency
     /// 
     /// # Returns
@@ -85,7 +153,7 @@ impl EmergencySystem {
         // Implementation details hidden - proprietary instruction generation
         Ok(vec![
             "Stay calm".to_string(),
-            "Call 911".to_string(),
+            "Call emergency services".to_string(),
             "Follow instructions".to_string(),
         ])
     }
@@ -95,6 +163,8 @@ impl EmergencySystem {
         // Implementation details hidden
         self.current_emergency = None;
         self.response_status = EmergencyStatus::Idle;
+        self.calls.clear();
+        self.event_bus.publish(AppEvent::EmergencyEnded);
         Ok(())
     }
 
@@ -119,59 +189,46 @@ pub enum EmergencyStatus {
     Failed,
 }
 
-/// Emergency call data structure
+/// Emergency call data structure, one per call attempt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EmergencyCallData {
-    /// Emergency type
-    pub emergency_type: EmergencyType,
+    /// Incident the call belongs to
+    pub incident_id: String,
+    /// Number dialed
+    pub number: String,
+    /// Attempt number, from 1
+    pub attempt: u32,
+    /// Emergency type, if known
+    pub emergency_type: Option<EmergencyType>,
     /// Call timestamp
     pub timestamp: chrono::DateTime<chrono::Utc>,
     /// Location coordinates
     pub location: Option<(f64, f64)>,
-    /// Call duration
+    /// Time from dialing until the call ended or failed
     pub duration: std::time::Duration,
     /// Call outcome
     pub outcome: CallOutcome,
+    /// Why the call failed, if it did
+    pub error: Option<String>,
 }
 
 /// Emergency call outcome
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CallOutcome {
-    /// Call successful
+    /// Call connected and ended normally
     Successful,
-    /// Call failed
+    /// Call could not be placed, went unanswered or dropped
     Failed,
     /// Call in progress
     InProgress,
 }
 
-/// Emergency response configuration
-pub struct EmergencyConfig {
-    /// Whether automatic 911 calling is enabled
-    pub auto_911_enabled: bool,
-    /// Whether location sharing is enabled
-    pub location_sharing_enabled: bool,
-    /// Emergency contact list
-    pub emergency_contacts: Vec<String>,
-    /// Response timeout
-    pub response_timeout: std::time::Duration,
-}
-
-impl Default for EmergencyConfig {
-    fn default() -> Self {
-        Self {
-            auto_911_enabled: true,
-            location_sharing_enabled: true,
-            emergency_contacts: vec![],
-            response_timeout: std::time::Duration::from_secs(30),
-        }
-    }
-}
-
 /// Emergency response statistics
 pub struct EmergencyStats {
     /// Number of emergency responses initiated
     pub responses_initiated: u32,
-    /// Number of 911 calls made
+    /// Number of emergency services calls made
     pub calls_made: u32,
     /// Average response time
     pub avg_response_time: std::time::Duration,
@@ -188,4 +245,39 @@ impl Default for EmergencyStats {
             success_rate: 0.0,
         }
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(attempt: u32, outcome: CallOutcome) -> EmergencyCallData {
+        EmergencyCallData {
+            incident_id: "incident-1".to_string(),
+            number: "112".to_string(),
+            attempt,
+            emergency_type: Some(EmergencyType::HeartAttack),
+            timestamp: chrono::Utc::now(),
+            location: None,
+            duration: std::time::Duration::from_secs(5),
+            outcome,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_records_calls_of_active_response() {
+        let mut system = EmergencySystem::new();
+        assert!(system.record_emergency_call(attempt(1, CallOutcome::Successful)).is_err());
+
+        system.initiate_emergency_response(EmergencyType::HeartAttack).unwrap();
+        system.record_emergency_call(attempt(1, CallOutcome::Failed)).unwrap();
+        assert!(matches!(system.get_status(), EmergencyStatus::Active));
+        system.record_emergency_call(attempt(2, CallOutcome::Successful)).unwrap();
+        assert!(matches!(system.get_status(), EmergencyStatus::ServicesContacted));
+        assert_eq!(system.emergency_calls().iter().map(|call| call.attempt).collect::<Vec<_>>(), vec![1, 2]);
+
+        system.end_emergency_response().unwrap();
+        assert!(system.emergency_calls().is_empty());
+    }
+}
@@ -2,8 +2,11 @@
 //! 
 //! This module provides common types and enums used throughout the application.
 
+use serde::{Deserialize, Serialize};
+
 /// Emergency types supported by Solana SOS
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum EmergencyType {
     /// Drowning emergency
     Drowning,
@@ -50,6 +53,45 @@ impl EmergencyType {
         }
     }
 
+    /// Gets the protocol key for the emergency type, e.g. "heart_attack"
+    pub fn key(&self) -> &'static str {
+        match self {
+            EmergencyType::Drowning => "drowning",
+            EmergencyType::HeartAttack => "heart_attack",
+            EmergencyType::Stroke => "stroke",
+            EmergencyType::Choking => "choking",
+            EmergencyType::Bleeding => "bleeding",
+            EmergencyType::Unconscious => "unconscious",
+            EmergencyType::Seizure => "seizure",
+            EmergencyType::Poisoning => "poisoning",
+            EmergencyType::SevereBurns => "burn",
+            EmergencyType::DiabeticEmergency => "diabetic",
+            EmergencyType::AllergicReaction => "allergic",
+            EmergencyType::Trauma => "trauma",
+        }
+    }
+
+    /// Parses a protocol key or spoken phrase, e.g. "heart attack" or "heart_attack"
+    pub fn from_key(key: &str) -> Option<Self> {
+        let normalized = key.trim().to_lowercase().replace([' ', '-'], "_");
+        let emergency_type = match normalized.as_str() {
+            "drowning" => EmergencyType::Drowning,
+            "heart_attack" | "cardiac_arrest" | "chest_pain" => EmergencyType::HeartAttack,
+            "stroke" => EmergencyType::Stroke,
+            "choking" => EmergencyType::Choking,
+            "bleeding" => EmergencyType::Bleeding,
+            "unconscious" => EmergencyType::Unconscious,
+            "seizure" => EmergencyType::Seizure,
+            "poisoning" | "overdose" => EmergencyType::Poisoning,
+            "burn" | "severe_burns" => EmergencyType::SevereBurns,
+            "diabetic" | "diabetic_emergency" => EmergencyType::DiabeticEmergency,
+            "allergic" | "allergic_reaction" => EmergencyType::AllergicReaction,
+            "trauma" => EmergencyType::Trauma,
+            _ => return None,
+        };
+        Some(emergency_type)
+    }
+
     /// Gets the description for the emergency type
     pub fn description(&self) -> &'static str {
         match self {
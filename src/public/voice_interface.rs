@@ -3,7 +3,7 @@
 //! This module provides the public interface for voice recognition functionality.
 //! Implementation details are hidden to protect proprietary algorithms.
 
-use crate::error::AppResult;
+use crate::error::{AppError, AppResult};
 use crate::config::VoiceConfig;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -70,9 +70,16 @@ pub enum Emotion {
     Confused,
 }
 
+/// Speaks to the user, e.g. through the phone's text-to-speech
+pub trait Speaker: Send + Sync {
+    /// Speak `text` aloud, returning once it is queued
+    fn speak(&self, text: &str) -> AppResult<()>;
+}
+
 /// Enhanced voice interface with advanced features
 pub struct VoiceInterface {
     config: VoiceConfig,
+    speaker: Option<Arc<dyn Speaker>>,
     stats: Arc<RwLock<VoiceStats>>,
     model_path: String,
     // vosk_model: Option<vosk::Model>,  // Optional Vosk support
@@ -220,6 +227,7 @@ impl VoiceInterface {
 
         Self {
             config,
+            speaker: None,
             stats,
             model_path: actual_model_path,
             // vosk_model: None,  // Optional Vosk support
@@ -229,6 +237,30 @@ impl VoiceInterface {
         }
     }
 
+    /// Ask questions through `speaker`
+    pub fn with_speaker(mut self, speaker: Arc<dyn Speaker>) -> Self {
+        self.speaker = Some(speaker);
+        self
+    }
+
+    /// Whether questions can be asked out loud
+    pub fn can_ask(&self) -> bool {
+        self.speaker.is_some()
+    }
+
+    /// Ask the user a question out loud
+    ///
+    /// The answer is heard like any other speech, so keep listening until
+    /// it is due.
+    pub fn ask(&self, question: &str) -> AppResult<()> {
+        let speaker = self
+            .speaker
+            .as_ref()
+            .ok_or_else(|| AppError::Voice("no speaker to ask through".to_string()))?;
+        tracing::info!("Asking: {}", question);
+        speaker.speak(question)
+    }
+
     /// Initialize voice recognition with enhanced pattern recognition
     pub async fn initialize(&mut self) -> AppResult<()> {
         // Enhanced pattern recognition with advanced features
@@ -397,6 +429,14 @@ impl VoiceInterface {
         self.enhanced_pattern_recognition_with_context(&samples)
     }
     
+    /// Recognize speech from already-denoised 16-bit PCM samples
+    ///
+    /// Used by the streaming pipeline, which denoises frame by frame and
+    /// hands over its rolling window without re-encoding it as bytes.
+    pub fn recognize_pcm(&self, samples: &[i16]) -> AppResult<String> {
+        self.enhanced_pattern_recognition_with_context(samples)
+    }
+
     /// Simplified Vosk recognition that works with immutable references
     fn real_vosk_recognition_simplified(&self, samples: &[i16]) -> AppResult<String> {
         tracing::info!("Using simplified Vosk recognition with {} samples", samples.len());
@@ -870,7 +910,11 @@ impl VoiceInterface {
         tracing::info!("- Sample rate: {}Hz", self.config.sample_rate);
         tracing::info!("- Model path: {}", self.model_path);
         tracing::info!("- Mode: ENHANCED PATTERN RECOGNITION + RNNOISE + CONTEXT AWARENESS");
-        
+
+        let model = crate::diagnostics::check_speech_model(std::path::Path::new(&self.model_path));
+        if model.status == crate::diagnostics::CheckStatus::Fail {
+            return Err(AppError::Voice(model.detail));
+        }
         Ok(())
     }
     
@@ -0,0 +1,378 @@
+//! Streaming Voice Pipeline
+//!
+//! This module feeds microphone audio into voice recognition frame by frame.
+//! It is the ingestion path used by the Android bridge, where audio arrives
+//! in small buffers owned by the JVM; direct buffers are processed without
+//! copying.
+//!
+//! ## Frame Contract
+//!
+//! - **Format**: mono, signed 16-bit PCM
+//! - **Sample rate**: [`STREAM_SAMPLE_RATE`] (16 kHz)
+//! - **Frame size**: [`STREAM_FRAME_SAMPLES`] samples (30 ms), the RNNoise frame size
+//! - **Byte order**: native for direct buffers and `short[]`, little-endian for `byte[]`
+//!
+//! Callers may push any number of samples per call. Whole frames are denoised
+//! straight from the caller's slice; only a trailing partial frame, or a
+//! trailing odd byte on the `byte[]` path, is carried over to the next call.
+//!
+//! ## Latency
+//!
+//! Detection latency is measured from speech onset: the first frame in the
+//! recognition window whose denoised level reaches [`SPEECH_ONSET_LEVEL`].
+//! Silence before it, and audio that has slid out of the window, don't count.
+
+use crate::error::AppResult;
+use crate::public::voice_interface::VoiceInterface;
+use nnnoiseless::DenoiseState;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Sample rate every pushed frame must use (Hz)
+pub const STREAM_SAMPLE_RATE: u32 = 16_000;
+
+/// Samples per frame (30 ms at 16 kHz)
+pub const STREAM_FRAME_SAMPLES: usize = 480;
+
+/// Bytes per frame for 16-bit PCM, for sizing direct buffers
+pub const STREAM_FRAME_BYTES: usize = STREAM_FRAME_SAMPLES * 2;
+
+/// Length of the rolling recognition window
+const WINDOW_DURATION_MS: usize = 2000;
+
+/// Mean absolute sample level of a denoised frame that counts as speech
+pub const SPEECH_ONSET_LEVEL: f32 = 500.0;
+
+/// Kind of detection produced by the stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DetectionKind {
+    /// The "hey sos" wake word
+    WakeWord,
+    /// One of the configured emergency phrases
+    EmergencyPhrase(String),
+    /// Recognized speech that is neither of the above
+    Speech,
+}
+
+/// A detection produced by the streaming pipeline
+#[derive(Debug, Clone)]
+pub struct VoiceDetection {
+    /// Recognized text
+    pub text: String,
+    /// What the text was classified as
+    pub kind: DetectionKind,
+    /// Time from speech onset to this detection
+    pub latency: Duration,
+}
+
+/// Streaming pipeline statistics
+#[derive(Debug, Clone, Default)]
+pub struct StreamStats {
+    /// Total frames denoised
+    pub frames_processed: u64,
+    /// Latency of the first detection since the stream was created or reset
+    pub first_detection_latency: Option<Duration>,
+    /// Latency of the most recent detection
+    pub last_detection_latency: Option<Duration>,
+    /// Slowest single-frame denoise time observed
+    pub max_denoise_time: Duration,
+    /// When the most recent frame arrived
+    pub last_frame_at: Option<Instant>,
+}
+
+/// Frame-by-frame voice recognition pipeline
+pub struct VoiceStream {
+    voice: VoiceInterface,
+    denoiser: Box<DenoiseState<'static>>,
+    pending: [i16; STREAM_FRAME_SAMPLES],
+    pending_len: usize,
+    frame_in: [f32; STREAM_FRAME_SAMPLES],
+    frame_out: [f32; STREAM_FRAME_SAMPLES],
+    window: Vec<i16>,
+    window_capacity: usize,
+    /// Arrival time of each frame in the window and whether it was speech
+    window_frames: VecDeque<(Instant, bool)>,
+    /// Low byte of a sample split across `push_le_bytes` calls
+    odd_byte: Option<u8>,
+    last_detection: Option<VoiceDetection>,
+    stats: StreamStats,
+}
+
+impl VoiceStream {
+    /// Create a new stream on top of a voice interface
+    pub fn new(voice: VoiceInterface) -> Self {
+        let window_capacity = STREAM_SAMPLE_RATE as usize * WINDOW_DURATION_MS / 1000;
+
+        Self {
+            voice,
+            denoiser: DenoiseState::new(),
+            pending: [0; STREAM_FRAME_SAMPLES],
+            pending_len: 0,
+            frame_in: [0.0; STREAM_FRAME_SAMPLES],
+            frame_out: [0.0; STREAM_FRAME_SAMPLES],
+            window: Vec::with_capacity(window_capacity),
+            window_capacity,
+            window_frames: VecDeque::with_capacity(window_capacity / STREAM_FRAME_SAMPLES),
+            odd_byte: None,
+            last_detection: None,
+            stats: StreamStats::default(),
+        }
+    }
+
+    /// Push native-endian PCM samples
+    ///
+    /// # Returns
+    /// * `AppResult<Option<VoiceDetection>>` - The first detection made while consuming the samples
+    pub fn push_samples(&mut self, samples: &[i16]) -> AppResult<Option<VoiceDetection>> {
+        let mut detection = None;
+        let mut rest = samples;
+
+        // Top up a carried partial frame first
+        if self.pending_len > 0 {
+            let take = (STREAM_FRAME_SAMPLES - self.pending_len).min(rest.len());
+            self.pending[self.pending_len..self.pending_len + take].copy_from_slice(&rest[..take]);
+            self.pending_len += take;
+            rest = &rest[take..];
+
+            if self.pending_len == STREAM_FRAME_SAMPLES {
+                let frame = self.pending;
+                self.pending_len = 0;
+                detection = self.process_frame(&frame)?;
+            }
+        }
+
+        // Whole frames are processed in place from the caller's slice
+        let mut frames = rest.chunks_exact(STREAM_FRAME_SAMPLES);
+        for frame in &mut frames {
+            let result = self.process_frame(frame)?;
+            if detection.is_none() {
+                detection = result;
+            }
+        }
+
+        let remainder = frames.remainder();
+        self.pending[..remainder.len()].copy_from_slice(remainder);
+        self.pending_len += remainder.len();
+
+        Ok(detection)
+    }
+
+    /// Push little-endian 16-bit PCM bytes (the legacy `byte[]` path)
+    ///
+    /// A trailing odd byte is kept as the low byte of the next call's first sample.
+    pub fn push_le_bytes(&mut self, bytes: &[u8]) -> AppResult<Option<VoiceDetection>> {
+        let mut detection = None;
+        let mut rest = bytes;
+
+        if let Some(low) = self.odd_byte {
+            let Some((&high, tail)) = rest.split_first() else {
+                return Ok(None);
+            };
+            self.odd_byte = None;
+            rest = tail;
+            detection = self.push_sample(i16::from_le_bytes([low, high]))?;
+        }
+
+        let mut chunks = rest.chunks_exact(2);
+        for chunk in &mut chunks {
+            let result = self.push_sample(i16::from_le_bytes([chunk[0], chunk[1]]))?;
+            if detection.is_none() {
+                detection = result;
+            }
+        }
+        self.odd_byte = chunks.remainder().first().copied();
+
+        Ok(detection)
+    }
+
+    /// Append one sample to the carried frame, processing it once full
+    fn push_sample(&mut self, sample: i16) -> AppResult<Option<VoiceDetection>> {
+        self.pending[self.pending_len] = sample;
+        self.pending_len += 1;
+        if self.pending_len < STREAM_FRAME_SAMPLES {
+            return Ok(None);
+        }
+        let frame = self.pending;
+        self.pending_len = 0;
+        self.process_frame(&frame)
+    }
+
+    /// Denoise one frame, append it to the window and try to recognize
+    fn process_frame(&mut self, frame: &[i16]) -> AppResult<Option<VoiceDetection>> {
+        let now = Instant::now();
+        self.stats.last_frame_at = Some(now);
+
+        // RNNoise works on i16-range floats
+        for (input, &sample) in self.frame_in.iter_mut().zip(frame) {
+            *input = sample as f32;
+        }
+        self.denoiser.process_frame(&mut self.frame_out, &self.frame_in);
+
+        if self.window.len() + STREAM_FRAME_SAMPLES > self.window_capacity {
+            self.window.drain(..STREAM_FRAME_SAMPLES);
+            self.window_frames.pop_front();
+        }
+        self.window.extend(
+            self.frame_out
+                .iter()
+                .map(|&sample| sample.clamp(i16::MIN as f32, i16::MAX as f32) as i16),
+        );
+        let level = self.frame_out.iter().map(|sample| sample.abs()).sum::<f32>() / STREAM_FRAME_SAMPLES as f32;
+        self.window_frames.push_back((now, level >= SPEECH_ONSET_LEVEL));
+
+        self.stats.frames_processed += 1;
+        self.stats.max_denoise_time = self.stats.max_denoise_time.max(now.elapsed());
+
+        // The recognizer needs more than half a second of audio
+        if self.window.len() <= STREAM_SAMPLE_RATE as usize / 2 {
+            return Ok(None);
+        }
+
+        let text = self.voice.recognize_pcm(&self.window)?;
+        let kind = match self.classify(&text) {
+            Some(kind) => kind,
+            None => return Ok(None),
+        };
+
+        let latency = self.speech_onset().map(|start| start.elapsed()).unwrap_or_default();
+        self.stats.first_detection_latency.get_or_insert(latency);
+        self.stats.last_detection_latency = Some(latency);
+
+        // Start a fresh utterance after each detection
+        self.window.clear();
+        self.window_frames.clear();
+
+        let detection = VoiceDetection { text, kind, latency };
+        tracing::info!("Streaming detection: {:?} in {:?}", detection.kind, detection.latency);
+        self.last_detection = Some(detection.clone());
+        Ok(Some(detection))
+    }
+
+    /// Arrival of the first speech frame still in the window, or of the
+    /// oldest frame if none reached the onset level
+    fn speech_onset(&self) -> Option<Instant> {
+        self.window_frames
+            .iter()
+            .find(|(_, speech)| *speech)
+            .or(self.window_frames.front())
+            .map(|(at, _)| *at)
+    }
+
+    /// Classify recognized text, returning `None` for rejections
+    fn classify(&self, text: &str) -> Option<DetectionKind> {
+        if text.starts_with("no_emergency") {
+            return None;
+        }
+
+        let text_lower = text.to_lowercase();
+        if text_lower.contains("hey sos") {
+            return Some(DetectionKind::WakeWord);
+        }
+
+        self.voice
+            .get_config()
+            .emergency_phrases
+            .iter()
+            .find(|phrase| text_lower.contains(phrase.as_str()))
+            .map(|phrase| DetectionKind::EmergencyPhrase(phrase.clone()))
+            .or(Some(DetectionKind::Speech))
+    }
+
+    /// Discard buffered audio and start a new utterance
+    pub fn reset(&mut self) {
+        self.pending_len = 0;
+        self.odd_byte = None;
+        self.window.clear();
+        self.window_frames.clear();
+        self.last_detection = None;
+        self.stats.first_detection_latency = None;
+    }
+
+    /// Get the most recent detection
+    pub fn last_detection(&self) -> Option<&VoiceDetection> {
+        self.last_detection.as_ref()
+    }
+
+    /// Get streaming statistics
+    pub fn stats(&self) -> &StreamStats {
+        &self.stats
+    }
+
+    /// Get the underlying voice interface
+    pub fn voice(&self) -> &VoiceInterface {
+        &self.voice
+    }
+
+    /// Get the voice interface for reconfiguration
+    pub fn voice_mut(&mut self) -> &mut VoiceInterface {
+        &mut self.voice
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream() -> VoiceStream {
+        VoiceStream::new(VoiceInterface::new("vosk-model-small-en-us-0.15"))
+    }
+
+    /// A loud square wave the recognizer reports as an emergency phrase
+    fn shout(samples: usize) -> Vec<i16> {
+        (0..samples).map(|i| if (i / 8) % 2 == 0 { 24_000 } else { -24_000 }).collect()
+    }
+
+    #[test]
+    fn test_frames_split_across_pushes() {
+        let mut stream = stream();
+        let samples: Vec<i16> = (0..STREAM_FRAME_SAMPLES as i16 * 3).collect();
+
+        stream.push_samples(&samples[..100]).unwrap();
+        assert_eq!((stream.stats().frames_processed, stream.pending_len), (0, 100));
+        stream.push_samples(&samples[100..1000]).unwrap();
+        assert_eq!((stream.stats().frames_processed, stream.pending_len), (2, 40));
+        assert_eq!(&stream.pending[..40], &samples[960..1000]);
+        stream.push_samples(&samples[1000..]).unwrap();
+        assert_eq!((stream.stats().frames_processed, stream.pending_len), (3, 0));
+
+        stream.push_samples(&samples[..10]).unwrap();
+        stream.reset();
+        assert_eq!(stream.pending_len, 0);
+    }
+
+    #[test]
+    fn test_odd_bytes_carry_over() {
+        let samples: Vec<i16> = (0..STREAM_FRAME_SAMPLES as i16 + 5).map(|i| i * 37 - 9000).collect();
+        let bytes: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+
+        let mut stream = stream();
+        for chunk in bytes.chunks(7) {
+            stream.push_le_bytes(chunk).unwrap();
+        }
+        assert_eq!((stream.stats().frames_processed, stream.pending_len), (1, 5));
+        assert_eq!(&stream.pending[..5], &samples[STREAM_FRAME_SAMPLES..]);
+        assert_eq!(stream.odd_byte, None);
+
+        stream.push_le_bytes(&[0x34]).unwrap();
+        assert_eq!((stream.pending_len, stream.odd_byte), (5, Some(0x34)));
+        stream.push_le_bytes(&[0x12, 0xff]).unwrap();
+        assert_eq!((stream.pending_len, stream.pending[5], stream.odd_byte), (6, 0x1234, Some(0xff)));
+    }
+
+    #[test]
+    fn test_latency_counts_from_speech_onset() {
+        let mut stream = stream();
+        for _ in 0..10 {
+            assert!(stream.push_samples(&[0; STREAM_FRAME_SAMPLES]).unwrap().is_none());
+        }
+        std::thread::sleep(Duration::from_millis(300));
+
+        let detection = (0..100)
+            .find_map(|_| stream.push_samples(&shout(STREAM_FRAME_SAMPLES)).unwrap())
+            .expect("shouting is detected");
+        assert!(matches!(detection.kind, DetectionKind::EmergencyPhrase(_)));
+        assert!(detection.latency < Duration::from_millis(300), "latency {:?}", detection.latency);
+        assert_eq!(stream.stats().first_detection_latency, Some(detection.latency));
+        assert!(stream.window_frames.is_empty());
+    }
+}
@@ -0,0 +1,571 @@
+//! Calls
+//!
+//! The call task places emergency and contact calls one at a time, and the
+//! call notifier routes call alerts from escalations to it.
+
+use crate::call_script::{CallScript, IncidentSummary};
+use crate::config::AppConfig;
+use crate::dead_reckoning::Estimate;
+use crate::dialer::{CallPolicy, CallReports, CallUpdate, EmergencyCall, EmergencyDialer};
+use crate::emergency_numbers;
+use crate::error::{AppError, AppResult};
+use crate::event_bus::{AppEvent, EventBus, Subscription};
+use crate::location::Fix;
+use crate::notifier::{Alert, DeliveryRouter, Notifier};
+use crate::public::types::EmergencyType;
+use crate::contacts::{Contact, NotificationChannel};
+use crate::templates::{AlertContext, AlertTemplates};
+use super::notifications::NotificationRequest;
+use super::storage::TimelineEntry;
+use super::supervisor::Subsystem;
+use futures::future::BoxFuture;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch};
+
+/// How long a contact's call alert is followed once the message is spoken
+pub(super) const CONTACT_CALL_FOLLOW_LIMIT: Duration = Duration::from_secs(2 * 60);
+
+/// Work item for the call task
+#[derive(Debug)]
+pub(super) struct CallRequest {
+    /// Incident the call belongs to
+    pub(super) incident_id: String,
+    /// Number to call
+    pub(super) number: String,
+    /// What the call is about, if known
+    pub(super) emergency_type: Option<EmergencyType>,
+    /// Best fix when the call was requested
+    pub(super) location: Option<Fix>,
+    /// Spoken into the call once connected, for when the user can't speak
+    pub(super) message: Option<String>,
+    /// Publish nothing on the event bus, so no screen reacts
+    pub(super) covert: bool,
+    /// How often the number is dialed
+    pub(super) policy: CallPolicy,
+    /// A call to emergency services: goes ahead of contact calls and hangs
+    /// up one in progress
+    pub(super) urgent: bool,
+    /// What happens once the call ends
+    pub(super) follow_up: CallFollowUp,
+}
+
+/// What the call task does once a call ends
+#[derive(Debug)]
+pub(super) enum CallFollowUp {
+    /// If no call connected, text the number and alert `contacts`
+    FallBack {
+        /// Facts the fallback messages are rendered from
+        context: Box<AlertContext>,
+        /// Contacts to fall back to
+        contacts: Vec<Contact>,
+    },
+    /// Tell the [`CallNotifier`] that asked whether the call connected
+    Reply(oneshot::Sender<AppResult<()>>),
+}
+
+/// Emergency call task: calls emergency services and falls back to text
+///
+/// One call runs at a time and is retried as its [`CallPolicy`] allows; call
+/// states arrive from the host through [`CallReports`]. A cancel stops
+/// further retries, since a redial would show on screen, but a call already
+/// ringing or connected carries on. Under duress the cancel looks the same:
+/// the call carries on, but from then on publishes no events and speaks
+/// nothing into the call, and if it never connects emergency services are
+/// still texted. Every attempt is added to the incident timeline. When no
+/// call to emergency services connects, they are texted on the same number
+/// and contacts are alerted. Calls the escalation places through the
+/// [`CallNotifier`] are only reported back.
+///
+/// Requests arriving during a call wait their turn, urgent ones ahead of
+/// contact calls. An urgent request hangs up a contact call in progress, so
+/// a contact who picked up can't keep emergency services waiting.
+pub(super) struct CallTask {
+    pub(super) requests: mpsc::Receiver<CallRequest>,
+    pub(super) queue: VecDeque<CallRequest>,
+    pub(super) updates: mpsc::UnboundedReceiver<CallUpdate>,
+    pub(super) reports: CallReports,
+    pub(super) dialer: Arc<dyn EmergencyDialer>,
+    pub(super) notifier: Arc<DeliveryRouter>,
+    pub(super) notifications: mpsc::Sender<NotificationRequest>,
+    pub(super) storage: mpsc::Sender<TimelineEntry>,
+    pub(super) settings: watch::Receiver<AppConfig>,
+    pub(super) events: Subscription,
+    pub(super) duress: watch::Receiver<bool>,
+    pub(super) event_bus: EventBus,
+}
+
+impl CallTask {
+    async fn call(&mut self, request: CallRequest) -> AppResult<()> {
+        // Cancels from before this call don't stop it
+        self.events.drain();
+        let mut call = EmergencyCall::new(&request.incident_id, &request.number, self.dialer.clone(), self.reports.clone())
+            .with_policy(request.policy)
+            .with_covert(self.duress.clone());
+        if !request.covert {
+            call = call.with_event_bus(self.event_bus.clone());
+        }
+        if let Some(message) = &request.message {
+            call = call.with_message(message);
+        }
+        if let Some(emergency_type) = request.emergency_type.clone() {
+            call = call.with_emergency_type(emergency_type);
+        }
+        if let Some(fix) = request.location {
+            call = call.with_location(fix.latitude, fix.longitude);
+        }
+        let (preempt, preempt_rx) = watch::channel(false);
+        if !request.urgent {
+            call = call.with_preempt(preempt_rx);
+        }
+
+        let (stop, mut stop_rx) = watch::channel(false);
+        let mut cancelled = false;
+        let mut requests_open = true;
+        let report = {
+            let run = call.run(&mut self.updates, &mut stop_rx);
+            tokio::pin!(run);
+            loop {
+                tokio::select! {
+                    report = &mut run => break report,
+                    Some(event) = self.events.recv() => {
+                        if matches!(event, AppEvent::AlertsCancelled | AppEvent::EmergencyEnded) {
+                            stop.send_replace(true);
+                            cancelled |= !*self.duress.borrow();
+                        }
+                    }
+                    next = self.requests.recv(), if requests_open => match next {
+                        Some(next) if next.urgent => {
+                            if !request.urgent {
+                                tracing::warn!("Hanging up the call to {} for a call to {}", request.number, next.number);
+                                preempt.send_replace(true);
+                            }
+                            let at = self.queue.iter().position(|queued| !queued.urgent).unwrap_or(self.queue.len());
+                            self.queue.insert(at, next);
+                        }
+                        Some(next) => self.queue.push_back(next),
+                        None => requests_open = false,
+                    },
+                }
+            }
+        };
+
+        let connected = report.connected();
+        let error = report.attempts.last().and_then(|attempt| attempt.error.clone());
+        for attempt in report.attempts {
+            self.store(TimelineEntry::Call(attempt)).await?;
+        }
+        match request.follow_up {
+            CallFollowUp::FallBack { context, contacts } if !connected && !cancelled => {
+                self.fall_back(&request.incident_id, &request.number, request.location, *context, contacts).await?;
+            }
+            CallFollowUp::FallBack { .. } => {}
+            CallFollowUp::Reply(reply) => {
+                let result = if connected {
+                    Ok(())
+                } else {
+                    let reason = error.as_deref().unwrap_or("cancelled");
+                    Err(AppError::Network(format!("call to {} did not connect: {}", request.number, reason)))
+                };
+                // The escalation may have been stopped meanwhile
+                let _ = reply.send(result);
+            }
+        }
+        Ok(())
+    }
+
+    /// Text emergency services and alert contacts when no call connected
+    async fn fall_back(
+        &mut self,
+        incident_id: &str,
+        number: &str,
+        location: Option<Fix>,
+        context: AlertContext,
+        contacts: Vec<Contact>,
+    ) -> AppResult<()> {
+        tracing::warn!("No emergency call to {} connected; texting it and alerting contacts", number);
+        let language = self.settings.borrow().ui.language.clone();
+        let mut text_context = context.clone();
+        if let Some(fix) = location {
+            text_context = text_context.with_report(fix.report(chrono::Utc::now()));
+        }
+        let alert = Alert {
+            incident_id: incident_id.to_string(),
+            recipient: "Emergency services".to_string(),
+            address: number.to_string(),
+            channel: NotificationChannel::Sms,
+            message: AlertTemplates::for_language(&language).text_to_911(&text_context),
+        };
+        if let Err(e) = self.notifier.deliver(&alert).await {
+            tracing::error!("Failed to text {}: {}", number, e);
+        }
+
+        let notification = NotificationRequest {
+            incident_id: incident_id.to_string(),
+            context,
+            contacts,
+        };
+        self.notifications
+            .send(notification)
+            .await
+            .map_err(|_| AppError::Internal("notification task is not running".to_string()))
+    }
+
+    async fn store(&mut self, entry: TimelineEntry) -> AppResult<()> {
+        self.storage
+            .send(entry)
+            .await
+            .map_err(|_| AppError::Internal("storage task is not running".to_string()))
+    }
+}
+
+impl Subsystem for CallTask {
+    fn name(&self) -> &'static str {
+        "calls"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        // A call in progress carries on through shutdown until it ends
+        loop {
+            let request = match self.queue.pop_front() {
+                Some(request) => request,
+                None => match self.requests.recv().await {
+                    Some(request) => request,
+                    None => return Ok(()),
+                },
+            };
+            self.call(request).await?;
+        }
+    }
+}
+
+/// Delivers call alerts by placing them through the call task
+///
+/// The alert text is spoken into the call once it connects, and delivery
+/// succeeds only if it connected. Contacts are dialed once, quietly, and
+/// followed for [`CONTACT_CALL_FOLLOW_LIMIT`]; an unanswered contact is
+/// covered by the rest of the escalation. Emergency numbers are urgent,
+/// redialed under `emergency_policy`, and their call states are published
+/// like any emergency call. The notifier holds no strong sender,
+/// so the call task still stops once the protocol engine has.
+pub(super) struct CallNotifier {
+    pub(super) calls: mpsc::WeakSender<CallRequest>,
+    pub(super) best: watch::Receiver<Option<Fix>>,
+    pub(super) emergency_policy: CallPolicy,
+}
+
+impl Notifier for CallNotifier {
+    fn deliver<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, AppResult<()>> {
+        Box::pin(async move {
+            let not_running = || AppError::Internal("call task is not running".to_string());
+            let calls = self.calls.upgrade().ok_or_else(not_running)?;
+            let (reply, result) = oneshot::channel();
+            let emergency = emergency_numbers::is_emergency_number(&alert.address);
+            let request = CallRequest {
+                incident_id: alert.incident_id.clone(),
+                number: alert.address.clone(),
+                emergency_type: None,
+                location: *self.best.borrow(),
+                message: Some(alert.message.clone()),
+                covert: !emergency,
+                policy: if emergency {
+                    self.emergency_policy
+                } else {
+                    CallPolicy { max_attempts: 1, follow_limit: CONTACT_CALL_FOLLOW_LIMIT, ..CallPolicy::default() }
+                },
+                urgent: emergency,
+                follow_up: CallFollowUp::Reply(reply),
+            };
+            calls.send(request).await.map_err(|_| not_running())?;
+            drop(calls);
+            result.await.map_err(|_| not_running())?
+        })
+    }
+}
+
+/// Writes dispatcher call scripts for the incident in progress
+#[derive(Debug, Clone)]
+pub struct CallScripts {
+    pub(super) summary: watch::Receiver<Option<IncidentSummary>>,
+    pub(super) location: watch::Receiver<Option<Fix>>,
+    pub(super) estimate: watch::Receiver<Option<Estimate>>,
+}
+
+impl CallScripts {
+    /// The script for the incident in progress, located at `address` if
+    /// the host could look one up; `None` when no incident is in progress
+    pub fn script(&self, address: Option<&str>) -> Option<CallScript> {
+        let summary = self.summary.borrow().clone()?;
+        let now = chrono::Utc::now();
+        let estimate = *self.estimate.borrow();
+        let report = self.location.borrow().map(|fix| fix.report(now).with_estimate(estimate));
+        Some(CallScript::new(&summary, report.as_ref(), address, now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialer::{CallState, LocalDialer};
+    use crate::event_bus::{BackpressurePolicy, EventKind};
+    use crate::notifier::DeliveryStatus;
+    use crate::public::emergency_interface::CallOutcome;
+    use crate::templates::AlertKind;
+    use std::sync::Mutex;
+    use tokio::task::JoinHandle;
+
+    /// A running call task and the ends of its channels
+    struct CallHarness {
+        calls: mpsc::Sender<CallRequest>,
+        reports: CallReports,
+        timeline: mpsc::Receiver<TimelineEntry>,
+        notifications: mpsc::Receiver<NotificationRequest>,
+        duress: watch::Sender<bool>,
+        event_bus: EventBus,
+        handle: JoinHandle<AppResult<()>>,
+    }
+
+    fn start_call_task(dialer: Arc<dyn EmergencyDialer>) -> CallHarness {
+        let (calls, requests) = mpsc::channel(8);
+        let (reports, updates) = CallReports::new();
+        let (storage, timeline) = mpsc::channel(8);
+        let (notifications, notifications_rx) = mpsc::channel(8);
+        let (duress_tx, duress) = watch::channel(false);
+        let event_bus = EventBus::new();
+        let mut task = CallTask {
+            requests,
+            queue: VecDeque::new(),
+            updates,
+            reports: reports.clone(),
+            dialer,
+            notifier: Arc::new(DeliveryRouter::new()),
+            notifications,
+            storage,
+            settings: watch::channel(AppConfig::default()).1,
+            events: event_bus.subscribe(Some(&[EventKind::AlertsCancelled]), BackpressurePolicy::Unbounded),
+            duress,
+            event_bus: event_bus.clone(),
+        };
+        CallHarness {
+            calls,
+            reports,
+            timeline,
+            notifications: notifications_rx,
+            duress: duress_tx,
+            event_bus,
+            handle: tokio::spawn(async move { task.run().await }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_alerts_are_placed_through_the_dialer() {
+        let dialer = Arc::new(LocalDialer::new());
+        let CallHarness { calls: call_tx, mut timeline, handle, .. } = start_call_task(dialer.clone());
+        let (_best, best) = watch::channel(None);
+        let (receipts, mut receipts_rx) = mpsc::unbounded_channel();
+        let router = DeliveryRouter::new()
+            .route(NotificationChannel::Call, Arc::new(CallNotifier { calls: call_tx.downgrade(), best, emergency_policy: CallPolicy::default() }))
+            .with_receipts(receipts);
+
+        let alert = Alert {
+            incident_id: "incident-1".to_string(),
+            recipient: "Mom".to_string(),
+            address: "+12025550143".to_string(),
+            channel: NotificationChannel::Call,
+            message: "Alex needs help".to_string(),
+        };
+        router.deliver(&alert).await.unwrap();
+        assert_eq!(dialer.dialed(), vec!["+12025550143"]);
+        assert_eq!(dialer.spoken(), vec!["Alex needs help"]);
+        assert!(matches!(timeline.recv().await, Some(TimelineEntry::Call(_))));
+        assert_eq!(receipts_rx.recv().await.unwrap().status, DeliveryStatus::Sent);
+
+        // An unanswered call fails the alert instead of reporting it sent
+        dialer.unanswered_times(1);
+        let unanswered = Alert { message: "Alex still needs help".to_string(), ..alert.clone() };
+        assert!(router.deliver(&unanswered).await.is_err());
+        assert_eq!(dialer.dialed().len(), 2, "contact calls are dialed once");
+        assert_eq!(receipts_rx.recv().await.unwrap().status, DeliveryStatus::Failed);
+
+        // The notifier doesn't keep the call task alive
+        drop(call_tx);
+        handle.await.unwrap().unwrap();
+        let late = Alert { message: "Update".to_string(), ..alert };
+        assert!(matches!(router.deliver(&late).await, Err(AppError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn test_emergency_number_alerts_are_redialed_and_published() {
+        let dialer = Arc::new(LocalDialer::new());
+        let CallHarness { calls: call_tx, mut timeline, event_bus, .. } = start_call_task(dialer.clone());
+        let mut placed = event_bus.subscribe(Some(&[EventKind::CallPlaced]), BackpressurePolicy::Unbounded);
+        let emergency_policy = CallPolicy { retry_delay: Duration::from_millis(10), ..CallPolicy::default() };
+        let calls = CallNotifier { calls: call_tx.downgrade(), best: watch::channel(None).1, emergency_policy };
+        let router = DeliveryRouter::new().route(NotificationChannel::Call, Arc::new(calls));
+
+        // A contact's call is dialed once and shows nothing
+        dialer.unanswered_times(1);
+        let contact = Alert {
+            incident_id: "incident-1".to_string(),
+            recipient: "Mom".to_string(),
+            address: "+12025550143".to_string(),
+            channel: NotificationChannel::Call,
+            message: "Alex needs help".to_string(),
+        };
+        assert!(router.deliver(&contact).await.is_err());
+
+        // Emergency services are redialed until the call connects
+        dialer.unanswered_times(1);
+        let services = Alert { recipient: "Emergency services".to_string(), address: "911".to_string(), ..contact };
+        router.deliver(&services).await.unwrap();
+        assert_eq!(dialer.dialed(), vec!["+12025550143", "911", "911"]);
+        for _ in 0..2 {
+            assert!(matches!(placed.recv().await, Some(AppEvent::CallPlaced { number }) if number == "911"));
+        }
+        assert!(placed.try_recv().is_none());
+
+        let mut attempts = Vec::new();
+        for _ in 0..3 {
+            let Some(TimelineEntry::Call(attempt)) = timeline.recv().await else { panic!("expected a call attempt") };
+            attempts.push((attempt.number, attempt.attempt, attempt.outcome));
+        }
+        assert_eq!(attempts[1..], [("911".to_string(), 1, CallOutcome::Failed), ("911".to_string(), 2, CallOutcome::Successful)]);
+    }
+
+    /// Reports dialing and leaves the rest of each call to the test
+    #[derive(Default)]
+    struct ManualDialer {
+        dialed: Mutex<Vec<String>>,
+        spoken: Mutex<Vec<String>>,
+        hung_up: Mutex<Vec<String>>,
+    }
+
+    impl EmergencyDialer for ManualDialer {
+        fn dial(&self, _number: &str, call_id: &str, reports: &CallReports) -> AppResult<()> {
+            self.dialed.lock().unwrap().push(call_id.to_string());
+            reports.report(call_id, CallState::Dialing, None);
+            Ok(())
+        }
+
+        fn hang_up(&self, call_id: &str) -> AppResult<()> {
+            self.hung_up.lock().unwrap().push(call_id.to_string());
+            Ok(())
+        }
+
+        fn speak(&self, _call_id: &str, text: &str) -> AppResult<()> {
+            self.spoken.lock().unwrap().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    impl ManualDialer {
+        /// ID of the latest call
+        fn last(&self) -> String {
+            self.dialed.lock().unwrap().last().cloned().expect("a call was dialed")
+        }
+    }
+
+    fn emergency_call() -> CallRequest {
+        CallRequest {
+            incident_id: "incident-1".to_string(),
+            number: "911".to_string(),
+            emergency_type: None,
+            location: None,
+            message: Some("Silent SOS from Alex".to_string()),
+            covert: false,
+            policy: CallPolicy { max_attempts: 3, retry_delay: Duration::ZERO, ..CallPolicy::default() },
+            urgent: true,
+            follow_up: CallFollowUp::FallBack {
+                context: Box::new(AlertContext::new(AlertKind::SilentSos, "Alex")),
+                contacts: Vec::new(),
+            },
+        }
+    }
+
+    async fn settle() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duress_silences_call_in_progress() {
+        let dialer = Arc::new(ManualDialer::default());
+        let mut harness = start_call_task(dialer.clone());
+        let mut tap = harness.event_bus.tap();
+
+        harness.calls.send(emergency_call()).await.unwrap();
+        settle().await;
+        let first = dialer.last();
+
+        // The duress code is entered while the call rings
+        harness.duress.send_replace(true);
+        harness.event_bus.publish(AppEvent::AlertsCancelled);
+        settle().await;
+        tap.drain();
+
+        harness.reports.report(&first, CallState::Connected, None);
+        harness.reports.report(&first, CallState::Ended, None);
+        assert!(matches!(harness.timeline.recv().await, Some(TimelineEntry::Call(_))));
+        assert!(dialer.spoken.lock().unwrap().is_empty());
+        assert!(tap.drain().is_empty(), "no call states once covert");
+
+        // A call that fails under duress isn't redialed, but contacts are still alerted
+        harness.calls.send(emergency_call()).await.unwrap();
+        settle().await;
+        harness.event_bus.publish(AppEvent::AlertsCancelled);
+        settle().await;
+        let second = dialer.last();
+        assert_ne!(second, first, "each call has its own ID");
+        // A late report for the first call doesn't end the second
+        harness.reports.report(&first, CallState::Failed, Some("late".to_string()));
+        settle().await;
+        assert!(harness.timeline.try_recv().is_err());
+        harness.reports.report(&second, CallState::Failed, Some("busy".to_string()));
+        assert!(matches!(harness.timeline.recv().await, Some(TimelineEntry::Call(_))));
+        assert_eq!(dialer.dialed.lock().unwrap().len(), 2);
+        assert_eq!(harness.notifications.recv().await.unwrap().incident_id, "incident-1");
+
+        drop(harness.calls);
+        harness.handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_emergency_call_hangs_up_contact_call_in_progress() {
+        let dialer = Arc::new(ManualDialer::default());
+        let harness = start_call_task(dialer.clone());
+        let calls = CallNotifier { calls: harness.calls.downgrade(), best: watch::channel(None).1, emergency_policy: CallPolicy::default() };
+        let router = Arc::new(DeliveryRouter::new().route(NotificationChannel::Call, Arc::new(calls)));
+        let deliver = |alert: Alert| {
+            let router = router.clone();
+            tokio::spawn(async move { router.deliver(&alert).await })
+        };
+
+        let contact = Alert {
+            incident_id: "incident-1".to_string(),
+            recipient: "Mom".to_string(),
+            address: "+12025550143".to_string(),
+            channel: NotificationChannel::Call,
+            message: "Alex needs help".to_string(),
+        };
+        let contact_delivery = deliver(contact.clone());
+        settle().await;
+        let contact_call = dialer.last();
+        harness.reports.report(&contact_call, CallState::Connected, None);
+        settle().await;
+
+        // The escalation reaches emergency services while Mom is still on the line
+        let services_delivery = deliver(Alert { recipient: "Emergency services".to_string(), address: "911".to_string(), ..contact });
+        settle().await;
+        assert_eq!(*dialer.hung_up.lock().unwrap(), vec![contact_call.clone()]);
+        contact_delivery.await.unwrap().unwrap();
+
+        let services_call = dialer.last();
+        assert_ne!(services_call, contact_call);
+        harness.reports.report(&services_call, CallState::Connected, None);
+        harness.reports.report(&services_call, CallState::Ended, None);
+        services_delivery.await.unwrap().unwrap();
+        assert_eq!(dialer.hung_up.lock().unwrap().len(), 1, "the emergency call isn't pre-empted");
+    }
+}
@@ -0,0 +1,639 @@
+//! Detection
+//!
+//! Tasks that listen to the user and the phone: voice, key and tap gestures,
+//! sensors, and the countdowns that give the user a chance to cancel.
+
+use crate::config::AppConfig;
+use crate::countdown::{CancelSource, Clock, Countdown, CountdownAction, CountdownEvent, CountdownOutcome};
+use crate::crash::{CrashDetector, Impact, ImpactKind};
+use crate::dead_reckoning::{DeadReckoner, Estimate};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::{AppEvent, EventBus};
+use crate::fall::{Fall, FallDetector, FALL_CHECK_QUESTION};
+use crate::gesture::{GestureRecognizer, InputEvent};
+use crate::location::Fix;
+use crate::public::voice_stream::{StreamStats, VoiceStream};
+use crate::sensors::{SensorReading, SensorSample};
+use crate::settings::ConfigTarget;
+use super::protocol::ProtocolCommand;
+use super::storage::{CountdownRecord, TimelineEntry};
+use super::supervisor::Subsystem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+
+/// Work item for the countdown task
+#[derive(Debug)]
+pub(super) enum CountdownRequest {
+    /// Count down, then send `on_expiry` to the protocol engine
+    Start {
+        /// Recorded with the outcome
+        reason: &'static str,
+        /// What expiry does, as announced to the user
+        action: CountdownAction,
+        /// Time to cancel; zero sends `on_expiry` at once
+        duration: Duration,
+        /// Spoken before the opening prompt
+        intro: Option<&'static str>,
+        /// Asked through the voice interface when the countdown starts
+        question: Option<&'static str>,
+        /// Sent to the protocol engine on expiry
+        on_expiry: ProtocolCommand,
+    },
+    /// Stop the running countdown
+    Cancel(CancelSource),
+}
+
+/// Voice listening task: turns audio frames into protocol commands
+///
+/// Countdown questions are asked through the voice interface, and audio is
+/// heard while a countdown waits for an answer even if listening is paused.
+pub(super) struct VoiceListener {
+    pub(super) stream: VoiceStream,
+    pub(super) audio: mpsc::Receiver<Vec<i16>>,
+    pub(super) listening: watch::Receiver<bool>,
+    /// Set while a countdown is waiting for the cancel phrase
+    pub(super) checking: watch::Receiver<bool>,
+    pub(super) questions: mpsc::Receiver<&'static str>,
+    pub(super) commands: mpsc::Sender<ProtocolCommand>,
+    pub(super) stats: watch::Sender<StreamStats>,
+    pub(super) settings: watch::Receiver<AppConfig>,
+    pub(super) shutdown: watch::Receiver<bool>,
+}
+
+impl Subsystem for VoiceListener {
+    fn name(&self) -> &'static str {
+        "voice"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        loop {
+            let samples = tokio::select! {
+                biased;
+                _ = self.shutdown.wait_for(|stop| *stop) => return Ok(()),
+                Ok(()) = self.settings.changed() => {
+                    let config = self.settings.borrow_and_update().clone();
+                    self.stream.apply_config(&config);
+                    continue;
+                }
+                Some(question) = self.questions.recv() => {
+                    if let Err(e) = self.stream.voice().ask(question) {
+                        tracing::warn!("Could not ask \"{}\": {}", question, e);
+                    }
+                    continue;
+                }
+                frame = self.audio.recv() => match frame {
+                    Some(samples) => samples,
+                    None => return Ok(()),
+                },
+            };
+
+            // Paused: drop audio and forget any partial utterance, unless a
+            // countdown is waiting for an answer
+            let changed = self.listening.has_changed().unwrap_or(false) | self.checking.has_changed().unwrap_or(false);
+            let listening = *self.listening.borrow_and_update() || *self.checking.borrow_and_update();
+            if changed && !listening {
+                self.stream.reset();
+            }
+            if !listening {
+                continue;
+            }
+
+            let detection = self.stream.push_samples(&samples)?;
+            self.stats.send_replace(self.stream.stats().clone());
+
+            if let Some(detection) = detection {
+                self.commands
+                    .send(ProtocolCommand::Detection(detection))
+                    .await
+                    .map_err(|_| AppError::Internal("protocol engine is not running".to_string()))?;
+            }
+        }
+    }
+}
+
+/// Gesture listening task: turns key and tap events into silent SOS commands
+pub(super) struct GestureListener {
+    pub(super) recognizer: GestureRecognizer,
+    pub(super) inputs: mpsc::Receiver<InputEvent>,
+    pub(super) commands: mpsc::Sender<ProtocolCommand>,
+    pub(super) settings: watch::Receiver<AppConfig>,
+    pub(super) shutdown: watch::Receiver<bool>,
+    /// Host timestamp of the latest event and when it arrived, to time holds
+    pub(super) last_input: Option<(u64, Instant)>,
+}
+
+impl GestureListener {
+    /// When a button hold in progress completes, on the local clock
+    fn hold_deadline(&self) -> Option<Instant> {
+        let (at, received) = self.last_input?;
+        let deadline = self.recognizer.hold_deadline()?;
+        Some(received + Duration::from_millis(deadline.saturating_sub(at)))
+    }
+}
+
+impl Subsystem for GestureListener {
+    fn name(&self) -> &'static str {
+        "gestures"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        loop {
+            let hold = self.hold_deadline();
+            let trigger = tokio::select! {
+                biased;
+                _ = self.shutdown.wait_for(|stop| *stop) => return Ok(()),
+                Ok(()) = self.settings.changed() => {
+                    let config = self.settings.borrow_and_update().clone();
+                    self.recognizer.apply_config(&config);
+                    continue;
+                }
+                _ = tokio::time::sleep_until(hold.unwrap_or_else(Instant::now).into()), if hold.is_some() => {
+                    let (at, received) = self.last_input.unwrap_or((0, Instant::now()));
+                    self.recognizer.poll(at + received.elapsed().as_millis() as u64)
+                }
+                event = self.inputs.recv() => match event {
+                    Some(event) => {
+                        self.last_input = Some((event.timestamp_ms, Instant::now()));
+                        self.recognizer.handle(event)
+                    }
+                    None => return Ok(()),
+                },
+            };
+
+            if let Some(trigger) = trigger {
+                self.commands
+                    .send(ProtocolCommand::SilentSos(trigger))
+                    .await
+                    .map_err(|_| AppError::Internal("protocol engine is not running".to_string()))?;
+            }
+        }
+    }
+}
+
+/// Position passed to the location tracker
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Position {
+    Fix(Fix),
+    Estimate(Estimate),
+}
+
+/// Sensor monitoring task: turns sensor samples into crash and fall responses
+///
+/// A crash is announced as `CrashDetected` and counts down
+/// `crash_response_window` seconds; a fall is announced as `FallDetected` and
+/// asks "Did you fall?" for `fall_check_timeout` seconds. The countdown task
+/// runs both and hands the response to the protocol engine unless the user
+/// cancels. Location samples, and step estimates from the tracker's latest
+/// best fix, are passed on to the location tracker.
+pub(super) struct SensorMonitor {
+    pub(super) crashes: CrashDetector,
+    pub(super) falls: FallDetector,
+    pub(super) reckoner: DeadReckoner,
+    pub(super) samples: mpsc::Receiver<SensorSample>,
+    /// Best fix from the location tracker, to count steps from
+    pub(super) best: watch::Receiver<Option<Fix>>,
+    pub(super) positions: mpsc::Sender<Position>,
+    pub(super) countdowns: mpsc::Sender<CountdownRequest>,
+    pub(super) settings: watch::Receiver<AppConfig>,
+    pub(super) event_bus: EventBus,
+    pub(super) shutdown: watch::Receiver<bool>,
+}
+
+impl SensorMonitor {
+    /// Pass a location sample or a step estimate on without holding up detection
+    fn forward_position(&mut self, sample: &SensorSample) {
+        let position = match sample.reading {
+            SensorReading::Location { latitude, longitude, accuracy_m } => {
+                match Fix::new(latitude, longitude, accuracy_m, chrono::Utc::now()) {
+                    Some(fix) => Position::Fix(fix),
+                    None => {
+                        tracing::debug!("Invalid location sample ignored: {:?}", sample.reading);
+                        return;
+                    }
+                }
+            }
+            _ => match self.reckoner.handle(*sample) {
+                Some(estimate) => Position::Estimate(estimate),
+                None => return,
+            },
+        };
+        if self.positions.try_send(position).is_err() {
+            tracing::debug!("Location tracker busy; position dropped");
+        }
+    }
+
+    fn crash_countdown(&mut self, impact: Impact) -> CountdownRequest {
+        let (window, auto_dial) = {
+            let settings = self.settings.borrow();
+            (settings.emergency.crash_response_window, settings.emergency.auto_dial_911)
+        };
+        tracing::warn!(
+            "Crash detected: {:.1} g at {:.0} mph; responding in {} s unless cancelled",
+            impact.peak_g,
+            impact.speed_mph,
+            window
+        );
+        self.event_bus.publish(AppEvent::CrashDetected { countdown_secs: window });
+        CountdownRequest::Start {
+            reason: "crash",
+            action: if auto_dial { CountdownAction::CallEmergencyServices } else { CountdownAction::AlertContacts },
+            duration: Duration::from_secs(window),
+            intro: Some("Crash detected."),
+            question: None,
+            on_expiry: ProtocolCommand::Crash(impact),
+        }
+    }
+
+    fn fall_countdown(&mut self, fall: Fall) -> CountdownRequest {
+        let timeout = self.settings.borrow().emergency.fall_check_timeout;
+        tracing::warn!(
+            "Fall detected: {} ms free fall, {:.1} g impact; alerting contacts in {} s without an answer",
+            fall.free_fall_ms,
+            fall.impact_g,
+            timeout
+        );
+        self.event_bus.publish(AppEvent::FallDetected { countdown_secs: timeout });
+        CountdownRequest::Start {
+            reason: "fall",
+            action: CountdownAction::AlertContacts,
+            duration: Duration::from_secs(timeout),
+            intro: None,
+            question: Some(FALL_CHECK_QUESTION),
+            on_expiry: ProtocolCommand::Fall(fall),
+        }
+    }
+}
+
+impl Subsystem for SensorMonitor {
+    fn name(&self) -> &'static str {
+        "sensors"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        loop {
+            let sample = tokio::select! {
+                biased;
+                _ = self.shutdown.wait_for(|stop| *stop) => return Ok(()),
+                Ok(()) = self.settings.changed() => {
+                    let config = self.settings.borrow_and_update().clone();
+                    self.crashes.apply_config(&config);
+                    self.falls.apply_config(&config);
+                    continue;
+                }
+                Ok(()) = self.best.changed() => {
+                    let best = *self.best.borrow_and_update();
+                    if let Some(fix) = best {
+                        self.reckoner.anchor(fix);
+                    }
+                    continue;
+                }
+                sample = self.samples.recv() => match sample {
+                    Some(sample) => sample,
+                    None => return Ok(()),
+                },
+            };
+            self.forward_position(&sample);
+            let (impact, fall) = (self.crashes.handle(sample), self.falls.handle(sample));
+
+            let request = match (impact, fall) {
+                (Some(impact), _) if impact.kind == ImpactKind::Crash => self.crash_countdown(impact),
+                (_, Some(fall)) => self.fall_countdown(fall),
+                (Some(impact), None) => {
+                    tracing::debug!("Impact ignored: {:?}", impact);
+                    continue;
+                }
+                (None, None) => continue,
+            };
+            self.countdowns
+                .send(request)
+                .await
+                .map_err(|_| AppError::Internal("countdown task is not running".to_string()))?;
+        }
+    }
+}
+
+/// Countdown task: runs one cancellable countdown at a time
+///
+/// Prompts are published as `CountdownPrompt` for the host to speak and
+/// vibrate, and the end as `CountdownEnded`. Cancel requests come from the
+/// cancel button and cancel codes (through [`AppRuntime::cancel_countdown`])
+/// and from the protocol engine when it hears the cancel phrase. Every
+/// outcome is recorded; on expiry the pending command goes to the protocol
+/// engine. A countdown's question, such as the fall check's, is asked
+/// through the voice interface, which keeps listening until the countdown
+/// ends. A countdown requested while another runs is merged into it: a
+/// weaker or equal one is dropped, since the running one leads to the same
+/// help or more, and a stronger one, e.g. a crash that calls emergency
+/// services during a fall check that only alerts contacts, replaces it and
+/// expires no later than the running one would have.
+pub(super) struct CountdownTask {
+    pub(super) clock: Arc<dyn Clock>,
+    pub(super) requests: mpsc::Receiver<CountdownRequest>,
+    pub(super) commands: mpsc::Sender<ProtocolCommand>,
+    pub(super) storage: mpsc::Sender<TimelineEntry>,
+    pub(super) settings: watch::Receiver<AppConfig>,
+    pub(super) event_bus: EventBus,
+    /// Set while a countdown is waiting for the cancel phrase
+    pub(super) checking: watch::Sender<bool>,
+    /// Questions for the voice listener to ask, if it can
+    pub(super) questions: Option<mpsc::Sender<&'static str>>,
+    pub(super) shutdown: watch::Receiver<bool>,
+    pub(super) active: Option<(Countdown, &'static str, ProtocolCommand)>,
+}
+
+impl CountdownTask {
+    async fn start(
+        &mut self,
+        reason: &'static str,
+        action: CountdownAction,
+        duration: Duration,
+        intro: Option<&'static str>,
+        question: Option<&'static str>,
+        on_expiry: ProtocolCommand,
+    ) -> AppResult<()> {
+        let (duration, upgraded) = match self.active.take() {
+            None => (duration, false),
+            Some((running, running_reason, running_expiry)) if !action.outranks(running.action()) => {
+                tracing::warn!("Countdown for {} merged into the running {} countdown", reason, running_reason);
+                self.active = Some((running, running_reason, running_expiry));
+                return Ok(());
+            }
+            Some((running, running_reason, _)) => {
+                tracing::warn!("Countdown for {} upgraded to {:?} by {}", running_reason, action, reason);
+                (duration.min(running.remaining()), true)
+            }
+        };
+        if duration.is_zero() {
+            if upgraded {
+                self.checking.send_replace(false);
+                self.event_bus.publish(AppEvent::CountdownEnded { expired: true });
+            }
+            return self.send(on_expiry).await;
+        }
+        // Listening for the answer before the question is asked
+        self.checking.send_replace(true);
+        let phrase = self.settings.borrow().emergency.crash_cancel_phrase.clone();
+        let mut countdown = Countdown::start(self.clock.clone(), action, duration, &phrase);
+        // A question that can't be asked out loud opens the prompts instead
+        let asked = match (question, &self.questions) {
+            (Some(question), Some(questions)) => questions.try_send(question).is_ok(),
+            _ => false,
+        };
+        if let Some(intro) = if asked { intro } else { intro.or(question) } {
+            countdown = countdown.with_intro(intro);
+        }
+        self.active = Some((countdown, reason, on_expiry));
+        Ok(())
+    }
+
+    async fn cancel(&mut self, by: CancelSource) -> AppResult<()> {
+        let Some((mut countdown, reason, _)) = self.active.take() else {
+            return Ok(());
+        };
+        match countdown.cancel(by) {
+            Some(outcome) => {
+                tracing::info!("Countdown for {} cancelled ({:?})", reason, by);
+                self.finish(reason, outcome).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Publish due prompts; on expiry, record it and hand over the command
+    async fn tick(&mut self) -> AppResult<()> {
+        let Some((countdown, ..)) = &mut self.active else {
+            return Ok(());
+        };
+        let events: Vec<_> = std::iter::from_fn(|| countdown.poll()).collect();
+        for event in events {
+            match event {
+                CountdownEvent::Prompt(prompt) => self.event_bus.publish(AppEvent::CountdownPrompt {
+                    remaining_secs: prompt.remaining_secs,
+                    speech: prompt.speech,
+                    haptic: prompt.haptic,
+                }),
+                CountdownEvent::Expired(outcome) => {
+                    if let Some((_, reason, command)) = self.active.take() {
+                        tracing::warn!("Countdown for {} expired", reason);
+                        self.finish(reason, outcome).await?;
+                        self.send(command).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self, reason: &'static str, outcome: CountdownOutcome) -> AppResult<()> {
+        self.checking.send_replace(false);
+        self.event_bus.publish(AppEvent::CountdownEnded { expired: outcome.expired() });
+        let record = CountdownRecord {
+            timestamp: chrono::Utc::now(),
+            reason: reason.to_string(),
+            outcome,
+        };
+        self.storage
+            .send(TimelineEntry::Countdown(record))
+            .await
+            .map_err(|_| AppError::Internal("storage task is not running".to_string()))
+    }
+
+    async fn send(&mut self, command: ProtocolCommand) -> AppResult<()> {
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| AppError::Internal("protocol engine is not running".to_string()))
+    }
+}
+
+impl Subsystem for CountdownTask {
+    fn name(&self) -> &'static str {
+        "countdown"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        loop {
+            let wake = self.active.as_ref().and_then(|(countdown, ..)| countdown.next_wake());
+            let request = tokio::select! {
+                biased;
+                // A countdown still running at shutdown is dropped
+                _ = self.shutdown.wait_for(|stop| *stop) => return Ok(()),
+                request = self.requests.recv() => match request {
+                    Some(request) => Some(request),
+                    None => return Ok(()),
+                },
+                _ = tokio::time::sleep(wake.unwrap_or_default()), if wake.is_some() => None,
+            };
+
+            match request {
+                Some(CountdownRequest::Start { reason, action, duration, intro, question, on_expiry }) => {
+                    self.start(reason, action, duration, intro, question, on_expiry).await?
+                }
+                Some(CountdownRequest::Cancel(by)) => self.cancel(by).await?,
+                None => {}
+            }
+            self.tick().await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countdown::ManualClock;
+    use crate::event_bus::Subscription;
+    use crate::public::voice_interface::{Speaker, VoiceInterface};
+    use crate::public::voice_stream::STREAM_FRAME_SAMPLES;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_paused_listener_drops_audio_until_resumed() {
+        let (audio_tx, audio) = mpsc::channel(8);
+        let (listening_tx, listening) = watch::channel(false);
+        let (commands, _commands) = mpsc::channel(8);
+        let (stats_tx, mut stats) = watch::channel(StreamStats::default());
+        let (_settings, settings) = watch::channel(AppConfig::default());
+        let (_stop, shutdown) = watch::channel(false);
+        let mut listener = VoiceListener {
+            stream: VoiceStream::new(VoiceInterface::new("vosk-model-small-en-us-0.15")),
+            audio,
+            listening,
+            checking: watch::channel(false).1,
+            questions: mpsc::channel(1).1,
+            commands,
+            stats: stats_tx,
+            settings,
+            shutdown,
+        };
+        let handle = tokio::spawn(async move { listener.run().await });
+
+        // Wait for the listener to take the frame off the queue
+        audio_tx.send(vec![0; STREAM_FRAME_SAMPLES]).await.unwrap();
+        while audio_tx.capacity() < audio_tx.max_capacity() {
+            tokio::task::yield_now().await;
+        }
+        assert!(!stats.has_changed().unwrap());
+
+        listening_tx.send_replace(true);
+        audio_tx.send(vec![0; STREAM_FRAME_SAMPLES]).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(1), stats.changed()).await.unwrap().unwrap();
+        assert_eq!(stats.borrow().frames_processed, 1);
+
+        drop(audio_tx);
+        handle.await.unwrap().unwrap();
+    }
+
+    /// Speaker that keeps what it was asked to say
+    #[derive(Default)]
+    struct RecordingSpeaker {
+        spoken: Mutex<Vec<String>>,
+    }
+
+    impl Speaker for RecordingSpeaker {
+        fn speak(&self, text: &str) -> AppResult<()> {
+            self.spoken.lock().unwrap().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_listener_asks_and_hears_the_answer_while_paused() {
+        let speaker = Arc::new(RecordingSpeaker::default());
+        let (audio_tx, audio) = mpsc::channel(8);
+        let (_listening, listening) = watch::channel(false);
+        let (checking_tx, checking) = watch::channel(false);
+        let (questions_tx, questions) = mpsc::channel(1);
+        let (stats_tx, mut stats) = watch::channel(StreamStats::default());
+        let (_stop, shutdown) = watch::channel(false);
+        let mut listener = VoiceListener {
+            stream: VoiceStream::new(VoiceInterface::new("vosk-model-small-en-us-0.15").with_speaker(speaker.clone())),
+            audio,
+            listening,
+            checking,
+            questions,
+            commands: mpsc::channel(8).0,
+            stats: stats_tx,
+            settings: watch::channel(AppConfig::default()).1,
+            shutdown,
+        };
+        let handle = tokio::spawn(async move { listener.run().await });
+
+        // The check is heard although listening is paused
+        checking_tx.send_replace(true);
+        questions_tx.send(FALL_CHECK_QUESTION).await.unwrap();
+        audio_tx.send(vec![0; STREAM_FRAME_SAMPLES]).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(1), stats.changed()).await.unwrap().unwrap();
+        assert_eq!(stats.borrow_and_update().frames_processed, 1);
+        assert_eq!(*speaker.spoken.lock().unwrap(), [FALL_CHECK_QUESTION]);
+
+        // And dropped again once it ends
+        checking_tx.send_replace(false);
+        audio_tx.send(vec![0; STREAM_FRAME_SAMPLES]).await.unwrap();
+        while audio_tx.capacity() < audio_tx.max_capacity() {
+            tokio::task::yield_now().await;
+        }
+        assert!(!stats.has_changed().unwrap());
+
+        drop(audio_tx);
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stronger_countdown_upgrades_the_running_one() {
+        let clock = Arc::new(ManualClock::new());
+        let (commands, mut commands_rx) = mpsc::channel(8);
+        let (storage, mut timeline) = mpsc::channel(8);
+        let event_bus = EventBus::new();
+        let mut tap = event_bus.tap();
+        let mut task = CountdownTask {
+            clock: clock.clone(),
+            requests: mpsc::channel(1).1,
+            commands,
+            storage,
+            settings: watch::channel(AppConfig::default()).1,
+            event_bus,
+            checking: watch::channel(false).0,
+            questions: None,
+            shutdown: watch::channel(false).1,
+            active: None,
+        };
+        let fall = Fall { timestamp_ms: 0, impact_g: 4.0, free_fall_ms: 400 };
+        let impact = Impact { kind: ImpactKind::Crash, timestamp_ms: 0, peak_g: 12.0, speed_mph: 40.0 };
+        let spoken = |tap: &mut Subscription| -> Vec<String> {
+            tap.drain()
+                .into_iter()
+                .filter_map(|event| match event {
+                    AppEvent::CountdownPrompt { speech, .. } => Some(speech),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let check = || (CountdownAction::AlertContacts, Duration::from_secs(30), Some(FALL_CHECK_QUESTION), ProtocolCommand::Fall(fall.clone()));
+        let (action, duration, question, on_expiry) = check();
+        task.start("fall", action, duration, None, question, on_expiry).await.unwrap();
+        task.tick().await.unwrap();
+        clock.advance(Duration::from_secs(10));
+
+        // A crash that calls emergency services replaces the fall check, due when the check was
+        task.start("crash", CountdownAction::CallEmergencyServices, Duration::from_secs(60), Some("Crash detected."), None, ProtocolCommand::Crash(impact))
+            .await
+            .unwrap();
+        task.tick().await.unwrap();
+        let prompts = spoken(&mut tap);
+        assert!(prompts.last().unwrap().starts_with("Crash detected. Calling emergency services in 20 seconds"), "{:?}", prompts);
+
+        // Another fall check adds nothing to a call
+        let (action, duration, question, on_expiry) = check();
+        task.start("fall", action, duration, None, question, on_expiry).await.unwrap();
+
+        clock.advance(Duration::from_secs(20));
+        task.tick().await.unwrap();
+        assert!(matches!(commands_rx.try_recv(), Ok(ProtocolCommand::Crash(_))));
+        assert!(commands_rx.try_recv().is_err());
+        let Some(TimelineEntry::Countdown(record)) = timeline.recv().await else { panic!("expected the countdown record") };
+        assert_eq!(record.reason, "crash");
+        assert!(record.outcome.expired());
+    }
+}
@@ -0,0 +1,509 @@
+//! Application Runtime
+//!
+//! This module runs the Solana SOS subsystems as supervised tasks wired
+//! together by channels:
+//!
+//! ```text
+//! audio frames   -> voice listener   --+-> protocol engine -> storage
+//! key/tap events -> gesture listener --+         |         -> notifications (contact escalation) -> storage
+//!                                      |         |         -> calls (retries, then text and contacts) -> notifications, storage
+//! sensor samples -> sensor monitor ----+         |
+//!                        |             |         |
+//!                        |             +-> countdown (prompts, cancel or expiry) -> protocol engine
+//!                        +-> location tracker (best fix or step estimate, shared at adaptive intervals) -> notifications, storage
+//!                 connectivity monitor ----------+
+//!                 health monitor (diagnostics every minute)
+//!                 config watcher (live settings for every task)
+//! ```
+//!
+//! A supervisor restarts failed tasks with exponential backoff and reports
+//! failures through the shared [`AppStatus`] until the restarted task has run
+//! stably. Shutdown is graceful: the voice
+//! and gesture listeners and the sensor monitor stop first and each downstream
+//! task drains its queue before exiting, because it only stops once every
+//! upstream sender is gone.
+//!
+//! Each group of tasks lives in its own submodule: `detection` (listeners,
+//! sensor monitor, countdown), `protocol`, `calls`, `notifications`
+//! (escalation and location tracker), `storage`, `monitors` (connectivity,
+//! health, config) and `supervisor`.
+
+mod calls;
+mod detection;
+mod monitors;
+mod notifications;
+mod protocol;
+mod storage;
+mod supervisor;
+
+pub use calls::CallScripts;
+pub use notifications::NotificationRequest;
+pub use protocol::ProtocolCommand;
+pub use storage::{CancellationRecord, CountdownRecord, IncidentRecord, LocationRecord, TimelineEntry};
+pub use supervisor::{RestartPolicy, Subsystem};
+
+use crate::app::AppStatus;
+use crate::countdown::{CancelSource, MonotonicClock};
+use crate::crash::CrashDetector;
+use crate::dead_reckoning::{DeadReckoner, Estimate};
+use crate::dialer::{CallPolicy, CallReports, EmergencyDialer};
+use crate::diagnostics::HealthReport;
+use crate::emergency_numbers::{CountryHints, NumberSelection};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::{BackpressurePolicy, EventBus, EventKind};
+use crate::fall::FallDetector;
+use crate::gesture::{GestureRecognizer, InputEvent};
+use crate::location::{Fix, LastKnownStore, LocationManager, LocationReport};
+use crate::notifier::DeliveryRouter;
+use crate::public::emergency_interface::EmergencySystem;
+use crate::public::types::ConnectivityMode;
+use crate::public::voice_interface::{Speaker, VoiceInterface};
+use crate::public::voice_stream::{StreamStats, VoiceStream};
+use crate::sensors::SensorSample;
+use crate::contacts::{ContactBook, JsonContactStore, NotificationChannel};
+use crate::settings::LayeredConfig;
+use crate::SolanaSOS;
+use calls::{CallNotifier, CallTask};
+use detection::{CountdownRequest, CountdownTask, GestureListener, SensorMonitor, VoiceListener};
+use monitors::{ConfigWatcher, ConnectivityMonitor, HealthMonitor};
+use notifications::{LocationTracker, NotificationDispatcher};
+use protocol::{resolve_numbers, ProtocolEngine};
+use storage::StorageWriter;
+use supervisor::{drain, supervise, SupervisorHealth};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Queue depth for audio frames waiting for the voice listener
+const AUDIO_QUEUE_DEPTH: usize = 64;
+
+/// Queue depth for sensor samples waiting for the sensor monitor
+const SENSOR_QUEUE_DEPTH: usize = 256;
+
+/// Queue depth for fixes and estimates waiting for the location tracker
+const FIX_QUEUE_DEPTH: usize = 16;
+
+/// Queue depth for the protocol, storage and notification channels
+const WORK_QUEUE_DEPTH: usize = 32;
+
+/// How the host reaches people in an emergency
+pub struct HostServices {
+    /// Delivers alerts to contacts
+    pub notifier: DeliveryRouter,
+    /// Places calls to emergency services
+    pub dialer: Arc<dyn EmergencyDialer>,
+    /// Asks the user questions; without one they open the countdown prompts
+    pub speaker: Option<Arc<dyn Speaker>>,
+}
+
+/// Handle to the running subsystems
+pub struct AppRuntime {
+    handles: Vec<JoinHandle<()>>,
+    shutdown: watch::Sender<bool>,
+    listening: watch::Sender<bool>,
+    connectivity: watch::Receiver<ConnectivityMode>,
+    audio: Option<mpsc::Sender<Vec<i16>>>,
+    inputs: Option<mpsc::Sender<InputEvent>>,
+    sensors: Option<mpsc::Sender<SensorSample>>,
+    commands: Option<mpsc::Sender<ProtocolCommand>>,
+    countdowns: mpsc::Sender<CountdownRequest>,
+    location: watch::Receiver<Option<Fix>>,
+    estimate: watch::Receiver<Option<Estimate>>,
+    hints: watch::Receiver<CountryHints>,
+    notifier: Arc<DeliveryRouter>,
+    calls: CallReports,
+    scripts: CallScripts,
+    duress: watch::Sender<bool>,
+}
+
+impl AppRuntime {
+    /// Spawn all subsystems on the current Tokio runtime
+    ///
+    /// Alerts and calls go through the `host` services; emergency numbers
+    /// are picked by the best fix, then by the host's `hints`.
+    pub fn start(
+        settings: Arc<Mutex<LayeredConfig>>,
+        status: Arc<watch::Sender<AppStatus>>,
+        health_report: Arc<watch::Sender<HealthReport>>,
+        hints: watch::Receiver<CountryHints>,
+        event_bus: EventBus,
+        host: HostServices,
+        policy: RestartPolicy,
+    ) -> Self {
+        let (config, settings_rx) = {
+            let layered = settings.lock().unwrap_or_else(|e| e.into_inner());
+            (layered.effective().clone(), layered.subscribe())
+        };
+        let config = &config;
+
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (listening_tx, listening_rx) = watch::channel(true);
+        let (duress_tx, duress_rx) = watch::channel(false);
+        let (checking_tx, checking_rx) = watch::channel(false);
+        let (mode_tx, mode_rx) = watch::channel(ConnectivityMode::Offline);
+        let (audio_tx, audio_rx) = mpsc::channel(AUDIO_QUEUE_DEPTH);
+        let (input_tx, input_rx) = mpsc::channel(WORK_QUEUE_DEPTH);
+        let (sensor_tx, sensor_rx) = mpsc::channel(SENSOR_QUEUE_DEPTH);
+        let (position_tx, position_rx) = mpsc::channel(FIX_QUEUE_DEPTH);
+        let (best_tx, best_rx) = watch::channel(None);
+        let (estimate_tx, estimate_rx) = watch::channel(None);
+        let (shared_tx, shared_rx) = watch::channel(None);
+        let (incident_tx, incident_rx) = watch::channel(None);
+        let (summary_tx, summary_rx) = watch::channel(None);
+        let incident_tx = Arc::new(incident_tx);
+        let (command_tx, command_rx) = mpsc::channel(WORK_QUEUE_DEPTH);
+        let (countdown_tx, countdown_rx) = mpsc::channel(WORK_QUEUE_DEPTH);
+        let (record_tx, record_rx) = mpsc::channel(WORK_QUEUE_DEPTH);
+        let (notify_tx, notify_rx) = mpsc::channel(WORK_QUEUE_DEPTH);
+        let (call_tx, call_rx) = mpsc::channel(WORK_QUEUE_DEPTH);
+        let (call_reports, call_updates) = CallReports::new();
+        let (receipt_tx, receipt_rx) = mpsc::unbounded_channel();
+        let HostServices { notifier, dialer, speaker } = host;
+        let calls = Arc::new(CallNotifier {
+            calls: call_tx.downgrade(),
+            best: best_tx.subscribe(),
+            emergency_policy: CallPolicy::default(),
+        });
+        let notifier = Arc::new(notifier.route(NotificationChannel::Call, calls).with_receipts(receipt_tx));
+        let (stats_tx, stats_rx) = watch::channel(StreamStats::default());
+        let startup_warnings = health_report.borrow().warnings();
+
+        let health = Arc::new(SupervisorHealth::new(status));
+
+        let mut voice = VoiceInterface::new("vosk-model-small-en-us-0.15");
+        voice.update_config(config.voice.clone());
+        if let Some(speaker) = speaker {
+            voice = voice.with_speaker(speaker);
+        }
+        let (question_tx, question_rx) = mpsc::channel(WORK_QUEUE_DEPTH);
+        let question_tx = voice.can_ask().then_some(question_tx);
+
+        let mut sos = SolanaSOS::new();
+        if let Err(e) = sos.initialize() {
+            tracing::error!("Protocol engine failed to initialize: {}", e);
+        }
+        let contacts = ContactBook::new(&config.emergency.contact_region)
+            .and_then(|book| book.with_store(Box::new(JsonContactStore::new(&config.database.contacts_path))));
+        match contacts {
+            Ok(contacts) => sos.set_contact_book(contacts),
+            Err(e) => tracing::error!("Failed to load emergency contacts: {}", e),
+        }
+        let last_location = LastKnownStore::new(&config.database.last_location_path);
+        let last_known = last_location.load().unwrap_or_else(|e| {
+            tracing::warn!("Failed to load last known location: {}", e);
+            None
+        });
+        best_tx.send_replace(last_known);
+        // Steps are only counted from fixes taken while running
+        let anchor_rx = best_tx.subscribe();
+        let scripts = CallScripts {
+            summary: summary_rx,
+            location: best_rx.clone(),
+            estimate: estimate_rx.clone(),
+        };
+
+        let handles = vec![
+            supervise(
+                ConfigWatcher {
+                    settings,
+                    last_error: None,
+                    shutdown: shutdown_rx.clone(),
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                StorageWriter {
+                    path: config.database.emergency_history_path.clone().into(),
+                    records: record_rx,
+                    unsaved: None,
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                NotificationDispatcher {
+                    requests: notify_rx,
+                    events: event_bus.subscribe(
+                        Some(&[
+                            EventKind::AlertAcknowledged,
+                            EventKind::AlertsCancelled,
+                            EventKind::EmergencyEnded,
+                        ]),
+                        BackpressurePolicy::Unbounded,
+                    ),
+                    settings: settings_rx.clone(),
+                    notifier: notifier.clone(),
+                    receipts: receipt_rx,
+                    storage: record_tx.clone(),
+                    event_bus: event_bus.clone(),
+                    location: shared_rx,
+                    incident: incident_tx,
+                    duress: duress_rx.clone(),
+                    best: best_tx.subscribe(),
+                    hints: hints.clone(),
+                    escalation: None,
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                CallTask {
+                    requests: call_rx,
+                    queue: VecDeque::new(),
+                    updates: call_updates,
+                    reports: call_reports.clone(),
+                    dialer,
+                    notifier: notifier.clone(),
+                    notifications: notify_tx.clone(),
+                    storage: record_tx.clone(),
+                    settings: settings_rx.clone(),
+                    events: event_bus.subscribe(
+                        Some(&[EventKind::AlertsCancelled, EventKind::EmergencyEnded]),
+                        BackpressurePolicy::Unbounded,
+                    ),
+                    duress: duress_rx,
+                    event_bus: event_bus.clone(),
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                LocationTracker {
+                    manager: LocationManager::from_config(&config.emergency, last_known),
+                    cache: last_location,
+                    cached: last_known,
+                    positions: position_rx,
+                    events: event_bus.subscribe(Some(&[EventKind::LocationUpdated]), BackpressurePolicy::Unbounded),
+                    best: best_tx,
+                    estimate: estimate_tx,
+                    shared: shared_tx,
+                    incident: incident_rx,
+                    storage: record_tx.clone(),
+                    settings: settings_rx.clone(),
+                    shutdown: shutdown_rx.clone(),
+                    next_share: config.emergency.enable_location_sharing.then(tokio::time::Instant::now),
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                ProtocolEngine {
+                    sos,
+                    emergency: EmergencySystem::with_event_bus(event_bus.clone()),
+                    settings: settings_rx.clone(),
+                    commands: command_rx,
+                    connectivity: mode_rx.clone(),
+                    storage: record_tx.clone(),
+                    notifications: notify_tx,
+                    countdowns: countdown_tx.clone(),
+                    calls: call_tx,
+                    checking: checking_rx.clone(),
+                    best: best_rx.clone(),
+                    hints: hints.clone(),
+                    summary: summary_tx,
+                    scripts: scripts.clone(),
+                    active_emergency: None,
+                    last_incident: None,
+                    call_placed: false,
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                HealthMonitor {
+                    settings: settings_rx.clone(),
+                    listening: listening_rx.clone(),
+                    listening_since: Instant::now(),
+                    stream_stats: stats_rx,
+                    report: health_report,
+                    warnings: startup_warnings,
+                    event_bus: event_bus.clone(),
+                    shutdown: shutdown_rx.clone(),
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                ConnectivityMonitor::new(config, mode_tx, event_bus.clone(), shutdown_rx.clone()),
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                SensorMonitor {
+                    crashes: CrashDetector::from_config(&config.emergency),
+                    falls: FallDetector::from_config(&config.emergency),
+                    reckoner: DeadReckoner::new(),
+                    samples: sensor_rx,
+                    best: anchor_rx,
+                    positions: position_tx,
+                    countdowns: countdown_tx.clone(),
+                    settings: settings_rx.clone(),
+                    event_bus: event_bus.clone(),
+                    shutdown: shutdown_rx.clone(),
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                CountdownTask {
+                    clock: Arc::new(MonotonicClock::new()),
+                    requests: countdown_rx,
+                    commands: command_tx.clone(),
+                    storage: record_tx,
+                    settings: settings_rx.clone(),
+                    event_bus: event_bus.clone(),
+                    checking: checking_tx,
+                    questions: question_tx,
+                    shutdown: shutdown_rx.clone(),
+                    active: None,
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                GestureListener {
+                    recognizer: GestureRecognizer::from_config(&config.emergency),
+                    inputs: input_rx,
+                    commands: command_tx.clone(),
+                    settings: settings_rx.clone(),
+                    shutdown: shutdown_rx.clone(),
+                    last_input: None,
+                },
+                policy.clone(),
+                health.clone(),
+                shutdown_rx.clone(),
+            ),
+            supervise(
+                VoiceListener {
+                    stream: VoiceStream::new(voice),
+                    audio: audio_rx,
+                    listening: listening_rx,
+                    checking: checking_rx,
+                    questions: question_rx,
+                    commands: command_tx.clone(),
+                    stats: stats_tx,
+                    settings: settings_rx,
+                    shutdown: shutdown_rx.clone(),
+                },
+                policy,
+                health,
+                shutdown_rx,
+            ),
+        ];
+
+        Self {
+            handles,
+            shutdown: shutdown_tx,
+            listening: listening_tx,
+            connectivity: mode_rx,
+            audio: Some(audio_tx),
+            inputs: Some(input_tx),
+            sensors: Some(sensor_tx),
+            commands: Some(command_tx),
+            countdowns: countdown_tx,
+            location: best_rx,
+            estimate: estimate_rx,
+            hints,
+            notifier,
+            calls: call_reports,
+            scripts,
+            duress: duress_tx,
+        }
+    }
+
+    /// Get a sender for 16 kHz mono PCM audio frames
+    pub fn audio_sender(&self) -> Option<mpsc::Sender<Vec<i16>>> {
+        self.audio.clone()
+    }
+
+    /// Get a sender for timestamped key and tap events
+    pub fn input_sender(&self) -> Option<mpsc::Sender<InputEvent>> {
+        self.inputs.clone()
+    }
+
+    /// Get a sender for sensor samples, watched for crashes and falls
+    pub fn sensor_sender(&self) -> Option<mpsc::Sender<SensorSample>> {
+        self.sensors.clone()
+    }
+
+    /// Get a sender for protocol commands
+    pub fn command_sender(&self) -> Option<mpsc::Sender<ProtocolCommand>> {
+        self.commands.clone()
+    }
+
+    /// Stop the running countdown, if any
+    pub async fn cancel_countdown(&self, by: CancelSource) -> AppResult<()> {
+        self.countdowns
+            .send(CountdownRequest::Cancel(by))
+            .await
+            .map_err(|_| AppError::Internal("countdown task is not running".to_string()))
+    }
+
+    /// Get the best known location with its accuracy and staleness labels,
+    /// and where the user has likely walked since if GPS was lost
+    pub fn location(&self) -> Option<LocationReport> {
+        let estimate = *self.estimate.borrow();
+        self.location.borrow().map(|fix| fix.report(chrono::Utc::now()).with_estimate(estimate))
+    }
+
+    /// Get the emergency numbers of the country the phone is in, falling
+    /// back to `region` when nothing else tells
+    pub fn emergency_numbers(&self, region: &str) -> NumberSelection {
+        resolve_numbers(&self.hints.borrow(), *self.location.borrow(), region)
+    }
+
+    /// Get the router delivering alerts to contacts
+    pub fn notifier(&self) -> Arc<DeliveryRouter> {
+        self.notifier.clone()
+    }
+
+    /// Get the writer of dispatcher call scripts for the incident in progress
+    pub fn call_scripts(&self) -> CallScripts {
+        self.scripts.clone()
+    }
+
+    /// Get the reporter the host's call states go to
+    pub fn call_reports(&self) -> CallReports {
+        self.calls.clone()
+    }
+
+    /// Set or clear duress: while set, cancels don't stand contacts down
+    pub fn set_duress(&self, duress: bool) {
+        self.duress.send_replace(duress);
+    }
+
+    /// Pause or resume voice listening
+    pub fn set_listening(&self, listening: bool) {
+        self.listening.send_replace(listening);
+    }
+
+    /// Get the current connectivity mode
+    pub fn connectivity(&self) -> ConnectivityMode {
+        self.connectivity.borrow().clone()
+    }
+
+    /// Stop listening, drain in-flight work and wait for every task to exit
+    ///
+    /// Tasks still running after `timeout` are aborted.
+    pub async fn shutdown(mut self, timeout: Duration) -> AppResult<()> {
+        self.audio = None;
+        self.inputs = None;
+        self.sensors = None;
+        self.commands = None;
+        self.shutdown.send_replace(true);
+        drain(&mut self.handles, timeout).await
+    }
+}
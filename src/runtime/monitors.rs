@@ -0,0 +1,221 @@
+//! Monitors
+//!
+//! Background tasks that watch connectivity, health and the config file.
+
+use crate::config::AppConfig;
+use crate::diagnostics::{Diagnostics, HealthReport, MicState, HEALTH_CHECK_INTERVAL};
+use crate::error::AppResult;
+use crate::event_bus::{AppEvent, EventBus};
+use crate::public::types::ConnectivityMode;
+use crate::public::voice_stream::StreamStats;
+use crate::settings::{LayeredConfig, RELOAD_POLL_INTERVAL};
+use super::supervisor::Subsystem;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Connectivity monitoring task: probes the network and publishes the mode
+pub(super) struct ConnectivityMonitor {
+    pub(super) preference: String,
+    pub(super) probe_target: Option<(String, u16)>,
+    pub(super) check_interval: Duration,
+    pub(super) network_timeout: Duration,
+    pub(super) offline_fallback: bool,
+    pub(super) mode: watch::Sender<ConnectivityMode>,
+    pub(super) event_bus: EventBus,
+    pub(super) shutdown: watch::Receiver<bool>,
+}
+
+impl ConnectivityMonitor {
+    pub(super) fn new(
+        config: &AppConfig,
+        mode: watch::Sender<ConnectivityMode>,
+        event_bus: EventBus,
+        shutdown: watch::Receiver<bool>,
+    ) -> Self {
+        let connectivity = &config.connectivity;
+        Self {
+            preference: connectivity.user_preference.clone().unwrap_or_else(|| "hybrid".to_string()),
+            probe_target: connectivity.llm_endpoints.first().and_then(|url| probe_target(url)),
+            check_interval: Duration::from_secs(connectivity.check_interval.max(1)),
+            network_timeout: Duration::from_secs(connectivity.network_timeout.max(1)),
+            offline_fallback: connectivity.offline_fallback,
+            mode,
+            event_bus,
+            shutdown,
+        }
+    }
+
+    async fn probe(&self) -> bool {
+        let Some((host, port)) = &self.probe_target else {
+            return false;
+        };
+        matches!(
+            tokio::time::timeout(self.network_timeout, tokio::net::TcpStream::connect((host.as_str(), *port))).await,
+            Ok(Ok(_))
+        )
+    }
+}
+
+/// Extract host and port from an endpoint URL
+pub(super) fn probe_target(url: &str) -> Option<(String, u16)> {
+    let (default_port, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (443, rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (80, rest)
+    } else {
+        return None;
+    };
+
+    let authority = rest.split('/').next()?;
+    match authority.rsplit_once(':') {
+        Some((host, port)) => Some((host.to_string(), port.parse().ok()?)),
+        None if !authority.is_empty() => Some((authority.to_string(), default_port)),
+        None => None,
+    }
+}
+
+impl Subsystem for ConnectivityMonitor {
+    fn name(&self) -> &'static str {
+        "connectivity"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        if self.preference == "offline" {
+            self.mode.send_replace(ConnectivityMode::Offline);
+            let _ = self.shutdown.wait_for(|stop| *stop).await;
+            return Ok(());
+        }
+
+        let mut interval = tokio::time::interval(self.check_interval);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.shutdown.wait_for(|stop| *stop) => return Ok(()),
+                _ = interval.tick() => {}
+            }
+
+            let reachable = self.probe().await;
+            let mode = match (self.preference.as_str(), reachable) {
+                ("online", true) => ConnectivityMode::Online,
+                ("online", false) if !self.offline_fallback => ConnectivityMode::Online,
+                (_, true) => ConnectivityMode::Hybrid,
+                (_, false) => ConnectivityMode::Offline,
+            };
+
+            let changed = self.mode.send_if_modified(|current| {
+                if *current == mode {
+                    return false;
+                }
+                tracing::info!("Connectivity changed: {} -> {}", current.display_name(), mode.display_name());
+                *current = mode.clone();
+                true
+            });
+            if changed {
+                self.event_bus.publish(AppEvent::ConnectivityChanged { mode });
+            }
+        }
+    }
+}
+
+/// Health monitoring task: re-runs diagnostics and reports degradation
+pub(super) struct HealthMonitor {
+    pub(super) settings: watch::Receiver<AppConfig>,
+    pub(super) listening: watch::Receiver<bool>,
+    pub(super) listening_since: Instant,
+    pub(super) stream_stats: watch::Receiver<StreamStats>,
+    pub(super) report: Arc<watch::Sender<HealthReport>>,
+    pub(super) warnings: Vec<String>,
+    pub(super) event_bus: EventBus,
+    pub(super) shutdown: watch::Receiver<bool>,
+}
+
+impl HealthMonitor {
+    fn mic_state(&mut self) -> MicState {
+        if self.listening.has_changed().unwrap_or(false) && *self.listening.borrow_and_update() {
+            self.listening_since = Instant::now();
+        }
+        if !*self.listening.borrow() {
+            return MicState::Paused;
+        }
+        MicState::Listening {
+            since: self.listening_since,
+            stats: self.stream_stats.borrow().clone(),
+        }
+    }
+}
+
+impl Subsystem for HealthMonitor {
+    fn name(&self) -> &'static str {
+        "health"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.shutdown.wait_for(|stop| *stop) => return Ok(()),
+                _ = interval.tick() => {}
+            }
+
+            let mic = self.mic_state();
+            let report = Diagnostics::from_config(&self.settings.borrow()).run(&mic);
+            let warnings = report.warnings();
+            self.report.send_replace(report);
+
+            // Only changes are surfaced, so a lasting fault warns once
+            if warnings != self.warnings {
+                if warnings.is_empty() {
+                    tracing::info!("Safety net restored");
+                }
+                for warning in &warnings {
+                    tracing::warn!("Safety net degraded: {}", warning);
+                }
+                self.event_bus.publish(AppEvent::HealthChanged { warnings: warnings.clone() });
+                self.warnings = warnings;
+            }
+        }
+    }
+}
+
+/// Config watching task: reloads changed settings for the other tasks
+pub(super) struct ConfigWatcher {
+    pub(super) settings: Arc<Mutex<LayeredConfig>>,
+    pub(super) last_error: Option<String>,
+    pub(super) shutdown: watch::Receiver<bool>,
+}
+
+impl Subsystem for ConfigWatcher {
+    fn name(&self) -> &'static str {
+        "config"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        let mut interval = tokio::time::interval(RELOAD_POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.shutdown.wait_for(|stop| *stop) => return Ok(()),
+                _ = interval.tick() => {}
+            }
+
+            let result = self.settings.lock().unwrap_or_else(|e| e.into_inner()).reload();
+            match result {
+                Ok(true) => {
+                    tracing::info!("Configuration reloaded");
+                    self.last_error = None;
+                }
+                Ok(false) => self.last_error = None,
+                // Invalid edits keep the previous settings; report each problem once
+                Err(e) => {
+                    let message = e.to_string();
+                    if self.last_error.as_ref() != Some(&message) {
+                        tracing::warn!("Keeping previous configuration: {}", message);
+                        self.last_error = Some(message);
+                    }
+                }
+            }
+        }
+    }
+}
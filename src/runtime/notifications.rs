@@ -0,0 +1,313 @@
+//! Notifications
+//!
+//! Escalation through the trusted network, and the location tracker that
+//! shares the best fix with it.
+
+use crate::config::AppConfig;
+use crate::dead_reckoning::Estimate;
+use crate::emergency_numbers::CountryHints;
+use crate::error::{AppError, AppResult};
+use crate::escalation::{Escalation, EscalationPolicy, EscalationSignal};
+use crate::event_bus::{AppEvent, EventBus, Subscription};
+use crate::location::{Fix, LastKnownStore, LocationManager, LocationReport};
+use crate::notifier::{DeliveryReceipt, DeliveryRouter};
+use crate::contacts::Contact;
+use crate::settings::ConfigTarget;
+use crate::templates::{AlertContext, AlertKind, AlertTemplates};
+use super::detection::Position;
+use super::protocol::resolve_numbers;
+use super::storage::{LocationRecord, TimelineEntry};
+use super::supervisor::Subsystem;
+use super::WORK_QUEUE_DEPTH;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
+
+/// Notification request handled by the notification task
+#[derive(Debug, Clone)]
+pub struct NotificationRequest {
+    /// Incident the notification belongs to
+    pub incident_id: String,
+    /// Facts the alert messages are rendered from
+    pub context: AlertContext,
+    /// Contacts to escalate through
+    pub contacts: Vec<Contact>,
+}
+
+/// Location task: keeps the best fix and shares it with contacts
+///
+/// Fixes come from location samples, passed on by the sensor monitor, and
+/// from `LocationUpdated` events; step estimates from the sensor monitor
+/// extend the best fix while GPS is lost. While `enable_location_sharing` is on, the
+/// best fix is shared with escalations at the manager's adaptive interval,
+/// and at once when an escalation starts. Shares during an incident are
+/// added to its timeline. Good fixes are cached as the last known location
+/// when shared and on shutdown.
+pub(super) struct LocationTracker {
+    pub(super) manager: LocationManager,
+    pub(super) cache: LastKnownStore,
+    pub(super) cached: Option<Fix>,
+    pub(super) positions: mpsc::Receiver<Position>,
+    pub(super) events: Subscription,
+    /// Best fix, for the host and the sensor monitor
+    pub(super) best: watch::Sender<Option<Fix>>,
+    /// Estimate from the best fix, for the host
+    pub(super) estimate: watch::Sender<Option<Estimate>>,
+    /// Latest share, for escalations
+    pub(super) shared: watch::Sender<Option<LocationReport>>,
+    /// Incident being escalated, if any
+    pub(super) incident: watch::Receiver<Option<String>>,
+    pub(super) storage: mpsc::Sender<TimelineEntry>,
+    pub(super) settings: watch::Receiver<AppConfig>,
+    pub(super) shutdown: watch::Receiver<bool>,
+    pub(super) next_share: Option<tokio::time::Instant>,
+}
+
+impl LocationTracker {
+    fn sharing(&self) -> bool {
+        self.settings.borrow().emergency.enable_location_sharing
+    }
+
+    async fn offer(&mut self, fix: Fix) -> AppResult<()> {
+        if !self.manager.update(fix, chrono::Utc::now()) {
+            return Ok(());
+        }
+        self.best.send_replace(Some(fix));
+        self.estimate.send_replace(None);
+        // The first fix is shared without waiting for the interval
+        if self.shared.borrow().is_none() && self.sharing() {
+            self.share().await?;
+        }
+        Ok(())
+    }
+
+    fn walked(&mut self, estimate: Estimate) {
+        let now = chrono::Utc::now();
+        self.manager.estimate(estimate, now);
+        self.estimate.send_replace(self.manager.report(now).and_then(|report| report.estimate));
+    }
+
+    async fn share(&mut self) -> AppResult<()> {
+        if !self.sharing() {
+            self.next_share = None;
+            return Ok(());
+        }
+        let now = chrono::Utc::now();
+        if let Some(report) = self.manager.share(now) {
+            self.shared.send_replace(Some(report));
+            let incident = self.incident.borrow().clone();
+            if let Some(incident_id) = incident {
+                tracing::info!("Location shared for incident {}: {}", incident_id, report.label());
+                let record = LocationRecord { timestamp: now, incident_id, report };
+                self.storage
+                    .send(TimelineEntry::Location(record))
+                    .await
+                    .map_err(|_| AppError::Internal("storage task is not running".to_string()))?;
+            }
+            self.save_last_known();
+        }
+        self.next_share = Some(tokio::time::Instant::now() + self.manager.share_interval());
+        Ok(())
+    }
+
+    fn save_last_known(&mut self) {
+        let Some(best) = self.manager.best().filter(|best| Some(*best) != self.cached) else {
+            return;
+        };
+        match self.cache.save(&best) {
+            Ok(true) => self.cached = Some(best),
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to cache last known location: {}", e),
+        }
+    }
+}
+
+impl Subsystem for LocationTracker {
+    fn name(&self) -> &'static str {
+        "location"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        loop {
+            let due = self.next_share;
+            let position = tokio::select! {
+                biased;
+                _ = self.shutdown.wait_for(|stop| *stop) => break,
+                Ok(()) = self.settings.changed() => {
+                    let config = self.settings.borrow_and_update().clone();
+                    self.manager.apply_config(&config);
+                    if !config.emergency.enable_location_sharing {
+                        self.shared.send_replace(None);
+                        self.next_share = None;
+                    } else if self.next_share.is_none() {
+                        self.next_share = Some(tokio::time::Instant::now());
+                    }
+                    continue;
+                }
+                Ok(()) = self.incident.changed() => {
+                    if self.incident.borrow_and_update().is_none() {
+                        continue;
+                    }
+                    // Record where the incident started, even if nothing moved
+                    self.manager.reset_sharing();
+                    None
+                }
+                _ = tokio::time::sleep_until(due.unwrap_or_else(tokio::time::Instant::now)), if due.is_some() => None,
+                position = self.positions.recv() => match position {
+                    Some(position) => Some(position),
+                    None => break,
+                },
+                Some(event) = self.events.recv() => match event {
+                    AppEvent::LocationUpdated { latitude, longitude, accuracy_m } => {
+                        match Fix::new(latitude, longitude, accuracy_m, chrono::Utc::now()) {
+                            Some(fix) => Some(Position::Fix(fix)),
+                            None => continue,
+                        }
+                    }
+                    _ => continue,
+                },
+            };
+            match position {
+                Some(Position::Fix(fix)) => self.offer(fix).await?,
+                Some(Position::Estimate(estimate)) => self.walked(estimate),
+                None => self.share().await?,
+            }
+        }
+        self.save_last_known();
+        Ok(())
+    }
+}
+
+/// Notification task: escalates incidents through the trusted network
+///
+/// One escalation runs at a time; requests that arrive while it runs belong
+/// to the same emergency. Acknowledgements and cancels arrive on the event
+/// bus and are forwarded to the running escalation, which also follows the
+/// location tracker's shares; the tracker is told which incident runs.
+/// Delivery receipts are added to the incident timeline, and the router
+/// forgets an incident's alerts once its escalation ends. While the duress
+/// flag is set, cancels are ignored and a duress request turns the running
+/// escalation covert instead of starting another.
+pub(super) struct NotificationDispatcher {
+    pub(super) requests: mpsc::Receiver<NotificationRequest>,
+    pub(super) events: Subscription,
+    pub(super) settings: watch::Receiver<AppConfig>,
+    pub(super) notifier: Arc<DeliveryRouter>,
+    pub(super) receipts: mpsc::UnboundedReceiver<DeliveryReceipt>,
+    pub(super) storage: mpsc::Sender<TimelineEntry>,
+    pub(super) event_bus: EventBus,
+    pub(super) location: watch::Receiver<Option<LocationReport>>,
+    pub(super) incident: Arc<watch::Sender<Option<String>>>,
+    pub(super) duress: watch::Receiver<bool>,
+    pub(super) best: watch::Receiver<Option<Fix>>,
+    pub(super) hints: watch::Receiver<CountryHints>,
+    pub(super) escalation: Option<mpsc::Sender<EscalationSignal>>,
+}
+
+impl NotificationDispatcher {
+    async fn handle(&mut self, request: NotificationRequest) {
+        if request.context.kind == AlertKind::Duress {
+            if let Some(signals) = self.escalation.as_ref().filter(|signals| !signals.is_closed()) {
+                let _ = signals.send(EscalationSignal::Duress).await;
+                return;
+            }
+        }
+        self.start_escalation(request);
+    }
+
+    fn start_escalation(&mut self, request: NotificationRequest) {
+        if self.escalation.as_ref().is_some_and(|signals| !signals.is_closed()) {
+            return;
+        }
+        let (emergency, language) = {
+            let settings = self.settings.borrow();
+            (settings.emergency.clone(), settings.ui.language.clone())
+        };
+        if !emergency.enable_family_alerts {
+            tracing::info!("Family alerts are off; not alerting contacts for incident {}", request.incident_id);
+            return;
+        }
+
+        let numbers = resolve_numbers(&self.hints.borrow(), *self.best.borrow(), &emergency.contact_region);
+        let (signals, signals_rx) = mpsc::channel(WORK_QUEUE_DEPTH);
+        let escalation = Escalation::new(
+            &request.incident_id,
+            request.context,
+            request.contacts,
+            EscalationPolicy::from_config(&emergency, numbers.numbers.ambulance),
+            self.notifier.clone(),
+        )
+        .with_templates(AlertTemplates::for_language(&language))
+        .with_event_bus(self.event_bus.clone());
+        let location = self.location.clone();
+        let incident = self.incident.clone();
+        let notifier = self.notifier.clone();
+        let incident_id = request.incident_id.clone();
+        incident.send_replace(Some(incident_id.clone()));
+        tokio::spawn(async move {
+            let report = escalation.run(signals_rx, location).await;
+            notifier.forget_incident(&incident_id);
+            incident.send_if_modified(|current| {
+                let ended = current.as_deref() == Some(incident_id.as_str());
+                if ended {
+                    *current = None;
+                }
+                ended
+            });
+            let failed = report.attempts.iter().filter(|attempt| attempt.error.is_some()).count();
+            tracing::info!(
+                "Incident {}: {} alert attempts, {} failed, outcome {:?}",
+                report.incident_id,
+                report.attempts.len(),
+                failed,
+                report.outcome
+            );
+        });
+        self.escalation = Some(signals);
+    }
+
+    async fn forward(&mut self, event: AppEvent) {
+        let signal = match event {
+            AppEvent::AlertAcknowledged { contact } => EscalationSignal::Acknowledged { by: contact },
+            // The cancel the user was forced into; alerts carry on
+            AppEvent::AlertsCancelled | AppEvent::EmergencyEnded if *self.duress.borrow() => return,
+            AppEvent::AlertsCancelled | AppEvent::EmergencyEnded => EscalationSignal::Cancelled,
+            _ => return,
+        };
+        if let Some(signals) = self.escalation.take() {
+            // A finished escalation has dropped its receiver; nothing to stop
+            let _ = signals.send(signal).await;
+        }
+    }
+
+    async fn record(&self, receipt: DeliveryReceipt) -> AppResult<()> {
+        self.storage
+            .send(TimelineEntry::Delivery(receipt))
+            .await
+            .map_err(|_| AppError::Internal("storage task is not running".to_string()))
+    }
+}
+
+impl Subsystem for NotificationDispatcher {
+    fn name(&self) -> &'static str {
+        "notifications"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        loop {
+            tokio::select! {
+                request = self.requests.recv() => match request {
+                    Some(request) => self.handle(request).await,
+                    // A running escalation carries on; alerts must not stop with the app
+                    None => {
+                        while let Ok(receipt) = self.receipts.try_recv() {
+                            self.record(receipt).await?;
+                        }
+                        return Ok(());
+                    }
+                },
+                Some(event) = self.events.recv() => self.forward(event).await,
+                Some(receipt) = self.receipts.recv() => self.record(receipt).await?,
+            }
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Protocol Engine
+//!
+//! Turns detections, gestures, crashes and falls into guidance, calls,
+//! incident records and alerts.
+
+use crate::call_script::IncidentSummary;
+use crate::config::AppConfig;
+use crate::countdown::{CancelSource, CountdownAction};
+use crate::crash::Impact;
+use crate::dialer::CallPolicy;
+use crate::duress::{self, CancelMethod};
+use crate::emergency_numbers::{CountryHints, NumberSelection};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::{AppEvent, DetectionSource};
+use crate::fall::Fall;
+use crate::gesture::SilentSosTrigger;
+use crate::location::Fix;
+use crate::public::emergency_interface::EmergencySystem;
+use crate::public::types::{ConnectivityMode, EmergencyType};
+use crate::public::voice_stream::{DetectionKind, VoiceDetection};
+use crate::templates::{AlertContext, AlertKind};
+use crate::SolanaSOS;
+use super::calls::{CallFollowUp, CallRequest, CallScripts};
+use super::detection::CountdownRequest;
+use super::notifications::NotificationRequest;
+use super::storage::{CancellationRecord, IncidentRecord, TimelineEntry};
+use super::supervisor::Subsystem;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+/// Work item for the protocol engine
+#[derive(Debug, Clone)]
+pub enum ProtocolCommand {
+    /// A detection from the voice listener
+    Detection(VoiceDetection),
+    /// An emergency started from the UI or a host application
+    Manual {
+        /// Emergency type key, e.g. "drowning"
+        emergency_type: String,
+        /// What the user said or typed
+        user_input: String,
+    },
+    /// A silent SOS gesture from the gesture listener
+    SilentSos(SilentSosTrigger),
+    /// A crash the user didn't cancel in time
+    Crash(Impact),
+    /// A fall the user didn't answer in time
+    Fall(Fall),
+    /// The automatic emergency call countdown ran out
+    CallEmergencyServices,
+    /// The user entered a cancel code
+    Cancel {
+        /// How the code was entered
+        method: CancelMethod,
+        /// Whether it was the duress code
+        duress: bool,
+    },
+}
+
+/// Pick the country to call emergency services in, by the best fix first
+pub(super) fn resolve_numbers(hints: &CountryHints, best: Option<Fix>, region: &str) -> NumberSelection {
+    let location = best.map(|fix| (fix.latitude, fix.longitude)).or(hints.location);
+    CountryHints { location, ..hints.clone() }.resolve(region)
+}
+
+/// Protocol engine task: turns detections into guidance, calls, records and alerts
+///
+/// Calls go to the ambulance number of the country the phone is in, picked
+/// from the best fix and the host's SIM and locale hints, and are handed to
+/// the call task. The engine keeps an [`IncidentSummary`] of the incident in
+/// progress for dispatcher call scripts.
+pub(super) struct ProtocolEngine {
+    pub(super) sos: SolanaSOS,
+    pub(super) emergency: EmergencySystem,
+    pub(super) settings: watch::Receiver<AppConfig>,
+    pub(super) commands: mpsc::Receiver<ProtocolCommand>,
+    pub(super) connectivity: watch::Receiver<ConnectivityMode>,
+    pub(super) storage: mpsc::Sender<TimelineEntry>,
+    pub(super) notifications: mpsc::Sender<NotificationRequest>,
+    pub(super) countdowns: mpsc::Sender<CountdownRequest>,
+    pub(super) calls: mpsc::Sender<CallRequest>,
+    /// Set while a countdown is waiting for the cancel phrase
+    pub(super) checking: watch::Receiver<bool>,
+    pub(super) best: watch::Receiver<Option<Fix>>,
+    pub(super) hints: watch::Receiver<CountryHints>,
+    /// What is known about the incident in progress, for call scripts
+    pub(super) summary: watch::Sender<Option<IncidentSummary>>,
+    pub(super) scripts: CallScripts,
+    pub(super) active_emergency: Option<String>,
+    pub(super) last_incident: Option<String>,
+    pub(super) call_placed: bool,
+}
+
+impl ProtocolEngine {
+    async fn handle(&mut self, command: ProtocolCommand) -> AppResult<()> {
+        let (emergency_type, user_input, source) = match command {
+            ProtocolCommand::SilentSos(trigger) => return self.silent_sos(trigger).await,
+            ProtocolCommand::Crash(impact) => return self.crash(impact).await,
+            ProtocolCommand::Fall(fall) => return self.fall(fall).await,
+            ProtocolCommand::CallEmergencyServices => return self.dial().await,
+            ProtocolCommand::Cancel { method, duress } => return self.cancel(method, duress).await,
+            ProtocolCommand::Manual { emergency_type, user_input } => (emergency_type, user_input, DetectionSource::Manual),
+            ProtocolCommand::Detection(detection) => match detection.kind {
+                DetectionKind::Speech if self.answers_check(&detection.text) => return self.spoken_cancel().await,
+                DetectionKind::EmergencyPhrase(phrase) => (phrase.replace(' ', "_"), detection.text, DetectionSource::Voice),
+                // Follow-up speech refines the emergency already in progress
+                DetectionKind::Speech => match &self.active_emergency {
+                    Some(active) => (active.clone(), detection.text, DetectionSource::Voice),
+                    None => return Ok(()),
+                },
+                DetectionKind::WakeWord => {
+                    tracing::info!("Wake word heard, waiting for emergency phrase");
+                    return Ok(());
+                }
+            },
+        };
+
+        if self.active_emergency.as_deref() != Some(emergency_type.as_str()) {
+            if let Some(kind) = EmergencyType::from_key(&emergency_type) {
+                self.emergency.initiate_emergency_response_from(kind, source)?;
+            }
+            self.active_emergency = Some(emergency_type.clone());
+            self.call_placed = false;
+            let summary = IncidentSummary::new(AlertKind::Emergency, &self.settings.borrow().emergency.owner_name)
+                .with_emergency_type(EmergencyType::from_key(&emergency_type));
+            self.summary.send_replace(Some(summary));
+        }
+
+        let response = self.sos.process_emergency(&emergency_type, &user_input).await;
+        self.emergency.event_bus().publish(AppEvent::GuidanceIssued {
+            instruction: response.instruction.clone(),
+        });
+        self.summarize(|summary| {
+            summary.flag(&response.context_flags);
+            summary.instruction = Some(response.instruction.clone());
+        });
+
+        let auto_dial = self.settings.borrow().emergency.auto_dial_911;
+        let dial = response.should_call_emergency_services && auto_dial && !self.call_placed;
+
+        let record = IncidentRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            emergency_type,
+            instruction: response.instruction,
+            should_call_emergency_services: response.should_call_emergency_services,
+            context_flags: response.context_flags,
+            connectivity: self.connectivity.borrow().display_name().to_string(),
+        };
+
+        if record.should_call_emergency_services {
+            let mut context = AlertContext::new(AlertKind::Emergency, &self.settings.borrow().emergency.owner_name);
+            context.emergency_type = EmergencyType::from_key(&record.emergency_type);
+            self.notify(&record.id, context).await?;
+        }
+        self.last_incident = Some(record.id.clone());
+        if dial {
+            self.call_placed = true;
+            self.dial_after_countdown().await?;
+        }
+        self.store(TimelineEntry::Incident(record)).await
+    }
+
+    /// Respond to a crash the user didn't cancel: call for help and alert contacts
+    async fn crash(&mut self, impact: Impact) -> AppResult<()> {
+        self.emergency.initiate_emergency_response_from(EmergencyType::Trauma, DetectionSource::CrashDetection)?;
+        self.active_emergency = Some("trauma".to_string());
+        self.call_placed = false;
+
+        let record = IncidentRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            emergency_type: "car_crash".to_string(),
+            instruction: String::new(),
+            should_call_emergency_services: true,
+            context_flags: vec![
+                "crash_detected".to_string(),
+                format!("peak_g:{:.1}", impact.peak_g),
+                format!("speed_mph:{:.0}", impact.speed_mph),
+            ],
+            connectivity: self.connectivity.borrow().display_name().to_string(),
+        };
+        let context = AlertContext::new(AlertKind::CrashDetected, &self.settings.borrow().emergency.owner_name);
+        let mut summary = IncidentSummary::new(AlertKind::CrashDetected, &context.name).with_emergency_type(Some(EmergencyType::Trauma));
+        // Nobody cancelled the countdown
+        summary.flag(&record.context_flags);
+        summary.flag(&["no_response".to_string()]);
+        self.summary.send_replace(Some(summary));
+        self.last_incident = Some(record.id.clone());
+        if self.settings.borrow().emergency.auto_dial_911 {
+            self.place_call(context.clone(), Some(EmergencyType::Trauma)).await?;
+            self.call_placed = true;
+        }
+        self.notify(&record.id, context).await?;
+        self.store(TimelineEntry::Incident(record)).await
+    }
+
+    /// Count down `auto_dial_countdown` seconds, then call emergency services
+    async fn dial_after_countdown(&mut self) -> AppResult<()> {
+        let secs = self.settings.borrow().emergency.auto_dial_countdown;
+        let request = CountdownRequest::Start {
+            reason: "auto_dial",
+            action: CountdownAction::CallEmergencyServices,
+            duration: Duration::from_secs(secs),
+            intro: None,
+            question: None,
+            on_expiry: ProtocolCommand::CallEmergencyServices,
+        };
+        if self.countdowns.send(request).await.is_err() {
+            // Shutting down: the call must not depend on the countdown task
+            tracing::warn!("Countdown task unavailable; calling emergency services now");
+            return self.dial().await;
+        }
+        Ok(())
+    }
+
+    /// Call emergency services at the end of the auto-dial countdown, unless cancelled since
+    async fn dial(&mut self) -> AppResult<()> {
+        let Some(active) = self.active_emergency.clone() else {
+            return Ok(());
+        };
+        let emergency_type = EmergencyType::from_key(&active);
+        let mut context = AlertContext::new(AlertKind::Emergency, &self.settings.borrow().emergency.owner_name);
+        context.emergency_type = emergency_type.clone();
+        self.place_call(context, emergency_type).await
+    }
+
+    /// Hand a call for the current incident to the call task
+    ///
+    /// With `automated_voice_message` on, the call script is spoken into
+    /// calls for a silent SOS or a crash nobody cancelled.
+    async fn place_call(&mut self, context: AlertContext, emergency_type: Option<EmergencyType>) -> AppResult<()> {
+        let speak = self.settings.borrow().emergency.automated_voice_message
+            && matches!(context.kind, AlertKind::SilentSos | AlertKind::CrashDetected);
+        let message = speak.then(|| self.scripts.script(None)).flatten().map(|script| script.spoken);
+        let covert = context.kind.is_covert();
+        let request = CallRequest {
+            incident_id: self.last_incident.get_or_insert_with(|| uuid::Uuid::new_v4().to_string()).clone(),
+            number: self.emergency_number().to_string(),
+            emergency_type,
+            location: *self.best.borrow(),
+            message,
+            covert,
+            policy: CallPolicy::default(),
+            urgent: true,
+            follow_up: CallFollowUp::FallBack {
+                context: Box::new(context),
+                contacts: self.sos.get_emergency_contacts().to_vec(),
+            },
+        };
+        self.calls
+            .send(request)
+            .await
+            .map_err(|_| AppError::Internal("call task is not running".to_string()))
+    }
+
+    /// The ambulance number of the country the phone is in
+    ///
+    /// Every emergency the engine handles is medical, and where police, fire
+    /// and ambulance have separate numbers the ambulance dispatcher is the
+    /// one to reach.
+    fn emergency_number(&self) -> &'static str {
+        let selection = resolve_numbers(
+            &self.hints.borrow(),
+            *self.best.borrow(),
+            &self.settings.borrow().emergency.contact_region,
+        );
+        tracing::info!(
+            "Emergency services in {} (from {:?}): {}",
+            selection.country,
+            selection.source,
+            selection.numbers.ambulance
+        );
+        selection.numbers.ambulance
+    }
+
+    /// Alert contacts about a fall the user didn't answer
+    ///
+    /// Nobody is called: the trusted contacts check on the user first.
+    async fn fall(&mut self, fall: Fall) -> AppResult<()> {
+        tracing::warn!("No answer to the fall check; alerting contacts");
+        let record = IncidentRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            emergency_type: "fall".to_string(),
+            instruction: String::new(),
+            should_call_emergency_services: false,
+            context_flags: vec![
+                "fall_detected".to_string(),
+                "no_response".to_string(),
+                format!("impact_g:{:.1}", fall.impact_g),
+            ],
+            connectivity: self.connectivity.borrow().display_name().to_string(),
+        };
+        let context = AlertContext::new(AlertKind::FallDetected, &self.settings.borrow().emergency.owner_name);
+        let mut summary = IncidentSummary::new(AlertKind::FallDetected, &context.name);
+        summary.flag(&record.context_flags);
+        self.summary.send_replace(Some(summary));
+        self.notify(&record.id, context).await?;
+        self.last_incident = Some(record.id.clone());
+        self.store(TimelineEntry::Incident(record)).await
+    }
+
+    /// Whether speech answers a running countdown with the cancel phrase
+    fn answers_check(&self, speech: &str) -> bool {
+        *self.checking.borrow() && duress::spoken(speech, &self.settings.borrow().emergency.crash_cancel_phrase)
+    }
+
+    /// Cancel a running countdown the user answered out loud
+    async fn spoken_cancel(&mut self) -> AppResult<()> {
+        tracing::info!("Cancel phrase heard");
+        if self.countdowns.send(CountdownRequest::Cancel(CancelSource::Phrase)).await.is_err() {
+            tracing::warn!("Countdown task unavailable to cancel");
+        }
+        self.emergency.event_bus().publish(AppEvent::AlertsCancelled);
+        self.cancel(CancelMethod::Phrase, false).await
+    }
+
+    /// Record a cancel code; under duress, alert contacts covertly
+    ///
+    /// A genuine cancel ends the emergency here, while the notification task
+    /// stands contacts down on `AlertsCancelled`. Under duress the emergency
+    /// stays active and contacts are told the user was forced to cancel.
+    async fn cancel(&mut self, method: CancelMethod, duress: bool) -> AppResult<()> {
+        let record = CancellationRecord {
+            timestamp: chrono::Utc::now(),
+            incident_id: self.last_incident.clone(),
+            method,
+            duress,
+        };
+        if duress {
+            tracing::info!("Duress code entered; alerts continue");
+            let incident_id = self.last_incident.get_or_insert_with(|| uuid::Uuid::new_v4().to_string()).clone();
+            let context = AlertContext::new(AlertKind::Duress, &self.settings.borrow().emergency.owner_name);
+            self.notify(&incident_id, context).await?;
+        } else {
+            self.active_emergency = None;
+            self.last_incident = None;
+            self.call_placed = false;
+            self.summary.send_replace(None);
+        }
+        self.store(TimelineEntry::Cancellation(record)).await
+    }
+
+    /// Alert contacts without guidance, audio, events or calls
+    ///
+    /// Nothing is published on the event bus, so no screen reacts; the
+    /// incident is only visible in the timeline.
+    async fn silent_sos(&mut self, trigger: SilentSosTrigger) -> AppResult<()> {
+        tracing::info!("Silent SOS triggered ({:?})", trigger.method);
+        let record = IncidentRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            emergency_type: "silent_sos".to_string(),
+            instruction: String::new(),
+            should_call_emergency_services: false,
+            context_flags: vec!["silent".to_string()],
+            connectivity: self.connectivity.borrow().display_name().to_string(),
+        };
+        let context = AlertContext::new(AlertKind::SilentSos, &self.settings.borrow().emergency.owner_name);
+        let mut summary = IncidentSummary::new(AlertKind::SilentSos, &context.name);
+        summary.flag(&record.context_flags);
+        self.summary.send_replace(Some(summary));
+        self.last_incident = Some(record.id.clone());
+        // The user can't talk, so only a call with the spoken script helps
+        let speak = {
+            let emergency = &self.settings.borrow().emergency;
+            emergency.auto_dial_911 && emergency.automated_voice_message
+        };
+        if speak {
+            self.place_call(context.clone(), None).await?;
+        }
+        self.notify(&record.id, context).await?;
+        self.store(TimelineEntry::Incident(record)).await
+    }
+
+    async fn notify(&mut self, incident_id: &str, context: AlertContext) -> AppResult<()> {
+        if self.settings.borrow().emergency.enable_family_alerts {
+            self.summarize(|summary| summary.record("trusted contacts alerted"));
+        }
+        let request = NotificationRequest {
+            incident_id: incident_id.to_string(),
+            context,
+            contacts: self.sos.get_emergency_contacts().to_vec(),
+        };
+        self.notifications
+            .send(request)
+            .await
+            .map_err(|_| AppError::Internal("notification task is not running".to_string()))
+    }
+
+    async fn store(&mut self, entry: TimelineEntry) -> AppResult<()> {
+        self.storage
+            .send(entry)
+            .await
+            .map_err(|_| AppError::Internal("storage task is not running".to_string()))
+    }
+
+    /// Update the summary of the incident in progress, if any
+    fn summarize(&self, update: impl FnOnce(&mut IncidentSummary)) {
+        self.summary.send_if_modified(|summary| match summary {
+            Some(summary) => {
+                update(summary);
+                true
+            }
+            None => false,
+        });
+    }
+}
+
+impl Subsystem for ProtocolEngine {
+    fn name(&self) -> &'static str {
+        "protocol"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        // Runs until every command sender is dropped, draining the queue
+        while let Some(command) = self.commands.recv().await {
+            self.handle(command).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! Incident Timeline
+//!
+//! Records of an incident and the task that appends them to the history file.
+
+use crate::countdown::CountdownOutcome;
+use crate::duress::CancelMethod;
+use crate::error::{AppError, AppResult};
+use crate::location::LocationReport;
+use crate::notifier::DeliveryReceipt;
+use crate::public::emergency_interface::EmergencyCallData;
+use super::supervisor::Subsystem;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// Incident record persisted by the storage task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncidentRecord {
+    /// Unique record ID
+    pub id: String,
+    /// When the record was created
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Emergency type key
+    pub emergency_type: String,
+    /// Instruction given to the user
+    pub instruction: String,
+    /// Whether emergency services should be called
+    #[serde(alias = "should_call_911")]
+    pub should_call_emergency_services: bool,
+    /// Context flags from analysis
+    pub context_flags: Vec<String>,
+    /// Connectivity mode when the record was created
+    pub connectivity: String,
+}
+
+/// Cancel code entry persisted by the storage task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancellationRecord {
+    /// When the code was entered
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Latest incident at the time, if any
+    pub incident_id: Option<String>,
+    /// How the code was entered
+    pub method: CancelMethod,
+    /// Whether it was the duress code: the user saw a normal cancel while
+    /// contacts kept being alerted
+    pub duress: bool,
+}
+
+/// Countdown result persisted by the storage task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CountdownRecord {
+    /// When the countdown ended
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// What started it: "crash", "fall" or "auto_dial"
+    pub reason: String,
+    /// Whether the user cancelled or the timer expired
+    #[serde(flatten)]
+    pub outcome: CountdownOutcome,
+}
+
+/// Location shared during an incident, persisted by the storage task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationRecord {
+    /// When the location was shared
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Incident it was shared for
+    pub incident_id: String,
+    /// Position with its accuracy and staleness labels
+    #[serde(flatten)]
+    pub report: LocationReport,
+}
+
+/// Entry in the incident timeline persisted by the storage task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+pub enum TimelineEntry {
+    /// Guidance given during an incident
+    Incident(IncidentRecord),
+    /// Delivery outcome of an alert to a contact
+    Delivery(DeliveryReceipt),
+    /// A cancel or duress code was entered
+    Cancellation(CancellationRecord),
+    /// A countdown was cancelled or expired
+    Countdown(CountdownRecord),
+    /// A location was shared with contacts
+    Location(LocationRecord),
+    /// An attempt to call emergency services or, for an alert, a contact
+    Call(EmergencyCallData),
+}
+
+/// Storage task: appends timeline entries as JSON lines
+pub(super) struct StorageWriter {
+    pub(super) path: std::path::PathBuf,
+    pub(super) records: mpsc::Receiver<TimelineEntry>,
+    pub(super) unsaved: Option<TimelineEntry>,
+}
+
+impl StorageWriter {
+    async fn append(&self, record: &TimelineEntry) -> AppResult<()> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+impl Subsystem for StorageWriter {
+    fn name(&self) -> &'static str {
+        "storage"
+    }
+
+    async fn run(&mut self) -> AppResult<()> {
+        loop {
+            // A record that failed to save is retried before taking new work
+            let record = match self.unsaved.take() {
+                Some(record) => record,
+                None => match self.records.recv().await {
+                    Some(record) => record,
+                    None => return Ok(()),
+                },
+            };
+
+            if let Err(e) = self.append(&record).await {
+                self.unsaved = Some(record);
+                return Err(AppError::Database(format!("failed to save incident: {}", e)));
+            }
+        }
+    }
+}
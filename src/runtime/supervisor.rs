@@ -0,0 +1,354 @@
+//! Supervision
+//!
+//! Restarts failed tasks with backoff, tracks their health and drains them
+//! on shutdown.
+
+use crate::app::AppStatus;
+use crate::error::{AppError, AppResult};
+use futures::FutureExt;
+use std::collections::HashSet;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Restart policy for supervised tasks
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Delay before the first restart
+    pub initial_backoff: Duration,
+    /// Upper bound for the restart delay
+    pub max_backoff: Duration,
+    /// Consecutive restarts allowed before the task is abandoned
+    pub max_restarts: u32,
+    /// A run lasting this long resets the restart count
+    pub stable_after: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_restarts: 5,
+            stable_after: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A long-running task managed by the supervisor
+///
+/// `run` takes `&mut self` so that channel endpoints and any unfinished work
+/// survive a restart. Returning `Ok(())` means the task finished for good.
+pub trait Subsystem: Send + 'static {
+    /// Name used in logs and health reporting
+    fn name(&self) -> &'static str;
+
+    /// Run until finished or failed
+    fn run(&mut self) -> impl Future<Output = AppResult<()>> + Send;
+}
+
+/// Failure tracking shared by all supervised tasks
+pub(super) struct SupervisorHealth {
+    pub(super) status: Arc<watch::Sender<AppStatus>>,
+    pub(super) failing: Mutex<Failures>,
+}
+
+/// Tasks currently failing and the status to restore once they recover
+pub(super) struct Failures {
+    pub(super) tasks: HashSet<&'static str>,
+    pub(super) restore: Option<AppStatus>,
+}
+
+impl SupervisorHealth {
+    pub(super) fn new(status: Arc<watch::Sender<AppStatus>>) -> Self {
+        Self {
+            status,
+            failing: Mutex::new(Failures { tasks: HashSet::new(), restore: None }),
+        }
+    }
+
+    fn mark_failed(&self, name: &'static str) {
+        let mut failing = self.failing.lock().unwrap_or_else(|e| e.into_inner());
+        failing.tasks.insert(name);
+        let previous = self.status.send_replace(AppStatus::Error);
+        if !matches!(previous, AppStatus::Error) {
+            failing.restore = Some(previous);
+        }
+    }
+
+    /// Clear a task's failure, restoring the status from before the first
+    /// failure once no task is failing
+    ///
+    /// A status set while failing, e.g. `Paused`, is left alone.
+    fn mark_recovered(&self, name: &'static str) {
+        let mut failing = self.failing.lock().unwrap_or_else(|e| e.into_inner());
+        if !failing.tasks.remove(name) || !failing.tasks.is_empty() {
+            return;
+        }
+        let restore = failing.restore.take().unwrap_or(AppStatus::Running);
+        self.status.send_if_modified(|status| {
+            if matches!(status, AppStatus::Error) {
+                *status = restore;
+                true
+            } else {
+                false
+            }
+        });
+    }
+}
+
+/// Spawn a subsystem under supervision
+///
+/// A restarted task only counts as recovered once it has run for
+/// `stable_after`, so [`AppStatus::Error`] stays visible while it keeps
+/// failing.
+pub(super) fn supervise<S: Subsystem>(
+    mut subsystem: S,
+    policy: RestartPolicy,
+    health: Arc<SupervisorHealth>,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let name = subsystem.name();
+        let mut restarts = 0;
+        let mut backoff = policy.initial_backoff;
+        let mut recovering = false;
+
+        loop {
+            let started = Instant::now();
+            let run = AssertUnwindSafe(subsystem.run()).catch_unwind();
+            tokio::pin!(run);
+            let result = if recovering {
+                tokio::select! {
+                    result = &mut run => result,
+                    _ = tokio::time::sleep(policy.stable_after) => {
+                        health.mark_recovered(name);
+                        run.await
+                    }
+                }
+            } else {
+                run.await
+            };
+            let error = match result {
+                Ok(Ok(())) => {
+                    tracing::info!("Subsystem '{}' stopped", name);
+                    health.mark_recovered(name);
+                    return;
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(panic) => panic_message(panic),
+            };
+
+            tracing::error!("Subsystem '{}' failed: {}", name, error);
+            health.mark_failed(name);
+
+            if started.elapsed() >= policy.stable_after {
+                restarts = 0;
+                backoff = policy.initial_backoff;
+            }
+            if restarts >= policy.max_restarts {
+                tracing::error!("Subsystem '{}' exceeded {} restarts, giving up", name, policy.max_restarts);
+                return;
+            }
+            restarts += 1;
+
+            // Restart immediately on shutdown so the task can drain its queue
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = shutdown.wait_for(|stop| *stop) => {}
+            }
+            backoff = (backoff * 2).min(policy.max_backoff);
+
+            tracing::info!("Restarting subsystem '{}' (attempt {})", name, restarts);
+            recovering = true;
+        }
+    })
+}
+
+/// Extract a readable message from a panic payload
+pub(super) fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        format!("panicked: {}", message)
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        format!("panicked: {}", message)
+    } else {
+        "panicked".to_string()
+    }
+}
+
+/// Wait for every task to exit, aborting those still running after `timeout`
+pub(super) async fn drain(handles: &mut [JoinHandle<()>], timeout: Duration) -> AppResult<()> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut timed_out = false;
+    for handle in handles {
+        if tokio::time::timeout_at(deadline, &mut *handle).await.is_err() {
+            handle.abort();
+            timed_out = true;
+        }
+    }
+
+    if timed_out {
+        return Err(AppError::Timeout("subsystems did not drain before shutdown deadline".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    /// Fails its first `failures` runs, then handles queued items until the
+    /// queue closes
+    struct Flaky {
+        failures: usize,
+        runs: Arc<Mutex<Vec<Instant>>>,
+        queue: mpsc::Receiver<u32>,
+        handled: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl Subsystem for Flaky {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        async fn run(&mut self) -> AppResult<()> {
+            let run = {
+                let mut runs = self.runs.lock().unwrap();
+                runs.push(Instant::now());
+                runs.len()
+            };
+            if run <= self.failures {
+                return Err(AppError::Internal(format!("failure {}", run)));
+            }
+            while let Some(item) = self.queue.recv().await {
+                self.handled.lock().unwrap().push(item);
+            }
+            Ok(())
+        }
+    }
+
+    fn flaky(failures: usize) -> (Flaky, mpsc::Sender<u32>) {
+        let (sender, queue) = mpsc::channel(8);
+        let subsystem = Flaky {
+            failures,
+            runs: Arc::new(Mutex::new(Vec::new())),
+            queue,
+            handled: Arc::new(Mutex::new(Vec::new())),
+        };
+        (subsystem, sender)
+    }
+
+    fn policy(initial_backoff: Duration, stable_after: Duration) -> RestartPolicy {
+        RestartPolicy {
+            initial_backoff,
+            max_backoff: initial_backoff * 2,
+            max_restarts: 3,
+            stable_after,
+        }
+    }
+
+    fn health(status: AppStatus) -> Arc<SupervisorHealth> {
+        Arc::new(SupervisorHealth::new(Arc::new(watch::channel(status).0)))
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_restarts_with_backoff() {
+        let (_stop, shutdown) = watch::channel(false);
+        let backoff = Duration::from_millis(20);
+
+        let (subsystem, sender) = flaky(3);
+        let runs = subsystem.runs.clone();
+        let handle = supervise(subsystem, policy(backoff, Duration::from_secs(60)), health(AppStatus::Running), shutdown.clone());
+        while runs.lock().unwrap().len() < 4 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        drop(sender);
+        handle.await.unwrap();
+
+        let runs = runs.lock().unwrap().clone();
+        let gaps: Vec<Duration> = runs.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        assert!(gaps[0] >= backoff);
+        assert!(gaps[1] >= backoff * 2);
+        assert!(gaps[2] >= backoff * 2 && gaps[2] < backoff * 4, "backoff is capped: {:?}", gaps);
+
+        // Gives up after max_restarts
+        let (subsystem, _sender) = flaky(usize::MAX);
+        let runs = subsystem.runs.clone();
+        let handle = supervise(subsystem, policy(Duration::from_millis(1), Duration::from_secs(60)), health(AppStatus::Running), shutdown);
+        tokio::time::timeout(Duration::from_secs(1), handle).await.unwrap().unwrap();
+        assert_eq!(runs.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_error_status_lasts_until_restart_is_stable() {
+        let (_stop, shutdown) = watch::channel(false);
+        let health = health(AppStatus::Paused);
+        let mut status = health.status.subscribe();
+        let stable_after = Duration::from_millis(200);
+
+        let started = Instant::now();
+        let (subsystem, _sender) = flaky(1);
+        let _handle = supervise(subsystem, policy(Duration::from_millis(10), stable_after), health.clone(), shutdown);
+
+        tokio::time::timeout(Duration::from_secs(1), status.wait_for(|s| *s == AppStatus::Error)).await.unwrap().unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(*status.borrow(), AppStatus::Error, "restarted but not yet stable");
+
+        // Recovery restores the status from before the failure
+        tokio::time::timeout(Duration::from_secs(1), status.wait_for(|s| *s == AppStatus::Paused)).await.unwrap().unwrap();
+        assert!(started.elapsed() >= stable_after);
+    }
+
+    #[test]
+    fn test_health_restores_status_once_all_tasks_recover() {
+        let health = health(AppStatus::Running);
+
+        health.mark_failed("voice");
+        health.mark_failed("storage");
+        health.mark_recovered("voice");
+        assert_eq!(*health.status.borrow(), AppStatus::Error);
+        health.mark_recovered("storage");
+        assert_eq!(*health.status.borrow(), AppStatus::Running);
+
+        // Paused while failing stays paused
+        health.mark_failed("voice");
+        health.status.send_replace(AppStatus::Paused);
+        health.mark_recovered("voice");
+        assert_eq!(*health.status.borrow(), AppStatus::Paused);
+
+        // Failing while paused comes back paused
+        health.mark_failed("voice");
+        health.mark_recovered("voice");
+        assert_eq!(*health.status.borrow(), AppStatus::Paused);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_queues_within_timeout() {
+        let (stop, shutdown) = watch::channel(false);
+
+        // A task in backoff restarts at once on shutdown and drains its queue
+        let (subsystem, sender) = flaky(1);
+        let handled = subsystem.handled.clone();
+        let mut handles = vec![supervise(subsystem, policy(Duration::from_secs(3600), Duration::from_secs(60)), health(AppStatus::Running), shutdown)];
+        for item in 0..5 {
+            sender.send(item).await.unwrap();
+        }
+        drop(sender);
+        stop.send_replace(true);
+
+        drain(&mut handles, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(*handled.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+
+        // A task that never finishes is aborted at the deadline
+        let mut handles = vec![tokio::spawn(std::future::pending::<()>())];
+        let started = Instant::now();
+        let result = drain(&mut handles, Duration::from_millis(50)).await;
+        assert!(matches!(result, Err(AppError::Timeout(_))));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(handles.remove(0).await.unwrap_err().is_cancelled());
+    }
+}
@@ -0,0 +1,206 @@
+//! Settings Schema
+//!
+//! This module exports a JSON Schema for [`AppConfig`], generated from the
+//! config types so descriptions, ranges and allowed values stay in step with
+//! the Rust model. Settings screens can render it directly, and edits are
+//! checked against it before they are persisted.
+//!
+//! Edits are a JSON object of dotted keys, e.g. `{"ui.theme": "dark"}`.
+//! `null` clears a persisted setting.
+
+use crate::config::{AppConfig, ConfigIssue};
+use schemars::gen::SchemaSettings;
+use serde_json::{Map, Value};
+
+/// Generate the JSON Schema (draft 7) for the whole configuration
+pub fn config_schema() -> Value {
+    let generator = SchemaSettings::draft07()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    serde_json::to_value(generator.into_root_schema_for::<AppConfig>()).unwrap_or_default()
+}
+
+/// Check settings edits against the schema
+///
+/// Returns one issue per rejected edit; an empty list means every edit has a
+/// known key and a value of the right type and range.
+pub fn validate_edits(edits: &Map<String, Value>) -> Vec<ConfigIssue> {
+    let schema = config_schema();
+    let mut issues = Vec::new();
+    for (key, value) in edits {
+        match property_schema(&schema, key) {
+            Some(_) if value.is_null() => {}
+            Some(property) => check(property, value, key, &mut issues),
+            None => issues.push(issue(key, "unknown key".to_string())),
+        }
+    }
+    issues
+}
+
+/// Find the schema of a dotted key
+fn property_schema<'a>(schema: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(schema, |current, segment| current.get("properties")?.get(segment))
+}
+
+/// Check `value` against the subset of JSON Schema the config types produce
+fn check(schema: &Value, value: &Value, key: &str, issues: &mut Vec<ConfigIssue>) {
+    if let Some(variants) = schema.get("oneOf").or_else(|| schema.get("anyOf")).and_then(Value::as_array) {
+        let matches = variants.iter().any(|variant| {
+            let mut nested = Vec::new();
+            check(variant, value, key, &mut nested);
+            nested.is_empty()
+        });
+        if !matches {
+            let allowed: Vec<String> = variants
+                .iter()
+                .filter_map(|variant| variant.get("enum")?.as_array().cloned())
+                .flatten()
+                .map(|allowed| allowed.to_string())
+                .collect();
+            issues.push(issue(key, format!("{} is not one of {}", value, allowed.join(", "))));
+        }
+        return;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            other => other.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|name| has_type(value, name)) {
+            issues.push(issue(key, format!("{} is not of type {}", value, types.join(" or "))));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            issues.push(issue(key, format!("{} is not one of {}", value, allowed.join(", "))));
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < min {
+                issues.push(issue(key, format!("{} is below the minimum of {}", value, min)));
+            }
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > max {
+                issues.push(issue(key, format!("{} is above the maximum of {}", value, max)));
+            }
+        }
+    }
+
+    if let Some(text) = value.as_str() {
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+            if (text.chars().count() as u64) < min {
+                issues.push(issue(key, format!("must be at least {} characters", min)));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+            if (text.chars().count() as u64) > max {
+                issues.push(issue(key, format!("must be at most {} characters", max)));
+            }
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if (items.len() as u64) < min {
+                issues.push(issue(key, format!("must have at least {} items", min)));
+            }
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for item in items {
+                check(item_schema, item, key, issues);
+            }
+        }
+    }
+
+    if let Some(fields) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, field) in fields {
+            let field_key = format!("{}.{}", key, name);
+            match (properties.and_then(|properties| properties.get(name)), schema.get("additionalProperties")) {
+                (Some(property), _) => check(property, field, &field_key, issues),
+                (None, Some(Value::Bool(false))) => issues.push(issue(&field_key, "unknown key".to_string())),
+                (None, Some(additional @ Value::Object(_))) => check(additional, field, &field_key, issues),
+                (None, _) => {}
+            }
+        }
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn issue(key: &str, message: String) -> ConfigIssue {
+    ConfigIssue { key: key.to_string(), line: None, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_carries_docs_ranges_and_enums() {
+        let schema = config_schema();
+
+        let threshold = property_schema(&schema, "voice.confidence_threshold").unwrap();
+        assert_eq!(threshold["minimum"], 0.0);
+        assert_eq!(threshold["maximum"], 1.0);
+        assert!(threshold["description"].as_str().unwrap().contains("confidence"));
+
+        assert_eq!(property_schema(&schema, "ui.theme").unwrap()["enum"].as_array().unwrap().len(), 4);
+        assert!(property_schema(&schema, "connectivity.user_preference").unwrap()["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&Value::Null));
+        assert!(property_schema(&schema, "emergency.silent_sos_method").unwrap()["oneOf"].is_array());
+        assert_eq!(schema["properties"]["ui"]["additionalProperties"], false);
+    }
+
+    #[test]
+    fn test_validate_edits() {
+        let edits = serde_json::json!({
+            "ui.theme": "dark",
+            "audio.emergency_volume": 0.9,
+            "connectivity.user_preference": null,
+            "emergency.silent_sos_method": "volume_sequence",
+        });
+        assert!(validate_edits(edits.as_object().unwrap()).is_empty());
+
+        let edits = serde_json::json!({
+            "ui.theme": "neon",
+            "voice.sample_rate": 1000,
+            "emergency.silent_sos_method": "shake",
+            "emergency.default_notification_preferences": ["silent_sos", "lunch"],
+            "ui.font": "serif",
+        });
+        let mut keys: Vec<String> = validate_edits(edits.as_object().unwrap()).into_iter().map(|issue| issue.key).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "emergency.default_notification_preferences",
+                "emergency.silent_sos_method",
+                "ui.font",
+                "ui.theme",
+                "voice.sample_rate",
+            ]
+        );
+    }
+}
@@ -0,0 +1,438 @@
+//! Secret Storage
+//!
+//! API keys and the wallet keypair never live in the configuration. Config
+//! refers to them by name with [`SecretRef`] and the values are kept in a
+//! [`SecretStore`]:
+//!
+//! - [`EncryptedFileStore`] encrypts each secret with ChaCha20-Poly1305 under
+//!   a key derived from the user's PIN with Argon2id
+//! - [`KeystoreSecretStore`] wraps each secret with a hardware-backed key
+//!   through a [`KeystoreBridge`], implemented by the Android host over the
+//!   Android Keystore
+//!
+//! Secret values are zeroed when dropped and never appear in `Debug` output.
+
+use crate::error::{AppError, AppResult};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject, StringValidation};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Longest allowed secret name
+pub const MAX_SECRET_NAME_LEN: usize = 64;
+
+/// Associated data of the record that checks the PIN
+const PIN_CHECK: &[u8] = b"solana-sos/pin-check";
+
+/// Name of a secret held in a [`SecretStore`]
+///
+/// Names are lowercase letters, digits, `_`, `-` and `.`, so a raw key
+/// pasted into the config is rejected rather than stored in cleartext.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SecretRef(String);
+
+impl SecretRef {
+    /// Create a reference, checking the name
+    pub fn new(name: &str) -> AppResult<Self> {
+        Self::try_from(name.to_string()).map_err(AppError::InvalidInput)
+    }
+
+    /// Get the secret name
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= MAX_SECRET_NAME_LEN
+            && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"_-.".contains(&b))
+    }
+}
+
+impl TryFrom<String> for SecretRef {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        if Self::is_valid_name(&name) {
+            Ok(Self(name))
+        } else {
+            // Don't echo the value back; it may be the secret itself
+            Err(format!(
+                "secret names use up to {} of a-z, 0-9, '_', '-' and '.'; store the value in the secret store",
+                MAX_SECRET_NAME_LEN
+            ))
+        }
+    }
+}
+
+impl From<SecretRef> for String {
+    fn from(secret: SecretRef) -> Self {
+        secret.0
+    }
+}
+
+impl fmt::Debug for SecretRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretRef({})", self.0)
+    }
+}
+
+impl fmt::Display for SecretRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl JsonSchema for SecretRef {
+    fn schema_name() -> String {
+        "SecretRef".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                description: Some("Name of a secret in the secret store".to_string()),
+                ..Default::default()
+            })),
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                min_length: Some(1),
+                max_length: Some(MAX_SECRET_NAME_LEN as u32),
+                pattern: Some("^[a-z0-9_.-]+$".to_string()),
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// A secret value, zeroed on drop
+pub struct Secret(Zeroizing<Vec<u8>>);
+
+impl Secret {
+    /// Wrap secret bytes
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(Zeroizing::new(bytes))
+    }
+
+    /// Borrow the secret bytes
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Borrow the secret as text, e.g. an API key
+    pub fn expose_str(&self) -> AppResult<&str> {
+        std::str::from_utf8(&self.0).map_err(|_| AppError::InvalidInput("secret is not UTF-8 text".to_string()))
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+/// Storage for named secrets
+pub trait SecretStore: Send {
+    /// Get a secret, or `None` if it is not stored
+    fn get(&self, name: &SecretRef) -> AppResult<Option<Secret>>;
+
+    /// Store a secret, replacing any previous value
+    fn put(&mut self, name: &SecretRef, value: &[u8]) -> AppResult<()>;
+
+    /// Remove a secret, returning whether it was stored
+    fn remove(&mut self, name: &SecretRef) -> AppResult<bool>;
+
+    /// List the stored secret names
+    fn names(&self) -> AppResult<Vec<SecretRef>>;
+
+    /// Get a secret that must be present
+    fn require(&self, name: &SecretRef) -> AppResult<Secret> {
+        self.get(name)?
+            .ok_or_else(|| AppError::NotFound(format!("secret '{}' is not in the secret store", name)))
+    }
+}
+
+/// Argon2id cost parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost (KiB)
+    pub memory_kib: u32,
+    /// Number of passes
+    pub iterations: u32,
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        // OWASP's recommended Argon2id minimum
+        Self { memory_kib: 19_456, iterations: 2, parallelism: 1 }
+    }
+}
+
+/// One encrypted value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// On-disk layout of an [`EncryptedFileStore`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SecretFile {
+    version: u32,
+    kdf: KdfParams,
+    salt: String,
+    check: Sealed,
+    entries: BTreeMap<String, Sealed>,
+}
+
+/// Secrets encrypted in a file under a key derived from the user's PIN
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    cipher: ChaCha20Poly1305,
+    file: SecretFile,
+}
+
+impl EncryptedFileStore {
+    /// Open the store at `path`, creating it if it does not exist
+    ///
+    /// A wrong PIN fails with [`AppError::PermissionDenied`].
+    pub fn open<P: AsRef<Path>>(path: P, pin: &str) -> AppResult<Self> {
+        Self::open_with(path, pin, KdfParams::default())
+    }
+
+    /// Open the store, using `params` if it has to be created
+    pub fn open_with<P: AsRef<Path>>(path: P, pin: &str, params: KdfParams) -> AppResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let file: SecretFile = serde_json::from_str(&text)
+                    .map_err(|e| AppError::Config(format!("{}: {}", path.display(), e)))?;
+                let cipher = derive_cipher(pin, &decode(&file.salt)?, file.kdf)?;
+                open_sealed(&cipher, &file.check, PIN_CHECK)
+                    .map_err(|_| AppError::PermissionDenied("wrong PIN for the secret store".to_string()))?;
+                Ok(Self { path, cipher, file })
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut salt = [0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                let cipher = derive_cipher(pin, &salt, params)?;
+                let check = seal(&cipher, b"", PIN_CHECK)?;
+                let file = SecretFile { version: 1, kdf: params, salt: hex::encode(salt), check, entries: BTreeMap::new() };
+                let store = Self { path, cipher, file };
+                store.save()?;
+                Ok(store)
+            }
+            Err(e) => Err(AppError::Config(format!("{}: {}", path.display(), e))),
+        }
+    }
+
+    fn save(&self) -> AppResult<()> {
+        write_private(&self.path, serde_json::to_string_pretty(&self.file)?.as_bytes())
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn get(&self, name: &SecretRef) -> AppResult<Option<Secret>> {
+        match self.file.entries.get(name.name()) {
+            Some(sealed) => open_sealed(&self.cipher, sealed, name.name().as_bytes())
+                .map(|value| Some(Secret::new(value)))
+                .map_err(|_| AppError::Internal(format!("secret '{}' is corrupted", name))),
+            None => Ok(None),
+        }
+    }
+
+    fn put(&mut self, name: &SecretRef, value: &[u8]) -> AppResult<()> {
+        // The name is bound as associated data so entries can't be swapped
+        let sealed = seal(&self.cipher, value, name.name().as_bytes())?;
+        self.file.entries.insert(name.name().to_string(), sealed);
+        self.save()
+    }
+
+    fn remove(&mut self, name: &SecretRef) -> AppResult<bool> {
+        if self.file.entries.remove(name.name()).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    fn names(&self) -> AppResult<Vec<SecretRef>> {
+        self.file.entries.keys().map(|name| SecretRef::new(name)).collect()
+    }
+}
+
+/// Host access to a hardware-backed key, e.g. the Android Keystore
+///
+/// The host creates a non-exportable key under `alias` and uses it to wrap
+/// and unwrap secrets; the key material never reaches this library.
+pub trait KeystoreBridge: Send {
+    /// Encrypt `plaintext`, returning an opaque blob
+    fn wrap(&self, alias: &str, plaintext: &[u8]) -> AppResult<Vec<u8>>;
+
+    /// Decrypt a blob returned by [`wrap`](Self::wrap)
+    fn unwrap(&self, alias: &str, blob: &[u8]) -> AppResult<Vec<u8>>;
+}
+
+/// Secrets wrapped by a [`KeystoreBridge`] and kept in a file
+pub struct KeystoreSecretStore<B: KeystoreBridge> {
+    bridge: B,
+    alias: String,
+    path: PathBuf,
+    blobs: BTreeMap<String, String>,
+}
+
+impl<B: KeystoreBridge> KeystoreSecretStore<B> {
+    /// Open the store at `path`, wrapping secrets with the key `alias`
+    pub fn open<P: AsRef<Path>>(bridge: B, alias: &str, path: P) -> AppResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let blobs = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| AppError::Config(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(AppError::Config(format!("{}: {}", path.display(), e))),
+        };
+        Ok(Self { bridge, alias: alias.to_string(), path, blobs })
+    }
+
+    fn save(&self) -> AppResult<()> {
+        write_private(&self.path, serde_json::to_string_pretty(&self.blobs)?.as_bytes())
+    }
+}
+
+impl<B: KeystoreBridge> SecretStore for KeystoreSecretStore<B> {
+    fn get(&self, name: &SecretRef) -> AppResult<Option<Secret>> {
+        match self.blobs.get(name.name()) {
+            Some(blob) => Ok(Some(Secret::new(self.bridge.unwrap(&self.alias, &decode(blob)?)?))),
+            None => Ok(None),
+        }
+    }
+
+    fn put(&mut self, name: &SecretRef, value: &[u8]) -> AppResult<()> {
+        let blob = self.bridge.wrap(&self.alias, value)?;
+        self.blobs.insert(name.name().to_string(), hex::encode(blob));
+        self.save()
+    }
+
+    fn remove(&mut self, name: &SecretRef) -> AppResult<bool> {
+        if self.blobs.remove(name.name()).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    fn names(&self) -> AppResult<Vec<SecretRef>> {
+        self.blobs.keys().map(|name| SecretRef::new(name)).collect()
+    }
+}
+
+/// Move a keypair file into the store under `name`
+///
+/// The file is left in place; delete it once the import is confirmed.
+pub fn import_keypair_file<P: AsRef<Path>>(store: &mut dyn SecretStore, name: &SecretRef, path: P) -> AppResult<()> {
+    let keypair = Zeroizing::new(std::fs::read(path.as_ref())?);
+    serde_json::from_slice::<Vec<u8>>(&keypair)
+        .map_err(|_| AppError::InvalidInput(format!("{} is not a keypair file", path.as_ref().display())))?;
+    store.put(name, &keypair)
+}
+
+fn derive_cipher(pin: &str, salt: &[u8], params: KdfParams) -> AppResult<ChaCha20Poly1305> {
+    let params = Params::new(params.memory_kib, params.iterations, params.parallelism, Some(32))
+        .map_err(|e| AppError::Config(format!("invalid KDF parameters: {}", e)))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(pin.as_bytes(), salt, key.as_mut())
+        .map_err(|e| AppError::Internal(format!("key derivation failed: {}", e)))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(key.as_ref())))
+}
+
+fn seal(cipher: &ChaCha20Poly1305, value: &[u8], aad: &[u8]) -> AppResult<Sealed> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: value, aad })
+        .map_err(|_| AppError::Internal("encryption failed".to_string()))?;
+    Ok(Sealed { nonce: hex::encode(nonce), ciphertext: hex::encode(ciphertext) })
+}
+
+fn open_sealed(cipher: &ChaCha20Poly1305, sealed: &Sealed, aad: &[u8]) -> AppResult<Vec<u8>> {
+    let nonce = decode(&sealed.nonce)?;
+    if nonce.len() != 12 {
+        return Err(AppError::Internal("malformed nonce".to_string()));
+    }
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), Payload { msg: &decode(&sealed.ciphertext)?, aad })
+        .map_err(|_| AppError::Internal("decryption failed".to_string()))
+}
+
+fn decode(text: &str) -> AppResult<Vec<u8>> {
+    hex::decode(text).map_err(|e| AppError::Internal(format!("malformed secret store: {}", e)))
+}
+
+/// Write a file readable only by the owner, replacing it atomically
+fn write_private(path: &Path, contents: &[u8]) -> AppResult<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let staging = path.with_extension("tmp");
+    {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(&mut options.open(&staging)?, contents)?;
+    }
+    std::fs::rename(&staging, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_KDF: KdfParams = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+    #[test]
+    fn test_encrypted_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("solana_sos_secrets_{}.json", uuid::Uuid::new_v4()));
+        let openai = SecretRef::new("openai").unwrap();
+
+        let mut store = EncryptedFileStore::open_with(&path, "4821", TEST_KDF).unwrap();
+        store.put(&openai, b"sk-live-123").unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("sk-live-123"));
+
+        let store = EncryptedFileStore::open(&path, "4821").unwrap();
+        assert_eq!(store.require(&openai).unwrap().expose_str().unwrap(), "sk-live-123");
+        assert_eq!(store.names().unwrap(), vec![openai]);
+        assert!(matches!(EncryptedFileStore::open(&path, "0000"), Err(AppError::PermissionDenied(_))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_secrets_stay_out_of_config() {
+        assert!(SecretRef::new("wallet.main").is_ok());
+        assert!(SecretRef::new("sk-Live-123ABC").is_err());
+
+        let mut config = crate::config::AppConfig::default();
+        config.connectivity.api_keys.insert("openai".to_string(), SecretRef::new("openai").unwrap());
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("SecretRef(openai)"));
+        assert_eq!(format!("{:?}", Secret::new(b"sk-live-123".to_vec())), "Secret(<redacted>)");
+
+        let source = "[connectivity]\napi_keys = { openai = \"sk-Live-123ABC\" }\n";
+        let issues = crate::config::AppConfig::from_toml_str(source).unwrap_err();
+        assert!(!issues.iter().any(|issue| issue.message.contains("sk-Live")));
+    }
+}
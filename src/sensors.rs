@@ -0,0 +1,502 @@
+//! Sensor Samples
+//!
+//! Detectors consume timestamped samples from the phone's motion, compass,
+//! location, pressure and battery sensors. A [`SensorSource`] delivers them: on a phone
+//! the host pushes readings into a [`LiveSensors`] feed, while tests and
+//! off-device development replay a recorded trace with [`ReplaySensors`].
+//! [`SensorRecorder`] writes the trace files.
+//!
+//! A trace has one sample per line, `timestamp_ms,kind,values...`:
+//!
+//! ```text
+//! # label: crash
+//! 0,accel,0.12,0.30,9.79
+//! 0,speed,17.9
+//! 20,accel,0.10,0.28,9.82
+//! ```
+//!
+//! | Kind | Values |
+//! |------|--------|
+//! | `accel` | acceleration including gravity in m/s² (x, y, z) |
+//! | `gyro` | angular velocity in rad/s (x, y, z) |
+//! | `heading` | compass heading in degrees clockwise from true north, and its accuracy in degrees |
+//! | `speed` | GPS ground speed in m/s |
+//! | `location` | GPS latitude, longitude (degrees) and accuracy (m) |
+//! | `pressure` | barometric pressure in hPa |
+//! | `battery` | charge in percent, and 1 if charging or 0 if not |
+//!
+//! Blank lines and `#` comments are ignored; `# key: value` comments carry
+//! trace metadata.
+
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use tokio::sync::mpsc;
+
+/// Standard gravity (m/s²)
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+
+/// Meters per second in one mile per hour
+pub const MPS_PER_MPH: f64 = 0.44704;
+
+/// Samples a live feed buffers before dropping new ones
+pub const LIVE_FEED_DEPTH: usize = 512;
+
+/// Physical sensor a reading comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorKind {
+    /// `accel` readings
+    Accelerometer,
+    /// `gyro` readings
+    Gyroscope,
+    /// `heading` readings, from the host's orientation sensor
+    Compass,
+    /// `speed` and `location` readings
+    Gps,
+    /// `pressure` readings
+    Barometer,
+    /// `battery` readings
+    Battery,
+}
+
+/// One sensor reading
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SensorReading {
+    /// Acceleration including gravity (m/s²)
+    Accel {
+        /// Device x axis
+        x: f64,
+        /// Device y axis
+        y: f64,
+        /// Device z axis
+        z: f64,
+    },
+    /// Angular velocity (rad/s)
+    Gyro {
+        /// About the device x axis
+        x: f64,
+        /// About the device y axis
+        y: f64,
+        /// About the device z axis
+        z: f64,
+    },
+    /// Direction the phone faces
+    Heading {
+        /// Degrees clockwise from true north (0-360)
+        degrees: f64,
+        /// Likely error either side (degrees)
+        accuracy_deg: f64,
+    },
+    /// GPS ground speed (m/s)
+    Speed {
+        /// Meters per second
+        meters_per_second: f64,
+    },
+    /// GPS fix
+    Location {
+        /// Degrees north
+        latitude: f64,
+        /// Degrees east
+        longitude: f64,
+        /// Radius of 68% confidence (m)
+        accuracy_m: f64,
+    },
+    /// Barometric pressure
+    Pressure {
+        /// Hectopascals
+        hpa: f64,
+    },
+    /// Battery state
+    Battery {
+        /// Charge (0-100)
+        percent: f64,
+        /// Whether it is charging
+        charging: bool,
+    },
+}
+
+impl SensorReading {
+    /// Build a reading from a trace kind and its values, if they are valid
+    pub fn from_values(kind: &str, values: &[f64]) -> Option<Self> {
+        if values.iter().any(|value| !value.is_finite()) {
+            return None;
+        }
+        let reading = match (kind, values) {
+            ("accel", [x, y, z]) => SensorReading::Accel { x: *x, y: *y, z: *z },
+            ("gyro", [x, y, z]) => SensorReading::Gyro { x: *x, y: *y, z: *z },
+            ("heading", [degrees, accuracy_deg]) if (0.0..=180.0).contains(accuracy_deg) => {
+                SensorReading::Heading { degrees: degrees.rem_euclid(360.0), accuracy_deg: *accuracy_deg }
+            }
+            ("speed", [meters_per_second]) if *meters_per_second >= 0.0 => {
+                SensorReading::Speed { meters_per_second: *meters_per_second }
+            }
+            ("location", [latitude, longitude, accuracy_m])
+                if latitude.abs() <= 90.0 && longitude.abs() <= 180.0 && *accuracy_m >= 0.0 =>
+            {
+                SensorReading::Location { latitude: *latitude, longitude: *longitude, accuracy_m: *accuracy_m }
+            }
+            ("pressure", [hpa]) if *hpa > 0.0 => SensorReading::Pressure { hpa: *hpa },
+            ("battery", [percent, charging]) if (0.0..=100.0).contains(percent) && (*charging == 0.0 || *charging == 1.0) => {
+                SensorReading::Battery { percent: *percent, charging: *charging == 1.0 }
+            }
+            _ => return None,
+        };
+        Some(reading)
+    }
+
+    /// Sensor the reading comes from
+    pub fn sensor(&self) -> SensorKind {
+        match self {
+            SensorReading::Accel { .. } => SensorKind::Accelerometer,
+            SensorReading::Gyro { .. } => SensorKind::Gyroscope,
+            SensorReading::Heading { .. } => SensorKind::Compass,
+            SensorReading::Speed { .. } | SensorReading::Location { .. } => SensorKind::Gps,
+            SensorReading::Pressure { .. } => SensorKind::Barometer,
+            SensorReading::Battery { .. } => SensorKind::Battery,
+        }
+    }
+
+    /// Acceleration magnitude in g, for accelerometer readings
+    pub fn g_force(&self) -> Option<f64> {
+        match self {
+            SensorReading::Accel { x, y, z } => Some((x * x + y * y + z * z).sqrt() / STANDARD_GRAVITY),
+            _ => None,
+        }
+    }
+}
+
+/// A reading with the host's monotonic timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SensorSample {
+    /// Milliseconds on the host's monotonic clock
+    pub timestamp_ms: u64,
+    /// What was measured
+    #[serde(flatten)]
+    pub reading: SensorReading,
+}
+
+impl SensorSample {
+    /// Create a sample
+    pub fn new(timestamp_ms: u64, reading: SensorReading) -> Self {
+        Self { timestamp_ms, reading }
+    }
+
+    /// Parse one trace line
+    pub fn parse(line: &str) -> AppResult<Self> {
+        let invalid = || AppError::InvalidInput(format!("invalid sensor sample '{}'", line));
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let (timestamp, kind, values) = match fields.as_slice() {
+            [timestamp, kind, values @ ..] => (timestamp, *kind, values),
+            _ => return Err(invalid()),
+        };
+        let timestamp_ms = timestamp.parse().map_err(|_| invalid())?;
+        let values: Vec<f64> = values
+            .iter()
+            .map(|value| value.parse::<f64>().ok())
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        let reading = SensorReading::from_values(kind, &values).ok_or_else(invalid)?;
+        Ok(Self { timestamp_ms, reading })
+    }
+}
+
+impl fmt::Display for SensorSample {
+    /// Format as a trace line
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let t = self.timestamp_ms;
+        match self.reading {
+            SensorReading::Accel { x, y, z } => write!(f, "{},accel,{:.3},{:.3},{:.3}", t, x, y, z),
+            SensorReading::Gyro { x, y, z } => write!(f, "{},gyro,{:.4},{:.4},{:.4}", t, x, y, z),
+            SensorReading::Heading { degrees, accuracy_deg } => write!(f, "{},heading,{:.1},{:.1}", t, degrees, accuracy_deg),
+            SensorReading::Speed { meters_per_second } => write!(f, "{},speed,{:.2}", t, meters_per_second),
+            SensorReading::Location { latitude, longitude, accuracy_m } => {
+                write!(f, "{},location,{:.6},{:.6},{:.1}", t, latitude, longitude, accuracy_m)
+            }
+            SensorReading::Pressure { hpa } => write!(f, "{},pressure,{:.2}", t, hpa),
+            SensorReading::Battery { percent, charging } => write!(f, "{},battery,{:.0},{}", t, percent, u8::from(charging)),
+        }
+    }
+}
+
+/// A parsed trace file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SensorTrace {
+    /// `# key: value` metadata, in file order
+    pub metadata: Vec<(String, String)>,
+    /// Samples, in file order
+    pub samples: Vec<SensorSample>,
+}
+
+impl SensorTrace {
+    /// Parse trace text; errors name the offending line
+    pub fn parse(text: &str) -> AppResult<Self> {
+        let mut trace = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some((key, value)) = comment.split_once(':') {
+                    trace.metadata.push((key.trim().to_string(), value.trim().to_string()));
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let sample = SensorSample::parse(line)
+                .map_err(|e| AppError::InvalidInput(format!("line {}: {}", index + 1, e)))?;
+            trace.samples.push(sample);
+        }
+        Ok(trace)
+    }
+
+    /// Read and parse a trace file
+    pub fn load(path: impl AsRef<Path>) -> AppResult<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| AppError::InvalidInput(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Get a metadata value
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.metadata.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for SensorTrace {
+    /// Format as trace text
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.metadata {
+            writeln!(f, "# {}: {}", key, value)?;
+        }
+        for sample in &self.samples {
+            writeln!(f, "{}", sample)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where sensor samples come from
+pub trait SensorSource: Send + 'static {
+    /// Wait for the next sample; `None` once the source has ended
+    fn next(&mut self) -> impl Future<Output = Option<SensorSample>> + Send;
+}
+
+/// Samples pushed by the host as the phone's sensors report them
+#[derive(Debug)]
+pub struct LiveSensors {
+    samples: mpsc::Receiver<SensorSample>,
+}
+
+impl LiveSensors {
+    /// Create a source and the feed the host pushes into
+    ///
+    /// The source ends once every clone of the feed is dropped.
+    pub fn new(depth: usize) -> (SensorFeed, Self) {
+        let (sender, samples) = mpsc::channel(depth);
+        (SensorFeed { sender }, Self { samples })
+    }
+}
+
+impl SensorSource for LiveSensors {
+    async fn next(&mut self) -> Option<SensorSample> {
+        self.samples.recv().await
+    }
+}
+
+/// Host side of a [`LiveSensors`] source
+#[derive(Debug, Clone)]
+pub struct SensorFeed {
+    sender: mpsc::Sender<SensorSample>,
+}
+
+impl SensorFeed {
+    /// Push a sample without blocking the sensor thread
+    ///
+    /// Returns false if the sample was dropped because the consumer is behind
+    /// or gone.
+    pub fn push(&self, sample: SensorSample) -> bool {
+        self.sender.try_send(sample).is_ok()
+    }
+}
+
+/// Samples from a recorded trace
+///
+/// By default samples are delivered as fast as they are read; [`Self::paced`]
+/// spaces them by their timestamps on the Tokio clock, which a paused test
+/// clock steps through without waiting.
+#[derive(Debug)]
+pub struct ReplaySensors {
+    samples: std::vec::IntoIter<SensorSample>,
+    paced: bool,
+    /// First sample's timestamp and when it was delivered
+    origin: Option<(u64, tokio::time::Instant)>,
+}
+
+impl ReplaySensors {
+    /// Replay a parsed trace
+    pub fn new(trace: SensorTrace) -> Self {
+        Self {
+            samples: trace.samples.into_iter(),
+            paced: false,
+            origin: None,
+        }
+    }
+
+    /// Replay a trace file
+    pub fn open(path: impl AsRef<Path>) -> AppResult<Self> {
+        SensorTrace::load(path).map(Self::new)
+    }
+
+    /// Deliver samples at their recorded pace
+    pub fn paced(mut self) -> Self {
+        self.paced = true;
+        self
+    }
+}
+
+impl SensorSource for ReplaySensors {
+    async fn next(&mut self) -> Option<SensorSample> {
+        let sample = self.samples.next()?;
+        if self.paced {
+            let (first, started) = *self.origin.get_or_insert((sample.timestamp_ms, tokio::time::Instant::now()));
+            let offset = std::time::Duration::from_millis(sample.timestamp_ms.saturating_sub(first));
+            tokio::time::sleep_until(started + offset).await;
+        }
+        Some(sample)
+    }
+}
+
+/// Writes samples as a trace, timestamps relative to the first sample
+#[derive(Debug)]
+pub struct SensorRecorder<W: Write> {
+    writer: W,
+    origin: Option<u64>,
+}
+
+impl SensorRecorder<BufWriter<File>> {
+    /// Record to a new file
+    pub fn create(path: impl AsRef<Path>, metadata: &[(&str, &str)]) -> AppResult<Self> {
+        Self::new(BufWriter::new(File::create(path)?), metadata)
+    }
+}
+
+impl<W: Write> SensorRecorder<W> {
+    /// Record to `writer`, starting with `# key: value` metadata lines
+    pub fn new(mut writer: W, metadata: &[(&str, &str)]) -> AppResult<Self> {
+        for (key, value) in metadata {
+            writeln!(writer, "# {}: {}", key, value)?;
+        }
+        Ok(Self { writer, origin: None })
+    }
+
+    /// Append one sample
+    pub fn record(&mut self, sample: &SensorSample) -> AppResult<()> {
+        let origin = *self.origin.get_or_insert(sample.timestamp_ms);
+        let sample = SensorSample::new(sample.timestamp_ms.saturating_sub(origin), sample.reading);
+        writeln!(self.writer, "{}", sample)?;
+        Ok(())
+    }
+
+    /// Flush and return the writer
+    pub fn finish(mut self) -> AppResult<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Send every sample from `source` to `sink` until either ends
+///
+/// Returns the number of samples forwarded.
+pub async fn forward<S: SensorSource>(mut source: S, sink: mpsc::Sender<SensorSample>) -> u64 {
+    let mut forwarded = 0;
+    while let Some(sample) = source.next().await {
+        if sink.send(sample).await.is_err() {
+            break;
+        }
+        forwarded += 1;
+    }
+    forwarded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_round_trip() {
+        let text = "# label: crash\n# note: synthetic\n\n0,accel,0.000,0.000,9.807\n0,speed,17.90\n\
+                    20,gyro,0.0100,-0.0200,0.0000\n20,location,51.501364,-0.141890,8.0\n40,pressure,1013.25\n40,battery,85,1\n60,heading,47.5,15.0\n";
+        let trace = SensorTrace::parse(text).unwrap();
+        assert_eq!(trace.get("label"), Some("crash"));
+        assert_eq!(trace.samples.len(), 7);
+        assert!((trace.samples[0].reading.g_force().unwrap() - 1.0).abs() < 1e-3);
+        let sensors: Vec<_> = trace.samples.iter().map(|sample| sample.reading.sensor()).collect();
+        assert_eq!(
+            sensors,
+            [
+                SensorKind::Accelerometer,
+                SensorKind::Gps,
+                SensorKind::Gyroscope,
+                SensorKind::Gps,
+                SensorKind::Barometer,
+                SensorKind::Battery,
+                SensorKind::Compass
+            ]
+        );
+        assert_eq!(trace.to_string(), text.replace("\n\n", "\n"));
+    }
+
+    #[test]
+    fn test_rejects_malformed_lines() {
+        for line in [
+            "",
+            "x,accel,0,0,0",
+            "5,accel,0,0",
+            "5,speed,-1",
+            "5,speed,NaN",
+            "5,magnet,0,0,0",
+            "5,location,91,0,5",
+            "5,battery,50,2",
+            "5,heading,90,200",
+        ] {
+            assert!(SensorSample::parse(line).is_err(), "{}", line);
+        }
+        let error = SensorTrace::parse("0,speed,1\n1,speed\n").unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let (feed, live) = LiveSensors::new(8);
+        let mut recorder = SensorRecorder::new(Vec::new(), &[("device", "test")]).unwrap();
+        for (at, reading) in [
+            (5_000, SensorReading::Accel { x: 0.0, y: 0.0, z: STANDARD_GRAVITY }),
+            (5_020, SensorReading::Pressure { hpa: 1009.5 }),
+            (5_040, SensorReading::Battery { percent: 42.0, charging: false }),
+        ] {
+            let sample = SensorSample::new(at, reading);
+            assert!(feed.push(sample));
+            recorder.record(&sample).unwrap();
+        }
+        drop(feed);
+
+        let (sink, mut received) = mpsc::channel(8);
+        assert_eq!(forward(live, sink).await, 3);
+        assert_eq!(received.recv().await.unwrap().timestamp_ms, 5_000);
+
+        // The recording starts at zero and replays the same readings
+        let text = String::from_utf8(recorder.finish().unwrap()).unwrap();
+        let mut replay = ReplaySensors::new(SensorTrace::parse(&text).unwrap());
+        let mut replayed = Vec::new();
+        while let Some(sample) = replay.next().await {
+            replayed.push((sample.timestamp_ms, sample.reading.sensor()));
+        }
+        assert_eq!(replayed, [(0, SensorKind::Accelerometer), (20, SensorKind::Barometer), (40, SensorKind::Battery)]);
+    }
+}
@@ -0,0 +1,662 @@
+//! Layered Settings
+//!
+//! This module resolves the effective [`AppConfig`] from ordered layers,
+//! each overriding the one before it:
+//!
+//! 1. built-in defaults
+//! 2. the config file (`config.toml`)
+//! 3. `CRISIS_COMPANION_<SECTION>__<KEY>` environment variables
+//! 4. persisted user settings (rows of the `user_settings` table)
+//! 5. runtime overrides set while the app is running
+//!
+//! The runtime polls for file and user-setting changes and pushes every new
+//! effective config to subscribers, which apply it through [`ConfigTarget`].
+//! [`LayeredConfig::sources`] reports which layer each value came from.
+
+use crate::config::{format_issues, AppConfig, ConfigIssue};
+use crate::crash::CrashDetector;
+use crate::error::{AppError, AppResult};
+use crate::fall::FallDetector;
+use crate::gesture::GestureRecognizer;
+use crate::location::LocationManager;
+use crate::public::audio_interface::AudioProcessor;
+use crate::public::voice_interface::VoiceInterface;
+use crate::public::voice_stream::VoiceStream;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// How often the runtime checks the file and user settings for changes
+pub const RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Prefix for environment overrides, e.g. `CRISIS_COMPANION_VOICE__SAMPLE_RATE`
+pub const ENV_PREFIX: &str = "CRISIS_COMPANION_";
+
+/// `user_settings` keys seeded by `assets/database.sql` and the config keys they set
+const USER_SETTING_KEYS: &[(&str, &str)] = &[
+    ("emergency_volume", "audio.emergency_volume"),
+    ("voice_confidence_threshold", "voice.confidence_threshold"),
+    ("auto_dial_911", "emergency.auto_dial_911"),
+    ("enable_location_sharing", "emergency.enable_location_sharing"),
+    ("enable_audio_recording", "emergency.enable_audio_recording"),
+    ("enable_family_alerts", "emergency.enable_family_alerts"),
+    ("ble_coordination_timeout", "coordination.coordination_timeout"),
+    ("max_nearby_devices", "coordination.max_nearby_devices"),
+    ("battery_threshold", "coordination.battery_threshold"),
+    ("ui_theme", "ui.theme"),
+    ("ui_language", "ui.language"),
+];
+
+/// Where a setting's effective value came from, lowest precedence first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigLayer {
+    /// Built-in default
+    Default,
+    /// Config file
+    File,
+    /// Environment variable
+    Environment,
+    /// Persisted user setting
+    UserSettings,
+    /// Set at runtime
+    Runtime,
+}
+
+impl ConfigLayer {
+    /// Gets the display name for the layer
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ConfigLayer::Default => "default",
+            ConfigLayer::File => "config file",
+            ConfigLayer::Environment => "environment",
+            ConfigLayer::UserSettings => "user settings",
+            ConfigLayer::Runtime => "runtime",
+        }
+    }
+}
+
+/// Effective value of one setting and the layer it came from
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingSource {
+    /// Dotted key, e.g. "voice.confidence_threshold"
+    pub key: String,
+    /// Effective value in TOML notation
+    pub value: String,
+    /// Layer that set the value
+    pub layer: ConfigLayer,
+}
+
+/// Persisted user settings, stored as `setting_key`/`setting_value` rows
+///
+/// Keys are either the legacy `user_settings` names (`ui_theme`) or dotted
+/// config keys (`ui.theme`). Values are text, as in the table.
+pub trait UserSettingsStore: Send {
+    /// Load every row
+    fn load(&self) -> AppResult<BTreeMap<String, String>>;
+
+    /// Replace every row
+    fn save(&mut self, settings: &BTreeMap<String, String>) -> AppResult<()>;
+}
+
+impl UserSettingsStore for BTreeMap<String, String> {
+    fn load(&self) -> AppResult<BTreeMap<String, String>> {
+        Ok(self.clone())
+    }
+
+    fn save(&mut self, settings: &BTreeMap<String, String>) -> AppResult<()> {
+        self.clone_from(settings);
+        Ok(())
+    }
+}
+
+/// User settings kept in a JSON object file until the SQLite store returns
+pub struct JsonUserSettings {
+    path: PathBuf,
+}
+
+impl JsonUserSettings {
+    /// Create a store backed by the given file
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+}
+
+impl UserSettingsStore for JsonUserSettings {
+    fn load(&self) -> AppResult<BTreeMap<String, String>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| AppError::Config(format!("{}: {}", self.path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(AppError::Config(format!("{}: {}", self.path.display(), e))),
+        }
+    }
+
+    fn save(&mut self, settings: &BTreeMap<String, String>) -> AppResult<()> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text = serde_json::to_string_pretty(settings)?;
+        std::fs::write(&self.path, text)
+            .map_err(|e| AppError::Config(format!("{}: {}", self.path.display(), e)))
+    }
+}
+
+/// Something that takes the effective config when it changes
+pub trait ConfigTarget {
+    /// Apply the relevant part of `config`
+    fn apply_config(&mut self, config: &AppConfig);
+}
+
+impl ConfigTarget for VoiceInterface {
+    fn apply_config(&mut self, config: &AppConfig) {
+        self.update_config(config.voice.clone());
+    }
+}
+
+impl ConfigTarget for VoiceStream {
+    fn apply_config(&mut self, config: &AppConfig) {
+        self.voice_mut().apply_config(config);
+    }
+}
+
+impl ConfigTarget for AudioProcessor {
+    fn apply_config(&mut self, config: &AppConfig) {
+        self.update_config(config.audio.clone());
+    }
+}
+
+impl ConfigTarget for CrashDetector {
+    fn apply_config(&mut self, config: &AppConfig) {
+        self.configure(&config.emergency);
+    }
+}
+
+impl ConfigTarget for FallDetector {
+    fn apply_config(&mut self, config: &AppConfig) {
+        self.configure(&config.emergency);
+    }
+}
+
+impl ConfigTarget for LocationManager {
+    fn apply_config(&mut self, config: &AppConfig) {
+        self.configure(&config.emergency);
+    }
+}
+
+impl ConfigTarget for GestureRecognizer {
+    fn apply_config(&mut self, config: &AppConfig) {
+        self.configure(&config.emergency);
+    }
+}
+
+/// Apply every config change to `target` until the settings are dropped
+pub async fn follow_settings<T: ConfigTarget>(
+    mut settings: watch::Receiver<AppConfig>,
+    target: Arc<tokio::sync::Mutex<T>>,
+) {
+    while settings.changed().await.is_ok() {
+        let config = settings.borrow_and_update().clone();
+        target.lock().await.apply_config(&config);
+    }
+}
+
+/// Raw inputs of the file, environment and user settings layers
+#[derive(Debug, Clone, Default, PartialEq)]
+struct LayerInputs {
+    file: Option<String>,
+    env: BTreeMap<String, String>,
+    user: BTreeMap<String, String>,
+}
+
+/// Effective configuration resolved from all layers
+pub struct LayeredConfig {
+    defaults: AppConfig,
+    path: Option<PathBuf>,
+    user_settings: Option<Box<dyn UserSettingsStore>>,
+    runtime: BTreeMap<String, toml::Value>,
+    inputs: LayerInputs,
+    effective: AppConfig,
+    provenance: BTreeMap<String, ConfigLayer>,
+    updates: watch::Sender<AppConfig>,
+}
+
+impl LayeredConfig {
+    /// Resolve defaults, the config file and the environment
+    pub fn load<P: AsRef<Path>>(path: P) -> AppResult<Self> {
+        Self::build(AppConfig::default(), Some(path.as_ref().to_path_buf()))
+    }
+
+    /// Resolve from an in-memory config, which takes the place of the defaults
+    pub fn from_config(config: AppConfig) -> AppResult<Self> {
+        Self::build(config, None)
+    }
+
+    fn build(defaults: AppConfig, path: Option<PathBuf>) -> AppResult<Self> {
+        let mut layered = Self {
+            effective: defaults.clone(),
+            updates: watch::channel(defaults.clone()).0,
+            defaults,
+            path,
+            user_settings: None,
+            runtime: BTreeMap::new(),
+            inputs: LayerInputs::default(),
+            provenance: BTreeMap::new(),
+        };
+        layered.refresh(true)?;
+        Ok(layered)
+    }
+
+    /// Add the persisted user settings layer
+    pub fn with_user_settings(mut self, store: Box<dyn UserSettingsStore>) -> AppResult<Self> {
+        self.user_settings = Some(store);
+        self.refresh(true)?;
+        Ok(self)
+    }
+
+    /// Get the effective configuration
+    pub fn effective(&self) -> &AppConfig {
+        &self.effective
+    }
+
+    /// Subscribe to effective configuration changes
+    pub fn subscribe(&self) -> watch::Receiver<AppConfig> {
+        self.updates.subscribe()
+    }
+
+    /// Re-read the file, environment and user settings
+    ///
+    /// Returns whether the effective configuration changed. An invalid
+    /// change is rejected and the previous configuration stays in effect.
+    pub fn reload(&mut self) -> AppResult<bool> {
+        self.refresh(false)
+    }
+
+    /// Override a setting until it is cleared, e.g. `set_runtime("audio.emergency_volume", "0.9")`
+    pub fn set_runtime(&mut self, key: &str, value: &str) -> AppResult<()> {
+        let current = toml::Table::try_from(&self.effective).map_err(|e| AppError::Config(e.to_string()))?;
+        let value = coerce(value, get_path(&current, key))
+            .map_err(|message| AppError::Config(format!("{}: {}", key, message)))?;
+
+        let previous = self.runtime.insert(key.to_string(), value);
+        if let Err(issues) = self.apply(self.inputs.clone()) {
+            match previous {
+                Some(previous) => self.runtime.insert(key.to_string(), previous),
+                None => self.runtime.remove(key),
+            };
+            return Err(AppError::Config(format_issues(ConfigLayer::Runtime.display_name(), &issues)));
+        }
+        Ok(())
+    }
+
+    /// Remove a runtime override, returning whether one was set
+    pub fn clear_runtime(&mut self, key: &str) -> AppResult<bool> {
+        if self.runtime.remove(key).is_none() {
+            return Ok(false);
+        }
+        self.apply(self.inputs.clone())
+            .map_err(|issues| AppError::Config(format_issues(ConfigLayer::Runtime.display_name(), &issues)))?;
+        Ok(true)
+    }
+
+    /// Validate settings edits and persist them as user settings
+    ///
+    /// `edits` maps dotted (or legacy `user_settings`) keys to JSON values;
+    /// `null` removes the persisted value. Edits are checked against
+    /// [`config_schema`](crate::schema::config_schema) and then against the
+    /// full configuration, and nothing is written unless every edit passes.
+    pub fn persist_user_settings(&mut self, edits: &serde_json::Map<String, serde_json::Value>) -> AppResult<()> {
+        let mut normalized = serde_json::Map::new();
+        let mut issues = Vec::new();
+        for (name, value) in edits {
+            match user_setting_key(name) {
+                Some(key) => {
+                    normalized.insert(key, value.clone());
+                }
+                None => issues.push(issue(name, "unknown key")),
+            }
+        }
+        issues.extend(crate::schema::validate_edits(&normalized));
+        let origin = ConfigLayer::UserSettings.display_name();
+        if !issues.is_empty() {
+            return Err(AppError::Config(format_issues(origin, &issues)));
+        }
+
+        let store = self
+            .user_settings
+            .as_mut()
+            .ok_or_else(|| AppError::Config("no user settings store is configured".to_string()))?;
+        let mut rows = store.load()?;
+        for (key, value) in &normalized {
+            rows.retain(|name, _| user_setting_key(name).as_deref() != Some(key.as_str()));
+            match value {
+                serde_json::Value::Null => {}
+                serde_json::Value::String(text) => {
+                    rows.insert(key.clone(), text.clone());
+                }
+                other => {
+                    rows.insert(key.clone(), other.to_string());
+                }
+            }
+        }
+
+        // Resolve with the new rows before writing, to catch cross-field problems
+        let mut inputs = self.read_inputs()?;
+        inputs.user = rows.clone();
+        self.resolve(&inputs)
+            .map_err(|issues| AppError::Config(format_issues(origin, &issues)))?;
+
+        if let Some(store) = self.user_settings.as_mut() {
+            store.save(&rows)?;
+        }
+        self.refresh(true).map(|_| ())
+    }
+
+    /// Get the layer that set a dotted key
+    pub fn source_of(&self, key: &str) -> Option<ConfigLayer> {
+        self.provenance.get(key).copied()
+    }
+
+    /// Every effective value with the layer it came from
+    pub fn sources(&self) -> Vec<SettingSource> {
+        let mut leaves = BTreeMap::new();
+        if let Ok(table) = toml::Table::try_from(&self.effective) {
+            flatten("", &toml::Value::Table(table), &mut leaves);
+        }
+        leaves
+            .into_iter()
+            .map(|(key, value)| SettingSource {
+                layer: self.source_of(&key).unwrap_or(ConfigLayer::Default),
+                value: display_value(&value),
+                key,
+            })
+            .collect()
+    }
+
+    fn read_inputs(&self) -> AppResult<LayerInputs> {
+        let file = match &self.path {
+            Some(path) => Some(std::fs::read_to_string(path)
+                .map_err(|e| AppError::Config(format!("{}: {}", path.display(), e)))?),
+            None => None,
+        };
+        let env = std::env::vars().filter(|(name, _)| name.starts_with(ENV_PREFIX)).collect();
+        let user = match &self.user_settings {
+            Some(store) => store.load()?,
+            None => BTreeMap::new(),
+        };
+        Ok(LayerInputs { file, env, user })
+    }
+
+    fn refresh(&mut self, force: bool) -> AppResult<bool> {
+        let inputs = self.read_inputs()?;
+        if !force && inputs == self.inputs {
+            return Ok(false);
+        }
+
+        let origin = match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "settings".to_string(),
+        };
+        // Remember the inputs even when rejected, so a bad edit is reported once
+        let result = self.apply(inputs.clone());
+        self.inputs = inputs;
+        result.map_err(|issues| AppError::Config(format_issues(&origin, &issues)))
+    }
+
+    /// Resolve `inputs` and publish the result if it changed
+    fn apply(&mut self, inputs: LayerInputs) -> Result<bool, Vec<ConfigIssue>> {
+        let (config, provenance) = self.resolve(&inputs)?;
+        self.provenance = provenance;
+
+        let changed = toml::Value::try_from(&config).ok() != toml::Value::try_from(&self.effective).ok();
+        self.effective = config;
+        if changed {
+            self.updates.send_replace(self.effective.clone());
+        }
+        Ok(changed)
+    }
+
+    fn resolve(&self, inputs: &LayerInputs) -> Result<(AppConfig, BTreeMap<String, ConfigLayer>), Vec<ConfigIssue>> {
+        let mut merged = toml::Table::try_from(&self.defaults).map_err(|e| vec![issue("<defaults>", e.to_string())])?;
+        let mut provenance = BTreeMap::new();
+        let mut defaults = BTreeMap::new();
+        flatten("", &toml::Value::Table(merged.clone()), &mut defaults);
+        for key in defaults.into_keys() {
+            provenance.insert(key, ConfigLayer::Default);
+        }
+
+        // The file is checked on its own first so problems carry line numbers
+        if let Some(source) = &inputs.file {
+            AppConfig::from_toml_str(source)?;
+            let table: toml::Table = toml::from_str(source).map_err(|e| vec![issue("<file>", e.message().to_string())])?;
+            let mut leaves = BTreeMap::new();
+            flatten("", &toml::Value::Table(table), &mut leaves);
+            for (key, value) in leaves {
+                set_path(&mut merged, &key, value).map_err(|message| vec![issue(&key, message)])?;
+                provenance.insert(key, ConfigLayer::File);
+            }
+        }
+
+        let mut issues = Vec::new();
+        let mut layer_values = Vec::new();
+        for (name, raw) in &inputs.env {
+            let key = name[ENV_PREFIX.len()..].to_lowercase().replace("__", ".");
+            layer_values.push((key, raw.as_str(), ConfigLayer::Environment));
+        }
+        for (name, raw) in &inputs.user {
+            match user_setting_key(name) {
+                Some(key) => layer_values.push((key, raw.as_str(), ConfigLayer::UserSettings)),
+                None => tracing::warn!("Ignoring unknown user setting '{}'", name),
+            }
+        }
+        for (key, raw, layer) in layer_values {
+            let value = match coerce(raw, get_path(&merged, &key)) {
+                Ok(value) => value,
+                Err(message) => {
+                    issues.push(issue(&key, format!("{} (set by {})", message, layer.display_name())));
+                    continue;
+                }
+            };
+            match set_path(&mut merged, &key, value) {
+                Ok(()) => {
+                    provenance.insert(key, layer);
+                }
+                Err(message) => issues.push(issue(&key, message)),
+            }
+        }
+
+        for (key, value) in &self.runtime {
+            match set_path(&mut merged, key, value.clone()) {
+                Ok(()) => {
+                    provenance.insert(key.clone(), ConfigLayer::Runtime);
+                }
+                Err(message) => issues.push(issue(key, message)),
+            }
+        }
+        if !issues.is_empty() {
+            return Err(issues);
+        }
+
+        let mut unknown = Vec::new();
+        let config: AppConfig = serde_ignored::deserialize(toml::Value::Table(merged), |path| unknown.push(path.to_string()))
+            .map_err(|e| vec![issue("<settings>", e.message().to_string())])?;
+
+        let layer_of = |key: &str| provenance.get(key).copied().unwrap_or(ConfigLayer::Default);
+        for key in unknown {
+            let message = format!("unknown key (set by {})", layer_of(&key).display_name());
+            issues.push(issue(&key, message));
+        }
+        for mut problem in config.validate() {
+            problem.message = format!("{} (set by {})", problem.message, layer_of(&problem.key).display_name());
+            issues.push(problem);
+        }
+
+        if issues.is_empty() {
+            Ok((config, provenance))
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+fn issue(key: &str, message: impl Into<String>) -> ConfigIssue {
+    ConfigIssue { key: key.to_string(), line: None, message: message.into() }
+}
+
+/// Map a `user_settings` row key to a dotted config key
+fn user_setting_key(name: &str) -> Option<String> {
+    if let Some((_, key)) = USER_SETTING_KEYS.iter().find(|(legacy, _)| *legacy == name) {
+        return Some(key.to_string());
+    }
+    name.contains('.').then(|| name.to_string())
+}
+
+/// Collect leaf values under dotted keys
+fn flatten(prefix: &str, value: &toml::Value, out: &mut BTreeMap<String, toml::Value>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (name, item) in table {
+                let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+                flatten(&key, item, out);
+            }
+        }
+        leaf => {
+            out.insert(prefix.to_string(), leaf.clone());
+        }
+    }
+}
+
+fn get_path<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let mut segments = key.split('.');
+    let mut value = table.get(segments.next()?)?;
+    for segment in segments {
+        value = value.as_table()?.get(segment)?;
+    }
+    Some(value)
+}
+
+fn set_path(table: &mut toml::Table, key: &str, value: toml::Value) -> Result<(), String> {
+    let segments: Vec<&str> = key.split('.').collect();
+    let (last, parents) = segments.split_last().ok_or_else(|| "empty key".to_string())?;
+
+    let mut current = table;
+    for segment in parents {
+        current = current
+            .entry(segment.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| format!("'{}' is not a table", segment))?;
+    }
+    current.insert(last.to_string(), value);
+    Ok(())
+}
+
+/// Convert a text value to the type of the value it replaces
+fn coerce(raw: &str, existing: Option<&toml::Value>) -> Result<toml::Value, String> {
+    let raw = raw.trim();
+    match existing {
+        Some(toml::Value::Boolean(_)) => raw.parse().map(toml::Value::Boolean)
+            .map_err(|_| format!("\"{}\" is not true or false", raw)),
+        Some(toml::Value::Integer(_)) => raw.parse().map(toml::Value::Integer)
+            .map_err(|_| format!("\"{}\" is not an integer", raw)),
+        Some(toml::Value::Float(_)) => raw.parse().map(toml::Value::Float)
+            .map_err(|_| format!("\"{}\" is not a number", raw)),
+        Some(toml::Value::Array(_)) => toml::from_str::<toml::Table>(&format!("value = {}", raw))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .ok_or_else(|| format!("\"{}\" is not a TOML array", raw)),
+        _ => Ok(toml::Value::String(raw.to_string())),
+    }
+}
+
+fn display_value(value: &toml::Value) -> String {
+    match value {
+        // Settings floats are f32, so print them at that precision
+        toml::Value::Float(float) => (*float as f32).to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let path = std::env::temp_dir().join(format!("solana_sos_layers_{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "[audio]\nemergency_volume = 0.6\ndefault_volume = 0.4\n[ui]\ntheme = \"dark\"\n").unwrap();
+
+        let user = BTreeMap::from([
+            ("emergency_volume".to_string(), "0.9".to_string()),
+            ("voice_confidence_threshold".to_string(), "0.7".to_string()),
+        ]);
+        let mut layered = LayeredConfig::load(&path).unwrap().with_user_settings(Box::new(user)).unwrap();
+
+        assert_eq!(layered.effective().audio.emergency_volume, 0.9);
+        assert_eq!(layered.source_of("audio.emergency_volume"), Some(ConfigLayer::UserSettings));
+        assert_eq!(layered.source_of("audio.default_volume"), Some(ConfigLayer::File));
+        assert_eq!(layered.source_of("voice.sample_rate"), Some(ConfigLayer::Default));
+
+        layered.set_runtime("ui.theme", "emergency").unwrap();
+        assert_eq!(layered.effective().ui.theme, "emergency");
+        assert_eq!(layered.source_of("ui.theme"), Some(ConfigLayer::Runtime));
+        assert!(layered.set_runtime("voice.confidence_threshold", "3").is_err());
+        assert_eq!(layered.effective().voice.confidence_threshold, 0.7);
+
+        assert!(layered.clear_runtime("ui.theme").unwrap());
+        assert_eq!(layered.source_of("ui.theme"), Some(ConfigLayer::File));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_applies_to_targets() {
+        let path = std::env::temp_dir().join(format!("solana_sos_reload_{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "[audio]\nenable_enhancement = true\n").unwrap();
+        let mut layered = LayeredConfig::load(&path).unwrap();
+
+        let processor = Arc::new(tokio::sync::Mutex::new(AudioProcessor::new("/tmp")));
+        let follower = tokio::spawn(follow_settings(layered.subscribe(), processor.clone()));
+
+        assert!(!layered.reload().unwrap());
+        std::fs::write(&path, "[audio]\nenable_enhancement = false\n").unwrap();
+        assert!(layered.reload().unwrap());
+
+        // A rejected edit keeps the last good configuration
+        std::fs::write(&path, "[audio]\nenable_enhancement = \"maybe\"\n").unwrap();
+        assert!(layered.reload().is_err());
+        assert!(!layered.effective().audio.enable_enhancement);
+
+        drop(layered);
+        follower.await.unwrap();
+        assert!(!processor.lock().await.get_config().enable_enhancement);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_persist_validates_before_saving() {
+        let user = BTreeMap::from([("ui_theme".to_string(), "dark".to_string())]);
+        let mut layered = LayeredConfig::from_config(AppConfig::default())
+            .unwrap()
+            .with_user_settings(Box::new(user))
+            .unwrap();
+
+        let edits = serde_json::json!({"ui.theme": "light", "emergency.crash_response_window": 60});
+        layered.persist_user_settings(edits.as_object().unwrap()).unwrap();
+        assert_eq!(layered.effective().ui.theme, "light");
+        assert_eq!(layered.effective().emergency.crash_response_window, 60);
+        assert_eq!(layered.source_of("ui.theme"), Some(ConfigLayer::UserSettings));
+
+        let edits = serde_json::json!({"ui.theme": "neon", "emergency.crash_response_window": 10});
+        assert!(layered.persist_user_settings(edits.as_object().unwrap()).is_err());
+        assert_eq!(layered.effective().ui.theme, "light");
+        assert_eq!(layered.effective().emergency.crash_response_window, 60);
+
+        let edits = serde_json::json!({"ui.theme": null});
+        layered.persist_user_settings(edits.as_object().unwrap()).unwrap();
+        assert_eq!(layered.source_of("ui.theme"), Some(ConfigLayer::Default));
+    }
+}
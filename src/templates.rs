@@ -0,0 +1,591 @@
+//! Alert Message Templates
+//!
+//! This module renders the text contacts receive. Each [`AlertKind`] has a
+//! localized template with placeholders:
+//!
+//! - `{name}` - the user's name
+//! - `{type}` - the emergency type
+//! - `{time}` - when it happened, with UTC offset
+//! - `{battery}` - battery level in percent
+//! - `{map}` - map link for the coordinates
+//! - `{plus_code}` - Open Location Code for the coordinates
+//! - `{coords}` - latitude and longitude in degrees
+//! - `{dms}` - degrees, minutes and seconds
+//! - `{utm}` / `{mgrs}` - UTM and MGRS grid references, empty near the poles
+//! - `{accuracy}` - accuracy radius of the position in meters
+//! - `{age}` - how old the position is, e.g. "4 min"
+//! - `{fix_time}` - time of day the position was taken
+//! - `{estimate}` - where the user has likely walked since, e.g. "300 m NE ±150 m"
+//!
+//! Battery and location lines are added when known, and the position's
+//! accuracy and age when it came from the location service, or its
+//! dead-reckoning estimate once GPS was lost. SMS text is split with
+//! [`segment_sms`] using GSM-7 where every character allows it and UCS-2
+//! otherwise. [`AlertTemplates::text_to_911`] packs the most critical facts
+//! into a single segment for text-to-911.
+
+pub use crate::coordinates::plus_code;
+use crate::coordinates::{dms, Utm};
+use crate::location::{format_age, Freshness, LocationReport};
+use crate::public::types::EmergencyType;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Septets in a single GSM-7 SMS
+const GSM7_SINGLE: usize = 160;
+/// Septets per part of a multipart GSM-7 SMS
+const GSM7_PART: usize = 153;
+/// UTF-16 code units in a single UCS-2 SMS
+const UCS2_SINGLE: usize = 70;
+/// UTF-16 code units per part of a multipart UCS-2 SMS
+const UCS2_PART: usize = 67;
+
+/// GSM 03.38 default alphabet, one septet each (escape excluded)
+const GSM7_BASIC: &str = "@£$¥èéùìòÇ\nØø\rÅåΔ_ΦΓΛΩΠΨΣΘΞÆæßÉ !\"#¤%&'()*+,-./0123456789:;<=>?\
+¡ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÑÜ§¿abcdefghijklmnopqrstuvwxyzäöñüà";
+
+/// GSM 03.38 extension table, two septets each
+const GSM7_EXTENDED: &str = "\u{c}^{}\\[~]|€";
+
+/// What an alert is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// The user triggered a silent SOS and may not be able to talk
+    SilentSos,
+    /// A crash was detected
+    CrashDetected,
+    /// A fall was detected and the user didn't answer the voice check
+    FallDetected,
+    /// An emergency of a known type
+    Emergency,
+    /// The location changed after the first alert
+    LocationUpdate,
+    /// The user cancelled; no help is needed
+    StandDown,
+    /// The user was forced to cancel with the duress code
+    Duress,
+}
+
+impl AlertKind {
+    /// Parse a kind such as "silent_sos"
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key.trim().to_lowercase().replace([' ', '-'], "_").as_str() {
+            "silent_sos" => Some(AlertKind::SilentSos),
+            "crash_detected" | "crash_detection" => Some(AlertKind::CrashDetected),
+            "fall_detected" | "fall_detection" => Some(AlertKind::FallDetected),
+            "emergency" | "emergency_activation" => Some(AlertKind::Emergency),
+            "location_update" => Some(AlertKind::LocationUpdate),
+            "stand_down" => Some(AlertKind::StandDown),
+            "duress" => Some(AlertKind::Duress),
+            _ => None,
+        }
+    }
+
+    /// Whether the alert must not show on the user's phone: no calls are
+    /// placed and no delivery events are published
+    pub fn is_covert(self) -> bool {
+        matches!(self, AlertKind::SilentSos | AlertKind::Duress)
+    }
+}
+
+/// Facts an alert is rendered from
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertContext {
+    /// What the alert is about
+    pub kind: AlertKind,
+    /// The user's name; empty if unknown
+    pub name: String,
+    /// Emergency type, for `Emergency` alerts
+    pub emergency_type: Option<EmergencyType>,
+    /// When it happened
+    pub time: chrono::DateTime<chrono::FixedOffset>,
+    /// Battery level in percent
+    pub battery: Option<u8>,
+    /// Latest position as (latitude, longitude)
+    pub location: Option<(f64, f64)>,
+    /// Accuracy and age of the position, if known
+    pub fix: Option<LocationReport>,
+}
+
+impl AlertContext {
+    /// Facts for an alert happening now, in local time
+    pub fn new(kind: AlertKind, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.trim().to_string(),
+            emergency_type: None,
+            time: chrono::Local::now().fixed_offset(),
+            battery: None,
+            location: None,
+            fix: None,
+        }
+    }
+
+    /// Set the emergency type
+    pub fn with_emergency_type(mut self, emergency_type: EmergencyType) -> Self {
+        self.emergency_type = Some(emergency_type);
+        self
+    }
+
+    /// Set the battery level in percent
+    pub fn with_battery(mut self, percent: u8) -> Self {
+        self.battery = Some(percent.min(100));
+        self
+    }
+
+    /// Set the position
+    pub fn with_location(mut self, latitude: f64, longitude: f64) -> Self {
+        self.location = Some((latitude, longitude));
+        self
+    }
+
+    /// Set the position with its accuracy and age
+    pub fn with_report(mut self, report: LocationReport) -> Self {
+        self.location = Some((report.latitude, report.longitude));
+        self.fix = Some(report);
+        self
+    }
+}
+
+/// Built-in wording for one language
+struct Phrases {
+    silent_sos: &'static str,
+    crash_detected: &'static str,
+    fall_detected: &'static str,
+    emergency: &'static str,
+    location_update: &'static str,
+    stand_down: &'static str,
+    duress: &'static str,
+    battery: &'static str,
+    location: &'static str,
+    fix_live: &'static str,
+    fix_recent: &'static str,
+    fix_stale: &'static str,
+    fix_estimate: &'static str,
+    no_location: &'static str,
+    someone: &'static str,
+    unknown_type: &'static str,
+}
+
+const EN: Phrases = Phrases {
+    silent_sos: "SOS from {name} at {time}. They may not be able to talk; do not call them.",
+    crash_detected: "{name} may have been in a car crash at {time}.",
+    fall_detected: "{name} may have fallen at {time} and did not respond.",
+    emergency: "{name} has an emergency ({type}) at {time}.",
+    location_update: "Location update from {name} at {time}.",
+    stand_down: "{name} cancelled the alert at {time}. No help is needed.",
+    duress: "{name} was forced to cancel an emergency alert at {time} and may be in danger. Do not call or text them; contact the police.",
+    battery: "Battery {battery}%.",
+    location: "Location: {map} (plus code {plus_code})",
+    fix_live: "Accurate to {accuracy} m, live.",
+    fix_recent: "Accurate to {accuracy} m, {age} old.",
+    fix_stale: "Last known position, {age} old, accurate to {accuracy} m.",
+    fix_estimate: "Last known at {fix_time}, estimated {estimate}.",
+    no_location: "Location not yet known.",
+    someone: "Your contact",
+    unknown_type: "emergency",
+};
+
+const ES: Phrases = Phrases {
+    silent_sos: "SOS de {name} a las {time}. Puede que no pueda hablar; no le llames.",
+    crash_detected: "{name} puede haber tenido un accidente de coche a las {time}.",
+    fall_detected: "{name} puede haberse caído a las {time} y no ha respondido.",
+    emergency: "{name} tiene una emergencia ({type}) a las {time}.",
+    location_update: "Nueva ubicación de {name} a las {time}.",
+    stand_down: "{name} canceló la alerta a las {time}. No necesita ayuda.",
+    duress: "{name} fue obligado a cancelar una alerta de emergencia a las {time} y puede estar en peligro. No le llames ni le escribas; avisa a la policía.",
+    battery: "Batería {battery}%.",
+    location: "Ubicación: {map} (plus code {plus_code})",
+    fix_live: "Precisión {accuracy} m, en tiempo real.",
+    fix_recent: "Precisión {accuracy} m, de hace {age}.",
+    fix_stale: "Última ubicación conocida, de hace {age}, precisión {accuracy} m.",
+    fix_estimate: "Última ubicación conocida a las {fix_time}, estimada a {estimate}.",
+    no_location: "Ubicación aún desconocida.",
+    someone: "Tu contacto",
+    unknown_type: "emergencia",
+};
+
+const FR: Phrases = Phrases {
+    silent_sos: "SOS de {name} à {time}. Il se peut qu'il ne puisse pas parler ; ne l'appelez pas.",
+    crash_detected: "{name} a peut-être eu un accident de voiture à {time}.",
+    fall_detected: "{name} a peut-être fait une chute à {time} et n'a pas répondu.",
+    emergency: "{name} a une urgence ({type}) à {time}.",
+    location_update: "Nouvelle position de {name} à {time}.",
+    stand_down: "{name} a annulé l'alerte à {time}. Aucune aide n'est nécessaire.",
+    duress: "{name} a été contraint d'annuler une alerte d'urgence à {time} et est peut-être en danger. Ne l'appelez pas et ne lui écrivez pas ; prévenez la police.",
+    battery: "Batterie {battery} %.",
+    location: "Position : {map} (plus code {plus_code})",
+    fix_live: "Précision {accuracy} m, en direct.",
+    fix_recent: "Précision {accuracy} m, datant de {age}.",
+    fix_stale: "Dernière position connue, datant de {age}, précision {accuracy} m.",
+    fix_estimate: "Dernière position connue à {fix_time}, estimée à {estimate}.",
+    no_location: "Position encore inconnue.",
+    someone: "Votre contact",
+    unknown_type: "urgence",
+};
+
+/// Localized alert templates
+#[derive(Clone)]
+pub struct AlertTemplates {
+    language: &'static str,
+    phrases: &'static Phrases,
+    overrides: HashMap<AlertKind, String>,
+}
+
+impl Default for AlertTemplates {
+    fn default() -> Self {
+        Self::for_language("en")
+    }
+}
+
+impl std::fmt::Debug for AlertTemplates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AlertTemplates")
+            .field("language", &self.language)
+            .field("overrides", &self.overrides)
+            .finish()
+    }
+}
+
+impl AlertTemplates {
+    /// Templates for a language tag such as "es" or "fr-CA"
+    ///
+    /// English, Spanish and French are built in; other languages use English.
+    pub fn for_language(language: &str) -> Self {
+        let primary = language.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        let (language, phrases) = match primary.as_str() {
+            "es" => ("es", &ES),
+            "fr" => ("fr", &FR),
+            _ => ("en", &EN),
+        };
+        Self { language, phrases, overrides: HashMap::new() }
+    }
+
+    /// Language the templates are in
+    pub fn language(&self) -> &str {
+        self.language
+    }
+
+    /// Replace the headline template of `kind`
+    pub fn with_template(mut self, kind: AlertKind, template: &str) -> Self {
+        self.overrides.insert(kind, template.to_string());
+        self
+    }
+
+    /// Render the alert for contacts
+    pub fn render(&self, context: &AlertContext) -> String {
+        let phrases = self.phrases;
+        let headline = self.overrides.get(&context.kind).map(String::as_str).unwrap_or(match context.kind {
+            AlertKind::SilentSos => phrases.silent_sos,
+            AlertKind::CrashDetected => phrases.crash_detected,
+            AlertKind::FallDetected => phrases.fall_detected,
+            AlertKind::Emergency => phrases.emergency,
+            AlertKind::LocationUpdate => phrases.location_update,
+            AlertKind::StandDown => phrases.stand_down,
+            AlertKind::Duress => phrases.duress,
+        });
+
+        let mut lines = vec![headline];
+        if context.kind != AlertKind::StandDown {
+            if context.battery.is_some() {
+                lines.push(phrases.battery);
+            }
+            lines.push(if context.location.is_some() { phrases.location } else { phrases.no_location });
+            if let (Some(_), Some(fix)) = (context.location, &context.fix) {
+                lines.push(match fix.freshness {
+                    _ if fix.estimate.is_some() => phrases.fix_estimate,
+                    Freshness::Live => phrases.fix_live,
+                    Freshness::Recent => phrases.fix_recent,
+                    Freshness::Stale => phrases.fix_stale,
+                });
+            }
+        }
+        self.fill(&lines.join(" "), context)
+    }
+
+    /// Render the alert for text-to-911, in English and within one SMS segment
+    ///
+    /// Facts are added in order of importance (what, where, who, battery,
+    /// when) for as long as the text still fits a single segment.
+    pub fn text_to_911(&self, context: &AlertContext) -> String {
+        let what = match context.kind {
+            AlertKind::SilentSos => "SILENT SOS, caller may not be able to talk".to_string(),
+            AlertKind::CrashDetected => "CAR CRASH detected".to_string(),
+            AlertKind::FallDetected => "FALL detected, no response".to_string(),
+            AlertKind::StandDown => "CANCELLED, no help needed".to_string(),
+            AlertKind::Duress => "DURESS, caller was forced to cancel an alert".to_string(),
+            AlertKind::Emergency | AlertKind::LocationUpdate => match &context.emergency_type {
+                Some(emergency_type) => format!("EMERGENCY: {}", emergency_type.display_name()),
+                None => "EMERGENCY".to_string(),
+            },
+        };
+
+        let mut facts = vec![what];
+        match context.location {
+            Some((latitude, longitude)) => {
+                let quality = match &context.fix {
+                    Some(fix) if fix.freshness == Freshness::Live => format!(" within {:.0} m", fix.accuracy_m),
+                    Some(fix) => format!(" within {:.0} m, {} old", fix.accuracy_m, format_age(fix.age_secs)),
+                    None => String::new(),
+                };
+                facts.push(format!("Location {:.5},{:.5}{}", latitude, longitude, quality));
+                if let Some(estimate) = context.fix.and_then(|fix| fix.estimate) {
+                    // Stay within GSM-7 so the fact doesn't force UCS-2
+                    facts.push(format!("Since then est. {} on foot", estimate.offset_label().replace('±', "+/-")));
+                }
+                facts.push(format!("Plus code {}", plus_code(latitude, longitude)));
+            }
+            None => facts.push("Location unknown".to_string()),
+        }
+        if !context.name.is_empty() {
+            facts.push(format!("Name: {}", context.name));
+        }
+        if let Some(battery) = context.battery {
+            facts.push(format!("Phone battery {}%", battery));
+        }
+        facts.push(format!("Sent by Solana SOS at {}", format_time(context)));
+
+        let mut text = String::new();
+        for fact in facts {
+            let candidate = if text.is_empty() { fact } else { format!("{}. {}", text, fact) };
+            if segment_sms(&candidate).segments.len() > 1 {
+                continue;
+            }
+            text = candidate;
+        }
+        text
+    }
+
+    fn fill(&self, template: &str, context: &AlertContext) -> String {
+        let name = if context.name.is_empty() { self.phrases.someone } else { context.name.as_str() };
+        let emergency_type = match &context.emergency_type {
+            Some(emergency_type) => type_name(self.language, emergency_type),
+            None => self.phrases.unknown_type,
+        };
+        let mut text = template
+            .replace("{name}", name)
+            .replace("{type}", emergency_type)
+            .replace("{time}", &format_time(context))
+            .replace("{battery}", &context.battery.map(|battery| battery.to_string()).unwrap_or_default());
+        if let Some((latitude, longitude)) = context.location {
+            text = text
+                .replace("{map}", &map_url(latitude, longitude))
+                .replace("{plus_code}", &plus_code(latitude, longitude))
+                .replace("{coords}", &format!("{:.5},{:.5}", latitude, longitude))
+                .replace("{dms}", &dms(latitude, longitude));
+            let utm = Utm::from_degrees(latitude, longitude);
+            text = text
+                .replace("{utm}", &utm.map(|utm| utm.to_string()).unwrap_or_default())
+                .replace("{mgrs}", &utm.map(|utm| utm.mgrs().to_string()).unwrap_or_default());
+        }
+        if let Some(fix) = &context.fix {
+            text = text
+                .replace("{accuracy}", &format!("{:.0}", fix.accuracy_m))
+                .replace("{age}", &format_age(fix.age_secs));
+            if let Some(estimate) = &fix.estimate {
+                let fix_time = estimate.anchor.time.with_timezone(&context.time.timezone());
+                let mut offset = estimate.offset_label();
+                if matches!(self.language, "es" | "fr") {
+                    // Oeste / ouest
+                    offset = offset.replace('W', "O");
+                }
+                text = text
+                    .replace("{fix_time}", &fix_time.format("%H:%M").to_string())
+                    .replace("{estimate}", &offset);
+            }
+        }
+        text
+    }
+}
+
+fn format_time(context: &AlertContext) -> String {
+    context.time.format("%H:%M UTC%:z").to_string()
+}
+
+/// Localized emergency type name
+fn type_name(language: &str, emergency_type: &EmergencyType) -> &'static str {
+    use EmergencyType::*;
+    match (language, emergency_type) {
+        ("es", Drowning) => "ahogamiento",
+        ("es", HeartAttack) => "ataque al corazón",
+        ("es", Stroke) => "derrame cerebral",
+        ("es", Choking) => "atragantamiento",
+        ("es", Bleeding) => "hemorragia",
+        ("es", Unconscious) => "inconsciente",
+        ("es", Seizure) => "convulsiones",
+        ("es", Poisoning) => "intoxicación",
+        ("es", SevereBurns) => "quemaduras graves",
+        ("es", DiabeticEmergency) => "emergencia diabética",
+        ("es", AllergicReaction) => "reacción alérgica",
+        ("es", Trauma) => "traumatismo",
+        ("fr", Drowning) => "noyade",
+        ("fr", HeartAttack) => "crise cardiaque",
+        ("fr", Stroke) => "AVC",
+        ("fr", Choking) => "étouffement",
+        ("fr", Bleeding) => "hémorragie",
+        ("fr", Unconscious) => "perte de connaissance",
+        ("fr", Seizure) => "crise convulsive",
+        ("fr", Poisoning) => "intoxication",
+        ("fr", SevereBurns) => "brûlures graves",
+        ("fr", DiabeticEmergency) => "urgence diabétique",
+        ("fr", AllergicReaction) => "réaction allergique",
+        ("fr", Trauma) => "traumatisme",
+        (_, emergency_type) => emergency_type.display_name(),
+    }
+}
+
+/// Map link for a position
+pub fn map_url(latitude: f64, longitude: f64) -> String {
+    format!("https://maps.google.com/?q={:.5},{:.5}", latitude, longitude)
+}
+
+/// Character set an SMS is sent in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmsEncoding {
+    /// GSM 03.38 7-bit default alphabet
+    Gsm7,
+    /// UCS-2, for text outside the GSM alphabet
+    Ucs2,
+}
+
+/// Text split into SMS segments
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SmsMessage {
+    /// Encoding every segment is sent in
+    pub encoding: SmsEncoding,
+    /// Segment texts, in order
+    pub segments: Vec<String>,
+}
+
+/// Split text into SMS segments
+///
+/// GSM-7 allows 160 septets in a single message and 153 per part when
+/// concatenated, with extension characters such as `€` taking two; UCS-2
+/// allows 70 and 67 UTF-16 units. Characters are never split across parts.
+pub fn segment_sms(text: &str) -> SmsMessage {
+    let gsm7 = text.chars().all(|c| gsm7_septets(c).is_some());
+    let (encoding, single, part): (_, usize, usize) = if gsm7 {
+        (SmsEncoding::Gsm7, GSM7_SINGLE, GSM7_PART)
+    } else {
+        (SmsEncoding::Ucs2, UCS2_SINGLE, UCS2_PART)
+    };
+    let width = |c: char| match encoding {
+        SmsEncoding::Gsm7 => gsm7_septets(c).unwrap_or(1),
+        SmsEncoding::Ucs2 => c.len_utf16(),
+    };
+
+    let total: usize = text.chars().map(width).sum();
+    if total <= single {
+        return SmsMessage { encoding, segments: vec![text.to_string()] };
+    }
+
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        if used + width(c) > part {
+            segments.push(std::mem::take(&mut current));
+            used = 0;
+        }
+        current.push(c);
+        used += width(c);
+    }
+    segments.push(current);
+    SmsMessage { encoding, segments }
+}
+
+/// Septets `c` takes in GSM-7, or `None` if it is not in the alphabet
+fn gsm7_septets(c: char) -> Option<usize> {
+    if GSM7_BASIC.contains(c) {
+        Some(1)
+    } else if GSM7_EXTENDED.contains(c) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dead_reckoning::Estimate;
+
+    fn context(kind: AlertKind) -> AlertContext {
+        AlertContext {
+            kind,
+            name: "Alex".to_string(),
+            emergency_type: Some(EmergencyType::HeartAttack),
+            time: chrono::DateTime::parse_from_rfc3339("2024-05-01T14:05:00-04:00").unwrap(),
+            battery: Some(12),
+            location: Some((47.365590, 8.524997)),
+            fix: None,
+        }
+    }
+
+    #[test]
+    fn test_renders_localized_templates() {
+        let en = AlertTemplates::default().render(&context(AlertKind::Emergency));
+        assert_eq!(
+            en,
+            "Alex has an emergency (Heart Attack) at 14:05 UTC-04:00. Battery 12%. \
+             Location: https://maps.google.com/?q=47.36559,8.52500 (plus code 8FVC9G8F+6X)"
+        );
+
+        let es = AlertTemplates::for_language("es-MX").render(&context(AlertKind::Emergency));
+        assert!(es.starts_with("Alex tiene una emergencia (ataque al corazón)"));
+
+        let mut unknown = context(AlertKind::SilentSos);
+        unknown.name.clear();
+        unknown.location = None;
+        let custom = AlertTemplates::for_language("fr")
+            .with_template(AlertKind::SilentSos, "{name}: SOS silencieux ({battery}%)")
+            .render(&unknown);
+        assert_eq!(custom, "Votre contact: SOS silencieux (12%) Batterie 12 %. Position encore inconnue.");
+
+        let grid = AlertTemplates::default()
+            .with_template(AlertKind::Emergency, "{name} at {mgrs} / {dms}.")
+            .render(&context(AlertKind::Emergency));
+        assert!(grid.starts_with("Alex at 32T MT 64134 45901 / 47°21'56.1\"N 8°31'30.0\"E."));
+
+        let fix = crate::location::Fix::new(47.365590, 8.524997, 45.0, chrono::Utc::now()).unwrap();
+        let aged = context(AlertKind::LocationUpdate).with_report(fix.report(fix.time + chrono::Duration::seconds(250)));
+        assert!(AlertTemplates::default().render(&aged).ends_with("(plus code 8FVC9G8F+6X) Accurate to 45 m, 4 min old."));
+        assert!(AlertTemplates::default().text_to_911(&aged).contains("Location 47.36559,8.52500 within 45 m, 4 min old."));
+        let stale = context(AlertKind::Emergency).with_report(fix.report(fix.time + chrono::Duration::hours(2)));
+        assert!(AlertTemplates::for_language("es").render(&stale).ends_with("Última ubicación conocida, de hace 2 h, precisión 45 m."));
+
+        let mut walked = fix.report(fix.time + chrono::Duration::minutes(5));
+        let (latitude, longitude) = fix.offset_by(-212.0, 212.0);
+        walked.estimate = Some(Estimate { anchor: fix, latitude, longitude, radius_m: 140.0, east_m: -212.0, north_m: 212.0, steps: 430 });
+        let walked = context(AlertKind::LocationUpdate).with_report(walked);
+        assert!(AlertTemplates::for_language("fr").render(&walked).ends_with(", estimée à 300 m NO ±140 m."));
+        assert!(AlertTemplates::default().text_to_911(&walked).contains("Since then est. 300 m NW +/-140 m on foot."));
+    }
+
+    #[test]
+    fn test_segments_sms_and_fits_text_to_911() {
+        let short = segment_sms("Help at the lake {€}");
+        assert_eq!((short.encoding, short.segments.len()), (SmsEncoding::Gsm7, 1));
+
+        // Extension characters count twice and are never split
+        let long = segment_sms(&"€".repeat(100));
+        assert_eq!(long.segments.iter().map(|s| s.chars().count()).collect::<Vec<_>>(), [76, 24]);
+
+        // Surrogate pairs stay whole
+        let unicode = segment_sms(&format!("{}😀şşş", "ş".repeat(66)));
+        assert_eq!(unicode.encoding, SmsEncoding::Ucs2);
+        assert_eq!(unicode.segments, ["ş".repeat(66), "😀şşş".to_string()]);
+
+        let text = AlertTemplates::for_language("es").text_to_911(&context(AlertKind::CrashDetected));
+        assert_eq!(segment_sms(&text).segments.len(), 1);
+        assert!(text.starts_with("CAR CRASH detected. Location 47.36559,8.52500. Plus code 8FVC9G8F+6X. Name: Alex"));
+
+        let mut long_name = context(AlertKind::SilentSos);
+        long_name.name = "Ş".repeat(40);
+        let text = AlertTemplates::default().text_to_911(&long_name);
+        assert_eq!(segment_sms(&text).segments.len(), 1);
+        assert!(text.contains("Plus code") && !text.contains("Name"));
+    }
+}
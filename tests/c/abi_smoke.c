@@ -0,0 +1,96 @@
+/*
+ * Smoke test for the Solana SOS C ABI.
+ *
+ * Built and run by build-c-abi.sh against libsolana_sos on Linux.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "solana_sos.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            fprintf(stderr, "FAIL %s:%d: %s\n", __FILE__, __LINE__, #cond); \
+            failures++;                                               \
+        }                                                             \
+    } while (0)
+
+int main(void) {
+    printf("libsolana_sos %s\n", sos_version());
+
+    SosEngine *engine = sos_engine_new();
+    CHECK(engine != NULL);
+    if (engine == NULL) {
+        return 1;
+    }
+
+    /* Emergency processing returns caller-owned JSON */
+    char *json = NULL;
+    CHECK(sos_engine_process_emergency(engine, "drowning", "not breathing", &json) == SOS_STATUS_OK);
+    CHECK(json != NULL && strstr(json, "\"instruction\"") != NULL);
+    sos_string_free(json);
+
+    char *types = NULL;
+    CHECK(sos_engine_emergency_types(engine, &types) == SOS_STATUS_OK);
+    CHECK(types != NULL && types[0] == '[');
+    sos_string_free(types);
+
+    /* Argument errors are reported, not crashed on */
+    json = NULL;
+    CHECK(sos_engine_process_emergency(engine, NULL, "x", &json) == SOS_STATUS_NULL_ARGUMENT);
+    CHECK(json == NULL);
+    CHECK(sos_engine_last_error(engine) != NULL);
+    CHECK(sos_engine_process_emergency(engine, "\xff\xfe", "x", &json) == SOS_STATUS_INVALID_UTF8);
+
+    CHECK(sos_engine_add_contact(engine, "Mom", "+1 202 555 0143", "Mother") == SOS_STATUS_OK);
+    CHECK(sos_engine_last_error(engine) == NULL);
+    CHECK(sos_engine_add_contact(engine, "Mum", "(202) 555-0143", "Mother") == SOS_STATUS_INVALID_INPUT);
+    CHECK(sos_engine_add_contact(engine, "Bob", "555-1234", "Friend") == SOS_STATUS_INVALID_INPUT);
+
+    /* One second of loud audio in frame-sized pushes */
+    int16_t frame[SOS_STREAM_FRAME_SAMPLES];
+    for (size_t i = 0; i < SOS_STREAM_FRAME_SAMPLES; i++) {
+        frame[i] = (i % 2 == 0) ? 30000 : -30000;
+    }
+    for (int i = 0; i < SOS_STREAM_SAMPLE_RATE / SOS_STREAM_FRAME_SAMPLES; i++) {
+        char *text = NULL;
+        CHECK(sos_engine_push_audio(engine, frame, SOS_STREAM_FRAME_SAMPLES, &text) == SOS_STATUS_OK);
+        sos_string_free(text);
+    }
+
+    char *text = NULL;
+    CHECK(sos_engine_push_audio(engine, NULL, 0, &text) == SOS_STATUS_OK);
+    CHECK(text == NULL);
+
+    /* Settings edits are checked against the config schema */
+    char *schema = NULL;
+    CHECK(sos_config_schema(&schema) == SOS_STATUS_OK);
+    CHECK(schema != NULL && strstr(schema, "\"properties\"") != NULL);
+    sos_string_free(schema);
+
+    char *errors = NULL;
+    CHECK(sos_settings_validate("{\"ui.theme\": \"dark\"}", &errors) == SOS_STATUS_OK);
+    CHECK(errors != NULL && strcmp(errors, "[]") == 0);
+    sos_string_free(errors);
+    CHECK(sos_settings_validate("{\"ui.theme\": \"neon\"}", &errors) == SOS_STATUS_OK);
+    CHECK(errors != NULL && strstr(errors, "ui.theme") != NULL);
+    sos_string_free(errors);
+    CHECK(sos_settings_validate("[]", &errors) == SOS_STATUS_INVALID_INPUT);
+
+    sos_engine_free(engine);
+    sos_engine_free(engine);
+    sos_engine_free(NULL);
+    sos_string_free(NULL);
+
+    if (failures > 0) {
+        fprintf(stderr, "%d check(s) failed\n", failures);
+        return 1;
+    }
+    printf("C ABI smoke test passed\n");
+    return 0;
+}
@@ -0,0 +1,349 @@
+//! Runtime integration tests: a full runtime with its files in a temporary
+//! directory, driven through its public channels
+
+use solana_sos::app::AppStatus;
+use solana_sos::config::{AppConfig, SilentSosMethod};
+use solana_sos::contacts::{Contact, ContactTier, NotificationChannel};
+use solana_sos::dialer::{EmergencyDialer, LocalDialer};
+use solana_sos::diagnostics::HealthReport;
+use solana_sos::duress::CancelMethod;
+use solana_sos::emergency_numbers::CountryHints;
+use solana_sos::error::AppResult;
+use solana_sos::event_bus::{AppEvent, EventBus, Subscription};
+use solana_sos::fall::FALL_CHECK_QUESTION;
+use solana_sos::gesture::SilentSosTrigger;
+use solana_sos::notifier::{DeliveryRouter, LocalNotifier};
+use solana_sos::public::types::ConnectivityMode;
+use solana_sos::public::voice_interface::Speaker;
+use solana_sos::public::voice_stream::{DetectionKind, VoiceDetection};
+use solana_sos::runtime::{AppRuntime, HostServices, ProtocolCommand, RestartPolicy, TimelineEntry};
+use solana_sos::sensors::{SensorReading, SensorSample, SensorTrace, STANDARD_GRAVITY};
+use solana_sos::settings::LayeredConfig;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// A full runtime with its files in a fresh temporary directory
+struct RuntimeHarness {
+    runtime: AppRuntime,
+    events: Subscription,
+    history: PathBuf,
+}
+
+impl RuntimeHarness {
+    fn start(configure: impl FnOnce(&mut AppConfig), host: HostServices) -> Self {
+        Self::start_in(&temp_dir(), configure, host)
+    }
+
+    fn start_in(dir: &Path, configure: impl FnOnce(&mut AppConfig), host: HostServices) -> Self {
+        let mut config = AppConfig::default();
+        config.database.emergency_history_path = dir.join("history.jsonl").to_string_lossy().into_owned();
+        config.database.contacts_path = dir.join("contacts.json").to_string_lossy().into_owned();
+        config.database.last_location_path = dir.join("last_location.json").to_string_lossy().into_owned();
+        configure(&mut config);
+        let history = config.database.emergency_history_path.clone().into();
+        let settings = Arc::new(Mutex::new(LayeredConfig::from_config(config).unwrap()));
+        let event_bus = EventBus::new();
+        let events = event_bus.tap();
+        let runtime = AppRuntime::start(
+            settings,
+            Arc::new(watch::channel(AppStatus::Running).0),
+            Arc::new(watch::channel(HealthReport::default()).0),
+            watch::channel(CountryHints::default()).1,
+            event_bus,
+            host,
+            RestartPolicy::default(),
+        );
+        Self { runtime, events, history }
+    }
+
+    /// Wait for the first event `pick` accepts
+    async fn next<T>(&mut self, mut pick: impl FnMut(AppEvent) -> Option<T>) -> T {
+        let wait = async {
+            loop {
+                if let Some(found) = self.events.recv().await.and_then(&mut pick) {
+                    return found;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(30), wait).await.expect("event not published")
+    }
+
+    /// Shut down and read back the incident timeline
+    async fn timeline(self) -> Vec<TimelineEntry> {
+        self.runtime.shutdown(Duration::from_secs(5)).await.unwrap();
+        read_timeline(&self.history)
+    }
+}
+
+fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("solana_sos_runtime_{}", uuid::Uuid::new_v4()))
+}
+
+fn read_timeline(history: &Path) -> Vec<TimelineEntry> {
+    let text = std::fs::read_to_string(history).unwrap_or_default();
+    text.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+fn host(dialer: Arc<dyn EmergencyDialer>) -> HostServices {
+    HostServices { notifier: DeliveryRouter::new(), dialer, speaker: None }
+}
+
+/// Wait until `done` holds
+async fn until(mut done: impl FnMut() -> bool) {
+    let wait = async {
+        while !done() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(30), wait).await.expect("condition never held");
+}
+
+#[derive(Default)]
+struct RecordingSpeaker {
+    spoken: Mutex<Vec<String>>,
+}
+
+impl Speaker for RecordingSpeaker {
+    fn speak(&self, text: &str) -> AppResult<()> {
+        self.spoken.lock().unwrap().push(text.to_string());
+        Ok(())
+    }
+}
+
+/// 50 Hz accelerometer samples of walking, a fall and lying still
+fn fall_samples() -> Vec<SensorSample> {
+    let segment = |start: u64, duration_ms: u64, g: &dyn Fn(u64) -> f64| -> Vec<SensorSample> {
+        (0..duration_ms / 20)
+            .map(|i| {
+                let t = start + i * 20;
+                SensorSample::new(t, SensorReading::Accel { x: 0.0, y: 0.0, z: g(t) * STANDARD_GRAVITY })
+            })
+            .collect()
+    };
+    let mut samples = segment(0, 2000, &|t| 1.0 + 0.3 * ((t as f64) / 150.0).sin());
+    samples.extend(segment(2000, 300, &|_| 0.2));
+    samples.extend(segment(2300, 60, &|_| 3.5));
+    samples.extend(segment(2360, 600, &|t| 1.0 + 0.8 * ((t as f64) / 40.0).sin()));
+    samples.extend(segment(2960, 5000, &|t| 1.0 + 0.03 * ((t as f64) / 300.0).sin()));
+    samples
+}
+
+/// Fall with listening paused, then wait until the check is asked
+async fn fall_check(harness: &mut RuntimeHarness, speaker: &RecordingSpeaker) {
+    harness.runtime.set_listening(false);
+    let sensors = harness.runtime.sensor_sender().unwrap();
+    for sample in fall_samples() {
+        sensors.send(sample).await.unwrap();
+    }
+    harness.next(|event| matches!(event, AppEvent::FallDetected { .. }).then_some(())).await;
+    until(|| !speaker.spoken.lock().unwrap().is_empty()).await;
+    assert_eq!(*speaker.spoken.lock().unwrap(), [FALL_CHECK_QUESTION]);
+}
+
+fn fall_detection(config: &mut AppConfig) {
+    config.emergency.enable_fall_detection = true;
+    config.emergency.fall_check_timeout = 10;
+}
+
+fn fall_host(speaker: Arc<RecordingSpeaker>) -> HostServices {
+    HostServices { speaker: Some(speaker), ..host(Arc::new(LocalDialer::new())) }
+}
+
+#[tokio::test]
+async fn test_answering_the_fall_check_cancels_it() {
+    let speaker = Arc::new(RecordingSpeaker::default());
+    let mut harness = RuntimeHarness::start(fall_detection, fall_host(speaker.clone()));
+    fall_check(&mut harness, &speaker).await;
+
+    let reply = VoiceDetection { text: "I'm okay".to_string(), kind: DetectionKind::Speech, latency: Duration::ZERO };
+    harness.runtime.command_sender().unwrap().send(ProtocolCommand::Detection(reply)).await.unwrap();
+    let expired = harness.next(|event| match event {
+        AppEvent::CountdownEnded { expired } => Some(expired),
+        _ => None,
+    })
+    .await;
+    assert!(!expired);
+
+    let timeline = harness.timeline().await;
+    assert!(timeline.iter().any(|entry| matches!(entry, TimelineEntry::Countdown(record) if record.reason == "fall" && !record.outcome.expired())));
+    assert!(!timeline.iter().any(|entry| matches!(entry, TimelineEntry::Incident(_))), "{:?}", timeline);
+}
+
+#[tokio::test]
+async fn test_unanswered_fall_check_alerts_contacts() {
+    let speaker = Arc::new(RecordingSpeaker::default());
+    let mut harness = RuntimeHarness::start(fall_detection, fall_host(speaker.clone()));
+    fall_check(&mut harness, &speaker).await;
+
+    // The prompts count down without asking again
+    let prompts = harness.next(|event| match event {
+        AppEvent::CountdownPrompt { speech, .. } => Some(speech),
+        _ => None,
+    })
+    .await;
+    assert!(!prompts.contains(FALL_CHECK_QUESTION), "{}", prompts);
+    let expired = harness.next(|event| match event {
+        AppEvent::CountdownEnded { expired } => Some(expired),
+        _ => None,
+    })
+    .await;
+    assert!(expired);
+
+    let timeline = harness.timeline().await;
+    assert!(timeline.iter().any(|entry| matches!(entry, TimelineEntry::Countdown(record) if record.reason == "fall" && record.outcome.expired())));
+    let incident = timeline.iter().find_map(|entry| match entry {
+        TimelineEntry::Incident(record) => Some(record),
+        _ => None,
+    });
+    assert!(incident.unwrap().context_flags.iter().any(|flag| flag == "fall_detected"));
+    assert_eq!(speaker.spoken.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_uncancelled_crash_countdown_calls_emergency_services() {
+    let dialer = Arc::new(LocalDialer::new());
+    let mut harness = RuntimeHarness::start(
+        |config| {
+            config.emergency.enable_crash_detection = true;
+            config.emergency.auto_dial_911 = true;
+            config.emergency.crash_response_window = 5;
+        },
+        host(dialer.clone()),
+    );
+    let trace = SensorTrace::parse(include_str!("data/crash/crash_frontal_40mph.csv")).unwrap();
+    let sensors = harness.runtime.sensor_sender().unwrap();
+    for sample in trace.samples {
+        sensors.send(sample).await.unwrap();
+    }
+
+    let countdown = harness.next(|event| match event {
+        AppEvent::CrashDetected { countdown_secs } => Some(countdown_secs),
+        _ => None,
+    })
+    .await;
+    assert_eq!(countdown, 5);
+    assert!(dialer.dialed().is_empty(), "called before the countdown ended");
+    let expired = harness.next(|event| match event {
+        AppEvent::CountdownEnded { expired } => Some(expired),
+        _ => None,
+    })
+    .await;
+    assert!(expired);
+    let number = harness.next(|event| match event {
+        AppEvent::CallPlaced { number } => Some(number),
+        _ => None,
+    })
+    .await;
+    assert_eq!(dialer.dialed(), [number]);
+
+    let timeline = harness.timeline().await;
+    assert!(timeline.iter().any(|entry| matches!(entry, TimelineEntry::Countdown(record) if record.reason == "crash" && record.outcome.expired())));
+    assert!(timeline.iter().any(|entry| matches!(entry, TimelineEntry::Incident(record) if record.emergency_type == "car_crash")));
+    assert!(timeline.iter().any(|entry| matches!(entry, TimelineEntry::Call(_))), "{:?}", timeline);
+}
+
+#[tokio::test]
+async fn test_escalation_shares_the_tracked_location() {
+    let dir = temp_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let contact = Contact::new("Alex", "+12025550143", ContactTier::Primary).with_channels(&[NotificationChannel::Sms]);
+    std::fs::write(dir.join("contacts.json"), serde_json::to_string(&vec![contact]).unwrap()).unwrap();
+    let sms = Arc::new(LocalNotifier::new());
+    let host = HostServices {
+        notifier: DeliveryRouter::new().route(NotificationChannel::Sms, sms.clone()),
+        ..host(Arc::new(LocalDialer::new()))
+    };
+    let sharing = |config: &mut AppConfig| {
+        config.emergency.enable_family_alerts = true;
+        config.emergency.enable_location_sharing = true;
+    };
+    let harness = RuntimeHarness::start_in(&dir, sharing, host);
+
+    let fix = SensorReading::Location { latitude: 38.8977, longitude: -77.0365, accuracy_m: 8.0 };
+    harness.runtime.sensor_sender().unwrap().send(SensorSample::new(0, fix)).await.unwrap();
+    until(|| harness.runtime.location().is_some()).await;
+
+    let trigger = SilentSosTrigger { method: SilentSosMethod::HoldButton, timestamp_ms: 0 };
+    harness.runtime.command_sender().unwrap().send(ProtocolCommand::SilentSos(trigger)).await.unwrap();
+    // A silent SOS publishes nothing, so watch the contact's messages
+    until(|| !sms.sent().is_empty()).await;
+    let sent = sms.sent();
+    assert_eq!(sent[0].address, "+12025550143");
+    assert!(sent[0].message.contains("38.89770,-77.03650"), "{}", sent[0].message);
+
+    let timeline = harness.timeline().await;
+    let incident = timeline.iter().find_map(|entry| match entry {
+        TimelineEntry::Incident(record) => Some(record.id.clone()),
+        _ => None,
+    });
+    let shared = timeline.iter().find_map(|entry| match entry {
+        TimelineEntry::Location(record) => Some(record),
+        _ => None,
+    });
+    let shared = shared.expect("location not recorded");
+    assert_eq!(Some(&shared.incident_id), incident.as_ref());
+    assert_eq!((shared.report.latitude, shared.report.longitude), (38.8977, -77.0365));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn test_timeline_is_appended_across_runs() {
+    // The history file's directory doesn't exist yet
+    let dir = temp_dir().join("nested");
+    let no_dial = |config: &mut AppConfig| config.emergency.auto_dial_911 = false;
+
+    let harness = RuntimeHarness::start_in(&dir, no_dial, host(Arc::new(LocalDialer::new())));
+    let commands = harness.runtime.command_sender().unwrap();
+    let emergency = ProtocolCommand::Manual { emergency_type: "choking".to_string(), user_input: "he can't breathe".to_string() };
+    commands.send(emergency).await.unwrap();
+    commands.send(ProtocolCommand::Cancel { method: CancelMethod::Pin, duress: false }).await.unwrap();
+    drop(commands);
+    let first = harness.timeline().await;
+    let incident = match first.as_slice() {
+        [TimelineEntry::Incident(incident), TimelineEntry::Cancellation(cancel)] => {
+            assert_eq!(incident.emergency_type, "choking");
+            assert_eq!(cancel.incident_id.as_ref(), Some(&incident.id));
+            incident.id.clone()
+        }
+        other => panic!("unexpected timeline: {:?}", other),
+    };
+
+    let harness = RuntimeHarness::start_in(&dir, no_dial, host(Arc::new(LocalDialer::new())));
+    let commands = harness.runtime.command_sender().unwrap();
+    commands.send(ProtocolCommand::Cancel { method: CancelMethod::Phrase, duress: false }).await.unwrap();
+    drop(commands);
+    let timeline = harness.timeline().await;
+    assert_eq!(timeline.len(), 3, "{:?}", timeline);
+    assert!(matches!(&timeline[0], TimelineEntry::Incident(record) if record.id == incident));
+    assert!(matches!(&timeline[2], TimelineEntry::Cancellation(record) if record.method == CancelMethod::Phrase));
+    let _ = std::fs::remove_dir_all(dir.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_connectivity_follows_the_endpoint() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+    let mut harness = RuntimeHarness::start(
+        |config| {
+            config.connectivity.user_preference = None;
+            config.connectivity.llm_endpoints = vec![endpoint];
+            config.connectivity.check_interval = 1;
+            config.connectivity.network_timeout = 1;
+        },
+        host(Arc::new(LocalDialer::new())),
+    );
+    let connected = |event| match event {
+        AppEvent::ConnectivityChanged { mode } => Some(mode),
+        _ => None,
+    };
+
+    assert_eq!(harness.next(connected).await, ConnectivityMode::Hybrid);
+    assert_eq!(harness.runtime.connectivity(), ConnectivityMode::Hybrid);
+    // The endpoint goes away
+    drop(listener);
+    assert_eq!(harness.next(connected).await, ConnectivityMode::Offline);
+    assert_eq!(harness.runtime.connectivity(), ConnectivityMode::Offline);
+    harness.timeline().await;
+}